@@ -93,6 +93,12 @@ async fn create_library(conn: &mut dim_database::DbConnection) -> i64 {
         name: "Tests".to_string(),
         locations: vec![],
         media_type: MediaType::Movie,
+        metadata_provider: Default::default(),
+        prefer_local_artwork: true,
+        scan_rules: Default::default(),
+        filename_rules: Default::default(),
+        rescan_schedule: None,
+        watcher_mode: Default::default(),
     }
     .insert(&mut tx)
     .await
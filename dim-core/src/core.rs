@@ -1,13 +1,17 @@
 use crate::scanner;
 
 use dim_database::library::MediaType;
+use dim_database::library::MetadataProvider;
 
+use dim_extern_api::local::LocalProvider;
+use dim_extern_api::nfo::NfoProvider;
 use dim_extern_api::tmdb::TMDBMetadataProvider;
+use dim_extern_api::ExternalQueryIntoShow;
 
 use once_cell::sync::OnceCell;
 
 use tokio::sync::mpsc::UnboundedSender;
-use tracing::{info, instrument};
+use tracing::{info, instrument, warn};
 
 use std::sync::Arc;
 
@@ -18,6 +22,39 @@ pub type EventTx = UnboundedSender<String>;
 /// Path to where metadata is stored and should be fetched to.
 pub static METADATA_PATH: OnceCell<String> = OnceCell::new();
 
+const TMDB_KEY: &str = "38c372f5bc572c8aadde7a802638534e";
+
+/// Function returns the metadata provider which should be used to match media of a library, or
+/// `None` if libraries of `media_type` can't be matched.
+///
+/// # Arguments
+/// * `media_type` - media type of the library.
+/// * `metadata_provider` - metadata provider configured for the library.
+/// * `locations` - locations of the library, sidecars are only read from within these.
+pub fn provider_for(
+    media_type: MediaType,
+    metadata_provider: MetadataProvider,
+    locations: &[String],
+) -> Option<Arc<dyn ExternalQueryIntoShow>> {
+    let tmdb = TMDBMetadataProvider::new(TMDB_KEY);
+
+    let (tmdb, nfo): (Arc<dyn ExternalQueryIntoShow>, _) = match media_type {
+        MediaType::Movie => (Arc::new(tmdb.movies()), NfoProvider::movies()),
+        MediaType::Tv => (Arc::new(tmdb.tv_shows()), NfoProvider::tv_shows()),
+        // music libraries source their metadata from tags.
+        MediaType::Music => return Some(Arc::new(LocalProvider)),
+        MediaType::Episode | MediaType::Album | MediaType::Track => return None,
+    };
+
+    let nfo = nfo.with_locations(locations);
+
+    Some(match metadata_provider {
+        MetadataProvider::Tmdb => tmdb,
+        MetadataProvider::Nfo => Arc::new(nfo),
+        MetadataProvider::NfoThenTmdb => Arc::new(nfo.with_fallback(tmdb)),
+    })
+}
+
 /// Function dumps a list of all libraries in the database and starts a scanner for each which
 /// monitors for new files using fsnotify. It also scans all orphans on boot.
 ///
@@ -38,35 +75,29 @@ pub async fn run_scanners(tx: EventTx) {
                 let tx_clone = tx.clone();
                 let media_type = lib.media_type;
 
-                let provider = TMDBMetadataProvider::new("38c372f5bc572c8aadde7a802638534e");
-
-                let provider = match media_type {
-                    MediaType::Movie => Arc::new(provider.movies()) as Arc<_>,
-                    MediaType::Tv => Arc::new(provider.tv_shows()) as Arc<_>,
-                    _ => unreachable!(),
+                let Some(provider) =
+                    provider_for(media_type, lib.metadata_provider, &lib.locations)
+                else {
+                    warn!(library_id, %media_type, "Skipping library of unsupported media type.");
+                    continue;
                 };
 
-                let mut watcher = scanner::daemon::FsWatcher::new(
+                let watcher = match scanner::daemon::FsWatcher::new(
                     conn.clone(),
                     library_id,
                     media_type,
                     tx_clone.clone(),
                     Arc::clone(&provider),
-                );
-
-                let conn_clone = conn.clone();
-
-                tokio::spawn(async move {
-                    let mut conn = conn_clone;
-                    scanner::start(&mut conn, library_id, tx_clone.clone(), provider).await
-                });
+                ) {
+                    Ok(x) => x,
+                    Err(e) => {
+                        warn!(library_id, reason = ?e, "Failed to start the fs-watcher.");
+                        continue;
+                    }
+                };
 
-                tokio::spawn(async move {
-                    watcher
-                        .start_daemon()
-                        .await
-                        .expect("Something went wrong with the fs-watcher");
-                });
+                scanner::scan_jobs().enqueue(conn.clone(), library_id, tx_clone, provider);
+                scanner::daemon::spawn_watcher(watcher);
             }
         }
     }
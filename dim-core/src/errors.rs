@@ -36,6 +36,8 @@ pub enum DimError {
     UploadFailed,
     /// Failed to deserialize request body: {description:?}.
     MissingFieldInBody { description: String },
+    /// Invalid library settings: {description}.
+    InvalidLibrarySettings { description: String },
     /// Unsupported file type.
     UnsupportedFile,
     /// Library does not exist.
@@ -50,6 +50,24 @@ async fn process_queue(mut rx: UnboundedReceiver<(String, String)>) {
     while let Some((url, outfile)) = rx.recv().await {
         debug!("Trying to cache {}", url);
 
+        // Artwork sourced from sidecar files lives on the local filesystem.
+        if let Some(local) = url::Url::parse(&url)
+            .ok()
+            .filter(|x| x.scheme() == "file")
+            .and_then(|x| x.to_file_path().ok())
+        {
+            let mut out_path = PathBuf::from(METADATA_PATH.get().unwrap());
+            out_path.push(outfile);
+
+            debug!("Copying {:?} -> {:?}", local, out_path);
+
+            if let Err(e) = tokio::fs::copy(&local, &out_path).await {
+                error!(error = ?e, url = &url, "Failed to copy local file.");
+            }
+
+            continue;
+        }
+
         match reqwest::get(url.as_str()).await {
             Ok(resp) => {
                 let meta_path = METADATA_PATH.get().unwrap();
@@ -51,6 +51,8 @@ pub mod streaming;
 mod tests;
 /// Tree-like structure for representing directories of files.
 pub mod tree;
+/// Labelling and ranking of the versions of a media.
+pub mod versions;
 
 pub use settings::get_global_settings;
 pub use settings::init_global_settings;
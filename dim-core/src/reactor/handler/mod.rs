@@ -136,7 +136,10 @@ impl EventReactor {
                     .await
                     .map_err(Error::MediaQuery)?;
 
-                if !matches!(media_type, MediaType::Movie | MediaType::Tv) {
+                if !matches!(
+                    media_type,
+                    MediaType::Movie | MediaType::Tv | MediaType::Album
+                ) {
                     return Ok(());
                 }
 
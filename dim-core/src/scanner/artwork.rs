@@ -0,0 +1,202 @@
+//! Module contains the logic used to detect local artwork stored next to media files.
+//!
+//! We follow the naming scheme used by Kodi, Plex and Jellyfin:
+//! * Movies use `poster`, `folder`, `cover` and `<filename>-poster` as posters and `fanart`,
+//!   `backdrop`, `background` and `<filename>-fanart` as backdrops.
+//! * Tv shows use the same names in the root folder of the show. Seasons use `seasonNN-poster` in
+//!   the show folder, or a poster placed inside of the season folder.
+//! * Episodes use `<filename>-thumb` as a still.
+//!
+//! Local artwork is registered as a asset whose remote url points to the file on disk, the
+//! fetcher will then copy it into the metadata folder.
+
+use super::format_path;
+
+use dim_database::asset::Asset;
+use dim_database::asset::InsertableAsset;
+use dim_database::DatabaseError;
+use dim_database::Transaction;
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use url::Url;
+
+/// Extensions of files we consider to be artwork.
+const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp"];
+/// Names of posters, in order of priority.
+const POSTER_NAMES: &[&str] = &["poster", "folder", "cover"];
+/// Names of backdrops, in order of priority.
+const BACKDROP_NAMES: &[&str] = &["fanart", "backdrop", "background"];
+
+/// Artwork found next to a media file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LocalArtwork {
+    pub posters: Vec<PathBuf>,
+    pub backdrops: Vec<PathBuf>,
+}
+
+/// Function returns all images in `dir` whose name (without extension) is one of `names`. Names
+/// are compared case-insensitively and results are ordered by the order of `names`.
+fn find_images(dir: &Path, names: &[String]) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return vec![];
+    };
+
+    let mut found = entries
+        .filter_map(Result::ok)
+        .map(|x| x.path())
+        .filter(|x| x.is_file())
+        .filter_map(|path| {
+            let ext = path.extension()?.to_str()?.to_lowercase();
+            let stem = path.file_stem()?.to_str()?.to_lowercase();
+
+            if !IMAGE_EXTENSIONS.contains(&ext.as_str()) {
+                return None;
+            }
+
+            let priority = names.iter().position(|x| *x == stem)?;
+            Some((priority, path))
+        })
+        .collect::<Vec<_>>();
+
+    found.sort();
+    found.into_iter().map(|(_, x)| x).collect()
+}
+
+fn names(prefix: Option<&str>, suffix: &str, generic: &[&str]) -> Vec<String> {
+    prefix
+        .map(|x| format!("{}-{suffix}", x.to_lowercase()))
+        .into_iter()
+        .chain(generic.iter().map(|x| x.to_string()))
+        .collect()
+}
+
+fn file_stem(file: &Path) -> Option<&str> {
+    file.file_stem()?.to_str()
+}
+
+/// Function returns whether `dir` looks like a season folder, ie `Season 1`, `S01` or `Specials`.
+fn is_season_dir(dir: &Path) -> bool {
+    let Some(name) = dir.file_name().and_then(|x| x.to_str()) else {
+        return false;
+    };
+
+    let name = name.to_lowercase();
+    let number = name
+        .strip_prefix("season")
+        .or_else(|| name.strip_prefix("series"))
+        .or_else(|| name.strip_prefix('s'))
+        .map(|x| x.trim_start_matches([' ', '_', '.', '-']));
+
+    name == "specials"
+        || number.map_or(false, |x| {
+            !x.is_empty() && x.chars().all(|c| c.is_ascii_digit())
+        })
+}
+
+/// Function returns the root folder of the show that the episode `file` belongs to.
+pub fn show_dir(file: &Path) -> Option<&Path> {
+    let parent = file.parent()?;
+
+    if is_season_dir(parent) {
+        return parent.parent();
+    }
+
+    Some(parent)
+}
+
+/// Function returns the local artwork for the movie `file`.
+pub fn movie_artwork(file: &Path) -> LocalArtwork {
+    let Some(dir) = file.parent() else {
+        return LocalArtwork::default();
+    };
+
+    let stem = file_stem(file);
+    let mut posters = names(stem, "poster", POSTER_NAMES);
+    // `<filename>-thumb` is usually a poster for movies.
+    posters.extend(names(stem, "thumb", &[]));
+
+    LocalArtwork {
+        posters: find_images(dir, &posters),
+        backdrops: find_images(dir, &names(stem, "fanart", BACKDROP_NAMES)),
+    }
+}
+
+/// Function returns the local artwork for the show that the episode `file` belongs to.
+pub fn show_artwork(file: &Path) -> LocalArtwork {
+    let Some(dir) = show_dir(file) else {
+        return LocalArtwork::default();
+    };
+
+    LocalArtwork {
+        posters: find_images(dir, &names(None, "", POSTER_NAMES)),
+        backdrops: find_images(dir, &names(None, "", BACKDROP_NAMES)),
+    }
+}
+
+/// Function returns the local posters for season `season` of the show that the episode `file`
+/// belongs to.
+pub fn season_posters(file: &Path, season: u64) -> Vec<PathBuf> {
+    let Some(dir) = show_dir(file) else {
+        return vec![];
+    };
+
+    let name = if season == 0 {
+        "season-specials-poster".to_string()
+    } else {
+        format!("season{season:02}-poster")
+    };
+
+    let mut posters = find_images(dir, &[name]);
+
+    if let Some(season_dir) = file.parent().filter(|x| is_season_dir(x)) {
+        posters.extend(find_images(season_dir, &names(None, "", POSTER_NAMES)));
+    }
+
+    posters
+}
+
+/// Function returns the local stills for the episode `file`.
+pub fn episode_stills(file: &Path) -> Vec<PathBuf> {
+    match file.parent() {
+        Some(dir) => find_images(dir, &names(file_stem(file), "thumb", &[])),
+        None => vec![],
+    }
+}
+
+/// Function creates a asset for a local image. The remote url of the asset points to the file on
+/// disk and carries the size and modification time of the image as its fragment, this way a image
+/// that has been replaced on disk gets registered as a new asset and copied again.
+pub fn local_asset(path: &Path) -> Option<InsertableAsset> {
+    let meta = std::fs::metadata(path).ok()?;
+    let mtime = meta
+        .modified()
+        .ok()
+        .and_then(|x| x.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|x| x.as_secs())
+        .unwrap_or_default();
+
+    let mut url = Url::from_file_path(path).ok()?;
+    url.set_fragment(Some(&format!("{}-{mtime}", meta.len())));
+
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    let filename = uuid::Uuid::new_v4().as_hyphenated().to_string();
+
+    Some(InsertableAsset {
+        remote_url: Some(url.into()),
+        local_path: format_path(Some(format!("{filename}.{ext}"))),
+        file_ext: ext,
+    })
+}
+
+/// Function registers local images as assets. Images which have been registered before and haven't
+/// changed on disk since are reused.
+pub async fn insert_local_assets(
+    tx: &mut Transaction<'_>,
+    paths: &[PathBuf],
+) -> Result<Vec<Asset>, DatabaseError> {
+    let assets = paths.iter().filter_map(|x| local_asset(x)).collect();
+
+    InsertableAsset::insert_many(tx, assets).await
+}
@@ -0,0 +1,151 @@
+//! Module contains the scoring of the metadata candidates extracted from a filename.
+//!
+//! Every filename parser gives us its own reading of a filename, and more often than not they
+//! disagree on odd filenames. Each candidate is scored by how many of the other parsers agree with
+//! it, whether it carries the fields the library type needs, and how close its name is to the
+//! folders the file is stored in. The scanner picks the candidate it is the most confident in, and
+//! files with a low confidence can be surfaced for review.
+
+use dim_database::library::MediaType;
+use dim_extern_api::filename::Metadata;
+
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::path::Path;
+
+/// Files parsed with a confidence below this are likely to be matched wrongly.
+pub const LOW_CONFIDENCE: u8 = 50;
+
+/// Most points a candidate can get for each of the signals we look at. They add up to 100.
+const AGREEMENT_POINTS: f64 = 30.0;
+const FOLDER_POINTS: f64 = 30.0;
+const FIELD_POINTS: f64 = 40.0;
+
+/// Points movies get for carrying a year and for not carrying a episode number. They add up to
+/// `FIELD_POINTS`.
+const MOVIE_YEAR_POINTS: f64 = 25.0;
+const MOVIE_NO_EPISODE_POINTS: f64 = 15.0;
+
+/// Points episodes get for carrying a season and for carrying a episode number or air date. They
+/// add up to `FIELD_POINTS`.
+const EPISODE_SEASON_POINTS: f64 = 20.0;
+const EPISODE_NUMBER_POINTS: f64 = 20.0;
+
+/// Function fills in the confidence of each candidate extracted from the filename of `file`.
+pub fn rate(file: &Path, candidates: &mut [Metadata], media_type: MediaType) {
+    let folders = file
+        .ancestors()
+        .skip(1)
+        .take(2)
+        .filter_map(Path::file_name)
+        .filter_map(OsStr::to_str)
+        .collect::<Vec<_>>();
+
+    let confidences = candidates
+        .iter()
+        .map(|candidate| score(candidate, candidates, &folders, media_type))
+        .collect::<Vec<_>>();
+
+    for (candidate, confidence) in candidates.iter_mut().zip(confidences) {
+        candidate.confidence = confidence;
+    }
+}
+
+/// Function returns the confidence from 0 to 100 in `candidate`. `folders` are the names of the
+/// folders the file is stored in, innermost first.
+fn score(
+    candidate: &Metadata,
+    candidates: &[Metadata],
+    folders: &[&str],
+    media_type: MediaType,
+) -> u8 {
+    let name = words(&candidate.name, None);
+
+    if name.is_empty() {
+        return 0;
+    }
+
+    // NOTE: A candidate always agrees with itself, a lone candidate gets half of the points as we
+    // have nothing to compare it against.
+    let agreement = match candidates.len() {
+        0 | 1 => 0.5,
+        len => {
+            let agreeing = candidates
+                .iter()
+                .filter(|x| words(&x.name, None) == name)
+                .count();
+
+            agreeing.saturating_sub(1) as f64 / (len - 1) as f64
+        }
+    };
+
+    // NOTE: Folder names often carry the year, ie `Movie (2020)`, which shouldn't count against
+    // the name.
+    let folder = folders
+        .iter()
+        .map(|x| similarity(&name, &words(x, candidate.year)))
+        .fold(0.0, f64::max);
+
+    let points =
+        AGREEMENT_POINTS * agreement + FOLDER_POINTS * folder + field_points(candidate, media_type);
+
+    points.round().clamp(0.0, 100.0) as u8
+}
+
+/// Function returns the points a candidate gets for carrying the fields needed to match files of
+/// `media_type`.
+fn field_points(candidate: &Metadata, media_type: MediaType) -> f64 {
+    match media_type {
+        MediaType::Movie => {
+            let year = if candidate.year.is_some() {
+                MOVIE_YEAR_POINTS
+            } else {
+                0.0
+            };
+            // NOTE: Movies dont have episodes, so a episode number means we misread the filename.
+            let not_episode = if candidate.episode.is_none() {
+                MOVIE_NO_EPISODE_POINTS
+            } else {
+                0.0
+            };
+
+            year + not_episode
+        }
+        MediaType::Tv => {
+            let season = match (candidate.season, candidate.air_date) {
+                (_, Some(_)) => EPISODE_SEASON_POINTS,
+                (Some(_), None) if !candidate.season_assumed => EPISODE_SEASON_POINTS,
+                (Some(_), None) => EPISODE_SEASON_POINTS / 2.0,
+                (None, None) => 0.0,
+            };
+            let episode = if candidate.episode.is_some() || candidate.air_date.is_some() {
+                EPISODE_NUMBER_POINTS
+            } else {
+                0.0
+            };
+
+            season + episode
+        }
+        // NOTE: Music gets matched by its tags, the filename only has to give us a name.
+        _ => FIELD_POINTS / 2.0,
+    }
+}
+
+/// Function splits `s` into its lowercase words, leaving out `year`.
+pub(super) fn words(s: &str, year: Option<i64>) -> HashSet<String> {
+    let year = year.map(|x| x.to_string());
+
+    s.split(|c: char| !c.is_alphanumeric())
+        .filter(|x| !x.is_empty() && Some(*x) != year.as_deref())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Function returns how similar two sets of words are, from 0 to 1.
+fn similarity(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+
+    2.0 * a.intersection(b).count() as f64 / (a.len() + b.len()) as f64
+}
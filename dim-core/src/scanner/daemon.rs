@@ -1,19 +1,24 @@
 use crate::core::EventTx;
 use dim_extern_api::ExternalQueryIntoShow;
 
-use super::movie;
-use super::tv_show;
+use super::matcher_for;
+use super::poller;
 use super::MediaMatcher;
+use super::ScanFilter;
+use super::ScanProgress;
 
+use std::collections::HashMap;
+use std::path::Path;
 use std::path::PathBuf;
 use std::sync::mpsc;
 use std::sync::Arc;
 
 use dim_database::library::Library;
 use dim_database::library::MediaType;
-use dim_database::media::Media;
+use dim_database::library::WatcherMode;
 use dim_database::mediafile::MediaFile;
 use dim_database::mediafile::UpdateMediaFile;
+use dim_database::subtitle::SubtitleSidecar;
 use dim_database::DbConnection;
 
 use notify::Config;
@@ -24,11 +29,16 @@ use notify::Watcher;
 
 use notify::event::ModifyKind;
 use notify::event::RenameMode;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
 use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_stream::StreamExt;
 
 use displaydoc::Display;
 use thiserror::Error;
-use tracing::{debug, error, warn};
+use tracing::{debug, error, info, warn};
 
 #[derive(Display, Debug, Error)]
 pub enum FsWatcherError {
@@ -36,6 +46,34 @@ pub enum FsWatcherError {
     DatabaseError(#[from] dim_database::DatabaseError),
     /// A error with notify has occured": {0:?}
     NotifyError(#[from] notify::Error),
+    /// Libraries of type {0} can't be watched.
+    UnsupportedMediaType(MediaType),
+}
+
+/// Fs-watchers that are running, by the id of the library they watch.
+static WATCHERS: Lazy<Mutex<HashMap<i64, JoinHandle<()>>>> = Lazy::new(Default::default);
+
+/// Function spawns the daemon of `watcher`. The watcher that is already running for the library,
+/// if any, gets stopped, this way changed settings of the library take effect.
+pub fn spawn_watcher(mut watcher: FsWatcher) {
+    let library_id = watcher.library_id;
+
+    let handle = tokio::spawn(async move {
+        if let Err(e) = watcher.start_daemon().await {
+            error!(library_id, reason = ?e, "The fs-watcher has failed.");
+        }
+    });
+
+    if let Some(previous) = WATCHERS.lock().insert(library_id, handle) {
+        previous.abort();
+    }
+}
+
+/// Function stops the fs-watcher of a library, if one is running.
+pub fn stop_watcher(library_id: i64) {
+    if let Some(watcher) = WATCHERS.lock().remove(&library_id) {
+        watcher.abort();
+    }
 }
 
 pub struct FsWatcher {
@@ -45,6 +83,8 @@ pub struct FsWatcher {
     conn: DbConnection,
     matcher: Arc<dyn MediaMatcher>,
     provider: Arc<dyn ExternalQueryIntoShow>,
+    /// Scan rules of the library, refreshed when the daemon starts.
+    filter: ScanFilter,
 }
 
 impl FsWatcher {
@@ -54,21 +94,19 @@ impl FsWatcher {
         media_type: MediaType,
         tx: EventTx,
         provider: Arc<dyn ExternalQueryIntoShow>,
-    ) -> Self {
-        let matcher = match media_type {
-            MediaType::Movie => Arc::new(movie::MovieMatcher) as Arc<dyn MediaMatcher>,
-            MediaType::Tv => Arc::new(tv_show::TvMatcher) as Arc<dyn MediaMatcher>,
-            _ => unimplemented!(),
-        };
+    ) -> Result<Self, FsWatcherError> {
+        let matcher =
+            matcher_for(media_type).ok_or(FsWatcherError::UnsupportedMediaType(media_type))?;
 
-        Self {
+        Ok(Self {
             library_id,
             media_type,
             tx,
             conn,
             matcher,
             provider,
-        }
+            filter: ScanFilter::for_media_type(media_type),
+        })
     }
 
     pub async fn start_daemon(&mut self) -> Result<(), FsWatcherError> {
@@ -84,9 +122,41 @@ impl FsWatcher {
             Library::get_one(&mut tx, self.library_id).await?
         };
 
-        let (mut rx, _watcher) = spawn_file_watcher(library.locations.as_slice())?;
+        self.filter = ScanFilter::new(
+            self.media_type,
+            library.scan_rules.clone(),
+            &library.locations,
+        );
+
+        // NOTE: network mounts dont generate native events, so we poll them for changes instead.
+        let (polled, native): (Vec<_>, Vec<_>) =
+            library
+                .locations
+                .iter()
+                .partition(|location| match library.watcher_mode {
+                    WatcherMode::Native => false,
+                    WatcherMode::Poll => true,
+                    WatcherMode::Auto => poller::is_unreliable_fs(Path::new(location)),
+                });
+
+        if !polled.is_empty() {
+            info!(
+                library_id = self.library_id,
+                ?polled,
+                "Polling locations for changes."
+            );
+        }
 
-        while let Some(ev) = rx.recv().await {
+        let (native_rx, _watcher) = spawn_file_watcher(native.as_slice())?;
+        let (poll_rx, _poller) = poller::spawn_poll_watcher(
+            polled.into_iter().map(PathBuf::from).collect(),
+            poller::POLL_INTERVAL,
+        );
+
+        let mut rx =
+            UnboundedReceiverStream::new(native_rx).merge(UnboundedReceiverStream::new(poll_rx));
+
+        while let Some(ev) = rx.next().await {
             let mut ev = match ev {
                 Ok(ev) => ev,
                 Err(err) => {
@@ -116,6 +186,18 @@ impl FsWatcher {
                         self.handle_remove(path).await
                     }
                 }
+                // NOTE: paths moved out of or into a watched location only report one side of
+                // the rename.
+                EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                    for path in ev.paths {
+                        self.handle_remove(path).await
+                    }
+                }
+                EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                    for path in ev.paths {
+                        self.handle_create(path).await
+                    }
+                }
                 event => debug!("Tried to handle unmatched event {:?}", event),
             }
         }
@@ -128,44 +210,106 @@ impl FsWatcher {
     async fn handle_create(&mut self, path: PathBuf) {
         debug!("Received handle_create event type: {:?}", path);
 
-        if path.is_file()
-            && path
-                .extension()
-                .and_then(|e| e.to_str())
-                .map_or(false, |e| super::SUPPORTED_EXTS.contains(&e))
-        {
-            if let Ok(mfile) =
-                super::insert_mediafiles(&mut self.conn, self.library_id, vec![path.clone()]).await
+        if path.is_file() && self.filter.accepts(&path) {
+            if self.restore_missing(&path).await {
+                return;
+            }
+
+            let progress = ScanProgress::new(self.library_id, self.tx.clone());
+
+            let units = match super::insert_file(
+                &self.conn,
+                self.library_id,
+                self.media_type,
+                path.clone(),
+                &progress,
+            )
+            .await
             {
-                let mut lock = self.conn.writer().lock_owned().await;
-                let mut tx = dim_database::write_tx(&mut lock).await.unwrap();
-
-                if let Err(e) = self
-                    .matcher
-                    .batch_match(&mut tx, self.provider.clone(), mfile)
-                    .await
-                {
-                    error!(error=?e, "Failed to match new file");
+                Ok(x) => x,
+                Err(e) => {
+                    error!(error = ?e, ?path, "Failed to insert new file");
+                    progress.failed(&path, e);
                     return;
                 }
+            };
+
+            if units.is_empty() {
+                return;
+            }
+
+            let count = units.len();
 
-                tx.commit().await.unwrap();
+            if let Err(e) = self
+                .matcher
+                .match_and_commit(&self.conn, self.provider.clone(), units)
+                .await
+            {
+                error!(error=?e, "Failed to match new file");
+                progress.failed(&path, e);
+                return;
             }
-        } else if path.is_dir() {
+
+            progress.matched(count);
+        } else if path.is_dir() && !self.filter.is_excluded(&path, true) {
             if let Some(x) = path.to_str() {
-                let _ = super::start_custom(
-                    &mut self.conn,
+                super::scan_jobs().enqueue_dirs(
+                    self.conn.clone(),
                     self.library_id,
                     vec![x.to_string()],
                     self.tx.clone(),
-                    self.media_type,
                     self.provider.clone(),
-                )
-                .await;
+                );
             }
         }
     }
 
+    /// Method clears the missing flag of the mediafile stored at `path`, if it has been flagged.
+    /// Returns whether `path` is already known to be a mediafile.
+    async fn restore_missing(&mut self, path: &Path) -> bool {
+        let Some(path) = path.to_str() else {
+            return false;
+        };
+
+        let mut lock = self.conn.writer().lock_owned().await;
+        let mut tx = match dim_database::write_tx(&mut lock).await {
+            Ok(x) => x,
+            Err(e) => {
+                error!(reason = ?e, "Failed to create transaction.");
+                return false;
+            }
+        };
+
+        let Ok(media_file) = MediaFile::get_by_file(&mut tx, path).await else {
+            return false;
+        };
+
+        if !media_file.missing {
+            return true;
+        }
+
+        // NOTE: we leave the fingerprint alone, so that the next rescan probes the file again if
+        // it has changed while it was gone.
+        let update_query = UpdateMediaFile {
+            missing: Some(false),
+            ..Default::default()
+        };
+
+        if let Err(e) = update_query.update(&mut tx, media_file.id).await {
+            error!(reason = ?e, "Failed to restore missing mediafile");
+            return true;
+        }
+
+        if let Err(e) = tx.commit().await {
+            error!(reason = ?e, "Failed to commit transaction.");
+            return true;
+        }
+
+        info!(library_id = self.library_id, %path, "Restored missing mediafile.");
+
+        true
+    }
+
     async fn handle_remove(&mut self, path: PathBuf) {
         debug!("Received handle remove {:?}", path);
 
@@ -186,31 +330,55 @@ impl FsWatcher {
             }
         };
 
-        if let Ok(media_file) = MediaFile::get_by_file(&mut tx, path).await {
-            let media = Media::get_of_mediafile(&mut tx, media_file.id).await;
-
-            if let Err(e) = MediaFile::delete(&mut tx, media_file.id).await {
-                error!(reason = ?e, "Failed to remove mediafile");
-                return;
-            }
-
-            // if we have a media with no mediafiles we want to purge it as it is a ghost media
-            // entry.
-            if let Ok(media) = media {
-                if let Ok(media_files) = MediaFile::get_of_media(&mut tx, media.id).await {
-                    if media_files.is_empty() {
-                        if let Err(e) = Media::delete(&mut tx, media.id).await {
-                            error!(reason = ?e, "Failed to delete ghost media");
-                            return;
-                        }
+        // NOTE: removing a directory only generates a event for the directory itself, so if the
+        // path isnt a mediafile we flag all mediafiles stored below it.
+        let media_files = match MediaFile::get_by_file(&mut tx, path).await {
+            Ok(x) => vec![x],
+            Err(_) => {
+                match MediaFile::get_in_dir(&mut tx, self.library_id, &dir_prefix(path)).await {
+                    Ok(x) => x,
+                    Err(e) => {
+                        error!(reason = ?e, "Failed to get mediafiles of directory.");
+                        return;
                     }
                 }
             }
+        };
 
-            if let Err(e) = tx.commit().await {
-                error!(reason = ?e, "Failed to commit transaction.");
+        // NOTE: the files might only be gone temporarily, ie because a drive got unmounted, so we
+        // flag them as missing and leave removing them to `missing::run_purger`.
+        let media_files = media_files
+            .into_iter()
+            .filter(|x| !x.missing)
+            .collect::<Vec<_>>();
+
+        if media_files.is_empty() {
+            return;
+        }
+
+        let update_query = UpdateMediaFile {
+            missing: Some(true),
+            ..Default::default()
+        };
+
+        for media_file in media_files.iter() {
+            if let Err(e) = update_query.update(&mut tx, media_file.id).await {
+                error!(reason = ?e, "Failed to flag mediafile as missing");
+                return;
             }
         }
+
+        if let Err(e) = tx.commit().await {
+            error!(reason = ?e, "Failed to commit transaction.");
+            return;
+        }
+
+        info!(
+            library_id = self.library_id,
+            %path,
+            missing = media_files.len(),
+            "Flagged mediafiles as missing."
+        );
     }
 
     async fn handle_rename(&mut self, from: PathBuf, to: PathBuf) {
@@ -269,8 +437,56 @@ impl FsWatcher {
             if let Err(e) = tx.commit().await {
                 error!(reason = ?e, "Failed to commit transaction.");
             }
+
+            return;
         }
+
+        // NOTE: renaming a directory only generates a event for the directory itself, so we move
+        // all mediafiles and subtitles stored below it in one go.
+        let (from, to) = (dir_prefix(from), dir_prefix(to));
+
+        let renamed = match MediaFile::rename_dir(&mut tx, self.library_id, &from, &to).await {
+            Ok(x) => x,
+            Err(e) => {
+                error!(reason = ?e, %from, %to, "Failed to rename mediafiles of directory.");
+                return;
+            }
+        };
+
+        if renamed == 0 {
+            return;
+        }
+
+        if let Err(e) = SubtitleSidecar::rename_dir(&mut tx, self.library_id, &from, &to).await {
+            error!(reason = ?e, %from, %to, "Failed to rename subtitles of directory.");
+            return;
+        }
+
+        if let Err(e) = tx.commit().await {
+            error!(reason = ?e, "Failed to commit transaction.");
+            return;
+        }
+
+        info!(
+            library_id = self.library_id,
+            %from,
+            %to,
+            renamed,
+            "Renamed directory."
+        );
+    }
+}
+
+/// Function returns `path` with a trailing path separator, so that it can be used as a prefix to
+/// match the paths stored below the directory.
+fn dir_prefix(path: &str) -> String {
+    let mut path = path.to_string();
+
+    if !path.ends_with(std::path::MAIN_SEPARATOR) {
+        path.push(std::path::MAIN_SEPARATOR);
     }
+
+    path
 }
 
 pub fn spawn_file_watcher<S>(
@@ -11,6 +11,8 @@ pub enum Error {
     MovieScanner(#[from] super::movie::Error),
     /// Tv show scanner error: {0:?}
     TvScanner(#[from] super::tv_show::Error),
+    /// Music scanner error: {0:?}
+    MusicScanner(#[from] super::music::Error),
     /// Mediafile insert error: {0:?}
     MediafileError(#[from] super::mediafile::Error),
     /// Failed to dispatch websocket event: {0:?}
@@ -23,4 +25,16 @@ pub enum Error {
     ),
     /// Library supplied doesnt exist: {0:?}
     LibraryNotFound(#[serde(skip)] dim_database::DatabaseError),
+    /// Failed to fetch the fingerprints of existing mediafiles: {0:?}
+    FingerprintsUnavailable(#[serde(skip)] dim_database::DatabaseError),
+    /// Failed to index subtitle sidecars: {0:?}
+    SubtitleIndex(#[serde(skip)] dim_database::DatabaseError),
+    /// Failed to purge missing mediafiles: {0:?}
+    PurgeMissing(#[serde(skip)] dim_database::DatabaseError),
+    /// Mediafile supplied doesnt exist: {0:?}
+    MediafileNotFound(#[serde(skip)] dim_database::DatabaseError),
+    /// Scan has been cancelled.
+    Cancelled,
+    /// Libraries of type {0} can't be scanned.
+    UnsupportedMediaType(dim_database::library::MediaType),
 }
@@ -0,0 +1,188 @@
+//! Module contains the logic used to recognise extras, like trailers and featurettes, stored next
+//! to a movie or a tv show.
+//!
+//! Extras are either stored in a folder named after their type, ie `Movie (2020)/Trailers/`, or
+//! have their type appended to their filename, ie `Movie (2020)-trailer.mkv`. Extras are never
+//! matched against a metadata provider, instead they get attached to the media of the movie, or the
+//! show, stored in the same folder.
+
+use dim_database::mediafile::ExtraType;
+use dim_database::mediafile::MediaFile;
+use dim_database::mediafile::UpdateMediaFile;
+use dim_database::DatabaseError;
+use dim_database::Transaction;
+
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::path::MAIN_SEPARATOR;
+
+use tracing::debug;
+
+/// Names of folders extras are stored in.
+static EXTRA_DIRS: &[(&str, ExtraType)] = &[
+    ("trailers", ExtraType::Trailer),
+    ("featurettes", ExtraType::Featurette),
+    ("behind the scenes", ExtraType::BehindTheScenes),
+    ("deleted scenes", ExtraType::DeletedScene),
+    ("interviews", ExtraType::Interview),
+    ("extras", ExtraType::Other),
+];
+
+/// Suffixes of filenames of extras.
+static EXTRA_SUFFIXES: &[(&str, ExtraType)] = &[
+    ("-trailer", ExtraType::Trailer),
+    ("-featurette", ExtraType::Featurette),
+    ("-behindthescenes", ExtraType::BehindTheScenes),
+    ("-deleted", ExtraType::DeletedScene),
+    ("-interview", ExtraType::Interview),
+    ("-scene", ExtraType::Scene),
+    ("-short", ExtraType::Short),
+    ("-other", ExtraType::Other),
+];
+
+/// A file that has been recognised as a extra.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Extra<'a> {
+    pub extra_type: ExtraType,
+    /// Folder in which the parent of the extra is stored.
+    pub parent_dir: &'a Path,
+    /// Filename, without extension, of the parent if the extra was recognised by its suffix.
+    pub parent_name: Option<&'a str>,
+}
+
+/// Function returns whether `file` is a extra and of what type.
+pub fn classify(file: &Path) -> Option<Extra<'_>> {
+    let dir = file.parent()?;
+
+    if let Some(name) = dir.file_name().and_then(|x| x.to_str()) {
+        let name = name.to_lowercase();
+
+        if let Some((_, extra_type)) = EXTRA_DIRS.iter().find(|(x, _)| *x == name) {
+            return Some(Extra {
+                extra_type: *extra_type,
+                parent_dir: dir.parent()?,
+                parent_name: None,
+            });
+        }
+    }
+
+    let stem = file.file_stem()?.to_str()?;
+    let lowercase = stem.to_lowercase();
+
+    EXTRA_SUFFIXES
+        .iter()
+        .find(|(suffix, _)| lowercase.ends_with(suffix))
+        .map(|(suffix, extra_type)| Extra {
+            extra_type: *extra_type,
+            parent_dir: dir,
+            // NOTE: the suffixes are ascii so the byte offset is valid for `stem` too.
+            parent_name: Some(&stem[..stem.len() - suffix.len()]).filter(|x| !x.is_empty()),
+        })
+}
+
+/// Function returns the type of extra `file` is, if any.
+pub fn extra_type(file: &Path) -> Option<ExtraType> {
+    classify(file).map(|x| x.extra_type)
+}
+
+/// Function picks the media a extra belongs to out of the matched files stored in its parent
+/// folder. We only guess if the choice is unambiguous.
+fn parent_media(extra: &Extra<'_>, candidates: &[MediaFile]) -> Option<i64> {
+    if let Some(name) = extra.parent_name {
+        let parent = candidates.iter().find(|x| {
+            Path::new(&x.target_file)
+                .file_stem()
+                .and_then(|x| x.to_str())
+                .map_or(false, |x| x.eq_ignore_ascii_case(name))
+        });
+
+        if let Some(parent) = parent {
+            return parent.media_id;
+        }
+    }
+
+    let media_id = candidates.first()?.media_id;
+    candidates
+        .iter()
+        .all(|x| x.media_id == media_id)
+        .then(|| media_id)
+        .flatten()
+}
+
+/// Function returns the folders in which extras belonging to `files` can be stored. For extras
+/// this is the folder of their parent. For all other files this is the folder they are stored in
+/// and the folder above it, which for episodes is the folder of the show.
+fn parent_dirs<'a>(files: impl IntoIterator<Item = &'a str>) -> BTreeSet<&'a Path> {
+    files
+        .into_iter()
+        .map(Path::new)
+        .flat_map(|file| match classify(file) {
+            Some(extra) => vec![extra.parent_dir],
+            None => file.ancestors().skip(1).take(2).collect(),
+        })
+        .collect()
+}
+
+/// Function attaches the extras stored in the folders of `files`, which dont have a parent yet,
+/// to the media stored in their parent folder. Extras of movies are attached to the movie, extras
+/// of tv shows to the episode they are named after or otherwise to the show. Returns the number of
+/// extras that got attached.
+///
+/// # Arguments
+/// * `tx` - mutable reference to a sqlx transaction.
+/// * `library_id` - id of the library the files belong to.
+/// * `files` - paths of the files of the batch that just got matched.
+pub async fn attach_extras(
+    tx: &mut Transaction<'_>,
+    library_id: i64,
+    files: &[String],
+) -> Result<usize, DatabaseError> {
+    let mut attached = 0;
+
+    for parent_dir in parent_dirs(files.iter().map(String::as_str)) {
+        let mut dir = parent_dir.to_string_lossy().to_string();
+        if !dir.ends_with(MAIN_SEPARATOR) {
+            dir.push(MAIN_SEPARATOR);
+        }
+
+        let extras = MediaFile::get_unattached_extras_in_dir(tx, library_id, &dir).await?;
+
+        if extras.is_empty() {
+            continue;
+        }
+
+        let candidates = MediaFile::get_matched_in_dir(tx, library_id, &dir).await?;
+        let shows = MediaFile::get_shows_in_dir(tx, library_id, &dir).await?;
+
+        for file in extras {
+            let Some(extra) = classify(Path::new(&file.target_file)) else {
+                continue;
+            };
+
+            // NOTE: Extras stored deeper down belong to a different folder.
+            if extra.parent_dir != parent_dir {
+                continue;
+            }
+
+            let media_id = match (parent_media(&extra, &candidates), shows.as_slice()) {
+                (Some(media_id), _) if extra.parent_name.is_some() || shows.is_empty() => media_id,
+                (_, [show_id]) => *show_id,
+                _ => {
+                    debug!(file = %file.target_file, "Couldn't find the parent of extra.");
+                    continue;
+                }
+            };
+
+            UpdateMediaFile {
+                media_id: Some(media_id),
+                ..Default::default()
+            }
+            .update(tx, file.id)
+            .await?;
+
+            attached += 1;
+        }
+    }
+
+    Ok(attached)
+}
@@ -0,0 +1,112 @@
+//! Module contains the filter the directory walker uses to decide which files of a library to
+//! pick up, based on the scan rules of the library.
+
+use super::supported_exts;
+
+use dim_database::library::MediaType;
+use dim_database::library::ScanRules;
+
+use ignore::gitignore::Gitignore;
+use ignore::gitignore::GitignoreBuilder;
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use tracing::warn;
+
+/// Scan rules of a library compiled for use by the directory walker.
+#[derive(Clone, Debug)]
+pub struct ScanFilter {
+    media_type: MediaType,
+    rules: ScanRules,
+    /// Exclude globs compiled for every location of the library.
+    excludes: Vec<(PathBuf, Gitignore)>,
+}
+
+impl ScanFilter {
+    /// Method compiles the scan rules of a library. Exclude globs are relative to `locations`.
+    pub fn new(media_type: MediaType, rules: ScanRules, locations: &[impl AsRef<Path>]) -> Self {
+        let excludes = if rules.exclude_globs.is_empty() {
+            vec![]
+        } else {
+            locations
+                .iter()
+                .filter_map(|root| {
+                    let mut builder = GitignoreBuilder::new(root);
+
+                    for glob in rules.exclude_globs.iter() {
+                        if let Err(error) = builder.add_line(None, glob) {
+                            warn!(?error, %glob, "Skipping invalid exclude glob.");
+                        }
+                    }
+
+                    match builder.build() {
+                        Ok(x) => Some((root.as_ref().to_path_buf(), x)),
+                        Err(error) => {
+                            warn!(?error, "Failed to compile exclude globs.");
+                            None
+                        }
+                    }
+                })
+                .collect()
+        };
+
+        Self {
+            media_type,
+            rules,
+            excludes,
+        }
+    }
+
+    /// Method returns a filter with the default scan rules for a library of `media_type`.
+    pub fn for_media_type(media_type: MediaType) -> Self {
+        Self::new(media_type, Default::default(), &[] as &[&Path])
+    }
+
+    /// Method returns whether symlinks should be followed.
+    pub fn follow_symlinks(&self) -> bool {
+        self.rules.follow_symlinks
+    }
+
+    /// Method returns whether `file` has a extension we should pick up.
+    pub fn accepts_ext(&self, file: &Path) -> bool {
+        let ext = match file.extension().and_then(|e| e.to_str()) {
+            Some(x) => x,
+            None => return false,
+        };
+
+        let matches = |x: &String| x.trim_start_matches('.').eq_ignore_ascii_case(ext);
+
+        if self.rules.excluded_exts.iter().any(matches) {
+            return false;
+        }
+
+        supported_exts(self.media_type).contains(&ext) || self.rules.extra_exts.iter().any(matches)
+    }
+
+    /// Method returns whether a file of `size` bytes is large enough to be picked up.
+    pub fn accepts_size(&self, size: u64) -> bool {
+        self.rules.min_file_size.map_or(true, |min| size >= min)
+    }
+
+    /// Method returns whether `path` is matched by one of the exclude globs.
+    pub fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        self.excludes
+            .iter()
+            .filter(|(root, _)| path.starts_with(root))
+            .any(|(_, globs)| globs.matched_path_or_any_parents(path, is_dir).is_ignore())
+    }
+
+    /// Method returns whether the file at `path` should be picked up.
+    pub fn accepts(&self, path: &Path) -> bool {
+        if !self.accepts_ext(path) || self.is_excluded(path, false) {
+            return false;
+        }
+
+        if self.rules.min_file_size.is_none() {
+            return true;
+        }
+
+        std::fs::metadata(path).map_or(false, |x| self.accepts_size(x.len()))
+    }
+}
@@ -0,0 +1,141 @@
+//! Module contains the hints we derive from the folders a file is stored in.
+//!
+//! Filenames like `Season 1/01.mkv` or `Movie Name (2004)/abc-xyz.mkv` carry next to nothing on
+//! their own, whereas the folders they are stored in tell us the title, the year and the season.
+//! Only the folders below the library root are looked at, the root and anything above it tell us
+//! nothing about the file.
+
+use dim_extern_api::filename::FilenameMetadata;
+use dim_extern_api::filename::TorrentMetadata;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use std::ffi::OsStr;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Matches the name of a title folder which ends with a year in brackets, ie `Movie Name (2004)`.
+static TITLE_YEAR_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(?P<title>.+?)[ ._\-]*[(\[](?P<year>(?:19|20)[0-9]{2})[)\]]$")
+        .expect("Failed to compile title folder regex.")
+});
+
+/// Matches the name of a title folder whose title ends with a year itself, followed by the year,
+/// ie `Blade Runner 2049 2017`.
+static TITLE_TWO_YEARS_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(?P<title>(?:.*[ ._\-])?(?:19|20)[0-9]{2})[ ._\-]+(?P<year>(?:19|20)[0-9]{2})$")
+        .expect("Failed to compile title folder regex.")
+});
+
+/// Matches the name of a folder which ends with a year on its own, ie `Blade Runner 2049` or
+/// `1917`. Such a year is more likely to be part of the title than the release year.
+static TRAILING_YEAR_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?:^|[ ._\-])(?:19|20)[0-9]{2}$").expect("Failed to compile trailing year regex.")
+});
+
+/// Hints derived from the folders a file is stored in.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FolderHints {
+    /// Title taken from the innermost folder that isn't a season folder, ie `Movie Name`.
+    pub title: Option<String>,
+    /// Year taken from the same folder as the title, ie `2004` for `Movie Name (2004)`.
+    pub year: Option<i64>,
+    /// Season taken from the innermost season folder, ie `2` for `Season 2`. Specials folders
+    /// hold season 0.
+    pub season: Option<i64>,
+    /// Folder the title was taken from. In tv libraries this is the show folder, all files below
+    /// it belong to the same show.
+    pub folder: Option<PathBuf>,
+}
+
+/// Function returns the hints the folders between `file` and the library root, out of `roots`,
+/// that it is stored in give us. Files outside of all roots get no hints.
+pub fn folder_hints(file: &Path, roots: &[PathBuf]) -> FolderHints {
+    let mut hints = FolderHints::default();
+
+    let Some(root) = roots
+        .iter()
+        .filter(|x| file.starts_with(x))
+        .max_by_key(|x| x.components().count())
+    else {
+        return hints;
+    };
+
+    let folders = file
+        .ancestors()
+        .skip(1)
+        .take_while(|x| *x != root.as_path() && x.starts_with(root));
+
+    for folder in folders {
+        let Some(name) = folder.file_name().and_then(OsStr::to_str) else {
+            break;
+        };
+
+        if let Some(season) = season_folder(name) {
+            hints.season.get_or_insert(season);
+            continue;
+        }
+
+        let (title, year) = split_folder_name(name);
+
+        if !title.is_empty() {
+            hints.title = Some(title);
+            hints.year = year;
+            hints.folder = Some(folder.to_path_buf());
+        }
+
+        break;
+    }
+
+    hints
+}
+
+/// Function returns the season stored in a folder named `name`, or `None` if the folder isn't a
+/// season folder. We recognise `Season 1`, `Series 1`, `S01` and specials folders.
+pub fn season_folder(name: &str) -> Option<i64> {
+    let name = name.trim().to_lowercase();
+
+    if name == "specials" || name == "special" {
+        return Some(0);
+    }
+
+    let number = ["season", "series", "s"]
+        .iter()
+        .find_map(|x| name.strip_prefix(x))?
+        .trim_start_matches([' ', '.', '_', '-']);
+
+    if number.is_empty() || !number.bytes().all(|x| x.is_ascii_digit()) {
+        return None;
+    }
+
+    number.parse().ok()
+}
+
+/// Function splits the name of a title folder, ie `Movie Name (2004)`, into the title and the
+/// year. Release folders, ie `Movie.Name.2004.1080p.BluRay`, are left to the torrent name parser.
+fn split_folder_name(name: &str) -> (String, Option<i64>) {
+    let clean = |x: &str| {
+        x.replace(['.', '_'], " ")
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    let captures = TITLE_YEAR_REGEX
+        .captures(name)
+        .or_else(|| TITLE_TWO_YEARS_REGEX.captures(name));
+
+    if let Some(captures) = captures {
+        return (clean(&captures["title"]), captures["year"].parse().ok());
+    }
+
+    if TRAILING_YEAR_REGEX.is_match(name) {
+        return (clean(name), None);
+    }
+
+    match TorrentMetadata::from_str(name) {
+        Some(x) if !x.name.trim().is_empty() => (x.name.trim().to_owned(), x.year),
+        _ => (name.trim().to_owned(), None),
+    }
+}
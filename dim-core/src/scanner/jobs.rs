@@ -0,0 +1,314 @@
+//! Module contains the scan job manager. All scans of whole libraries go through it, so that
+//! only one scan of a library runs at a time and scans can be tracked and cancelled.
+
+use super::Error;
+use super::ScanSummary;
+use crate::core::EventTx;
+
+use chrono::DateTime;
+use chrono::Utc;
+
+use dim_database::library::Library;
+
+use dim_extern_api::ExternalQueryIntoShow;
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use tracing::info;
+use tracing::warn;
+
+/// Number of finished jobs we keep around for the status api.
+const JOB_HISTORY: usize = 64;
+
+static SCAN_JOBS: Lazy<ScanJobs> = Lazy::new(ScanJobs::default);
+
+/// Function returns the global scan job manager.
+pub fn scan_jobs() -> &'static ScanJobs {
+    &SCAN_JOBS
+}
+
+/// Token used to cooperatively cancel a running scan. Scans check the token between batches.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    /// Job waits for a scan of the same library to finish.
+    Queued,
+    Running,
+    Finished,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    /// Method returns whether the job has not finished yet.
+    pub fn is_active(&self) -> bool {
+        matches!(self, Self::Queued | Self::Running)
+    }
+}
+
+/// A scan of a library.
+#[derive(Clone, Debug, Serialize)]
+pub struct ScanJob {
+    pub id: u64,
+    pub library_id: i64,
+    /// Directories the scan is limited to, `None` if the whole library gets scanned.
+    pub dirs: Option<Vec<String>>,
+    pub state: JobState,
+    pub queued_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Summary of the changes the scan has made, set once the scan has finished.
+    pub summary: Option<ScanSummary>,
+    /// Reason the scan has failed.
+    pub error: Option<String>,
+    #[serde(skip)]
+    cancel: CancelToken,
+}
+
+#[derive(Default)]
+struct Inner {
+    next_id: u64,
+    jobs: VecDeque<ScanJob>,
+    /// Locks used to run the scans of a library one after the other.
+    locks: HashMap<i64, Arc<tokio::sync::Mutex<()>>>,
+}
+
+impl Inner {
+    fn get_mut(&mut self, id: u64) -> Option<&mut ScanJob> {
+        self.jobs.iter_mut().find(|x| x.id == id)
+    }
+
+    /// Method drops the oldest finished jobs once we have more than `JOB_HISTORY` of them.
+    fn prune(&mut self) {
+        let finished = self.jobs.iter().filter(|x| !x.state.is_active()).count();
+
+        for _ in JOB_HISTORY..finished {
+            if let Some(idx) = self.jobs.iter().position(|x| !x.state.is_active()) {
+                self.jobs.remove(idx);
+            }
+        }
+    }
+}
+
+/// Function records that a full scan of a library has just finished. The scheduler uses this to
+/// decide when the library is due for a rescan.
+async fn set_last_scanned(
+    conn: &dim_database::DbConnection,
+    library_id: i64,
+) -> Result<(), dim_database::DatabaseError> {
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = dim_database::write_tx(&mut lock).await?;
+
+    Library::set_last_scanned(&mut tx, library_id, Utc::now().timestamp()).await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Manager which queues, runs and tracks scans of libraries.
+#[derive(Default)]
+pub struct ScanJobs {
+    inner: Mutex<Inner>,
+}
+
+impl ScanJobs {
+    /// Method queues a scan of a library. If a scan of the library is already waiting in the
+    /// queue, that job is returned instead of queueing a new one. The scan will start once the
+    /// running scan of the library, if any, has finished.
+    pub fn enqueue(
+        &'static self,
+        conn: dim_database::DbConnection,
+        library_id: i64,
+        tx: EventTx,
+        provider: Arc<dyn ExternalQueryIntoShow>,
+    ) -> ScanJob {
+        self.queue(conn, library_id, None, tx, provider)
+    }
+
+    /// Method queues a scan of some directories of a library, ie a directory that has just been
+    /// created. Like [`enqueue`](Self::enqueue) this reuses a scan of the library that is already
+    /// waiting in the queue.
+    pub fn enqueue_dirs(
+        &'static self,
+        conn: dim_database::DbConnection,
+        library_id: i64,
+        dirs: Vec<String>,
+        tx: EventTx,
+        provider: Arc<dyn ExternalQueryIntoShow>,
+    ) -> ScanJob {
+        self.queue(conn, library_id, Some(dirs), tx, provider)
+    }
+
+    fn queue(
+        &'static self,
+        conn: dim_database::DbConnection,
+        library_id: i64,
+        dirs: Option<Vec<String>>,
+        tx: EventTx,
+        provider: Arc<dyn ExternalQueryIntoShow>,
+    ) -> ScanJob {
+        let (job, lock) = {
+            let mut inner = self.inner.lock();
+
+            if let Some(job) = inner
+                .jobs
+                .iter_mut()
+                .find(|x| x.library_id == library_id && x.state == JobState::Queued)
+            {
+                // NOTE: A queued scan of the whole library covers any directory, otherwise the
+                // queued scan is widened to cover what we have been asked to scan.
+                match (job.dirs.as_mut(), dirs) {
+                    (Some(queued), Some(dirs)) => {
+                        queued.extend(dirs);
+                        queued.sort_unstable();
+                        queued.dedup();
+                    }
+                    (Some(_), None) => job.dirs = None,
+                    (None, _) => {}
+                }
+
+                return job.clone();
+            }
+
+            inner.next_id += 1;
+            let job = ScanJob {
+                id: inner.next_id,
+                library_id,
+                dirs,
+                state: JobState::Queued,
+                queued_at: Utc::now(),
+                started_at: None,
+                finished_at: None,
+                summary: None,
+                error: None,
+                cancel: CancelToken::default(),
+            };
+
+            inner.jobs.push_back(job.clone());
+            let lock = inner.locks.entry(library_id).or_default().clone();
+
+            (job, lock)
+        };
+
+        let id = job.id;
+        let cancel = job.cancel.clone();
+
+        tokio::spawn(async move {
+            let _guard = lock.lock().await;
+
+            // NOTE: The directories can change while the job is queued, so we read them once it
+            // starts.
+            let mut dirs = None;
+
+            if !self.update(id, |job| {
+                if job.cancel.is_cancelled() {
+                    return false;
+                }
+
+                job.state = JobState::Running;
+                job.started_at = Some(Utc::now());
+                dirs = job.dirs.clone();
+                true
+            }) {
+                return;
+            }
+
+            let whole_library = dirs.is_none();
+            let mut conn = conn;
+            let result = super::start(&mut conn, library_id, dirs, tx, provider, cancel).await;
+
+            if result.is_ok() && whole_library {
+                if let Err(error) = set_last_scanned(&conn, library_id).await {
+                    warn!(?error, library_id, "Failed to store the scan time.");
+                }
+            }
+
+            self.update(id, |job| {
+                job.finished_at = Some(Utc::now());
+
+                match result {
+                    Ok(summary) => {
+                        job.state = JobState::Finished;
+                        job.summary = Some(summary);
+                    }
+                    Err(Error::Cancelled) => {
+                        info!(library_id, "Scan has been cancelled.");
+                        job.state = JobState::Cancelled;
+                    }
+                    Err(error) => {
+                        warn!(?error, library_id, "Scan has failed.");
+                        job.state = JobState::Failed;
+                        job.error = Some(error.to_string());
+                    }
+                }
+
+                true
+            });
+        });
+
+        job
+    }
+
+    /// Method cancels the queued and running scans of a library. Running scans stop after the
+    /// batch they are working on. Returns the jobs that got cancelled.
+    pub fn cancel(&self, library_id: i64) -> Vec<ScanJob> {
+        let mut inner = self.inner.lock();
+        let mut cancelled = vec![];
+
+        for job in inner.jobs.iter_mut() {
+            if job.library_id != library_id || !job.state.is_active() {
+                continue;
+            }
+
+            job.cancel.cancel();
+
+            // queued jobs never start, so we flag them right away.
+            if job.state == JobState::Queued {
+                job.state = JobState::Cancelled;
+                job.finished_at = Some(Utc::now());
+            }
+
+            cancelled.push(job.clone());
+        }
+
+        inner.prune();
+
+        cancelled
+    }
+
+    /// Method returns all queued, running and recently finished jobs.
+    pub fn list(&self) -> Vec<ScanJob> {
+        self.inner.lock().jobs.iter().cloned().collect()
+    }
+
+    /// Method applies `f` to the job with the supplied id. Returns what `f` returns, or false if
+    /// the job doesn't exist anymore.
+    fn update(&self, id: u64, f: impl FnOnce(&mut ScanJob) -> bool) -> bool {
+        let mut inner = self.inner.lock();
+        let updated = inner.get_mut(id).map_or(false, f);
+        inner.prune();
+
+        updated
+    }
+}
@@ -1,6 +1,7 @@
 //! Module contains all the code that creates and inserts basic mediafiles into the database.
 
 use crate::streaming::ffprobe::FFProbeCtx;
+use crate::streaming::ffprobe::ProbeProblem;
 use crate::streaming::FFPROBE_BIN;
 use dim_extern_api::filename::Metadata;
 
@@ -8,17 +9,25 @@ use async_trait::async_trait;
 
 use dim_database::mediafile::InsertableMediaFile;
 use dim_database::mediafile::MediaFile;
+use dim_database::mediafile::MediaFileFingerprint;
+use dim_database::mediafile::UpdateMediaFile;
 use dim_database::DatabaseError;
 use dim_database::DbConnection;
 use displaydoc::Display;
 
 use serde::Serialize;
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fs::Metadata as FsMetadata;
+use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::UNIX_EPOCH;
 
 use tokio::sync::Semaphore;
 use tokio::sync::SemaphorePermit;
 
+use tracing::debug;
 use tracing::debug_span;
 use tracing::error;
 use tracing::warn;
@@ -56,6 +65,105 @@ pub enum Error {
     CommitFailed(#[serde(skip)] SqlxError),
     /// Failed to check if mediafile exists in the database: {0:?}
     ExistanceCheckFailed(#[serde(skip)] DatabaseError),
+    /// Failed to update mediafile in the database: {0:?}
+    UpdateFailed(#[serde(skip)] DatabaseError),
+}
+
+/// Number of bytes hashed at the start and at the end of a file by [`content_hash`].
+const HASH_CHUNK_SIZE: u64 = 64 * 1024;
+
+/// Function computes a cheap partial hash of the contents of a file. This is the same hash
+/// OpenSubtitles uses: the file size plus the sum of the first and last 64KiB of the file read as
+/// little-endian `u64`s.
+///
+/// # Return
+/// Returns `None` for files smaller than two chunks. These are usually samples or placeholders and
+/// would produce plenty of collisions.
+pub fn content_hash(path: impl AsRef<Path>) -> std::io::Result<Option<String>> {
+    use std::io::Read;
+    use std::io::Seek;
+    use std::io::SeekFrom;
+
+    let mut file = std::fs::File::open(path)?;
+    let size = file.metadata()?.len();
+
+    if size < HASH_CHUNK_SIZE * 2 {
+        return Ok(None);
+    }
+
+    let mut hash = size;
+    let mut buf = vec![0u8; HASH_CHUNK_SIZE as usize];
+
+    for offset in [0, size - HASH_CHUNK_SIZE] {
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut buf)?;
+
+        hash = buf.chunks_exact(8).fold(hash, |acc, x| {
+            acc.wrapping_add(u64::from_le_bytes(x.try_into().unwrap()))
+        });
+    }
+
+    Ok(Some(format!("{hash:016x}")))
+}
+
+/// Fingerprint of a file on disk. If the fingerprint of a file differs from the one stored in the
+/// database, the file has changed since we last probed it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Fingerprint {
+    /// Size of the file in bytes.
+    pub file_size: i64,
+    /// Modification time in seconds since the unix epoch.
+    pub mtime: Option<i64>,
+    /// Inode of the file, only available on unix.
+    pub inode: Option<i64>,
+}
+
+impl Fingerprint {
+    /// Create a fingerprint from the metadata of a file.
+    pub fn from_metadata(metadata: &FsMetadata) -> Self {
+        #[cfg(unix)]
+        let inode = {
+            use std::os::unix::fs::MetadataExt;
+            Some(metadata.ino() as i64)
+        };
+        #[cfg(not(unix))]
+        let inode = None;
+
+        Self {
+            file_size: metadata.len() as i64,
+            mtime: metadata
+                .modified()
+                .ok()
+                .and_then(|x| x.duration_since(UNIX_EPOCH).ok())
+                .map(|x| x.as_secs() as i64),
+            inode,
+        }
+    }
+
+    /// Obtain the fingerprint of the file at `path`. Returns `None` if the file cant be accessed.
+    pub fn of(path: impl AsRef<Path>) -> Option<Self> {
+        std::fs::metadata(path)
+            .ok()
+            .map(|x| Self::from_metadata(&x))
+    }
+
+    /// Method checks whether this fingerprint matches the one stored in the database.
+    pub fn matches(&self, stored: &MediaFileFingerprint) -> bool {
+        stored.file_size == Some(self.file_size)
+            && stored.mtime == self.mtime
+            && stored.inode == self.inode
+    }
+
+    /// Create a update query which stores this fingerprint and clears the missing flag.
+    pub fn into_update(self) -> UpdateMediaFile {
+        UpdateMediaFile {
+            file_size: Some(self.file_size),
+            mtime: self.mtime,
+            inode: self.inode,
+            missing: Some(false),
+            ..Default::default()
+        }
+    }
 }
 
 /// Struct is responsible for creating `InsertableMediaFile`'s and preparing them for insertions
@@ -131,6 +239,39 @@ impl MediafileCreator {
             }
         }
 
+        self.probe_mediafile(file, metadata).await
+    }
+
+    /// Method constructs a `InsertableMediaFile` without checking whether the file already exists
+    /// in the database. This is used to re-probe files which have changed on disk since they were
+    /// last scanned.
+    #[tracing::instrument(skip(self))]
+    pub async fn probe_mediafile(
+        &self,
+        file: PathBuf,
+        metadata: Metadata,
+    ) -> Result<InsertableMediaFile> {
+        let target_file = file.to_str().ok_or(Error::NonUnicodeFile)?.to_owned();
+        let fingerprint = tokio::fs::metadata(&file)
+            .await
+            .ok()
+            .map(|x| Fingerprint::from_metadata(&x))
+            .unwrap_or_default();
+
+        let hash_file = file.clone();
+        let content_hash = match tokio::task::spawn_blocking(move || content_hash(hash_file)).await
+        {
+            Ok(Ok(x)) => x,
+            Ok(Err(error)) => {
+                warn!(?error, file = &target_file, "Failed to hash file contents.");
+                None
+            }
+            Err(error) => {
+                error!(?error, file = &target_file, "File hashing task panicked.");
+                None
+            }
+        };
+
         // FIXME: This is a huge bottleneck. FFProbe is slow. I'm guessing one of the reasons is
         // that it is not embedded but rather we access an on-disk binary, and the second reason is
         // that we are io-bound here.
@@ -139,14 +280,25 @@ impl MediafileCreator {
         // and does its scheduling. If a user ever needs to obtain the metadata, we can request it
         // and patch it immediately. This would add a initial cost to the API call, but subsequent
         // API calls will be cheap.
-        let video_metadata = match FFProbeCtx::new(&FFPROBE_BIN).get_meta(&target_file).await {
-            Ok(x) => x,
-            Err(error) => {
-                error!(?error, "Couldn't extract media information with ffprobe");
-                error!(file = &target_file, "Assuming file is corrupted.");
-                Default::default()
-            }
-        };
+        let (video_metadata, problem) =
+            match FFProbeCtx::new(&FFPROBE_BIN).get_meta(&target_file).await {
+                Ok(x) => {
+                    let problem = x.problem(needs_video(&file));
+                    (x, problem)
+                }
+                Err(error) => {
+                    error!(?error, "Couldn't extract media information with ffprobe");
+                    error!(file = &target_file, "Assuming file is corrupted.");
+                    let problem = ProbeProblem::FfprobeError(error.to_string());
+                    (Default::default(), Some(problem))
+                }
+            };
+
+        if let Some(problem) = problem.as_ref() {
+            warn!(file = &target_file, %problem, "File can't be played.");
+        }
+
+        let extra_type = super::extras::extra_type(&file);
 
         Ok(InsertableMediaFile {
             library_id: self.library_id,
@@ -175,9 +327,74 @@ impl MediafileCreator {
                 .as_deref()
                 .and_then(crate::utils::lang_from_iso639)
                 .map(ToString::to_string),
+            file_size: Some(fingerprint.file_size),
+            mtime: fingerprint.mtime,
+            inode: fingerprint.inode,
+            content_hash,
+            // NOTE: Stacks are only recognised once a file gets matched to a movie.
+            stack_key: None,
+            stack_part: None,
+            extra_type,
+            edition: metadata.edition,
+            hdr: video_metadata.get_hdr(),
+            parse_confidence: Some(metadata.confidence.into()),
+            probe_problem: problem.map(|x| x.to_string()),
         })
     }
 
+    /// Method probes a mediafile which is already in the database again, ie after it has been
+    /// fixed or replaced on disk, and stores what it found. Fields extracted from the filename are
+    /// left untouched.
+    ///
+    /// # Return
+    /// Method will return the mediafile as it is stored after being probed.
+    #[tracing::instrument(skip(self, mediafile), fields(id = mediafile.id))]
+    pub async fn reprobe(&mut self, mediafile: &MediaFile) -> Result<MediaFile> {
+        let metadata = Metadata {
+            name: mediafile.raw_name.clone(),
+            year: mediafile.raw_year,
+            season: mediafile.season,
+            episode: mediafile.episode,
+            last_episode: None,
+            season_assumed: false,
+            air_date: None,
+            edition: mediafile.edition.clone(),
+            confidence: 0,
+        };
+
+        let probed = self
+            .probe_mediafile(PathBuf::from(&mediafile.target_file), metadata)
+            .await?;
+
+        let mut lock = self.conn.writer().lock_owned().await;
+        let mut tx = dim_database::write_tx(&mut lock)
+            .await
+            .map_err(|e| Error::FailedToAcquireWriter(e.into()))?;
+
+        // NOTE: Whether a file is missing is up to the scanner, a re-probe of a missing file just
+        // records that ffprobe couldn't read it.
+        UpdateMediaFile {
+            missing: None,
+            ..probed_update(&probed)
+        }
+        .update(&mut tx, mediafile.id)
+        .instrument(debug_span!("mediafile_update"))
+        .await
+        .map_err(Error::UpdateFailed)?;
+
+        let mediafile = MediaFile::get_one(&mut tx, mediafile.id)
+            .instrument(debug_span!("mediafile_select"))
+            .await
+            .map_err(Error::SelectFailed)?;
+
+        tx.commit()
+            .instrument(debug_span!("database_commit"))
+            .await
+            .map_err(|e| Error::CommitFailed(e.into()))?;
+
+        Ok(mediafile)
+    }
+
     /// Method will insert a batch of `InsertableMediaFile` within the context of one transaction.
     /// Before inserting a file it will attempt to look up if it is already in the database, and if
     /// so it will skip it.
@@ -236,6 +453,199 @@ impl MediafileCreator {
 
         Ok(work_done)
     }
+
+    /// Method looks for files in `batch` which are the result of a existing mediafile being moved.
+    /// A file has been moved if there is a row in the library with the same content hash whose
+    /// file no longer exists on disk. In this case the old row takes over the new path, keeping
+    /// its media link and thus the watch progress of its media.
+    ///
+    /// # Return
+    /// Method will return the files in `batch` that are actually new, as well as the mediafiles
+    /// that have been relinked to a new path.
+    #[tracing::instrument(skip(self, batch))]
+    pub async fn relink_moved(
+        &mut self,
+        batch: Vec<InsertableMediaFile>,
+    ) -> Result<(Vec<InsertableMediaFile>, Vec<MediaFile>)> {
+        if batch.iter().all(|x| x.content_hash.is_none()) {
+            return Ok((batch, vec![]));
+        }
+
+        // NOTE: Candidates are looked up and checked against the disk before we take the writer
+        // lock, otherwise slow filesystems would stall every other writer.
+        let mut moves = vec![];
+
+        {
+            let mut tx = self
+                .conn
+                .read()
+                .begin()
+                .await
+                .map_err(|e| Error::FailedToAcquireReader(e.into()))?;
+
+            for mediafile in batch {
+                let Some(hash) = mediafile.content_hash.as_deref() else {
+                    moves.push((mediafile, vec![]));
+                    continue;
+                };
+
+                let candidates = MediaFile::get_by_content_hash(&mut tx, self.library_id, hash)
+                    .await
+                    .map_err(Error::SelectFailed)?;
+
+                let mut missing = vec![];
+
+                for candidate in candidates {
+                    if candidate.target_file != mediafile.target_file
+                        && tokio::fs::metadata(&candidate.target_file).await.is_err()
+                    {
+                        missing.push(candidate);
+                    }
+                }
+
+                moves.push((mediafile, missing));
+            }
+        }
+
+        let mut new = vec![];
+        let mut relinked = vec![];
+        let mut claimed = HashSet::new();
+
+        let mut lock = self.conn.writer().lock_owned().await;
+        let mut tx = dim_database::write_tx(&mut lock)
+            .await
+            .map_err(|e| Error::FailedToAcquireWriter(e.into()))?;
+
+        for (mediafile, missing) in moves {
+            // NOTE: prefer rows that have already been matched, this way we keep the most state.
+            let previous = missing
+                .into_iter()
+                .filter(|x| !claimed.contains(&x.id))
+                .max_by_key(|x| x.media_id.is_some());
+
+            let previous = match previous {
+                Some(x) => x,
+                None => {
+                    new.push(mediafile);
+                    continue;
+                }
+            };
+
+            // The row could have been relinked or removed since we looked it up.
+            match MediaFile::get_one(&mut tx, previous.id).await {
+                Ok(x) if x.target_file == previous.target_file => {}
+                _ => {
+                    new.push(mediafile);
+                    continue;
+                }
+            }
+
+            claimed.insert(previous.id);
+
+            debug!(
+                from = &previous.target_file,
+                to = &mediafile.target_file,
+                "Found moved mediafile."
+            );
+
+            UpdateMediaFile {
+                target_file: Some(mediafile.target_file.clone()),
+                raw_name: Some(mediafile.raw_name.clone()),
+                raw_year: mediafile.raw_year,
+                episode: mediafile.episode,
+                season: mediafile.season,
+                ..probed_update(&mediafile)
+            }
+            .update(&mut tx, previous.id)
+            .instrument(debug_span!("mediafile_update"))
+            .await
+            .map_err(Error::UpdateFailed)?;
+
+            relinked.push(
+                MediaFile::get_one(&mut tx, previous.id)
+                    .instrument(debug_span!("mediafile_select"))
+                    .await
+                    .map_err(Error::SelectFailed)?,
+            );
+        }
+
+        tx.commit()
+            .instrument(debug_span!("database_commit"))
+            .await
+            .map_err(|e| Error::CommitFailed(e.into()))?;
+
+        Ok((new, relinked))
+    }
+
+    /// Method will apply a batch of updates to existing mediafiles within the context of one
+    /// transaction.
+    ///
+    /// # Return
+    /// Method will return the number of mediafiles updated.
+    #[tracing::instrument(skip(self, batch))]
+    pub async fn update_batch(
+        &mut self,
+        batch: impl Iterator<Item = (i64, UpdateMediaFile)>,
+    ) -> Result<usize> {
+        let mut updated = 0;
+
+        let mut lock = self.conn.writer().lock_owned().await;
+        let mut tx = dim_database::write_tx(&mut lock)
+            .await
+            .map_err(|e| Error::FailedToAcquireWriter(e.into()))?;
+
+        for (id, update) in batch {
+            update
+                .update(&mut tx, id)
+                .instrument(debug_span!("mediafile_update"))
+                .await
+                .map_err(Error::UpdateFailed)?;
+
+            updated += 1;
+        }
+
+        tx.commit()
+            .instrument(debug_span!("database_commit"))
+            .await
+            .map_err(|e| Error::CommitFailed(e.into()))?;
+
+        Ok(updated)
+    }
+}
+
+/// Function turns a freshly probed mediafile into a update for the row of a file that has changed
+/// on disk. Fields extracted from the filename are left untouched.
+pub fn probed_update(mediafile: &InsertableMediaFile) -> UpdateMediaFile {
+    UpdateMediaFile {
+        quality: mediafile.quality.clone(),
+        codec: mediafile.codec.clone(),
+        container: mediafile.container.clone(),
+        audio: mediafile.audio.clone(),
+        duration: mediafile.duration,
+        channels: mediafile.channels,
+        profile: mediafile.profile.clone(),
+        audio_language: mediafile.audio_language.clone(),
+        corrupt: mediafile.corrupt,
+        file_size: mediafile.file_size,
+        mtime: mediafile.mtime,
+        inode: mediafile.inode,
+        content_hash: mediafile.content_hash.clone(),
+        hdr: mediafile.hdr.clone(),
+        probe_problem: Some(mediafile.probe_problem.clone()),
+        missing: Some(false),
+        ..Default::default()
+    }
+}
+
+/// Function tells whether `file` must have a video stream to be playable. Audio files only need a
+/// audio stream.
+fn needs_video(file: &Path) -> bool {
+    let ext = file
+        .extension()
+        .and_then(OsStr::to_str)
+        .map(str::to_lowercase);
+
+    !ext.map_or(false, |x| super::SUPPORTED_AUDIO_EXTS.contains(&x.as_str()))
 }
 
 #[async_trait]
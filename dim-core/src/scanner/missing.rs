@@ -0,0 +1,122 @@
+//! Module contains the purging of mediafiles that have gone missing.
+//!
+//! Files that can't be found anymore are only flagged as missing, so that a drive or share going
+//! offline doesn't wipe the matched media along with everyone's progress. The watcher and rescans
+//! restore the files once they reappear. Files that are still missing once the grace period from
+//! the settings is over get purged, together with any media left without files.
+
+use super::matcher_for;
+use super::Error;
+use crate::settings::get_global_settings;
+
+use chrono::Utc;
+
+use dim_database::library::Library;
+use dim_database::mediafile::MediaFile;
+use dim_database::DbConnection;
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use tracing::info;
+use tracing::instrument;
+use tracing::warn;
+
+/// How often we check for missing files whose grace period is over.
+const PURGE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// Function removes the mediafiles that went missing before `before`, and cleans up the media
+/// objects left without mediafiles. If `library_id` is supplied only files of that library are
+/// removed. Returns the mediafiles that have been removed.
+pub async fn purge_missing(
+    conn: &DbConnection,
+    library_id: Option<i64>,
+    before: i64,
+) -> Result<Vec<MediaFile>, Error> {
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = dim_database::write_tx(&mut lock)
+        .await
+        .map_err(|e| Error::DatabaseError(e.into()))?;
+
+    let files = MediaFile::get_missing(&mut tx, before)
+        .await
+        .map_err(Error::PurgeMissing)?
+        .into_iter()
+        .filter(|x| library_id.map_or(true, |id| x.library_id == id))
+        .collect::<Vec<_>>();
+
+    if files.is_empty() {
+        return Ok(files);
+    }
+
+    let media_types = Library::get_all(&mut tx)
+        .await
+        .into_iter()
+        .map(|x| (x.id, x.media_type))
+        .collect::<HashMap<_, _>>();
+
+    let mut media_ids = HashSet::new();
+
+    for file in files.iter() {
+        // NOTE: Episodes after the first one of a file spanning several episodes are only linked
+        // to the file, so we have to collect them before the links get dropped with the file.
+        for episode_id in MediaFile::get_linked_episodes(&mut tx, file.id)
+            .await
+            .map_err(Error::PurgeMissing)?
+        {
+            media_ids.insert((file.library_id, episode_id));
+        }
+
+        MediaFile::delete(&mut tx, file.id)
+            .await
+            .map_err(Error::PurgeMissing)?;
+
+        // NOTE: extras dont own their parent, so the parent only gets purged through its own
+        // mediafiles.
+        if let (Some(media_id), None) = (file.media_id, file.extra_type.as_ref()) {
+            media_ids.insert((file.library_id, media_id));
+        }
+    }
+
+    for (library_id, media_id) in media_ids {
+        let Some(matcher) = media_types.get(&library_id).copied().and_then(matcher_for) else {
+            continue;
+        };
+
+        matcher.cleanup(&mut tx, media_id).await?;
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| Error::DatabaseError(e.into()))?;
+
+    Ok(files)
+}
+
+/// Function runs forever and purges the missing files whose grace period is over.
+#[instrument(skip_all)]
+pub async fn run_purger(conn: DbConnection) {
+    let mut interval = tokio::time::interval(PURGE_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let days = get_global_settings().missing_grace_period_days;
+        if days == 0 {
+            continue;
+        }
+
+        let grace_period = i64::try_from(days)
+            .unwrap_or(i64::MAX)
+            .saturating_mul(24 * 60 * 60);
+        let before = Utc::now().timestamp().saturating_sub(grace_period);
+
+        match purge_missing(&conn, None, before).await {
+            Ok(files) if !files.is_empty() => {
+                info!(purged = files.len(), "Purged missing mediafiles.")
+            }
+            Ok(_) => {}
+            Err(error) => warn!(?error, "Failed to purge missing mediafiles."),
+        }
+    }
+}
@@ -1,49 +1,86 @@
 //! Module contains all the code for the new generation media scanner.
 
+pub mod artwork;
+pub mod confidence;
 pub mod daemon;
 pub mod error;
+pub mod extras;
+pub mod filter;
+pub mod folders;
+pub mod jobs;
 mod mediafile;
+pub mod missing;
 pub mod movie;
+pub mod music;
+pub mod poller;
+pub mod problems;
+pub mod progress;
+pub mod schedule;
+pub mod stacking;
+pub mod subtitles;
 #[cfg(test)]
-mod tests;
+pub(crate) mod tests;
 pub mod tv_show;
 
+use self::folders::FolderHints;
+use self::mediafile::probed_update;
 use self::mediafile::Error as CreatorError;
+use self::mediafile::Fingerprint;
 use self::mediafile::MediafileCreator;
+use self::problems::problem_file;
 use crate::core::EventTx;
 
 use async_trait::async_trait;
 
+use dim_database::library::FilenameRules;
 use dim_database::library::Library;
 use dim_database::library::MediaType;
 use dim_database::mediafile::InsertableMediaFile;
 use dim_database::mediafile::MediaFile;
+use dim_database::mediafile::MediaFileFingerprint;
+use dim_database::mediafile::UpdateMediaFile;
 
+use dim_events::ProblemFile;
+use dim_events::ScanPhase;
+
+use dim_extern_api::filename::split_air_date;
 use dim_extern_api::filename::Anitomy;
 use dim_extern_api::filename::CombinedExtractor;
 use dim_extern_api::filename::FilenameMetadata;
 use dim_extern_api::filename::Metadata;
+use dim_extern_api::filename::RegexExtractor;
 use dim_extern_api::filename::TorrentMetadata;
 use dim_extern_api::ExternalQueryIntoShow;
 
-use futures::FutureExt;
+use futures::future;
+use futures::StreamExt;
 use ignore::WalkBuilder;
-use itertools::Itertools;
+use serde::Serialize;
 
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::collections::HashSet;
 use std::ffi::OsStr;
-use std::future::Future;
+use std::iter;
 use std::path::Path;
 use std::path::PathBuf;
-use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 use std::time::Instant;
 
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
 use tracing::error;
 use tracing::info;
 use tracing::instrument;
 use tracing::warn;
 
 pub use error::Error;
+pub use filter::ScanFilter;
+pub use jobs::scan_jobs;
+pub use jobs::CancelToken;
+pub use progress::ScanProgress;
 
 pub(super) static SUPPORTED_EXTS: &[&str] = &[
     "001", "3g2", "3gp", "amv", "asf", "asx", "avi", "bin", "bivx", "divx", "dv", "dvr-ms", "f4v",
@@ -53,18 +90,64 @@ pub(super) static SUPPORTED_EXTS: &[&str] = &[
     "wtv", "xvid",
 ];
 
-/// Function recursively walks the paths passed and returns all files in those directories.
+/// Number of ffprobe instances the scanner will run concurrently.
+const PROBE_WORKERS: usize = 8;
+/// Maximum number of mediafiles that get inserted and matched in one transaction.
+const INSERT_BATCH_SIZE: usize = 128;
+/// How long the scanner will wait for a insert batch to fill up before flushing it.
+const INSERT_BATCH_TIMEOUT: Duration = Duration::from_millis(500);
+
+pub(super) static SUPPORTED_AUDIO_EXTS: &[&str] = &[
+    "aac", "aif", "aiff", "alac", "ape", "dsf", "flac", "m4a", "mka", "mp3", "mpc", "oga", "ogg",
+    "opus", "wav", "wma", "wv",
+];
+
+/// Function returns the file extensions we should pick up for a library of `media_type`.
+pub fn supported_exts(media_type: MediaType) -> &'static [&'static str] {
+    match media_type {
+        MediaType::Music => SUPPORTED_AUDIO_EXTS,
+        _ => SUPPORTED_EXTS,
+    }
+}
+
+/// Function recursively walks the paths passed and returns all files in those directories that
+/// pass the scan rules of the library.
 /// FIXME: THIS IS NOT ASYNC-SAFE!!!
-/// NOTE: I've noticed that walking a directory mounted over ssh is very slow, 80 files in like 300
-/// seconds. Doubt theres a way to fix this but we could alliviate the UX-degradation by sending
-/// the files over a channel instead of returning them at once.
-pub fn get_subfiles(paths: impl Iterator<Item = impl AsRef<Path>>) -> Vec<PathBuf> {
+pub fn get_subfiles(
+    paths: impl Iterator<Item = impl AsRef<Path>>,
+    filter: &ScanFilter,
+) -> Vec<PathBuf> {
     let mut files = Vec::with_capacity(2048);
+    walk_subfiles(paths, filter, |file| {
+        files.push(file);
+        true
+    });
+
+    files
+}
+
+/// Function recursively walks the paths passed and calls `on_file` for every supported file as
+/// soon as it is found. Walking stops early if `on_file` returns `false`.
+///
+/// NOTE: I've noticed that walking a directory mounted over ssh is very slow, 80 files in like 300
+/// seconds. This is why the scanner consumes files as they are found rather than waiting for the
+/// whole walk to finish.
+pub fn walk_subfiles(
+    paths: impl Iterator<Item = impl AsRef<Path>>,
+    filter: &ScanFilter,
+    mut on_file: impl FnMut(PathBuf) -> bool,
+) {
     for path in paths {
-        let mut subfiles = WalkBuilder::new(path)
-            // we want to follow all symlinks in case of complex dir structures
-            .follow_links(true)
+        let excludes = filter.clone();
+        let subfiles = WalkBuilder::new(path)
+            // by default we follow all symlinks in case of complex dir structures
+            .follow_links(filter.follow_symlinks())
             .add_custom_ignore_filename(".plexignore")
+            // skip excluded directories as a whole instead of walking them.
+            .filter_entry(move |f| {
+                let is_dir = f.file_type().map_or(false, |x| x.is_dir());
+                !excludes.is_excluded(f.path(), is_dir)
+            })
             .build()
             .filter_map(Result::ok)
             // ignore all hidden files.
@@ -73,24 +156,61 @@ pub fn get_subfiles(paths: impl Iterator<Item = impl AsRef<Path>>) -> Vec<PathBu
                     .iter()
                     .any(|s| s.to_str().map(|x| x.starts_with('.')).unwrap_or(false))
             })
-            // check whether `f` has a supported extension
-            .filter(|f| {
-                f.path()
-                    .extension()
-                    .and_then(|e| e.to_str())
-                    .map_or(false, |e| SUPPORTED_EXTS.contains(&e))
-            })
-            .map(|f| f.into_path())
-            .collect();
+            // check whether `f` has a supported extension and passes the scan rules
+            .filter(|f| filter.accepts(f.path()))
+            .map(|f| f.into_path());
 
-        files.append(&mut subfiles);
+        for file in subfiles {
+            if !on_file(file) {
+                return;
+            }
+        }
     }
+}
 
-    files
+/// Parser extracting metadata from the filenames of the files of a library.
+#[derive(Clone, Debug)]
+pub struct FilenameParser {
+    media_type: MediaType,
+    /// Filename rules of the library.
+    rules: RegexExtractor,
+    /// Locations of the library, folders above them dont tell us anything about a file.
+    roots: Vec<PathBuf>,
 }
 
+impl FilenameParser {
+    /// Method compiles the filename rules of a library stored at `locations`. Invalid rules are
+    /// skipped so that a single bad rule doesnt stop the library from being scanned.
+    pub fn new(
+        media_type: MediaType,
+        rules: &FilenameRules,
+        locations: &[impl AsRef<Path>],
+    ) -> Self {
+        let valid = rules
+            .0
+            .iter()
+            .filter(|rule| match RegexExtractor::new([rule]) {
+                Ok(_) => true,
+                Err(error) => {
+                    warn!(%error, "Skipping invalid filename rule.");
+                    false
+                }
+            });
+
+        Self {
+            media_type,
+            rules: RegexExtractor::new(valid).unwrap_or_default(),
+            roots: locations.iter().map(|x| x.as_ref().to_path_buf()).collect(),
+        }
+    }
+}
+
+/// Function extracts metadata from the filenames of `files`. Metadata extracted with the filename
+/// rules of the library comes first, followed by the results of the built-in parsers and the hints
+/// of the folders the file is stored in, ranked by our confidence in them.
 pub fn parse_filenames(
     files: impl Iterator<Item = impl AsRef<Path>>,
+    parser: &FilenameParser,
 ) -> Vec<(PathBuf, Vec<Metadata>)> {
     let mut metadata = Vec::new();
 
@@ -103,27 +223,172 @@ pub fn parse_filenames(
             }
         };
 
-        let metas = IntoIterator::into_iter([
-            TorrentMetadata::from_str(&filename),
-            Anitomy::from_str(&filename),
-            CombinedExtractor::from_str(&filename),
+        let rule = parser.rules.extract(&filename);
+        let pinned = usize::from(rule.is_some());
+
+        // NOTE: Only episodes of daily shows are named by their air date, elsewhere a date is
+        // more likely to be part of the title or the release date of a movie.
+        let (name, air_date) = match parser.media_type {
+            MediaType::Tv => split_air_date(filename),
+            _ => (filename.to_owned(), None),
+        };
+
+        let parsed = IntoIterator::into_iter([
+            TorrentMetadata::from_str(&name),
+            Anitomy::from_str(&name),
+            CombinedExtractor::from_str(&name),
         ])
-        .filter_map(|x| x)
-        .collect::<Vec<_>>();
+        .flatten()
+        .map(|meta| Metadata { air_date, ..meta });
+
+        let mut metas = rule.into_iter().chain(parsed).collect::<Vec<_>>();
+
+        let hints = folders::folder_hints(file.as_ref(), &parser.roots);
+
+        // NOTE: Episodes often only carry a episode number, in which case their season folder
+        // tells us which season they belong in, ie season 0 for specials.
+        if let (MediaType::Tv, Some(season)) = (parser.media_type, hints.season) {
+            for meta in metas
+                .iter_mut()
+                .filter(|x| x.season_assumed || x.season.is_none())
+            {
+                meta.season = Some(season);
+                meta.season_assumed = false;
+            }
+        }
+
+        for meta in metas.iter_mut().filter(|x| x.year.is_none()) {
+            meta.year = hints.year;
+        }
+
+        if let Some(hint) = folder_candidate(filename, &metas, &hints, parser.media_type) {
+            metas.push(hint);
+        }
 
         if metas.is_empty() {
             warn!(file = ?file.as_ref(), "Failed to parse the filename and extract metadata.");
             continue;
         }
 
+        // NOTE: The filename rules were set up by the user for a reason, so their result stays
+        // first no matter how confident we are in it.
+        confidence::rate(file.as_ref(), &mut metas, parser.media_type);
+        metas[pinned..].sort_by_key(|x| Reverse(x.confidence));
+
         metadata.push((file.as_ref().into(), metas));
     }
 
     metadata
 }
 
+/// Function returns a candidate named after the folder the file `filename` is stored in, taking
+/// the episode from the candidates parsed from the filename. In tv libraries filenames which dont
+/// make sense to any of the parsers, ie `01`, are taken as bare episode numbers.
+fn folder_candidate(
+    filename: &str,
+    metas: &[Metadata],
+    hints: &FolderHints,
+    media_type: MediaType,
+) -> Option<Metadata> {
+    let title = hints.title.clone()?;
+
+    let mut candidate = metas
+        .iter()
+        .find(|x| x.episode.is_some() || x.air_date.is_some())
+        .or_else(|| metas.first())
+        .cloned()
+        .unwrap_or_else(|| Metadata {
+            name: String::new(),
+            year: None,
+            season: None,
+            episode: None,
+            last_episode: None,
+            season_assumed: false,
+            air_date: None,
+            edition: None,
+            confidence: 0,
+        });
+
+    candidate.name = title;
+    // NOTE: The year of the filename might not belong to the folder, ie a collection folder.
+    candidate.year = hints.year;
+
+    if media_type == MediaType::Tv {
+        if candidate.episode.is_none() && candidate.air_date.is_none() {
+            candidate.episode = bare_episode(filename);
+        }
+
+        if candidate.season_assumed || candidate.season.is_none() {
+            candidate.season = hints.season.or(candidate.episode.map(|_| 1));
+            candidate.season_assumed = hints.season.is_none() && candidate.season.is_some();
+        }
+    }
+
+    Some(candidate)
+}
+
+/// Function returns the episode number of a filename that is nothing but a episode number, ie
+/// `01` or `E01`.
+fn bare_episode(filename: &str) -> Option<i64> {
+    let filename = filename.trim().to_lowercase();
+
+    ["episode", "ep", "e"]
+        .iter()
+        .find_map(|x| filename.strip_prefix(x))
+        .unwrap_or(filename.as_str())
+        .trim_start_matches([' ', '.', '_', '-'])
+        .parse()
+        .ok()
+}
+
 pub struct WorkUnit(pub MediaFile, pub Vec<Metadata>);
 
+/// Function compiles the scan rules of a library.
+pub async fn scan_filter(
+    conn: &dim_database::DbConnection,
+    library_id: i64,
+    media_type: MediaType,
+) -> Result<ScanFilter, Error> {
+    let mut tx = conn
+        .read()
+        .begin()
+        .await
+        .map_err(|e| Error::DatabaseError(e.into()))?;
+
+    let library = Library::get_one(&mut tx, library_id)
+        .await
+        .map_err(Error::LibraryNotFound)?;
+
+    Ok(ScanFilter::new(
+        media_type,
+        library.scan_rules,
+        &library.locations,
+    ))
+}
+
+/// Function creates the filename parser of a library.
+pub async fn filename_parser(
+    conn: &dim_database::DbConnection,
+    library_id: i64,
+    media_type: MediaType,
+) -> Result<FilenameParser, Error> {
+    let mut tx = conn
+        .read()
+        .begin()
+        .await
+        .map_err(|e| Error::DatabaseError(e.into()))?;
+
+    let library = Library::get_one(&mut tx, library_id)
+        .await
+        .map_err(Error::LibraryNotFound)?;
+
+    Ok(FilenameParser::new(
+        media_type,
+        &library.filename_rules,
+        &library.locations,
+    ))
+}
+
 /// Trait that must be implemented by a media matcher. Matchers are responsible for fetching their
 /// own external metadata but it is provided a metadata provider at initialization time.
 #[async_trait]
@@ -143,65 +408,519 @@ pub trait MediaMatcher: Send + Sync {
         work: WorkUnit,
         external_id: &str,
     ) -> Result<(), Error>;
+
+    /// Method removes the media object `media_id`, and its parents, if mediafiles have been removed
+    /// from it and it has no children left.
+    async fn cleanup(
+        &self,
+        tx: &mut dim_database::Transaction<'_>,
+        media_id: i64,
+    ) -> Result<(), Error>;
+
+    /// Method takes the writer lock, matches `work` with [`batch_match`](Self::batch_match) and
+    /// commits the matches made. Matchers which have to do slow work that doesnt touch the
+    /// database, ie spawning ffmpeg, override this to do it before they take the lock.
+    async fn match_and_commit(
+        &self,
+        conn: &dim_database::DbConnection,
+        provider: Arc<dyn ExternalQueryIntoShow>,
+        work: Vec<WorkUnit>,
+    ) -> Result<(), Error> {
+        let mut lock = conn.writer().lock_owned().await;
+        let mut tx = dim_database::write_tx(&mut lock)
+            .await
+            .map_err(|e| Error::DatabaseError(e.into()))?;
+
+        // NOTE: matches made before a error are kept.
+        let result = self.batch_match(&mut tx, provider, work).await;
+
+        tx.commit()
+            .await
+            .map_err(|e| Error::DatabaseError(e.into()))?;
+
+        result
+    }
 }
 
-pub async fn insert_mediafiles(
-    conn: &mut dim_database::DbConnection,
+/// Function returns the matcher for libraries of `media_type`, or `None` if we can't match media
+/// of that type.
+pub fn matcher_for(media_type: MediaType) -> Option<Arc<dyn MediaMatcher>> {
+    match media_type {
+        MediaType::Movie => Some(Arc::new(movie::MovieMatcher)),
+        MediaType::Tv => Some(Arc::new(tv_show::TvMatcher)),
+        MediaType::Music => Some(Arc::new(music::MusicMatcher)),
+        _ => None,
+    }
+}
+
+/// Files of a insert batch that have been stored.
+struct Inserted {
+    /// Stored files that must be handed to the matcher.
+    units: Vec<WorkUnit>,
+    /// Number of new files that have been inserted.
+    added: usize,
+    /// Ids of the files that have been found under a new path.
+    moved: Vec<i64>,
+    /// Problems of the files stored.
+    problems: Vec<ProblemFile>,
+}
+
+/// Function stores the freshly probed files of `new`. Files which turn out to have been moved are
+/// relinked to their old entry instead, and only get matched again if they weren't matched before.
+async fn insert_new(
+    creator: &mut MediafileCreator,
+    new: Vec<(InsertableMediaFile, Vec<Metadata>)>,
+) -> Result<Inserted, Error> {
+    let (insertables, metadata): (Vec<_>, Vec<_>) = new.into_iter().unzip();
+    let mut metadata = insertables
+        .iter()
+        .map(|x| x.target_file.clone())
+        .zip(metadata)
+        .collect::<HashMap<_, _>>();
+
+    let (insertables, relinked) = creator.relink_moved(insertables).await?;
+
+    // NOTE: `insert_batch` skips files that already exist so we cant just zip the results with our
+    // metadata.
+    let inserted = creator.insert_batch(insertables.iter()).await?;
+
+    Ok(Inserted {
+        added: inserted.len(),
+        moved: relinked.iter().map(|x| x.id).collect(),
+        problems: relinked
+            .iter()
+            .chain(&inserted)
+            .filter_map(problem_file)
+            .collect(),
+        units: relinked
+            .into_iter()
+            .filter(|x| x.media_id.is_none())
+            .chain(inserted)
+            .filter_map(|mfile| {
+                let metadata = metadata.remove(&mfile.target_file)?;
+                Some(WorkUnit(mfile, metadata))
+            })
+            .collect(),
+    })
+}
+
+/// Function probes and stores a single new file of a library, ie one the filesystem watcher has
+/// picked up, the same way a scan would. Returns the work the matcher has to do for it, which is
+/// empty if the file is already known.
+pub async fn insert_file(
+    conn: &dim_database::DbConnection,
     library_id: i64,
-    dirs: Vec<impl AsRef<Path> + Send + 'static>,
+    media_type: MediaType,
+    path: PathBuf,
+    progress: &ScanProgress,
 ) -> Result<Vec<WorkUnit>, Error> {
-    let now = Instant::now();
-    let subfiles = tokio::task::spawn_blocking(|| get_subfiles(dirs.into_iter()))
+    let parser = filename_parser(conn, library_id, media_type).await?;
+
+    progress.discovered(&path);
+
+    let Some((path, metadata)) = parse_filenames(iter::once(path), &parser).pop() else {
+        return Ok(vec![]);
+    };
+
+    let mut creator = MediafileCreator::new(conn.clone(), library_id).await;
+
+    let mediafile = match creator
+        .construct_mediafile(path.clone(), metadata[0].clone())
         .await
-        .unwrap();
-    let elapsed = now.elapsed();
+    {
+        Ok(x) => x,
+        Err(CreatorError::FileExists) => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
 
-    info!(
-        elapsed_ms = elapsed.as_millis(),
-        files = subfiles.len(),
-        "Walked all target directories."
-    );
+    progress.probed(&path);
+
+    let inserted = insert_new(&mut creator, vec![(mediafile, metadata)]).await?;
+    progress.problems(inserted.problems);
 
-    let parsed = parse_filenames(subfiles.iter());
+    Ok(inserted.units)
+}
 
-    let mut instance = MediafileCreator::new(conn.clone(), library_id).await;
+/// Summary of the changes a scan has made to a library.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct ScanSummary {
+    /// Number of new mediafiles that have been inserted and handed to the matcher.
+    pub added: usize,
+    /// Number of existing mediafiles that have changed on disk and have been re-probed.
+    pub changed: usize,
+    /// Number of mediafiles that couldn't be found on disk anymore.
+    pub missing: usize,
+    /// Number of mediafiles that have been found under a new path.
+    pub moved: usize,
+}
 
-    let insertable_futures =
-        parsed
-            .clone()
-            .into_iter()
-            .map(|(path, meta)| instance.construct_mediafile(path, meta[0].clone()).boxed())
-            .chunks(4)
+/// A file found by the directory walker that must be probed.
+enum ProbeItem {
+    /// File is not in the database yet.
+    New(PathBuf),
+    /// File is in the database, but has changed since it was last probed.
+    Changed(MediaFileFingerprint, PathBuf),
+}
+
+/// Work the directory walker leaves behind for after the scan.
+#[derive(Default)]
+struct Reconcile {
+    /// Unchanged files whose fingerprint must be stored or whose missing flag must be cleared.
+    touched: Vec<(i64, Fingerprint)>,
+    /// Files which are in the database but weren't found on disk.
+    missing: Vec<MediaFileFingerprint>,
+}
+
+/// Function runs the scan of `dirs` as a pipeline of concurrent stages connected by channels:
+///
+/// 1. A blocking task walks the directories and compares every supported file it finds against
+///    the fingerprints stored in the database. Only new or changed files are sent downstream.
+/// 2. The filenames are parsed and up to `PROBE_WORKERS` ffprobe instances extract stream info.
+/// 3. Probed files are inserted or updated in batches of at most `INSERT_BATCH_SIZE`. A batch is
+///    flushed early if no new files arrived within `INSERT_BATCH_TIMEOUT`.
+/// 4. Each batch of newly inserted files is handed to the matcher.
+///
+/// This way the first titles get matched within seconds instead of having to wait for the whole
+/// library to be walked and probed. Every channel is bounded, so a slow stage will apply
+/// backpressure to the stages before it.
+///
+/// Once all stages have finished, files which weren't found in any of the directories walked are
+/// flagged as missing and the subtitle sidecars of the library are re-indexed.
+async fn scan_pipeline(
+    conn: &mut dim_database::DbConnection,
+    library_id: i64,
+    media_type: MediaType,
+    dirs: Vec<impl AsRef<Path> + Send + 'static>,
+    matcher: Arc<dyn MediaMatcher>,
+    provider: Arc<dyn ExternalQueryIntoShow>,
+    progress: Arc<ScanProgress>,
+    cancel: CancelToken,
+) -> Result<ScanSummary, Error> {
+    let mut existing = {
+        let mut tx = conn
+            .read()
+            .begin()
+            .await
+            .map_err(|e| Error::DatabaseError(e.into()))?;
+
+        MediaFile::get_fingerprints_by_lib(&mut tx, library_id)
+            .await
+            .map_err(Error::FingerprintsUnavailable)?
             .into_iter()
-            .map(|chunk| chunk.collect())
-            .collect::<Vec<
-                Vec<
-                    Pin<Box<dyn Future<Output = Result<InsertableMediaFile, CreatorError>> + Send>>,
-                >,
-            >>();
+            .map(|x| (x.target_file.clone(), x))
+            .collect::<HashMap<_, _>>()
+    };
+
+    let filter = scan_filter(conn, library_id, media_type).await?;
+    let parser = filename_parser(conn, library_id, media_type).await?;
+
+    let (path_tx, path_rx) = mpsc::channel::<ProbeItem>(1024);
+    let (probe_tx, probe_rx) = mpsc::channel::<(
+        Option<MediaFileFingerprint>,
+        InsertableMediaFile,
+        Vec<Metadata>,
+    )>(256);
+    let (unit_tx, mut unit_rx) = mpsc::channel::<Vec<WorkUnit>>(4);
+
+    let walk_progress = progress.clone();
+    let walk_cancel = cancel.clone();
+    let walker = tokio::task::spawn_blocking(move || {
+        let now = Instant::now();
+        let mut files = 0usize;
+        let mut reconcile = Reconcile::default();
+
+        // NOTE: If a location is unreachable (ie a unmounted network share) we dont want to flag
+        // all of its files as missing.
+        let roots = dirs
+            .iter()
+            .map(|x| x.as_ref().to_path_buf())
+            .filter(|root| {
+                let exists = root.exists();
+                if !exists {
+                    warn!(?root, "Library location doesn't exist, skipping.");
+                }
+                exists
+            })
+            .collect::<Vec<_>>();
+
+        walk_subfiles(roots.iter(), &filter, |file| {
+            if walk_cancel.is_cancelled() {
+                return false;
+            }
+
+            files += 1;
+            walk_progress.discovered(&file);
+
+            let item = match file.to_str().and_then(|x| existing.remove(x)) {
+                None => ProbeItem::New(file),
+                Some(stored) => {
+                    let fingerprint = match Fingerprint::of(&file) {
+                        Some(x) => x,
+                        None => return true,
+                    };
 
-    let mut insertables = vec![];
+                    // NOTE: Files scanned before we started storing fingerprints are assumed to
+                    // be unchanged, we just store their fingerprint.
+                    if stored.file_size.is_none() || fingerprint.matches(&stored) {
+                        if stored.file_size.is_none() || stored.missing {
+                            reconcile.touched.push((stored.id, fingerprint));
+                        }
 
-    for chunk in insertable_futures.into_iter() {
-        let results: Vec<Result<InsertableMediaFile, CreatorError>> =
-            futures::future::join_all(chunk).await;
+                        return true;
+                    }
 
-        for result in results {
-            insertables.push(result?);
+                    ProbeItem::Changed(stored, file)
+                }
+            };
+
+            // the receiving end only goes away if a later stage has failed.
+            path_tx.blocking_send(item).is_ok()
+        });
+
+        reconcile.missing = existing
+            .into_values()
+            .filter(|x| {
+                roots
+                    .iter()
+                    .any(|root| Path::new(&x.target_file).starts_with(root))
+            })
+            .collect();
+
+        info!(
+            library_id,
+            elapsed_ms = now.elapsed().as_millis(),
+            files,
+            "Walked all target directories."
+        );
+
+        walk_progress.set_phase(ScanPhase::Probing);
+
+        reconcile
+    });
+
+    let creator = Arc::new(MediafileCreator::new(conn.clone(), library_id).await);
+    let probe_progress = progress.clone();
+    let probe_stage = async move {
+        let mut probed = ReceiverStream::new(path_rx)
+            .filter_map(|item| {
+                let (stored, path) = match item {
+                    ProbeItem::New(path) => (None, path),
+                    ProbeItem::Changed(stored, path) => (Some(stored), path),
+                };
+
+                future::ready(
+                    parse_filenames(iter::once(path), &parser)
+                        .pop()
+                        .map(|(path, metadata)| (stored, path, metadata)),
+                )
+            })
+            .map(|(stored, path, metadata)| {
+                let creator = creator.clone();
+                async move {
+                    let result = match stored {
+                        Some(_) => {
+                            creator
+                                .probe_mediafile(path.clone(), metadata[0].clone())
+                                .await
+                        }
+                        None => {
+                            creator
+                                .construct_mediafile(path.clone(), metadata[0].clone())
+                                .await
+                        }
+                    };
+
+                    (stored, path, result, metadata)
+                }
+            })
+            .buffer_unordered(PROBE_WORKERS);
+
+        while let Some((stored, path, result, metadata)) = probed.next().await {
+            match result {
+                Ok(mediafile) => {
+                    probe_progress.probed(&path);
+
+                    if probe_tx.send((stored, mediafile, metadata)).await.is_err() {
+                        break;
+                    }
+                }
+                Err(CreatorError::FileExists) => {}
+                Err(error) => {
+                    warn!(?error, ?path, "Failed to construct mediafile.");
+                    probe_progress.failed(&path, error);
+                }
+            }
         }
-    }
 
-    let mut mediafiles = vec![];
+        // NOTE: the walker finishes before the probe stage does.
+        probe_progress.set_phase(ScanPhase::Matching);
+
+        Ok::<_, Error>(())
+    };
+
+    let insert_conn = conn.clone();
+    let insert_progress = progress.clone();
+    let insert_cancel = cancel.clone();
+    let insert_stage = async move {
+        let problem_conn = insert_conn.clone();
+        let mut creator = MediafileCreator::new(insert_conn, library_id).await;
+        let mut added = 0;
+        let mut changed = 0;
+        let mut moved = HashSet::new();
+        let batches = tokio_stream::StreamExt::chunks_timeout(
+            ReceiverStream::new(probe_rx),
+            INSERT_BATCH_SIZE,
+            INSERT_BATCH_TIMEOUT,
+        );
+        tokio::pin!(batches);
+
+        while let Some(batch) = batches.next().await {
+            if insert_cancel.is_cancelled() {
+                break;
+            }
+
+            let (new, updated): (Vec<_>, Vec<_>) = batch.into_iter().partition(|x| x.0.is_none());
+
+            // NOTE: Files which still have the problem they were stored with have already been
+            // reported, so we only report files whose problem is new or has changed.
+            let reprobed = updated
+                .iter()
+                .filter_map(|(stored, mfile, _)| {
+                    let stored = stored.as_ref()?;
+                    (mfile.probe_problem.is_some() && mfile.probe_problem != stored.probe_problem)
+                        .then_some(stored.id)
+                })
+                .collect::<Vec<_>>();
 
-    for chunk in insertables.chunks(256) {
-        mediafiles.append(&mut instance.insert_batch(chunk.iter()).await?);
+            if !updated.is_empty() {
+                let updates = updated
+                    .into_iter()
+                    .filter_map(|(stored, mfile, _)| Some((stored?.id, probed_update(&mfile))));
+
+                changed += creator.update_batch(updates).await?;
+            }
+
+            let mut problems = if reprobed.is_empty() {
+                Vec::new()
+            } else {
+                let mut tx = problem_conn
+                    .read()
+                    .begin()
+                    .await
+                    .map_err(|e| Error::DatabaseError(e.into()))?;
+
+                MediaFile::get_many(&mut tx, &reprobed)
+                    .await
+                    .map_err(Error::MediafileNotFound)?
+                    .iter()
+                    .filter_map(problem_file)
+                    .collect::<Vec<_>>()
+            };
+
+            let new = new
+                .into_iter()
+                .map(|(_, mfile, meta)| (mfile, meta))
+                .collect();
+            let inserted = insert_new(&mut creator, new).await?;
+
+            added += inserted.added;
+            moved.extend(inserted.moved);
+            problems.extend(inserted.problems);
+            insert_progress.problems(problems);
+
+            let units = inserted.units;
+            if !units.is_empty() && unit_tx.send(units).await.is_err() {
+                break;
+            }
+        }
+
+        Ok::<_, Error>((added, changed, moved))
+    };
+
+    let match_conn = conn.clone();
+    let match_progress = progress.clone();
+    let match_cancel = cancel.clone();
+    let match_stage = async move {
+        while let Some(units) = unit_rx.recv().await {
+            if match_cancel.is_cancelled() {
+                break;
+            }
+
+            let files = units
+                .iter()
+                .map(|x| x.0.target_file.clone())
+                .collect::<Vec<_>>();
+
+            match matcher
+                .match_and_commit(&match_conn, provider.clone(), units)
+                .await
+            {
+                Ok(()) => match_progress.matched(files.len()),
+                Err(e) => {
+                    error!(error = ?e, "Failed to match batch of mediafiles.");
+                    for file in files {
+                        match_progress.failed(file, &e);
+                    }
+                }
+            }
+        }
+
+        Ok::<_, Error>(())
+    };
+
+    let (_, (added, changed, moved), _) =
+        futures::try_join!(probe_stage, insert_stage, match_stage)?;
+
+    // NOTE: the walker only panics if the closures passed panic.
+    let mut reconcile = walker.await.expect("Directory walker panicked.");
+
+    // NOTE: the walk of a cancelled scan is incomplete, so we cant tell which files are missing.
+    if cancel.is_cancelled() {
+        return Err(Error::Cancelled);
     }
 
-    Ok(mediafiles
+    // files that have been moved obviously arent missing anymore.
+    reconcile.missing.retain(|x| !moved.contains(&x.id));
+    let missing = reconcile.missing.len();
+
+    let updates = reconcile
+        .touched
         .into_iter()
-        .zip(parsed.into_iter())
-        .map(|(mfile, (_, metadata))| WorkUnit(mfile, metadata))
-        .collect())
+        .map(|(id, fingerprint)| (id, fingerprint.into_update()))
+        .chain(
+            reconcile
+                .missing
+                .into_iter()
+                .filter(|x| !x.missing)
+                .map(|x| {
+                    (
+                        x.id,
+                        UpdateMediaFile {
+                            missing: Some(true),
+                            ..Default::default()
+                        },
+                    )
+                }),
+        )
+        .collect::<Vec<_>>();
+
+    if !updates.is_empty() {
+        let mut creator = MediafileCreator::new(conn.clone(), library_id).await;
+        creator.update_batch(updates.into_iter()).await?;
+    }
+
+    if media_type != MediaType::Music {
+        if let Err(error) = subtitles::index_sidecars(conn, library_id).await {
+            warn!(?error, library_id, "Failed to index subtitle sidecars.");
+        }
+    }
+
+    Ok(ScanSummary {
+        added,
+        changed,
+        missing,
+        moved: moved.len(),
+    })
 }
 
 #[instrument(skip(conn, dirs, tx))]
@@ -212,7 +931,10 @@ pub async fn start_custom(
     tx: EventTx,
     media_type: MediaType,
     provider: Arc<dyn ExternalQueryIntoShow>,
-) -> Result<(), Error> {
+    cancel: CancelToken,
+) -> Result<ScanSummary, Error> {
+    let matcher = matcher_for(media_type).ok_or(Error::UnsupportedMediaType(media_type))?;
+
     info!(library_id, "Scanning library");
 
     tx.send(
@@ -224,57 +946,56 @@ pub async fn start_custom(
     )
     .map_err(|x| Error::EventDispatch(x.into()))?;
 
-    let matcher = match media_type {
-        MediaType::Movie => Arc::new(movie::MovieMatcher) as Arc<dyn MediaMatcher>,
-        MediaType::Tv => Arc::new(tv_show::TvMatcher) as Arc<dyn MediaMatcher>,
-        _ => unimplemented!(),
-    };
-
     let now = Instant::now();
-    let workunits = insert_mediafiles(conn, library_id, dirs).await?;
-    let workunits_size = workunits.len();
-
-    info!(
+    let progress = Arc::new(ScanProgress::new(library_id, tx.clone()));
+    let summary = scan_pipeline(
+        conn,
         library_id,
-        units = workunits_size,
-        elapsed_ms = now.elapsed().as_millis(),
-        "Walked and inserted mediafiles."
-    );
-
-    // NOTE: itertools::GroupBy is used across an await point and thus must also be Sync. This
-    // breaks some of our higher-level logic where we spawn this task. Thus we collect it before
-    // we proceed consuming it.
-    let chunk_iter = workunits
-        .into_iter()
-        .chunks(128)
-        .into_iter()
-        .map(|x| x.collect())
-        .collect::<Vec<_>>();
+        media_type,
+        dirs,
+        matcher,
+        provider,
+        progress.clone(),
+        cancel,
+    )
+    .await;
 
-    // TODO: We can receive work over a channel so that we can in parallel create new mediafiles
-    // and match objects.
-    for unit in chunk_iter.into_iter() {
-        let mut lock = conn.writer().lock_owned().await;
-        let mut tx = dim_database::write_tx(&mut lock)
-            .await
-            .map_err(|e| Error::DatabaseError(e.into()))?;
+    // clients must be told that the scan has stopped, even if it failed or got cancelled.
+    let summary = match summary {
+        Ok(x) => x,
+        Err(e) => {
+            tx.send(
+                dim_events::Message {
+                    id: library_id,
+                    event_type: dim_events::PushEventType::EventStoppedScanning,
+                }
+                .to_string(),
+            )
+            .map_err(|e| Error::EventDispatch(e.into()))?;
 
-        if let Err(e) = matcher.batch_match(&mut tx, provider.clone(), unit).await {
-            error!(error = ?e, "Failed to match batch of mediafiles.");
+            return Err(e);
         }
-
-        tx.commit()
-            .await
-            .map_err(|e| Error::DatabaseError(e.into()))?;
-    }
+    };
 
     info!(
         library_id,
-        units = workunits_size,
+        added = summary.added,
+        changed = summary.changed,
+        missing = summary.missing,
+        moved = summary.moved,
         elapsed_ms = now.elapsed().as_millis(),
         "Finished scanning library."
     );
 
+    tx.send(
+        dim_events::Message {
+            id: library_id,
+            event_type: progress.summary(&summary),
+        }
+        .to_string(),
+    )
+    .map_err(|e| Error::EventDispatch(e.into()))?;
+
     tx.send(
         dim_events::Message {
             id: library_id,
@@ -284,15 +1005,19 @@ pub async fn start_custom(
     )
     .map_err(|e| Error::EventDispatch(e.into()))?;
 
-    Ok(())
+    Ok(summary)
 }
 
+/// Function scans all locations of a library, or only `dirs` if supplied. Scans should be started
+/// through [`scan_jobs`](jobs::scan_jobs) so that only one scan of a library runs at a time.
 pub async fn start(
     conn: &mut dim_database::DbConnection,
     library_id: i64,
+    dirs: Option<Vec<String>>,
     tx: EventTx,
     provider: Arc<dyn ExternalQueryIntoShow>,
-) -> Result<(), Error> {
+    cancel: CancelToken,
+) -> Result<ScanSummary, Error> {
     let mut tx_ = conn
         .read()
         .begin()
@@ -306,10 +1031,11 @@ pub async fn start(
     start_custom(
         conn,
         library_id,
-        lib.locations,
+        dirs.unwrap_or(lib.locations),
         tx,
         lib.media_type,
         provider,
+        cancel,
     )
     .await
 }
@@ -2,7 +2,12 @@
 #![allow(unused_imports)]
 
 use crate::inspect::ResultExt;
+use crate::scanner::artwork;
+use crate::scanner::extras;
 use crate::scanner::format_path;
+use crate::scanner::parse_filenames;
+use crate::scanner::stacking;
+use crate::scanner::FilenameParser;
 use dim_extern_api::ExternalMedia;
 use dim_extern_api::ExternalQueryIntoShow;
 
@@ -17,6 +22,7 @@ use dim_database::asset::InsertableAsset;
 use dim_database::genre::Genre;
 use dim_database::genre::InsertableGenre;
 use dim_database::genre::InsertableGenreMedia;
+use dim_database::library::Library;
 use dim_database::library::MediaType;
 use dim_database::media::InsertableMedia;
 use dim_database::media::Media;
@@ -26,6 +32,7 @@ use dim_database::movie::Movie;
 use dim_database::Transaction;
 
 use serde::Serialize;
+use std::path::Path;
 use std::sync::Arc;
 use tracing::error;
 use tracing::warn;
@@ -55,6 +62,8 @@ pub enum Error {
     ChildCleanup(#[serde(skip)] dim_database::DatabaseError),
     /// Failed to insert or get media object: {0:?}
     GetOrInsertMedia(#[serde(skip)] dim_database::DatabaseError),
+    /// Failed to attach extras to their parent: {0:?}
+    AttachExtras(#[serde(skip)] dim_database::DatabaseError),
 }
 
 pub fn asset_from_url(url: &str) -> Option<InsertableAsset> {
@@ -83,14 +92,24 @@ impl MovieMatcher {
         provided: ExternalMedia,
     ) -> Result<i64, Error> {
         // TODO: Push posters and backdrops to download queue. Push CDC events.
+        let local = Library::prefers_local_artwork(tx, file.library_id)
+            .await
+            .unwrap_or(false)
+            .then(|| artwork::movie_artwork(Path::new(&file.target_file)))
+            .unwrap_or_default();
+
+        // NOTE: Local artwork comes first so that it takes priority over remote artwork.
+        let mut poster_ids = artwork::insert_local_assets(tx, &local.posters)
+            .await
+            .inspect_err(|error| error!(?error, "Failed to insert local asset into db."))
+            .map_err(Error::PosterInsert)?;
+
         let posters = provided
             .posters
             .iter()
             .filter_map(|x| asset_from_url(x))
             .collect::<Vec<_>>();
 
-        let mut poster_ids = vec![];
-
         for poster in posters {
             let asset = poster
                 .insert(&mut *tx)
@@ -107,7 +126,10 @@ impl MovieMatcher {
             .filter_map(|x| asset_from_url(x))
             .collect::<Vec<_>>();
 
-        let mut backdrop_ids = vec![];
+        let mut backdrop_ids = artwork::insert_local_assets(tx, &local.backdrops)
+            .await
+            .inspect_err(|error| error!(?error, "Failed to insert local asset into db."))
+            .map_err(Error::BackdropInsert)?;
 
         for backdrop in backdrops {
             let asset = backdrop
@@ -179,10 +201,14 @@ impl MovieMatcher {
         // Update mediafile to point to a new parent media_id. We also want to set raw_name and
         // raw_year to what its parent has so that when we refresh metadata, files that were
         // matched manually (due to bogus filenames) dont get unmatched, or matched wrongly.
+        let stack = stacking::stack_info(Path::new(&file.target_file));
+
         UpdateMediaFile {
             media_id: Some(media_id),
             raw_name: Some(media.name),
             raw_year: media.year,
+            stack_key: stack.as_ref().map(|x| x.key.clone()),
+            stack_part: stack.map(|x| x.part),
             ..Default::default()
         }
         .update(tx, file.id)
@@ -223,12 +249,43 @@ impl MediaMatcher for MovieMatcher {
         provider: Arc<dyn ExternalQueryIntoShow>,
         work: Vec<WorkUnit>,
     ) -> Result<(), super::Error> {
+        let library_id = work.first().map(|WorkUnit(file, _)| file.library_id);
+        let files = work
+            .iter()
+            .map(|WorkUnit(file, _)| file.target_file.clone())
+            .collect::<Vec<_>>();
+
+        let parser = match library_id {
+            Some(library_id) => Library::get_one(tx, library_id)
+                .await
+                .map(|x| FilenameParser::new(MediaType::Movie, &x.filename_rules, &x.locations))
+                .ok(),
+            None => None,
+        };
+
+        // NOTE: Extras are never matched on their own, they get attached to their parent below.
         let metadata_futs = work
             .into_iter()
+            .filter(|WorkUnit(file, _)| file.extra_type.is_none())
+            .map(|WorkUnit(file, metadata)| {
+                // NOTE: All parts of a stack must be matched to the same movie, so we look up the
+                // name of the stack rather than the name of the part.
+                let metadata = stacking::stack_info(Path::new(&file.target_file))
+                    .and_then(|stack| {
+                        parse_filenames(std::iter::once(stack.base), parser.as_ref()?).pop()
+                    })
+                    .map_or(metadata, |(_, x)| x);
+
+                WorkUnit(file, metadata)
+            })
             .map(|WorkUnit(file, metadata)| async {
                 for meta in metadata {
                     match provider
-                        .search(meta.name.as_ref(), meta.year.map(|x| x as _))
+                        .search_for_file(
+                            Path::new(&file.target_file),
+                            meta.name.as_ref(),
+                            meta.year.map(|x| x as _),
+                        )
                         .await
                     {
                         Ok(provided) => return Some((file, provided)),
@@ -253,6 +310,15 @@ impl MediaMatcher for MovieMatcher {
             }
         }
 
+        // Extras can end up in a earlier batch than their parent, so we try to attach the extras
+        // stored next to any file of this batch that are still missing a parent.
+        if let Some(library_id) = library_id {
+            extras::attach_extras(tx, library_id, &files)
+                .await
+                .inspect_err(|error| error!(?error, "Failed to attach extras to their parent."))
+                .map_err(Error::AttachExtras)?;
+        }
+
         Ok(())
     }
 
@@ -279,6 +345,23 @@ impl MediaMatcher for MovieMatcher {
 
         Ok(())
     }
+
+    async fn cleanup(&self, tx: &mut Transaction<'_>, media_id: i64) -> Result<(), super::Error> {
+        let count = Movie::count_children(tx, media_id)
+            .await
+            .map_err(Error::ChildrenCount)?;
+
+        if count == 0 {
+            Media::delete(tx, media_id)
+                .await
+                .inspect_err(
+                    |error| error!(?error, %media_id, "Failed to cleanup child-less movie."),
+                )
+                .map_err(Error::ChildCleanup)?;
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,731 @@
+//! Module contains the matcher for music libraries. Unlike the movie and tv show matchers, music
+//! is matched purely based on the tags embedded in the audio files themselves.
+
+use crate::core::METADATA_PATH;
+use crate::scanner::format_path;
+use crate::streaming::ffprobe::FFPStream;
+use crate::streaming::ffprobe::FFProbeCtx;
+use crate::streaming::FFMPEG_BIN;
+use crate::streaming::FFPROBE_BIN;
+
+use super::MediaMatcher;
+use super::WorkUnit;
+use super::PROBE_WORKERS;
+
+use async_trait::async_trait;
+use chrono::prelude::Utc;
+
+use dim_database::asset::InsertableAsset;
+use dim_database::genre::InsertableGenre;
+use dim_database::genre::InsertableGenreMedia;
+use dim_database::library::MediaType;
+use dim_database::media::InsertableMedia;
+use dim_database::media::Media;
+use dim_database::media::UpdateMedia;
+use dim_database::mediafile::MediaFile;
+use dim_database::mediafile::UpdateMediaFile;
+use dim_database::music::Album;
+use dim_database::music::Artist;
+use dim_database::music::InsertableAlbum;
+use dim_database::music::InsertableArtist;
+use dim_database::music::InsertableTrack;
+use dim_database::music::Track;
+use dim_database::DbConnection;
+use dim_database::Transaction;
+
+use dim_extern_api::ExternalQueryIntoShow;
+
+use futures::StreamExt;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::path::MAIN_SEPARATOR;
+use std::process::Stdio;
+use std::sync::Arc;
+
+use tokio::process::Command;
+
+use tracing::error;
+use tracing::warn;
+
+use displaydoc::Display;
+use thiserror::Error;
+
+#[derive(Clone, Debug, Display, Error, Serialize)]
+pub enum Error {
+    /// Failed to create or get artist: {0:?}
+    GetOrInsertArtist(#[serde(skip)] dim_database::DatabaseError),
+    /// Failed to create or get album: {0:?}
+    GetOrInsertAlbum(#[serde(skip)] dim_database::DatabaseError),
+    /// Failed to create or get track: {0:?}
+    GetOrInsertTrack(#[serde(skip)] dim_database::DatabaseError),
+    /// Failed to insert cover art into database: {0:?}
+    CoverInsert(#[serde(skip)] dim_database::DatabaseError),
+    /// Failed to create or get genre: {0:?}
+    GetOrInsertGenre(#[serde(skip)] dim_database::DatabaseError),
+    /// Failed to attach genre to media object: {0:?}
+    CoupleGenre(#[serde(skip)] dim_database::DatabaseError),
+    /// Failed to update mediafile to point to new parent: {0:?}
+    UpdateMediafile(#[serde(skip)] dim_database::DatabaseError),
+    /// Failed to get children count: {0:?}
+    ChildrenCount(#[serde(skip)] dim_database::DatabaseError),
+    /// Failed to cleanup child-less parent: {0:?}
+    ChildCleanup(#[serde(skip)] dim_database::DatabaseError),
+}
+
+/// Tags we care about extracted from a audio file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AudioTags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album_artist: Option<String>,
+    pub album: Option<String>,
+    pub track: Option<i64>,
+    pub disc: Option<i64>,
+    pub year: Option<i64>,
+    pub genre: Option<String>,
+}
+
+impl AudioTags {
+    /// Extract the tags from the output of ffprobe. Different tagging formats use different names
+    /// for the same tags, so we try all the common ones.
+    pub fn from_probe(probe: &FFPStream) -> Self {
+        let tag = |keys: &[&str]| {
+            keys.iter()
+                .find_map(|k| probe.get_tag(k))
+                .map(str::trim)
+                .filter(|x| !x.is_empty())
+                .map(ToOwned::to_owned)
+        };
+
+        Self {
+            title: tag(&["title"]),
+            artist: tag(&["artist"]),
+            album_artist: tag(&["album_artist", "albumartist", "album artist"]),
+            album: tag(&["album"]),
+            track: tag(&["track", "tracknumber"]).and_then(|x| parse_position(&x)),
+            disc: tag(&["disc", "discnumber"]).and_then(|x| parse_position(&x)),
+            year: tag(&["date", "year", "originaldate"]).and_then(|x| parse_year(&x)),
+            genre: tag(&["genre"]),
+        }
+    }
+}
+
+/// Parses track and disc positions which are usually either `3` or `3/12`.
+fn parse_position(s: &str) -> Option<i64> {
+    s.split('/').next()?.trim().parse().ok()
+}
+
+/// Parses the year out of a date tag, which can either be a year or a full date.
+fn parse_year(s: &str) -> Option<i64> {
+    s.get(..4)?.parse().ok()
+}
+
+/// Function returns the name of the album `file` belongs to. Files without a album tag get grouped
+/// by the directory they live in as that is how most people organize their collections.
+fn album_name(file: &MediaFile, tags: &AudioTags) -> String {
+    tags.album
+        .clone()
+        .or_else(|| {
+            Path::new(&file.target_file)
+                .parent()?
+                .file_name()
+                .map(|x| x.to_string_lossy().to_string())
+        })
+        .unwrap_or_else(|| file.raw_name.clone())
+}
+
+/// Function extracts the embedded cover art of `file` into our metadata directory and returns the
+/// asset to insert.
+async fn extract_cover(file: &str, stream_index: i64) -> Option<InsertableAsset> {
+    let filename = format!("{}.jpg", uuid::Uuid::new_v4().as_hyphenated());
+    let out = Path::new(METADATA_PATH.get()?).join(&filename);
+
+    let status = Command::new(*FFMPEG_BIN)
+        .arg("-v")
+        .arg("error")
+        .arg("-y")
+        .arg("-i")
+        .arg(file)
+        .arg("-map")
+        .arg(format!("0:{stream_index}"))
+        .arg("-frames:v")
+        .arg("1")
+        .arg(&out)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .inspect_err(|error| warn!(?error, file, "Failed to spawn ffmpeg to extract cover art."))
+        .ok()?;
+
+    if !status.success() {
+        warn!(?status, file, "Failed to extract cover art.");
+        return None;
+    }
+
+    Some(InsertableAsset {
+        remote_url: None,
+        local_path: format_path(Some(filename)),
+        file_ext: "jpg".into(),
+    })
+}
+
+/// A album within a batch of files, identified by its name and the directory its files are stored
+/// in.
+type AlbumKey = (String, Option<PathBuf>);
+
+/// Function reads the tags of the files of `work`. Like the probe stage of the scanner, only
+/// `PROBE_WORKERS` ffprobe instances run at a time.
+async fn read_tags(work: Vec<WorkUnit>) -> Vec<(MediaFile, AudioTags, Option<FFPStream>)> {
+    futures::stream::iter(work)
+        .map(|WorkUnit(file, _)| async move {
+            let probe = FFProbeCtx::new(&FFPROBE_BIN)
+                .get_meta(&file.target_file)
+                .await
+                .inspect_err(
+                    |error| error!(?error, file = %file.target_file, "Failed to read tags."),
+                )
+                .ok();
+
+            let tags = probe
+                .as_ref()
+                .map(AudioTags::from_probe)
+                .unwrap_or_default();
+
+            (file, tags, probe)
+        })
+        .buffered(PROBE_WORKERS)
+        .collect()
+        .await
+}
+
+/// Function returns the key of the album `file` belongs to.
+fn album_key(file: &MediaFile, tags: &AudioTags) -> AlbumKey {
+    (
+        album_name(file, tags),
+        Path::new(&file.target_file).parent().map(Path::to_path_buf),
+    )
+}
+
+/// Function extracts the cover art of every album in `tagged`, taken from the first of its files
+/// with embedded cover art.
+async fn extract_covers(
+    tagged: &[(MediaFile, AudioTags, Option<FFPStream>)],
+) -> HashMap<AlbumKey, Option<InsertableAsset>> {
+    let mut covers = HashMap::new();
+
+    for (file, tags, probe) in tagged {
+        let key = album_key(file, tags);
+
+        if covers.contains_key(&key) {
+            continue;
+        }
+
+        let Some(index) = probe
+            .as_ref()
+            .and_then(FFPStream::get_attached_pic)
+            .map(|x| x.index)
+        else {
+            continue;
+        };
+
+        covers.insert(key, extract_cover(&file.target_file, index).await);
+    }
+
+    covers
+}
+
+/// Function removes the file of a cover that has been extracted but wasn't used.
+async fn remove_cover(asset: &InsertableAsset) {
+    let Some((dir, filename)) = METADATA_PATH
+        .get()
+        .zip(Path::new(&asset.local_path).file_name())
+    else {
+        return;
+    };
+
+    let path = Path::new(dir).join(filename);
+
+    if let Err(error) = tokio::fs::remove_file(&path).await {
+        warn!(?error, ?path, "Failed to remove unused cover art.");
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct MusicMatcher;
+
+impl MusicMatcher {
+    /// Method matches the files of `tagged` to tracks based on their tags. The covers of their
+    /// albums are taken from `covers`.
+    async fn match_tagged(
+        &self,
+        tx: &mut Transaction<'_>,
+        tagged: Vec<(MediaFile, AudioTags, Option<FFPStream>)>,
+        covers: &mut HashMap<AlbumKey, Option<InsertableAsset>>,
+    ) -> Result<(), Error> {
+        for (file, tags, _) in tagged {
+            let cover = covers.entry(album_key(&file, &tags)).or_default();
+
+            self.match_to_tags(tx, file, tags, cover)
+                .await
+                .inspect_err(|error| error!(?error, "failed to match to tags"))?;
+        }
+
+        Ok(())
+    }
+
+    /// Method will match a mediafile to a track based on the tags supplied, creating the album and
+    /// artist if they dont exist yet. `cover` is only used, and taken, if the album doesnt have a
+    /// poster yet.
+    #[tracing::instrument(skip(self, tx, cover))]
+    async fn match_to_tags(
+        &self,
+        tx: &mut Transaction<'_>,
+        file: MediaFile,
+        tags: AudioTags,
+        cover: &mut Option<InsertableAsset>,
+    ) -> Result<i64, Error> {
+        let path = Path::new(&file.target_file);
+        let added = Utc::now().to_string();
+
+        let album = InsertableAlbum {
+            media: InsertableMedia {
+                library_id: file.library_id,
+                name: album_name(&file, &tags),
+                year: tags.year,
+                added: added.clone(),
+                media_type: MediaType::Album,
+                ..Default::default()
+            },
+            artist_id: None,
+        };
+
+        // NOTE: Compilations without a album artist have a different artist on every track. Their
+        // tracks are grouped by the album name and the directory they live in instead, otherwise
+        // we would end up with one album per track artist.
+        let album_id = match (tags.album_artist.as_ref(), path.parent()) {
+            (Some(name), _) => {
+                let artist_id = InsertableArtist {
+                    library_id: file.library_id,
+                    name: name.to_owned(),
+                }
+                .insert(tx)
+                .await
+                .inspect_err(|error| error!(?error, "Failed to create or get artist."))
+                .map_err(Error::GetOrInsertArtist)?;
+
+                InsertableAlbum {
+                    artist_id: Some(artist_id),
+                    ..album
+                }
+                .insert(tx)
+                .await
+            }
+            (None, Some(dir)) => {
+                let dir = format!("{}{}", dir.to_string_lossy(), MAIN_SEPARATOR);
+
+                match Album::get_id_in_dir(tx, file.library_id, &album.media.name, &dir).await {
+                    Ok(Some(id)) => Ok(id),
+                    Ok(None) => album.insert_blind(tx).await,
+                    Err(e) => Err(e),
+                }
+            }
+            (None, None) => album.insert_blind(tx).await,
+        }
+        .inspect_err(|error| error!(?error, "Failed to create or get album."))
+        .map_err(Error::GetOrInsertAlbum)?;
+
+        let album = Album::get_by_id(tx, album_id)
+            .await
+            .map_err(Error::GetOrInsertAlbum)?;
+
+        if album.media.poster_path.is_none() {
+            if let Some(asset) = cover.take() {
+                let asset = asset
+                    .insert_local_asset(tx)
+                    .await
+                    .inspect_err(|error| error!(?error, "Failed to insert cover into db."))
+                    .map_err(Error::CoverInsert)?;
+
+                let _ = asset
+                    .into_media_poster(tx, album_id)
+                    .await
+                    .inspect_err(|error| warn!(?error, "Failed to link cover to album."));
+
+                UpdateMedia {
+                    poster: Some(asset.id),
+                    ..Default::default()
+                }
+                .update(tx, album_id)
+                .await
+                .map_err(Error::CoverInsert)?;
+            }
+        }
+
+        if let Some(name) = tags.genre.clone() {
+            let genre = InsertableGenre { name }
+                .insert(tx)
+                .await
+                .inspect_err(|error| error!(?error, "Failed to create or get genre."))
+                .map_err(Error::GetOrInsertGenre)?;
+
+            InsertableGenreMedia::insert_pair(genre, album_id, tx)
+                .await
+                .inspect_err(|error| error!(?error, %album_id, "Failed to attach genre."))
+                .map_err(Error::CoupleGenre)?;
+        }
+
+        let title = tags.title.clone().unwrap_or_else(|| {
+            path.file_stem()
+                .map(|x| x.to_string_lossy().to_string())
+                .unwrap_or_else(|| file.raw_name.clone())
+        });
+
+        let track_id = InsertableTrack {
+            media: InsertableMedia {
+                library_id: file.library_id,
+                name: title,
+                year: tags.year,
+                added,
+                media_type: MediaType::Track,
+                ..Default::default()
+            },
+            album_id,
+            track_number: tags.track,
+            disc_number: tags.disc,
+        }
+        .insert(tx)
+        .await
+        .inspect_err(|error| error!(?error, "Failed to create or get track."))
+        .map_err(Error::GetOrInsertTrack)?;
+
+        UpdateMediaFile {
+            media_id: Some(track_id),
+            ..Default::default()
+        }
+        .update(tx, file.id)
+        .await
+        .inspect_err(|error| error!(?error, "Failed to update mediafile to point to new parent."))
+        .map_err(Error::UpdateMediafile)?;
+
+        // Sometimes we rematch against a track that already exists but we are the last child for
+        // the parent. When this happens we want to cleanup the track, and the album and artist if
+        // they end up empty too.
+        if let Some(old_id) = file.media_id.filter(|x| *x != track_id) {
+            self.cleanup_track(tx, old_id).await?;
+        }
+
+        Ok(track_id)
+    }
+
+    async fn cleanup_track(&self, tx: &mut Transaction<'_>, track_id: i64) -> Result<(), Error> {
+        let count = Track::count_children(tx, track_id)
+            .await
+            .map_err(Error::ChildrenCount)?;
+
+        if count != 0 {
+            return Ok(());
+        }
+
+        let album_id = Track::get_albumid(tx, track_id)
+            .await
+            .map_err(Error::ChildrenCount)?;
+
+        Media::delete(tx, track_id)
+            .await
+            .inspect_err(|error| error!(?error, %track_id, "Failed to cleanup child-less track."))
+            .map_err(Error::ChildCleanup)?;
+
+        let count = Album::count_children(tx, album_id)
+            .await
+            .map_err(Error::ChildrenCount)?;
+
+        if count != 0 {
+            return Ok(());
+        }
+
+        let artist = Artist::get_of_album(tx, album_id)
+            .await
+            .map_err(Error::ChildrenCount)?;
+
+        Media::delete(tx, album_id)
+            .await
+            .inspect_err(|error| error!(?error, %album_id, "Failed to cleanup child-less album."))
+            .map_err(Error::ChildCleanup)?;
+
+        if let Some(artist) = artist {
+            let count = Artist::count_children(tx, artist.id)
+                .await
+                .map_err(Error::ChildrenCount)?;
+
+            if count == 0 {
+                Artist::delete(tx, artist.id)
+                    .await
+                    .map_err(Error::ChildCleanup)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MediaMatcher for MusicMatcher {
+    /// Cover art is only extracted by [`match_and_commit`](MediaMatcher::match_and_commit), as
+    /// we dont want to spawn ffmpeg while `tx` is held.
+    async fn batch_match(
+        &self,
+        tx: &mut Transaction<'_>,
+        _provider: Arc<dyn ExternalQueryIntoShow>,
+        work: Vec<WorkUnit>,
+    ) -> Result<(), super::Error> {
+        let tagged = read_tags(work).await;
+        Ok(self.match_tagged(tx, tagged, &mut HashMap::new()).await?)
+    }
+
+    async fn match_and_commit(
+        &self,
+        conn: &DbConnection,
+        _provider: Arc<dyn ExternalQueryIntoShow>,
+        work: Vec<WorkUnit>,
+    ) -> Result<(), super::Error> {
+        // NOTE: Tags and covers are read before we take the writer lock, so that we dont hold up
+        // other writers while ffprobe and ffmpeg run.
+        let tagged = read_tags(work).await;
+        let mut covers = extract_covers(&tagged).await;
+
+        let mut lock = conn.writer().lock_owned().await;
+        let mut tx = dim_database::write_tx(&mut lock)
+            .await
+            .map_err(|e| super::Error::DatabaseError(e.into()))?;
+
+        // NOTE: matches made before a error are kept.
+        let result = self.match_tagged(&mut tx, tagged, &mut covers).await;
+
+        tx.commit()
+            .await
+            .map_err(|e| super::Error::DatabaseError(e.into()))?;
+        drop(lock);
+
+        // covers of albums which already had a poster are left over.
+        for asset in covers.into_values().flatten() {
+            remove_cover(&asset).await;
+        }
+
+        Ok(result?)
+    }
+
+    async fn match_to_id(
+        &self,
+        _tx: &mut Transaction<'_>,
+        _provider: Arc<dyn ExternalQueryIntoShow>,
+        _work: WorkUnit,
+        external_id: &str,
+    ) -> Result<(), super::Error> {
+        // Music is only ever matched against the tags embedded in the files.
+        error!(%external_id, "Music can not be matched against external ids.");
+        Err(super::Error::InvalidExternalId)
+    }
+
+    async fn cleanup(&self, tx: &mut Transaction<'_>, media_id: i64) -> Result<(), super::Error> {
+        Ok(self.cleanup_track(tx, media_id).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::mediafile::create_library;
+
+    use super::parse_position;
+    use super::parse_year;
+    use super::AudioTags;
+    use super::MusicMatcher;
+
+    use std::path::Path;
+
+    use dim_database::media::Media;
+    use dim_database::mediafile::InsertableMediaFile;
+    use dim_database::mediafile::MediaFile;
+    use dim_database::music::Album;
+    use dim_database::music::Artist;
+    use dim_database::music::Track;
+    use dim_database::rw_pool::write_tx;
+
+    #[test]
+    fn parse_tag_values() {
+        assert_eq!(parse_position("3"), Some(3));
+        assert_eq!(parse_position("03/12"), Some(3));
+        assert_eq!(parse_position("/12"), None);
+        assert_eq!(parse_year("1997"), Some(1997));
+        assert_eq!(parse_year("1997-05-12"), Some(1997));
+        assert_eq!(parse_year("97"), None);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn match_to_tags() {
+        let mut conn = dim_database::get_conn_memory()
+            .await
+            .expect("Failed to obtain a in-memory db pool.");
+        let library = create_library(&mut conn).await;
+
+        let mut lock = conn.writer.lock_owned().await;
+        let mut tx = write_tx(&mut lock).await.unwrap();
+
+        let mut files = vec![];
+
+        for (i, name) in ["01 - Intro.flac", "02 - Song.flac"].iter().enumerate() {
+            let id = InsertableMediaFile {
+                library_id: library,
+                target_file: format!("/music/Artist/Album/{name}"),
+                raw_name: name.to_string(),
+                ..Default::default()
+            }
+            .insert(&mut tx)
+            .await
+            .unwrap();
+
+            let tags = AudioTags {
+                title: Some(format!("Track {i}")),
+                artist: Some("Artist".into()),
+                album_artist: Some("Artist".into()),
+                album: Some("Album".into()),
+                track: Some(i as i64 + 1),
+                disc: Some(1),
+                year: Some(2001),
+                ..Default::default()
+            };
+
+            files.push((MediaFile::get_one(&mut tx, id).await.unwrap(), tags));
+        }
+
+        const MATCHER: MusicMatcher = MusicMatcher;
+
+        let mut tracks = vec![];
+        for (file, tags) in files.iter().cloned() {
+            tracks.push(
+                MATCHER
+                    .match_to_tags(&mut tx, file, tags, &mut None)
+                    .await
+                    .unwrap(),
+            );
+        }
+
+        // both tracks should end up on the same album.
+        let album_id = Track::get_albumid(&mut tx, tracks[0]).await.unwrap();
+        assert_eq!(
+            Track::get_albumid(&mut tx, tracks[1]).await.unwrap(),
+            album_id
+        );
+
+        let album = Album::get_by_id(&mut tx, album_id).await.unwrap();
+        assert_eq!(album.media.name, "Album".to_string());
+        assert_eq!(album.media.year, Some(2001));
+
+        let artist = Artist::get_of_album(&mut tx, album_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(artist.name, "Artist".to_string());
+
+        let mfile = MediaFile::get_one(&mut tx, files[0].0.id).await.unwrap();
+        assert_eq!(mfile.media_id, Some(tracks[0]));
+
+        // retagging the first file onto a different album should cleanup the old track but keep
+        // the old album as it still has a track.
+        let (file, mut tags) = files[0].clone();
+        tags.album = Some("Other Album".into());
+        let file = MediaFile::get_one(&mut tx, file.id).await.unwrap();
+        let new_track = MATCHER
+            .match_to_tags(&mut tx, file, tags, &mut None)
+            .await
+            .unwrap();
+
+        assert!(Media::get(&mut tx, tracks[0]).await.is_err());
+        assert_ne!(
+            Track::get_albumid(&mut tx, new_track).await.unwrap(),
+            album_id
+        );
+        assert_eq!(Album::count_children(&mut tx, album_id).await.unwrap(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn match_compilation() {
+        let mut conn = dim_database::get_conn_memory()
+            .await
+            .expect("Failed to obtain a in-memory db pool.");
+        let library = create_library(&mut conn).await;
+
+        let mut lock = conn.writer.lock_owned().await;
+        let mut tx = write_tx(&mut lock).await.unwrap();
+
+        let mut tracks = vec![];
+        let various = Some("Various Artists");
+
+        for (file, artist, album_artist) in [
+            ("/music/Hits 42/01 - One.flac", "Artist A", None),
+            ("/music/Hits 42/02 - Two.flac", "Artist B", None),
+            ("/music/Other/Hits 42/01 - One.flac", "Artist C", None),
+            ("/music/Hits 43/01 - One.flac", "Artist A", various),
+            ("/music/Hits 43/02 - Two.flac", "Artist B", various),
+        ] {
+            let id = InsertableMediaFile {
+                library_id: library,
+                target_file: file.into(),
+                raw_name: file.into(),
+                ..Default::default()
+            }
+            .insert(&mut tx)
+            .await
+            .unwrap();
+
+            // compilations usually lack a album artist tag.
+            let tags = AudioTags {
+                artist: Some(artist.into()),
+                album_artist: album_artist.map(Into::into),
+                album: Path::new(file)
+                    .parent()
+                    .and_then(|x| x.file_name())
+                    .map(|x| x.to_string_lossy().to_string()),
+                ..Default::default()
+            };
+
+            let file = MediaFile::get_one(&mut tx, id).await.unwrap();
+            tracks.push(
+                MusicMatcher
+                    .match_to_tags(&mut tx, file, tags, &mut None)
+                    .await
+                    .unwrap(),
+            );
+        }
+
+        let album_id = Track::get_albumid(&mut tx, tracks[0]).await.unwrap();
+        assert_eq!(
+            Track::get_albumid(&mut tx, tracks[1]).await.unwrap(),
+            album_id
+        );
+        // compilations aren't filed under the artist of their first track.
+        assert!(Artist::get_of_album(&mut tx, album_id)
+            .await
+            .unwrap()
+            .is_none());
+        // a album with the same name in a different directory is a different album.
+        assert_ne!(
+            Track::get_albumid(&mut tx, tracks[2]).await.unwrap(),
+            album_id
+        );
+
+        let album_id = Track::get_albumid(&mut tx, tracks[3]).await.unwrap();
+        assert_eq!(
+            Track::get_albumid(&mut tx, tracks[4]).await.unwrap(),
+            album_id
+        );
+
+        let artist = Artist::get_of_album(&mut tx, album_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(artist.name, "Various Artists".to_string());
+    }
+}
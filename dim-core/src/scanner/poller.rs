@@ -0,0 +1,265 @@
+//! Module contains a filesystem watcher which periodically snapshots directories and diffs the
+//! snapshots. It is used for locations on filesystems which don't reliably generate native
+//! filesystem events, like NFS, SMB or FUSE mounts.
+//!
+//! The watcher emits the same [`notify::Event`]s the native watcher does, so that the daemon can
+//! handle both the same way. Renames are detected by pairing removed and created paths which share
+//! a inode.
+
+use notify::event::CreateKind;
+use notify::event::ModifyKind;
+use notify::event::RemoveKind;
+use notify::event::RenameMode;
+use notify::Event;
+use notify::EventKind;
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::mpsc::UnboundedReceiver;
+use tracing::debug;
+use walkdir::WalkDir;
+
+/// How often locations are polled for changes.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Filesystems on which native filesystem events are unreliable or not generated at all.
+#[cfg(target_os = "linux")]
+static UNRELIABLE_FS: &[&str] = &[
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "smbfs",
+    "9p",
+    "afs",
+    "ceph",
+    "glusterfs",
+    "sshfs",
+    "davfs",
+    "fuse",
+    "fuseblk",
+];
+
+/// Metadata of a path in a [`Snapshot`](Snapshot).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Entry {
+    is_dir: bool,
+    len: u64,
+    inode: Option<u64>,
+}
+
+impl Entry {
+    /// Key used to pair up the two sides of a rename. Sizes of directories change with their
+    /// contents, so we only compare the sizes of files.
+    fn rename_key(&self) -> Option<(u64, bool, u64)> {
+        Some((
+            self.inode?,
+            self.is_dir,
+            if self.is_dir { 0 } else { self.len },
+        ))
+    }
+}
+
+/// Snapshot of all the paths below a set of directories.
+#[derive(Clone, Debug, Default)]
+pub struct Snapshot(HashMap<PathBuf, Entry>);
+
+impl Snapshot {
+    /// Method walks `roots` and records every path below them. Unreadable paths are skipped.
+    pub fn take(roots: &[PathBuf]) -> Self {
+        let entries = roots
+            .iter()
+            .flat_map(|root| WalkDir::new(root).min_depth(1).into_iter())
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+
+                #[cfg(unix)]
+                let inode = {
+                    use std::os::unix::fs::MetadataExt;
+                    Some(metadata.ino())
+                };
+
+                #[cfg(not(unix))]
+                let inode = None;
+
+                Some((
+                    entry.into_path(),
+                    Entry {
+                        is_dir: metadata.is_dir(),
+                        len: metadata.len(),
+                        inode,
+                    },
+                ))
+            })
+            .collect();
+
+        Self(entries)
+    }
+
+    /// Method returns the events that turn this snapshot into `new`. Created directories are
+    /// reported without their contents, like the native watcher does, while every removed and
+    /// renamed path is reported.
+    pub fn diff(&self, new: &Snapshot) -> Vec<Event> {
+        let mut removed = self
+            .0
+            .iter()
+            .filter(|(path, _)| !new.0.contains_key(*path))
+            .collect::<Vec<_>>();
+
+        let mut created = new
+            .0
+            .iter()
+            .filter(|(path, _)| !self.0.contains_key(*path))
+            .collect::<Vec<_>>();
+
+        // NOTE: sorting keeps the order of events stable, parents before their children.
+        removed.sort_by(|a, b| a.0.cmp(b.0));
+        created.sort_by(|a, b| a.0.cmp(b.0));
+
+        let mut events = vec![];
+        let mut renamed = HashSet::new();
+        let mut created_by_inode = created
+            .iter()
+            .filter_map(|(path, entry)| Some((entry.rename_key()?, *path)))
+            .collect::<HashMap<_, _>>();
+
+        for (path, entry) in removed {
+            let to = entry
+                .rename_key()
+                .and_then(|key| created_by_inode.remove(&key));
+
+            match to {
+                Some(to) => {
+                    renamed.insert(to);
+                    events.push(
+                        Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both)))
+                            .add_path(path.clone())
+                            .add_path(to.clone()),
+                    );
+                }
+                None => events
+                    .push(Event::new(EventKind::Remove(RemoveKind::Any)).add_path(path.clone())),
+            }
+        }
+
+        let created = created
+            .into_iter()
+            .filter(|(path, _)| !renamed.contains(path))
+            .map(|(path, _)| path.as_path())
+            .collect::<HashSet<_>>();
+
+        let mut created = created
+            .iter()
+            .filter(|path| !path.ancestors().skip(1).any(|x| created.contains(x)))
+            .collect::<Vec<_>>();
+        created.sort();
+
+        events.extend(created.into_iter().map(|path| {
+            Event::new(EventKind::Create(CreateKind::Any)).add_path(path.to_path_buf())
+        }));
+
+        events
+    }
+}
+
+/// Handle of a polling watcher. The watcher stops once the handle is dropped.
+pub struct PollWatcher(Arc<AtomicBool>);
+
+impl Drop for PollWatcher {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Function spawns a watcher which polls `paths` for changes every `interval`.
+pub fn spawn_poll_watcher(
+    paths: Vec<PathBuf>,
+    interval: Duration,
+) -> (UnboundedReceiver<notify::Result<Event>>, PollWatcher) {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let stop = Arc::new(AtomicBool::new(false));
+    let handle = PollWatcher(stop.clone());
+
+    if paths.is_empty() {
+        return (rx, handle);
+    }
+
+    std::thread::spawn(move || {
+        let mut snapshot = Snapshot::take(&paths);
+
+        loop {
+            std::thread::sleep(interval);
+
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+
+            let new = Snapshot::take(&paths);
+            let events = snapshot.diff(&new);
+            snapshot = new;
+
+            debug!(
+                ?paths,
+                events = events.len(),
+                "Polled locations for changes."
+            );
+
+            if events.into_iter().any(|x| tx.send(Ok(x)).is_err()) {
+                break;
+            }
+        }
+    });
+
+    (rx, handle)
+}
+
+/// Function returns whether `path` is on a filesystem which doesn't reliably generate native
+/// filesystem events.
+#[cfg(target_os = "linux")]
+pub fn is_unreliable_fs(path: &Path) -> bool {
+    let Ok(mounts) = std::fs::read_to_string("/proc/mounts") else {
+        return false;
+    };
+
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+
+    mount_fs_type(&mounts, &path).map_or(false, |fs| {
+        UNRELIABLE_FS.contains(&fs) || fs.starts_with("fuse.")
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn is_unreliable_fs(_: &Path) -> bool {
+    false
+}
+
+/// Function returns the filesystem type of the mount `path` is on, given the contents of
+/// `/proc/mounts`.
+pub fn mount_fs_type<'a>(mounts: &'a str, path: &Path) -> Option<&'a str> {
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let mount_point = fields.nth(1)?;
+            let fs_type = fields.next()?;
+
+            // spaces and tabs in mount points are octal escaped.
+            let mount_point = mount_point
+                .replace("\\040", " ")
+                .replace("\\011", "\t")
+                .replace("\\134", "\\");
+
+            path.starts_with(&mount_point)
+                .then(|| (mount_point.len(), fs_type))
+        })
+        .max_by_key(|(len, _)| *len)
+        .map(|(_, fs_type)| fs_type)
+}
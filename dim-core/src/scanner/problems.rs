@@ -0,0 +1,53 @@
+//! Module contains the reporting of files that can't be played.
+//!
+//! Probing a file tells us more than what streams it has. ffprobe also complains about files that
+//! are truncated or that it can't read at all, and a movie without a video stream won't play no
+//! matter what. The reason is stored with the mediafile so that admins can get a report of the
+//! problem files in a library, fix them on disk and probe them again.
+
+use super::mediafile::MediafileCreator;
+use super::Error;
+
+use dim_database::mediafile::MediaFile;
+use dim_database::DbConnection;
+
+use dim_events::ProblemFile;
+
+use tracing::info;
+
+/// Function returns the problem of `mediafile` as reported to clients, or `None` if it plays fine.
+pub fn problem_file(mediafile: &MediaFile) -> Option<ProblemFile> {
+    Some(ProblemFile {
+        mediafile: mediafile.id,
+        path: mediafile.target_file.clone(),
+        problem: mediafile.probe_problem.clone()?,
+    })
+}
+
+/// Function probes the mediafile with the id `id` again and stores what it found. Returns the
+/// mediafile as it is stored afterwards, with its problem cleared if it plays fine now.
+pub async fn reprobe(conn: &DbConnection, id: i64) -> Result<MediaFile, Error> {
+    let mediafile = {
+        let mut tx = conn
+            .read()
+            .begin()
+            .await
+            .map_err(|e| Error::DatabaseError(e.into()))?;
+
+        MediaFile::get_one(&mut tx, id)
+            .await
+            .map_err(Error::MediafileNotFound)?
+    };
+
+    let mut creator = MediafileCreator::new(conn.clone(), mediafile.library_id).await;
+    let mediafile = creator.reprobe(&mediafile).await?;
+
+    info!(
+        id,
+        file = &mediafile.target_file,
+        problem = ?mediafile.probe_problem,
+        "Probed mediafile again."
+    );
+
+    Ok(mediafile)
+}
@@ -0,0 +1,181 @@
+//! Module contains the tracker used to report the progress of a scan to clients over the
+//! websocket.
+
+use super::ScanSummary;
+use crate::core::EventTx;
+
+use dim_events::Message;
+use dim_events::ProblemFile;
+use dim_events::PushEventType;
+use dim_events::ScanFileError;
+use dim_events::ScanPhase;
+
+use parking_lot::Mutex;
+
+use std::fmt::Display;
+use std::path::Path;
+use std::sync::atomic::AtomicU8;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
+
+use tracing::debug;
+
+/// Minimum time between two progress events of the same scan.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Tracks the progress of a scan and dispatches rate-limited progress events. The tracker is
+/// shared between the stages of a scan, hence all counters are atomic.
+pub struct ScanProgress {
+    library_id: i64,
+    tx: EventTx,
+    phase: AtomicU8,
+    discovered: AtomicUsize,
+    probed: AtomicUsize,
+    matched: AtomicUsize,
+    failed: AtomicUsize,
+    current_path: Mutex<Option<String>>,
+    errors: Mutex<Vec<ScanFileError>>,
+    /// When the last progress event was dispatched, `None` if none has been dispatched yet.
+    last_event: Mutex<Option<Instant>>,
+}
+
+impl ScanProgress {
+    pub fn new(library_id: i64, tx: EventTx) -> Self {
+        Self {
+            library_id,
+            tx,
+            phase: AtomicU8::new(ScanPhase::Walking as u8),
+            discovered: AtomicUsize::new(0),
+            probed: AtomicUsize::new(0),
+            matched: AtomicUsize::new(0),
+            failed: AtomicUsize::new(0),
+            current_path: Mutex::new(None),
+            errors: Mutex::new(vec![]),
+            last_event: Mutex::new(None),
+        }
+    }
+
+    /// Method returns the phase the scan is in.
+    pub fn phase(&self) -> ScanPhase {
+        match self.phase.load(Ordering::Relaxed) {
+            x if x == ScanPhase::Walking as u8 => ScanPhase::Walking,
+            x if x == ScanPhase::Probing as u8 => ScanPhase::Probing,
+            _ => ScanPhase::Matching,
+        }
+    }
+
+    /// Method moves the scan into `phase` and dispatches a progress event right away.
+    pub fn set_phase(&self, phase: ScanPhase) {
+        self.phase.store(phase as u8, Ordering::Relaxed);
+        self.dispatch(true);
+    }
+
+    /// Method records that the directory walker has found `file`.
+    pub fn discovered(&self, file: &Path) {
+        self.discovered.fetch_add(1, Ordering::Relaxed);
+        self.set_current(file);
+    }
+
+    /// Method records that `file` has been probed.
+    pub fn probed(&self, file: &Path) {
+        self.probed.fetch_add(1, Ordering::Relaxed);
+        self.set_current(file);
+    }
+
+    /// Method records that `count` files have been handed to the matcher.
+    pub fn matched(&self, count: usize) {
+        self.matched.fetch_add(count, Ordering::Relaxed);
+        self.dispatch(false);
+    }
+
+    /// Method records that `file` failed to be probed or matched.
+    pub fn failed(&self, file: impl AsRef<Path>, error: impl Display) {
+        self.failed.fetch_add(1, Ordering::Relaxed);
+        self.errors.lock().push(ScanFileError {
+            path: file.as_ref().to_string_lossy().to_string(),
+            error: error.to_string(),
+        });
+        self.dispatch(false);
+    }
+
+    /// Method returns the errors of all files that failed so far.
+    pub fn errors(&self) -> Vec<ScanFileError> {
+        self.errors.lock().clone()
+    }
+
+    /// Method tells clients about files found to be unplayable. Unlike progress events these are
+    /// never rate-limited.
+    pub fn problems(&self, files: Vec<ProblemFile>) {
+        if files.is_empty() {
+            return;
+        }
+
+        let message = Message {
+            id: self.library_id,
+            event_type: PushEventType::EventProblemFiles { files },
+        };
+
+        if let Err(error) = self.tx.send(message.to_string()) {
+            debug!(?error, "Failed to dispatch problem files event.");
+        }
+    }
+
+    fn set_current(&self, file: &Path) {
+        *self.current_path.lock() = Some(file.to_string_lossy().to_string());
+        self.dispatch(false);
+    }
+
+    /// Method returns the progress event for the current state of the scan.
+    pub fn event(&self) -> PushEventType {
+        PushEventType::EventScanProgress {
+            phase: self.phase(),
+            discovered: self.discovered.load(Ordering::Relaxed),
+            probed: self.probed.load(Ordering::Relaxed),
+            matched: self.matched.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+            current_path: self.current_path.lock().clone(),
+        }
+    }
+
+    /// Method returns the summary event of the scan.
+    pub fn summary(&self, summary: &ScanSummary) -> PushEventType {
+        PushEventType::EventScanSummary {
+            added: summary.added,
+            changed: summary.changed,
+            missing: summary.missing,
+            moved: summary.moved,
+            discovered: self.discovered.load(Ordering::Relaxed),
+            probed: self.probed.load(Ordering::Relaxed),
+            matched: self.matched.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+            errors: self.errors(),
+        }
+    }
+
+    /// Method dispatches a progress event, unless one has been dispatched within the last
+    /// `PROGRESS_INTERVAL` and `force` is false.
+    fn dispatch(&self, force: bool) {
+        {
+            let mut last_event = self.last_event.lock();
+            let now = Instant::now();
+
+            if !force && last_event.map_or(false, |x| now - x < PROGRESS_INTERVAL) {
+                return;
+            }
+
+            *last_event = Some(now);
+        }
+
+        let message = Message {
+            id: self.library_id,
+            event_type: self.event(),
+        };
+
+        // NOTE: progress events are best effort, a missed one will be superseded by the next.
+        if let Err(error) = self.tx.send(message.to_string()) {
+            debug!(?error, "Failed to dispatch scan progress event.");
+        }
+    }
+}
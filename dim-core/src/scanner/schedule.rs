@@ -0,0 +1,283 @@
+//! Module contains the scheduler which periodically rescans libraries according to their
+//! [`RescanSchedule`](RescanSchedule).
+//!
+//! The scheduler works off the time the last scan of a library has finished, which is stored with
+//! the library. If Dim was down while one or more scans were due, a single scan is queued as soon
+//! as the scheduler starts, afterwards the library returns to its regular schedule.
+
+use super::jobs::scan_jobs;
+use crate::core::provider_for;
+use crate::core::EventTx;
+
+use chrono::DateTime;
+use chrono::Datelike;
+use chrono::Duration;
+use chrono::Local;
+use chrono::NaiveDateTime;
+use chrono::TimeZone;
+use chrono::Timelike;
+use chrono::Utc;
+
+use dim_database::library::Library;
+use dim_database::library::RescanSchedule;
+use dim_database::DbConnection;
+
+use displaydoc::Display;
+use thiserror::Error;
+
+use std::collections::HashMap;
+
+use tracing::debug;
+use tracing::info;
+use tracing::instrument;
+use tracing::warn;
+
+/// How often the scheduler checks whether a library is due for a rescan.
+const SCHEDULER_TICK: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How far ahead we look for the next match of a cron expression before giving up.
+const CRON_LOOKAHEAD_DAYS: i64 = 366 * 5;
+
+#[derive(Clone, Debug, Display, Error, PartialEq, Eq)]
+pub enum ScheduleError {
+    /// Rescan interval must be at least one minute.
+    InvalidInterval,
+    /// Cron expressions must have 5 fields, found {0}.
+    FieldCount(usize),
+    /// Invalid value `{value}` for the {field} field of the cron expression.
+    InvalidField { field: &'static str, value: String },
+}
+
+/// A [`RescanSchedule`](RescanSchedule) that has been validated.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Schedule {
+    Interval(Duration),
+    Cron(Cron),
+}
+
+impl Schedule {
+    pub fn parse(schedule: &RescanSchedule) -> Result<Self, ScheduleError> {
+        match schedule {
+            RescanSchedule::Interval { minutes } => i64::try_from(*minutes)
+                .ok()
+                .filter(|x| *x > 0)
+                .and_then(Duration::try_minutes)
+                .map(Self::Interval)
+                .ok_or(ScheduleError::InvalidInterval),
+            RescanSchedule::Cron { expr } => Ok(Self::Cron(Cron::parse(expr)?)),
+        }
+    }
+
+    /// Method returns when a scan is due next if the last one has finished at `last`.
+    pub fn next_run(&self, last: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Interval(interval) => last.checked_add_signed(*interval),
+            Self::Cron(cron) => cron
+                .next_after(last.with_timezone(&Local).naive_local())
+                .and_then(|x| {
+                    // NOTE: times skipped by a DST transition run an hour later.
+                    Local.from_local_datetime(&x).earliest().or_else(|| {
+                        Local
+                            .from_local_datetime(&(x + Duration::hours(1)))
+                            .earliest()
+                    })
+                })
+                .map(|x| x.with_timezone(&Utc)),
+        }
+    }
+}
+
+/// A parsed cron expression. Every field is stored as a bitmask of the values it matches.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cron {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Whether the day-of-month and day-of-week fields were `*`. If both fields are restricted a
+    /// day matches if either field matches, like in cron.
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Cron {
+    /// Method parses a cron expression of the form `minute hour day-of-month month day-of-week`.
+    /// Fields support `*`, values, ranges (`1-5`), lists (`1,3`) and steps (`*/15`, `0-30/10`).
+    /// Day of week 0 and 7 are both Sunday.
+    pub fn parse(expr: &str) -> Result<Self, ScheduleError> {
+        let fields = expr.split_whitespace().collect::<Vec<_>>();
+
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(ScheduleError::FieldCount(fields.len()));
+        };
+
+        let mut weekday_mask = parse_field(weekdays, "day-of-week", 0, 7)?;
+        // fold sunday as 7 into sunday as 0.
+        if weekday_mask & (1 << 7) != 0 {
+            weekday_mask = (weekday_mask | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            minutes: parse_field(minutes, "minute", 0, 59)?,
+            hours: parse_field(hours, "hour", 0, 23)?,
+            days: parse_field(days, "day-of-month", 1, 31)?,
+            months: parse_field(months, "month", 1, 12)?,
+            weekdays: weekday_mask,
+            any_day: days == "*",
+            any_weekday: weekdays == "*",
+        })
+    }
+
+    fn matches_day(&self, time: &NaiveDateTime) -> bool {
+        let day = self.days & (1 << time.day()) != 0;
+        let weekday = self.weekdays & (1 << time.weekday().num_days_from_sunday()) != 0;
+
+        match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+
+    /// Method returns the first time strictly after `after` the expression matches.
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut time = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let limit = time + Duration::days(CRON_LOOKAHEAD_DAYS);
+
+        while time < limit {
+            if self.months & (1 << time.month()) == 0 {
+                let (year, month) = match time.month() {
+                    12 => (time.year() + 1, 1),
+                    x => (time.year(), x + 1),
+                };
+
+                time = chrono::NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+
+            if !self.matches_day(&time) {
+                time = time.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+
+            if self.hours & (1 << time.hour()) == 0 {
+                time = time.with_minute(0)? + Duration::hours(1);
+                continue;
+            }
+
+            if self.minutes & (1 << time.minute()) == 0 {
+                time += Duration::minutes(1);
+                continue;
+            }
+
+            return Some(time);
+        }
+
+        None
+    }
+}
+
+/// Function parses one field of a cron expression into a bitmask.
+fn parse_field(field: &str, name: &'static str, min: u32, max: u32) -> Result<u64, ScheduleError> {
+    let error = || ScheduleError::InvalidField {
+        field: name,
+        value: field.to_string(),
+    };
+
+    let parse = |x: &str| {
+        x.parse::<u32>()
+            .ok()
+            .filter(|x| (min..=max).contains(x))
+            .ok_or_else(error)
+    };
+
+    let mut mask = 0;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, Some(step.parse::<u32>().map_err(|_| error())?)),
+            None => (part, None),
+        };
+
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (parse(start)?, parse(end)?),
+            // `5/10` means every 10th value starting from 5.
+            None if step.is_some() => (parse(range)?, max),
+            None => (parse(range)?, parse(range)?),
+        };
+
+        let step = step.unwrap_or(1);
+        if step == 0 || start > end {
+            return Err(error());
+        }
+
+        for x in (start..=end).step_by(step as usize) {
+            mask |= 1 << x;
+        }
+    }
+
+    Ok(mask)
+}
+
+/// Function runs forever and queues a rescan of every library whose schedule is due.
+#[instrument(skip_all)]
+pub async fn run_scheduler(conn: DbConnection, tx: EventTx) {
+    let started = Utc::now();
+    // NOTE: failed scans dont update `last_scanned_at`, so we also remember when we last queued a
+    // scan to avoid retrying a failing library every tick.
+    let mut queued_at: HashMap<i64, DateTime<Utc>> = HashMap::new();
+    let mut interval = tokio::time::interval(SCHEDULER_TICK);
+
+    loop {
+        interval.tick().await;
+
+        let libraries = match conn.read().begin().await {
+            Ok(mut db_tx) => Library::get_all(&mut db_tx).await,
+            Err(error) => {
+                warn!(?error, "Failed to open a transaction.");
+                continue;
+            }
+        };
+
+        let now = Utc::now();
+
+        for library in libraries {
+            let Some(schedule) = library.rescan_schedule.as_ref() else {
+                continue;
+            };
+
+            let schedule = match Schedule::parse(schedule) {
+                Ok(x) => x,
+                Err(error) => {
+                    debug!(%error, library_id = library.id, "Skipping invalid rescan schedule.");
+                    continue;
+                }
+            };
+
+            // libraries that have never been scanned are scheduled relative to our startup.
+            let last = library
+                .last_scanned_at
+                .and_then(|x| Utc.timestamp_opt(x, 0).single())
+                .max(queued_at.get(&library.id).copied())
+                .unwrap_or(started);
+
+            if !schedule.next_run(last).map_or(false, |x| x <= now) {
+                continue;
+            }
+
+            info!(library_id = library.id, "Queueing scheduled rescan.");
+
+            let Some(provider) = provider_for(
+                library.media_type,
+                library.metadata_provider,
+                &library.locations,
+            ) else {
+                continue;
+            };
+
+            scan_jobs().enqueue(conn.clone(), library.id, tx.clone(), provider);
+            queued_at.insert(library.id, now);
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! Module contains the logic used to recognise movies that are split into several files, ie
+//! `Movie.cd1.avi` and `Movie.cd2.avi`.
+//!
+//! All parts of a movie form a stack which is identified by the directory, the name and the
+//! extension the parts share. The streaming layer plays a stack back as one continuous file.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Matches a stacking marker like `cd1`, `part 2`, `pt.3` or `disc-4` at the end of a filename.
+static STACK_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^(?P<name>.*?)(?:[ _.\-]+[(\[]?|[(\[])(?:cd|dvd|part|pt|disc|disk)[ _.\-]*(?P<part>[0-9]{1,2})[)\]]?$")
+        .expect("Failed to compile stacking regex.")
+});
+
+/// Stacking information extracted from the filename of a part.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StackInfo {
+    /// Key shared by all parts of the stack.
+    pub key: String,
+    /// Position of the part within the stack.
+    pub part: i64,
+    /// Path of the stack with the stacking marker removed, ie `Movie.avi` for `Movie.cd1.avi`.
+    pub base: PathBuf,
+}
+
+/// Function returns the stacking information of `file`, or `None` if `file` isn't part of a
+/// stack.
+pub fn stack_info(file: &Path) -> Option<StackInfo> {
+    let stem = file.file_stem()?.to_str()?;
+    let ext = file.extension()?.to_str()?;
+    let dir = file.parent()?;
+
+    let captures = STACK_REGEX.captures(stem)?;
+    let name = captures.name("name")?.as_str();
+    let part = captures.name("part")?.as_str().parse().ok()?;
+
+    // a file called `cd1.avi` has nothing to be stacked with.
+    if name.trim().is_empty() {
+        return None;
+    }
+
+    let base = dir.join(format!("{name}.{ext}"));
+
+    Some(StackInfo {
+        key: base.to_str()?.to_owned(),
+        part,
+        base,
+    })
+}
@@ -0,0 +1,214 @@
+//! Module contains the logic used to discover external subtitle files stored next to media files.
+//!
+//! A subtitle belongs to a video if its filename starts with the name of the video, for example
+//! `Movie.srt`, `Movie.en.srt` or `Movie.forced.de.ass` all belong to `Movie.mkv`. The tags
+//! between the name of the video and the extension tell us the language of the subtitles and
+//! whether they are forced or meant for the deaf and hard of hearing.
+
+use super::Error;
+use crate::utils::iso639_from_tag;
+
+use dim_database::mediafile::MediaFile;
+use dim_database::subtitle::InsertableSubtitleSidecar;
+use dim_database::subtitle::SubtitleSidecar;
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+
+use tracing::info;
+
+/// Extensions of subtitle files we can convert to WebVTT.
+pub static SUBTITLE_EXTS: &[&str] = &["srt", "ass", "ssa", "vtt"];
+
+/// A subtitle file found next to a video.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Sidecar {
+    pub path: PathBuf,
+    pub codec: String,
+    pub language: Option<String>,
+    pub forced: bool,
+    pub sdh: bool,
+}
+
+impl From<SubtitleSidecar> for Sidecar {
+    fn from(x: SubtitleSidecar) -> Self {
+        Self {
+            path: x.target_file.into(),
+            codec: x.codec,
+            language: x.language,
+            forced: x.forced,
+            sdh: x.sdh,
+        }
+    }
+}
+
+/// Function parses the filename of `sidecar`, returning `None` if it isnt a subtitle file of
+/// `video`.
+pub fn parse_sidecar(video: &Path, sidecar: &Path) -> Option<Sidecar> {
+    let stem = video.file_stem()?.to_str()?;
+    let name = sidecar.file_name()?.to_str()?;
+    let codec = sidecar.extension()?.to_str()?.to_lowercase();
+
+    if !SUBTITLE_EXTS.contains(&codec.as_str()) || video.parent() != sidecar.parent() {
+        return None;
+    }
+
+    // strip the extension first, then the name of the video.
+    let name = &name[..name.len() - codec.len() - 1];
+    let tags = match name.strip_prefix(stem) {
+        Some("") => "",
+        Some(x) => x.strip_prefix('.')?,
+        None => return None,
+    };
+
+    let mut result = Sidecar {
+        path: sidecar.to_path_buf(),
+        codec,
+        language: None,
+        forced: false,
+        sdh: false,
+    };
+
+    for tag in tags.split('.').map(str::to_lowercase) {
+        match tag.as_str() {
+            "forced" | "foreign" => result.forced = true,
+            "sdh" | "cc" | "hi" => result.sdh = true,
+            x if result.language.is_none() => result.language = iso639_from_tag(x).map(Into::into),
+            _ => {}
+        }
+    }
+
+    Some(result)
+}
+
+/// Function returns all subtitle files of `video` in `listing`, which must contain the files in
+/// the directory of `video`.
+pub fn find_sidecars(video: &Path, listing: &[PathBuf]) -> Vec<Sidecar> {
+    let mut sidecars = listing
+        .iter()
+        .filter_map(|x| parse_sidecar(video, x))
+        .collect::<Vec<_>>();
+
+    sidecars.sort_by(|a, b| a.path.cmp(&b.path));
+    sidecars
+}
+
+/// Function returns all subtitle files inside of `dir`.
+fn list_subtitles(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return vec![];
+    };
+
+    entries
+        .filter_map(Result::ok)
+        .map(|x| x.path())
+        .filter(|x| {
+            x.extension().and_then(|e| e.to_str()).map_or(false, |e| {
+                SUBTITLE_EXTS.contains(&e.to_lowercase().as_str())
+            })
+        })
+        .collect()
+}
+
+/// Function looks for subtitle sidecars next to all mediafiles of a library and brings the
+/// database in sync with what is on disk. Returns the number of mediafiles whose sidecars have
+/// changed.
+pub async fn index_sidecars(
+    conn: &dim_database::DbConnection,
+    library_id: i64,
+) -> Result<usize, Error> {
+    let (mediafiles, existing) = {
+        let mut tx = conn
+            .read()
+            .begin()
+            .await
+            .map_err(|e| Error::DatabaseError(e.into()))?;
+
+        let mediafiles = MediaFile::get_by_lib(&mut tx, library_id)
+            .await
+            .map_err(Error::SubtitleIndex)?;
+        let existing = SubtitleSidecar::get_of_library(&mut tx, library_id)
+            .await
+            .map_err(Error::SubtitleIndex)?;
+
+        (mediafiles, existing)
+    };
+
+    let mut stored: HashMap<i64, HashSet<Sidecar>> = HashMap::new();
+    for sidecar in existing {
+        stored
+            .entry(sidecar.mediafile_id)
+            .or_default()
+            .insert(sidecar.into());
+    }
+
+    // NOTE: We only list each directory once as season folders tend to have dozens of episodes.
+    let changed = tokio::task::spawn_blocking(move || {
+        let mut listings: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+
+        mediafiles
+            .into_iter()
+            .filter(|x| !x.missing)
+            .filter_map(|mfile| {
+                let video = Path::new(&mfile.target_file);
+                let listing = listings
+                    .entry(video.parent()?.to_path_buf())
+                    .or_insert_with_key(|dir| list_subtitles(dir));
+
+                let found = find_sidecars(video, listing);
+                let stored = stored.remove(&mfile.id).unwrap_or_default();
+
+                if found.iter().cloned().collect::<HashSet<_>>() == stored {
+                    return None;
+                }
+
+                Some((mfile.id, found))
+            })
+            .collect::<Vec<_>>()
+    })
+    .await
+    .expect("Subtitle indexer panicked.");
+
+    if changed.is_empty() {
+        return Ok(0);
+    }
+
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = dim_database::write_tx(&mut lock)
+        .await
+        .map_err(|e| Error::DatabaseError(e.into()))?;
+
+    for (mediafile_id, sidecars) in changed.iter() {
+        SubtitleSidecar::delete_of_mediafile(&mut tx, *mediafile_id)
+            .await
+            .map_err(Error::SubtitleIndex)?;
+
+        for sidecar in sidecars {
+            InsertableSubtitleSidecar {
+                mediafile_id: *mediafile_id,
+                target_file: sidecar.path.to_string_lossy().to_string(),
+                codec: sidecar.codec.clone(),
+                language: sidecar.language.clone(),
+                forced: sidecar.forced,
+                sdh: sidecar.sdh,
+            }
+            .insert(&mut tx)
+            .await
+            .map_err(Error::SubtitleIndex)?;
+        }
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| Error::DatabaseError(e.into()))?;
+
+    info!(
+        library_id,
+        mediafiles = changed.len(),
+        "Updated subtitle sidecars."
+    );
+
+    Ok(changed.len())
+}
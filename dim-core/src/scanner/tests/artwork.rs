@@ -0,0 +1,71 @@
+use super::temp_dir;
+use crate::scanner::artwork;
+
+#[test]
+fn test_movie_artwork() {
+    let tempdir = temp_dir(vec![
+        "Movie (2020)/Movie (2020).mkv",
+        "Movie (2020)/Movie (2020)-poster.jpg",
+        "Movie (2020)/Folder.JPG",
+        "Movie (2020)/fanart.png",
+        "Movie (2020)/notes.txt",
+        "Movie (2020)/poster.nfo",
+    ]);
+
+    let dir = tempdir.path().join("Movie (2020)");
+    let local = artwork::movie_artwork(&dir.join("Movie (2020).mkv"));
+
+    assert_eq!(
+        local.posters,
+        vec![dir.join("Movie (2020)-poster.jpg"), dir.join("Folder.JPG")]
+    );
+    assert_eq!(local.backdrops, vec![dir.join("fanart.png")]);
+}
+
+#[test]
+fn test_show_artwork() {
+    let tempdir = temp_dir(vec![
+        "Show/poster.jpg",
+        "Show/fanart.jpg",
+        "Show/season02-poster.jpg",
+        "Show/season-specials-poster.jpg",
+        "Show/Season 1/poster.jpg",
+        "Show/Season 1/S01E01.mkv",
+        "Show/Season 1/S01E01-thumb.jpg",
+        "Show/S02E01.mkv",
+    ]);
+
+    let show = tempdir.path().join("Show");
+    let episode = show.join("Season 1/S01E01.mkv");
+
+    assert_eq!(artwork::show_dir(&episode), Some(show.as_path()));
+    assert_eq!(
+        artwork::show_artwork(&episode).posters,
+        vec![show.join("poster.jpg")]
+    );
+    assert_eq!(
+        artwork::show_artwork(&episode).backdrops,
+        vec![show.join("fanart.jpg")]
+    );
+    assert_eq!(
+        artwork::season_posters(&episode, 1),
+        vec![show.join("Season 1/poster.jpg")]
+    );
+    assert_eq!(
+        artwork::episode_stills(&episode),
+        vec![show.join("Season 1/S01E01-thumb.jpg")]
+    );
+
+    let episode = show.join("S02E01.mkv");
+
+    assert_eq!(artwork::show_dir(&episode), Some(show.as_path()));
+    assert_eq!(
+        artwork::season_posters(&episode, 2),
+        vec![show.join("season02-poster.jpg")]
+    );
+    assert_eq!(
+        artwork::season_posters(&episode, 0),
+        vec![show.join("season-specials-poster.jpg")]
+    );
+    assert!(artwork::episode_stills(&episode).is_empty());
+}
@@ -0,0 +1,97 @@
+use crate::scanner::confidence::rate;
+use crate::scanner::confidence::LOW_CONFIDENCE;
+use crate::scanner::parse_filenames;
+use crate::scanner::FilenameParser;
+
+use dim_database::library::FilenameRules;
+use dim_database::library::MediaType;
+use dim_extern_api::filename::Metadata;
+
+use chrono::NaiveDate;
+
+use std::path::Path;
+
+fn candidate(name: &str, year: Option<i64>, season: Option<i64>, episode: Option<i64>) -> Metadata {
+    Metadata {
+        name: name.into(),
+        year,
+        season,
+        episode,
+        last_episode: None,
+        season_assumed: false,
+        air_date: None,
+        edition: None,
+        confidence: 0,
+    }
+}
+
+#[test]
+fn test_rate_movie() {
+    let file = Path::new("/movies/Blade Runner (1982)/Blade.Runner.1982.mkv");
+    let mut candidates = [
+        candidate("Blade Runner", Some(1982), None, None),
+        candidate("Blade Runner", None, None, None),
+        candidate("Runner", Some(1982), None, Some(1982)),
+    ];
+
+    rate(file, &mut candidates, MediaType::Movie);
+
+    assert_eq!(candidates[0].confidence, 85);
+    assert_eq!(candidates[1].confidence, 54);
+    assert_eq!(candidates[2].confidence, 45);
+    assert!(candidates[2].confidence < LOW_CONFIDENCE);
+}
+
+#[test]
+fn test_rate_episode() {
+    let file = Path::new("/tv/Show/Season 1/Show - 03.mkv");
+    let mut candidates = [Metadata {
+        season_assumed: true,
+        ..candidate("Show", None, Some(1), Some(3))
+    }];
+
+    rate(file, &mut candidates, MediaType::Tv);
+    assert_eq!(candidates[0].confidence, 75);
+
+    let mut candidates = [candidate("", None, Some(1), Some(3))];
+    rate(file, &mut candidates, MediaType::Tv);
+    assert_eq!(candidates[0].confidence, 0);
+}
+
+#[test]
+fn test_ranked_candidates() {
+    let files = ["/movies/Blade Runner (1982)/Blade.Runner.1982.1080p.mkv"];
+
+    let parser = FilenameParser::new(MediaType::Movie, &Default::default(), &["/movies"]);
+    let parsed = parse_filenames(files.iter(), &parser);
+    let (_, metas) = &parsed[0];
+    assert!(metas.windows(2).all(|x| x[0].confidence >= x[1].confidence));
+
+    // a match of the filename rules stays first.
+    let rules = FilenameRules(vec![r"^(?P<title>.+)$".into()]);
+    let parser = FilenameParser::new(MediaType::Movie, &rules, &["/movies"]);
+    let parsed = parse_filenames(files.iter(), &parser);
+    let (_, metas) = &parsed[0];
+    assert_eq!(metas[0].name, "Blade Runner 1982 1080p");
+    assert!(metas[1..]
+        .windows(2)
+        .all(|x| x[0].confidence >= x[1].confidence));
+}
+
+#[test]
+fn test_air_dates() {
+    let files = ["/media/The.Daily.Show.2023.05.01.mkv"];
+
+    let parser = FilenameParser::new(MediaType::Tv, &Default::default(), &["/media"]);
+    let parsed = parse_filenames(files.iter(), &parser);
+    let (_, metas) = &parsed[0];
+    assert!(metas
+        .iter()
+        .all(|x| x.air_date == NaiveDate::from_ymd_opt(2023, 5, 1)));
+
+    // only episodes are named by their air date.
+    let parser = FilenameParser::new(MediaType::Movie, &Default::default(), &["/media"]);
+    let parsed = parse_filenames(files.iter(), &parser);
+    let (_, metas) = &parsed[0];
+    assert!(metas.iter().all(|x| x.air_date.is_none()));
+}
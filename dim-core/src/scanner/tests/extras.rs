@@ -0,0 +1,214 @@
+use super::mediafile::create_library;
+use crate::scanner::extras;
+
+use dim_database::episode::InsertableEpisode;
+use dim_database::library::MediaType;
+use dim_database::media::InsertableMedia;
+use dim_database::mediafile::ExtraType;
+use dim_database::mediafile::InsertableMediaFile;
+use dim_database::mediafile::MediaFile;
+use dim_database::season::InsertableSeason;
+
+use std::path::Path;
+
+#[test]
+fn test_classify() {
+    let extra = extras::classify(Path::new("/movies/Movie (2020)/Trailers/Teaser.mkv")).unwrap();
+    assert_eq!(extra.extra_type, ExtraType::Trailer);
+    assert_eq!(extra.parent_dir, Path::new("/movies/Movie (2020)"));
+    assert_eq!(extra.parent_name, None);
+
+    let extra = extras::classify(Path::new(
+        "/movies/Movie (2020)/Behind The Scenes/Stunts.mkv",
+    ))
+    .unwrap();
+    assert_eq!(extra.extra_type, ExtraType::BehindTheScenes);
+
+    let extra = extras::classify(Path::new("/movies/Movie (2020)/Extras/Bloopers.mkv")).unwrap();
+    assert_eq!(extra.extra_type, ExtraType::Other);
+
+    let extra = extras::classify(Path::new(
+        "/movies/Movie (2020)/Movie (2020)-Featurette.mp4",
+    ))
+    .unwrap();
+    assert_eq!(extra.extra_type, ExtraType::Featurette);
+    assert_eq!(extra.parent_dir, Path::new("/movies/Movie (2020)"));
+    assert_eq!(extra.parent_name, Some("Movie (2020)"));
+
+    assert!(extras::classify(Path::new("/movies/Movie (2020)/Movie (2020).mkv")).is_none());
+    assert!(extras::classify(Path::new("/movies/Trailer Park Boys (2006).mkv")).is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_attach_extras() {
+    let mut conn = dim_database::get_conn_memory()
+        .await
+        .expect("Failed to obtain a in-memory db pool.");
+    let library_id = create_library(&mut conn).await;
+
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = dim_database::write_tx(&mut lock).await.unwrap();
+
+    let media_id = InsertableMedia {
+        library_id,
+        name: "Movie".into(),
+        media_type: MediaType::Movie,
+        ..Default::default()
+    }
+    .insert(&mut tx)
+    .await
+    .unwrap();
+
+    let insert = |target_file: &str, media_id: Option<i64>| InsertableMediaFile {
+        library_id,
+        media_id,
+        target_file: target_file.into(),
+        raw_name: "Movie".into(),
+        extra_type: extras::extra_type(Path::new(target_file)),
+        ..Default::default()
+    };
+
+    let parent = insert("/movies/Movie (2020)/Movie (2020).mkv", Some(media_id))
+        .insert(&mut tx)
+        .await
+        .unwrap();
+    let trailer = insert("/movies/Movie (2020)/Trailers/Teaser.mkv", None)
+        .insert(&mut tx)
+        .await
+        .unwrap();
+    let orphan = insert("/movies/Other (2021)/Extras/Bloopers.mkv", None)
+        .insert(&mut tx)
+        .await
+        .unwrap();
+
+    // extras are only attached when a file next to them is part of the batch.
+    let batch = ["/movies/Unrelated (2022)/Unrelated (2022).mkv".to_string()];
+    assert_eq!(
+        extras::attach_extras(&mut tx, library_id, &batch)
+            .await
+            .unwrap(),
+        0
+    );
+
+    let batch = [
+        "/movies/Movie (2020)/Movie (2020).mkv".to_string(),
+        "/movies/Other (2021)/Extras/Bloopers.mkv".to_string(),
+    ];
+    assert_eq!(
+        extras::attach_extras(&mut tx, library_id, &batch)
+            .await
+            .unwrap(),
+        1
+    );
+
+    let extras = MediaFile::get_extras_of_media(&mut tx, media_id)
+        .await
+        .unwrap();
+    assert_eq!(extras.len(), 1);
+    assert_eq!(extras[0].id, trailer);
+    assert_eq!(extras[0].extra_type, Some(ExtraType::Trailer));
+
+    // extras aren't versions of the movie.
+    let files = MediaFile::get_of_media(&mut tx, media_id).await.unwrap();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].id, parent);
+
+    let orphan = MediaFile::get_one(&mut tx, orphan).await.unwrap();
+    assert_eq!(orphan.media_id, None);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_attach_tv_extras() {
+    let mut conn = dim_database::get_conn_memory()
+        .await
+        .expect("Failed to obtain a in-memory db pool.");
+    let library_id = create_library(&mut conn).await;
+
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = dim_database::write_tx(&mut lock).await.unwrap();
+
+    let show_id = InsertableMedia {
+        library_id,
+        name: "Show".into(),
+        media_type: MediaType::Tv,
+        ..Default::default()
+    }
+    .insert(&mut tx)
+    .await
+    .unwrap();
+
+    let season_id = InsertableSeason {
+        season_number: 1,
+        ..Default::default()
+    }
+    .insert(&mut tx, show_id)
+    .await
+    .unwrap();
+
+    let mut episodes = vec![];
+    for episode in 1..=2 {
+        let episode_id = InsertableEpisode {
+            media: InsertableMedia {
+                library_id,
+                name: format!("Episode {episode}"),
+                media_type: MediaType::Episode,
+                ..Default::default()
+            },
+            seasonid: season_id,
+            episode,
+        }
+        .insert(&mut tx)
+        .await
+        .unwrap();
+
+        episodes.push(episode_id);
+    }
+
+    let insert = |target_file: &str, media_id: Option<i64>| InsertableMediaFile {
+        library_id,
+        media_id,
+        target_file: target_file.into(),
+        raw_name: "Show".into(),
+        extra_type: extras::extra_type(Path::new(target_file)),
+        ..Default::default()
+    };
+
+    for (episode, media_id) in episodes.iter().enumerate() {
+        insert(
+            &format!("/tv/Show/Season 1/Show S01E0{}.mkv", episode + 1),
+            Some(*media_id),
+        )
+        .insert(&mut tx)
+        .await
+        .unwrap();
+    }
+
+    let featurette = insert("/tv/Show/Featurettes/Making Of.mkv", None)
+        .insert(&mut tx)
+        .await
+        .unwrap();
+    let deleted = insert("/tv/Show/Season 1/Show S01E02-deleted.mkv", None)
+        .insert(&mut tx)
+        .await
+        .unwrap();
+
+    // the folder of the show is checked for extras too when a episode gets matched.
+    let batch = ["/tv/Show/Season 1/Show S01E01.mkv".to_string()];
+    assert_eq!(
+        extras::attach_extras(&mut tx, library_id, &batch)
+            .await
+            .unwrap(),
+        2
+    );
+
+    // extras named after a episode belong to the episode.
+    let deleted = MediaFile::get_one(&mut tx, deleted).await.unwrap();
+    assert_eq!(deleted.media_id, Some(episodes[1]));
+
+    // extras stored in the show folder belong to the show.
+    let extras = MediaFile::get_extras_of_media(&mut tx, show_id)
+        .await
+        .unwrap();
+    assert_eq!(extras.len(), 1);
+    assert_eq!(extras[0].id, featurette);
+}
@@ -1,4 +1,7 @@
+use super::super::ScanFilter;
 use super::temp_dir;
+use dim_database::library::MediaType;
+use dim_database::library::ScanRules;
 use std::path::PathBuf;
 
 #[tokio::test(flavor = "multi_thread")]
@@ -12,7 +15,10 @@ async fn test_walkdir() {
         ".hidden.mp4",
     ]);
 
-    let mut files = super::super::get_subfiles([tempdir.path()].iter());
+    let mut files = super::super::get_subfiles(
+        [tempdir.path()].iter(),
+        &ScanFilter::for_media_type(MediaType::Movie),
+    );
     files.sort();
 
     let mut expected: Vec<PathBuf> =
@@ -24,3 +30,74 @@ async fn test_walkdir() {
 
     assert_eq!(files, expected);
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_walkdir_music() {
+    let tempdir = temp_dir(vec![
+        "file1.mkv",
+        "Artist/Album/01 - Track.flac",
+        "Artist/Album/02 - Track.mp3",
+        "Artist/Album/cover.jpg",
+    ]);
+
+    let mut files = super::super::get_subfiles(
+        [tempdir.path()].iter(),
+        &ScanFilter::for_media_type(MediaType::Music),
+    );
+    files.sort();
+
+    let mut expected: Vec<PathBuf> = IntoIterator::into_iter([
+        "Artist/Album/01 - Track.flac",
+        "Artist/Album/02 - Track.mp3",
+    ])
+    .map(|x| tempdir.path().join(x))
+    .collect();
+
+    expected.sort();
+
+    assert_eq!(files, expected);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_walkdir_scan_rules() {
+    let tempdir = temp_dir(vec![
+        "Movie (2020)/Movie (2020).mkv",
+        "Movie (2020)/Movie (2020).avi",
+        "Movie (2020)/Sample/sample.mkv",
+        "Movie (2020)/tiny.mkv",
+        "Other (2021)/Other (2021).STRM",
+    ]);
+
+    // everything but `tiny.mkv` is large enough to pass the size check.
+    for file in [
+        "Movie (2020)/Movie (2020).mkv",
+        "Movie (2020)/Movie (2020).avi",
+        "Movie (2020)/Sample/sample.mkv",
+        "Other (2021)/Other (2021).STRM",
+    ] {
+        std::fs::write(tempdir.path().join(file), [0u8; 64]).unwrap();
+    }
+
+    let rules = ScanRules {
+        extra_exts: vec!["strm".into()],
+        excluded_exts: vec![".AVI".into()],
+        exclude_globs: vec!["**/Sample/".into()],
+        min_file_size: Some(32),
+        ..Default::default()
+    };
+
+    let filter = ScanFilter::new(MediaType::Movie, rules, &[tempdir.path()]);
+    let mut files = super::super::get_subfiles([tempdir.path()].iter(), &filter);
+    files.sort();
+
+    let mut expected: Vec<PathBuf> = IntoIterator::into_iter([
+        "Movie (2020)/Movie (2020).mkv",
+        "Other (2021)/Other (2021).STRM",
+    ])
+    .map(|x| tempdir.path().join(x))
+    .collect();
+
+    expected.sort();
+
+    assert_eq!(files, expected);
+}
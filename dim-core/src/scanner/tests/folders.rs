@@ -0,0 +1,90 @@
+use crate::scanner::folders::folder_hints;
+use crate::scanner::folders::season_folder;
+use crate::scanner::parse_filenames;
+use crate::scanner::FilenameParser;
+
+use dim_database::library::MediaType;
+
+use std::path::Path;
+use std::path::PathBuf;
+
+#[test]
+fn test_season_folder() {
+    assert_eq!(season_folder("Season 1"), Some(1));
+    assert_eq!(season_folder("season.02"), Some(2));
+    assert_eq!(season_folder("Series 3"), Some(3));
+    assert_eq!(season_folder("S04"), Some(4));
+    assert_eq!(season_folder("Specials"), Some(0));
+    assert_eq!(season_folder("Season 00"), Some(0));
+
+    assert_eq!(season_folder("Se7en"), None);
+    assert_eq!(season_folder("Seasons"), None);
+    assert_eq!(season_folder("Show"), None);
+}
+
+#[test]
+fn test_folder_hints() {
+    let roots = [PathBuf::from("/media/tv"), PathBuf::from("/media/movies")];
+
+    let hints = folder_hints(Path::new("/media/tv/Show (2019)/Season 2/01.mkv"), &roots);
+    assert_eq!(hints.title.as_deref(), Some("Show"));
+    assert_eq!(hints.year, Some(2019));
+    assert_eq!(hints.season, Some(2));
+    assert_eq!(hints.folder, Some(PathBuf::from("/media/tv/Show (2019)")));
+
+    let hints = folder_hints(
+        Path::new("/media/movies/Movie Name (2004)/abc-xyz.mkv"),
+        &roots,
+    );
+    assert_eq!(hints.title.as_deref(), Some("Movie Name"));
+    assert_eq!(hints.year, Some(2004));
+    assert_eq!(hints.season, None);
+
+    // years at the end of a title are only taken as the release year if they are set apart.
+    let title_year = |folder: &str| {
+        let hints = folder_hints(
+            &Path::new("/media/movies").join(folder).join("abc-xyz.mkv"),
+            &roots,
+        );
+        (hints.title.unwrap_or_default(), hints.year)
+    };
+
+    assert_eq!(
+        title_year("Blade Runner 2049"),
+        ("Blade Runner 2049".into(), None)
+    );
+    assert_eq!(
+        title_year("Blade Runner 2049 (2017)"),
+        ("Blade Runner 2049".into(), Some(2017))
+    );
+    assert_eq!(
+        title_year("Blade Runner 2049 2017"),
+        ("Blade Runner 2049".into(), Some(2017))
+    );
+    assert_eq!(title_year("1917"), ("1917".into(), None));
+    assert_eq!(title_year("1917 [2019]"), ("1917".into(), Some(2019)));
+    assert_eq!(title_year("2012"), ("2012".into(), None));
+
+    // the library root doesn't tell us anything.
+    let hints = folder_hints(Path::new("/media/movies/abc-xyz.mkv"), &roots);
+    assert_eq!(hints, Default::default());
+
+    let hints = folder_hints(Path::new("/elsewhere/Movie (2004)/abc-xyz.mkv"), &roots);
+    assert_eq!(hints, Default::default());
+}
+
+#[test]
+fn test_folder_candidates() {
+    let parser = FilenameParser::new(MediaType::Movie, &Default::default(), &["/movies"]);
+    let parsed = parse_filenames(["/movies/Movie Name (2004)/abc-xyz.mkv"].iter(), &parser);
+    let (_, metas) = &parsed[0];
+    assert_eq!(metas[0].name, "Movie Name");
+    assert_eq!(metas[0].year, Some(2004));
+
+    let parser = FilenameParser::new(MediaType::Tv, &Default::default(), &["/tv"]);
+    let parsed = parse_filenames(["/tv/Show/Season 3/01.mkv"].iter(), &parser);
+    let (_, metas) = &parsed[0];
+    assert_eq!(metas[0].name, "Show");
+    assert_eq!(metas[0].season, Some(3));
+    assert_eq!(metas[0].episode, Some(1));
+}
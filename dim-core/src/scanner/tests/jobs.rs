@@ -0,0 +1,40 @@
+use super::super::jobs::JobState;
+use super::super::jobs::ScanJobs;
+use super::mediafile::create_library;
+
+use dim_extern_api::local::LocalProvider;
+
+use std::sync::Arc;
+
+#[tokio::test]
+async fn test_cancel_queued_scan() {
+    let mut conn = dim_database::get_conn_memory()
+        .await
+        .expect("Failed to obtain a in-memory db pool.");
+    let library = create_library(&mut conn).await;
+
+    let jobs: &'static ScanJobs = Box::leak(Box::default());
+    let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+
+    // NOTE: the scan only starts once we yield to the runtime.
+    let job = jobs.enqueue(conn.clone(), library, tx.clone(), Arc::new(LocalProvider));
+    assert_eq!(job.state, JobState::Queued);
+
+    // a library only ever has one queued scan.
+    let duplicate = jobs.enqueue(conn.clone(), library, tx, Arc::new(LocalProvider));
+    assert_eq!(duplicate.id, job.id);
+    assert_eq!(jobs.list().len(), 1);
+
+    let cancelled = jobs.cancel(library);
+    assert_eq!(cancelled.len(), 1);
+    assert_eq!(cancelled[0].state, JobState::Cancelled);
+
+    tokio::task::yield_now().await;
+
+    let list = jobs.list();
+    assert_eq!(list.len(), 1);
+    assert_eq!(list[0].state, JobState::Cancelled);
+    assert!(list[0].started_at.is_none());
+
+    assert!(jobs.cancel(library).is_empty());
+}
@@ -1,18 +1,33 @@
+use super::super::mediafile::content_hash;
 use super::super::mediafile::Error as CreatorError;
 use super::super::mediafile::InsertBatch;
 use super::super::mediafile::MediafileCreator;
 use super::super::parse_filenames;
+use super::super::scan_pipeline;
+use super::super::Error;
+use super::super::FilenameParser;
+use super::super::MediaMatcher;
+use super::super::ScanProgress;
+use super::super::ScanSummary;
+use super::super::WorkUnit;
+use super::super::INSERT_BATCH_SIZE;
 
 use dim_database::library::InsertableLibrary;
 use dim_database::library::MediaType;
 use dim_database::mediafile::InsertableMediaFile;
 use dim_database::mediafile::MediaFile;
 
+use dim_extern_api::local::LocalProvider;
+use dim_extern_api::ExternalQueryIntoShow;
+
 use futures::stream::FuturesUnordered;
 use futures::StreamExt;
 use itertools::Itertools;
 
 use std::future::Future;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
 
 use core::pin::Pin;
 
@@ -21,6 +36,12 @@ use futures::FutureExt;
 use xtra::spawn::Tokio;
 use xtra::Actor;
 
+/// Function returns a progress tracker whose events go nowhere.
+fn scan_progress(library_id: i64) -> Arc<ScanProgress> {
+    let (tx, _) = tokio::sync::mpsc::unbounded_channel();
+    Arc::new(ScanProgress::new(library_id, tx))
+}
+
 pub(crate) async fn create_library(conn: &mut dim_database::DbConnection) -> i64 {
     let mut lock = conn.writer().lock_owned().await;
     let mut tx = dim_database::write_tx(&mut lock).await.unwrap();
@@ -29,6 +50,12 @@ pub(crate) async fn create_library(conn: &mut dim_database::DbConnection) -> i64
         name: "Tests".to_string(),
         locations: vec![],
         media_type: MediaType::Movie,
+        metadata_provider: Default::default(),
+        prefer_local_artwork: true,
+        scan_rules: Default::default(),
+        filename_rules: Default::default(),
+        rescan_schedule: None,
+        watcher_mode: Default::default(),
     }
     .insert(&mut tx)
     .await
@@ -53,7 +80,10 @@ async fn test_construct_mediafile() {
 
     let mut instance = MediafileCreator::new(conn.clone(), library).await;
 
-    let parsed = parse_filenames(files.iter());
+    let parsed = parse_filenames(
+        files.iter(),
+        &FilenameParser::new(MediaType::Movie, &Default::default(), &[_tempdir.path()]),
+    );
 
     assert_eq!(parsed.len(), files.len());
 
@@ -128,7 +158,10 @@ async fn test_multiple_instances() {
 
     let instance = MediafileCreator::new(conn.clone(), library).await;
 
-    let parsed = parse_filenames(files.iter());
+    let parsed = parse_filenames(
+        files.iter(),
+        &FilenameParser::new(MediaType::Movie, &Default::default(), &[_tempdir.path()]),
+    );
 
     assert_eq!(parsed.len(), files.len());
 
@@ -182,3 +215,277 @@ async fn test_multiple_instances() {
 
     assert_eq!(mediafiles.len(), files.len());
 }
+
+/// Matcher that only records how many work units it has been handed.
+#[derive(Default)]
+struct CountingMatcher {
+    units: AtomicUsize,
+}
+
+#[async_trait::async_trait]
+impl MediaMatcher for CountingMatcher {
+    async fn batch_match(
+        &self,
+        _: &mut dim_database::Transaction<'_>,
+        _: Arc<dyn ExternalQueryIntoShow>,
+        work: Vec<WorkUnit>,
+    ) -> Result<(), Error> {
+        assert!(work.len() <= INSERT_BATCH_SIZE);
+        self.units.fetch_add(work.len(), Ordering::SeqCst);
+        Ok(())
+    }
+
+    async fn match_to_id(
+        &self,
+        _: &mut dim_database::Transaction<'_>,
+        _: Arc<dyn ExternalQueryIntoShow>,
+        _: WorkUnit,
+        _: &str,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn cleanup(&self, _: &mut dim_database::Transaction<'_>, _: i64) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_scan_pipeline() {
+    let files = (0..300)
+        .map(|i| format!("Movie{i}.mkv"))
+        .collect::<Vec<String>>();
+    let (tempdir, files) = super::temp_dir_symlink(files.into_iter(), super::TEST_MP4_PATH);
+
+    let mut conn = dim_database::get_conn_memory()
+        .await
+        .expect("Failed to obtain a in-memory db pool.");
+    let library = create_library(&mut conn).await;
+
+    let matcher = Arc::new(CountingMatcher::default());
+    let summary = scan_pipeline(
+        &mut conn,
+        library,
+        MediaType::Movie,
+        vec![tempdir.path().to_owned()],
+        matcher.clone(),
+        Arc::new(LocalProvider),
+        scan_progress(library),
+        Default::default(),
+    )
+    .await
+    .expect("Failed to run scan pipeline.");
+
+    assert_eq!(summary.added, files.len());
+    assert_eq!(matcher.units.load(Ordering::SeqCst), files.len());
+
+    // Rescanning the same directory should not hand any files to the matcher as they all exist.
+    let summary = scan_pipeline(
+        &mut conn,
+        library,
+        MediaType::Movie,
+        vec![tempdir.path().to_owned()],
+        matcher.clone(),
+        Arc::new(LocalProvider),
+        scan_progress(library),
+        Default::default(),
+    )
+    .await
+    .expect("Failed to run scan pipeline.");
+
+    assert_eq!(summary, ScanSummary::default());
+    assert_eq!(matcher.units.load(Ordering::SeqCst), files.len());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_incremental_rescan() {
+    let files = (0..8)
+        .map(|i| format!("Movie{i}.mkv"))
+        .collect::<Vec<String>>();
+    let (tempdir, files) = super::temp_dir_symlink(files.into_iter(), super::TEST_MP4_PATH);
+
+    let mut conn = dim_database::get_conn_memory()
+        .await
+        .expect("Failed to obtain a in-memory db pool.");
+    let library = create_library(&mut conn).await;
+    let matcher = Arc::new(CountingMatcher::default());
+
+    let summary = scan_pipeline(
+        &mut conn,
+        library,
+        MediaType::Movie,
+        vec![tempdir.path().to_owned()],
+        matcher.clone(),
+        Arc::new(LocalProvider),
+        scan_progress(library),
+        Default::default(),
+    )
+    .await
+    .expect("Failed to run scan pipeline.");
+
+    assert_eq!(summary.added, files.len());
+
+    // Replacing a hardlink with a copy gives the file a new inode, thus it should be re-probed.
+    std::fs::remove_file(&files[0]).unwrap();
+    std::fs::copy(super::TEST_MP4_PATH, &files[0]).unwrap();
+    std::fs::remove_file(&files[1]).unwrap();
+
+    let summary = scan_pipeline(
+        &mut conn,
+        library,
+        MediaType::Movie,
+        vec![tempdir.path().to_owned()],
+        matcher.clone(),
+        Arc::new(LocalProvider),
+        scan_progress(library),
+        Default::default(),
+    )
+    .await
+    .expect("Failed to run scan pipeline.");
+
+    assert_eq!(
+        summary,
+        ScanSummary {
+            added: 0,
+            changed: 1,
+            missing: 1,
+            moved: 0,
+        }
+    );
+
+    let mut tx = conn.read().begin().await.unwrap();
+    let missing = MediaFile::get_by_lib(&mut tx, library)
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|x| x.missing)
+        .map(|x| x.target_file)
+        .collect::<Vec<_>>();
+
+    assert_eq!(missing, vec![files[1].to_string_lossy().to_string()]);
+}
+
+/// Write a file big enough to be content hashed. `seed` changes the contents of the file.
+fn write_hashable_file(path: &std::path::Path, seed: u8) {
+    let contents = (0..256 * 1024)
+        .map(|i| (i % 251) as u8 ^ seed)
+        .collect::<Vec<_>>();
+
+    std::fs::write(path, contents).expect("Failed to write test file.");
+}
+
+#[test]
+fn test_content_hash() {
+    let tempdir = super::temp_dir(["small.mkv"]);
+    let small = tempdir.path().join("small.mkv");
+    let a = tempdir.path().join("a.mkv");
+    let b = tempdir.path().join("b.mkv");
+    let c = tempdir.path().join("c.mkv");
+
+    write_hashable_file(&a, 1);
+    write_hashable_file(&b, 1);
+    write_hashable_file(&c, 2);
+
+    assert_eq!(content_hash(&small).unwrap(), None);
+
+    let hash = content_hash(&a).unwrap().expect("File should be hashable.");
+    assert_eq!(hash.len(), 16);
+    assert_eq!(content_hash(&b).unwrap(), Some(hash.clone()));
+    assert_ne!(content_hash(&c).unwrap(), Some(hash));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_move_detection() {
+    let tempdir = super::temp_dir(Vec::<&str>::new());
+    let original = tempdir.path().join("Movie1.mkv");
+    write_hashable_file(&original, 1);
+    write_hashable_file(&tempdir.path().join("Movie2.mkv"), 2);
+
+    let mut conn = dim_database::get_conn_memory()
+        .await
+        .expect("Failed to obtain a in-memory db pool.");
+    let library = create_library(&mut conn).await;
+    let matcher = Arc::new(CountingMatcher::default());
+
+    let summary = scan_pipeline(
+        &mut conn,
+        library,
+        MediaType::Movie,
+        vec![tempdir.path().to_owned()],
+        matcher.clone(),
+        Arc::new(LocalProvider),
+        scan_progress(library),
+        Default::default(),
+    )
+    .await
+    .expect("Failed to run scan pipeline.");
+
+    assert_eq!(summary.added, 2);
+
+    let original_id = {
+        let mut tx = conn.read().begin().await.unwrap();
+        MediaFile::get_by_file(&mut tx, original.to_str().unwrap())
+            .await
+            .unwrap()
+            .id
+    };
+
+    let moved = tempdir.path().join("Moved/Movie1 (2010).mkv");
+    std::fs::create_dir(tempdir.path().join("Moved")).unwrap();
+    std::fs::rename(&original, &moved).unwrap();
+
+    let summary = scan_pipeline(
+        &mut conn,
+        library,
+        MediaType::Movie,
+        vec![tempdir.path().to_owned()],
+        matcher.clone(),
+        Arc::new(LocalProvider),
+        scan_progress(library),
+        Default::default(),
+    )
+    .await
+    .expect("Failed to run scan pipeline.");
+
+    assert_eq!(
+        summary,
+        ScanSummary {
+            added: 0,
+            changed: 0,
+            missing: 0,
+            moved: 1,
+        }
+    );
+
+    let mut tx = conn.read().begin().await.unwrap();
+    let mfile = MediaFile::get_by_file(&mut tx, moved.to_str().unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(mfile.id, original_id);
+    assert!(!mfile.missing);
+}
+
+#[test]
+fn test_specials_folder() {
+    let files = [
+        "/tv/Show/Specials/Show - 03.mkv",
+        "/tv/Show/Season 00/Show - 04.mkv",
+        "/tv/Show/Season 1/Show S01E03.mkv",
+    ];
+
+    let parsed = parse_filenames(
+        files.iter(),
+        &FilenameParser::new(MediaType::Tv, &Default::default(), &["/tv"]),
+    );
+    assert_eq!(parsed.len(), files.len());
+
+    let seasons = parsed
+        .iter()
+        .map(|(_, metas)| metas.iter().map(|x| x.season).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    assert!(seasons[0].iter().all(|x| *x == Some(0)));
+    assert!(seasons[1].iter().all(|x| *x == Some(0)));
+    assert!(seasons[2].iter().all(|x| *x == Some(1)));
+}
@@ -0,0 +1,92 @@
+use super::mediafile::create_library;
+use crate::scanner::missing;
+
+use dim_database::library::MediaType;
+use dim_database::media::InsertableMedia;
+use dim_database::media::Media;
+use dim_database::mediafile::InsertableMediaFile;
+use dim_database::mediafile::MediaFile;
+use dim_database::mediafile::UpdateMediaFile;
+
+async fn insert_movie(
+    tx: &mut dim_database::Transaction<'_>,
+    library_id: i64,
+    name: &str,
+) -> (i64, i64) {
+    let media_id = InsertableMedia {
+        library_id,
+        name: name.into(),
+        media_type: MediaType::Movie,
+        ..Default::default()
+    }
+    .insert(tx)
+    .await
+    .unwrap();
+
+    let mfile_id = InsertableMediaFile {
+        library_id,
+        media_id: Some(media_id),
+        target_file: format!("/movies/{name}.mkv"),
+        raw_name: name.into(),
+        ..Default::default()
+    }
+    .insert(tx)
+    .await
+    .unwrap();
+
+    (media_id, mfile_id)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_purge_missing() {
+    let mut conn = dim_database::get_conn_memory()
+        .await
+        .expect("Failed to obtain a in-memory db pool.");
+    let library_id = create_library(&mut conn).await;
+
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = dim_database::write_tx(&mut lock).await.unwrap();
+
+    let (gone, gone_file) = insert_movie(&mut tx, library_id, "Gone").await;
+    let (kept, _) = insert_movie(&mut tx, library_id, "Kept").await;
+
+    UpdateMediaFile {
+        missing: Some(true),
+        ..Default::default()
+    }
+    .update(&mut tx, gone_file)
+    .await
+    .unwrap();
+
+    let missing_since = MediaFile::get_one(&mut tx, gone_file)
+        .await
+        .unwrap()
+        .missing_since
+        .unwrap();
+
+    tx.commit().await.unwrap();
+    drop(lock);
+
+    // the file is still within its grace period.
+    let purged = missing::purge_missing(&conn, None, missing_since)
+        .await
+        .unwrap();
+    assert!(purged.is_empty());
+
+    // files of other libraries are left alone.
+    let purged = missing::purge_missing(&conn, Some(library_id + 1), i64::MAX)
+        .await
+        .unwrap();
+    assert!(purged.is_empty());
+
+    let purged = missing::purge_missing(&conn, Some(library_id), missing_since + 1)
+        .await
+        .unwrap();
+    assert_eq!(purged.len(), 1);
+    assert_eq!(purged[0].id, gone_file);
+
+    let mut tx = conn.read().begin().await.unwrap();
+    assert!(MediaFile::get_one(&mut tx, gone_file).await.is_err());
+    assert!(Media::get(&mut tx, gone).await.is_err());
+    assert!(Media::get(&mut tx, kept).await.is_ok());
+}
@@ -1,5 +1,17 @@
+mod artwork;
+mod confidence;
+mod extras;
 mod file_walker;
+mod folders;
+mod jobs;
 pub(crate) mod mediafile;
+mod missing;
+mod poller;
+mod problems;
+mod progress;
+mod schedule;
+mod stacking;
+mod subtitles;
 
 use std::fs::hard_link;
 use std::fs::File;
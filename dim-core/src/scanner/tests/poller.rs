@@ -0,0 +1,90 @@
+use super::super::poller::mount_fs_type;
+use super::super::poller::Snapshot;
+use super::temp_dir;
+
+use notify::event::ModifyKind;
+use notify::event::RenameMode;
+use notify::EventKind;
+
+use std::path::Path;
+
+// NOTE: renames are detected through inodes, which only exist on unix.
+#[cfg(unix)]
+#[test]
+fn test_snapshot_diff() {
+    let tempdir = temp_dir(vec![
+        "Movie (2020)/Movie (2020).mkv",
+        "Other (2021)/Other (2021).mkv",
+        "Deleted (2019)/Deleted (2019).mkv",
+    ]);
+    let root = tempdir.path().to_path_buf();
+    let before = Snapshot::take(&[root.clone()]);
+
+    std::fs::rename(
+        root.join("Movie (2020)/Movie (2020).mkv"),
+        root.join("Movie (2020)/Renamed (2020).mkv"),
+    )
+    .unwrap();
+    std::fs::remove_file(root.join("Deleted (2019)/Deleted (2019).mkv")).unwrap();
+    std::fs::create_dir_all(root.join("New (2022)/Subs")).unwrap();
+    // NOTE: the new file must differ in size from the deleted one, in case its inode gets reused.
+    std::fs::write(root.join("New (2022)/New (2022).mkv"), b"new").unwrap();
+
+    let after = Snapshot::take(&[root.clone()]);
+    let events = before.diff(&after);
+
+    let of_kind = |f: fn(&EventKind) -> bool| {
+        events
+            .iter()
+            .filter(|x| f(&x.kind))
+            .map(|x| x.paths.clone())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        of_kind(|x| matches!(x, EventKind::Modify(ModifyKind::Name(RenameMode::Both)))),
+        vec![vec![
+            root.join("Movie (2020)/Movie (2020).mkv"),
+            root.join("Movie (2020)/Renamed (2020).mkv"),
+        ]]
+    );
+
+    assert_eq!(
+        of_kind(EventKind::is_remove),
+        vec![vec![root.join("Deleted (2019)/Deleted (2019).mkv")]]
+    );
+    // created directories are reported without their contents.
+    assert_eq!(
+        of_kind(EventKind::is_create),
+        vec![vec![root.join("New (2022)")]]
+    );
+
+    assert!(after.diff(&after).is_empty());
+}
+
+#[test]
+fn test_mount_fs_type() {
+    let mounts = "\
+/dev/sda1 / ext4 rw,relatime 0 0
+nas:/export/media /mnt/media nfs4 rw,relatime 0 0
+//nas/share /mnt/my\\040share cifs rw 0 0
+sshfs#host: /mnt/remote fuse.sshfs rw 0 0
+";
+
+    assert_eq!(
+        mount_fs_type(mounts, Path::new("/home/user/movies")),
+        Some("ext4")
+    );
+    assert_eq!(
+        mount_fs_type(mounts, Path::new("/mnt/media/movies")),
+        Some("nfs4")
+    );
+    assert_eq!(
+        mount_fs_type(mounts, Path::new("/mnt/my share/tv")),
+        Some("cifs")
+    );
+    assert_eq!(
+        mount_fs_type(mounts, Path::new("/mnt/remote")),
+        Some("fuse.sshfs")
+    );
+}
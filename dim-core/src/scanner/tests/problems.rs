@@ -0,0 +1,142 @@
+use super::super::problems::reprobe;
+use super::mediafile::create_library;
+
+use crate::streaming::ffprobe::FFPStream;
+use crate::streaming::ffprobe::ProbeProblem;
+
+use dim_database::mediafile::InsertableMediaFile;
+use dim_database::mediafile::MediaFile;
+
+/// Function returns the json ffprobe prints for a file with streams of `codec_types` lasting
+/// `duration` seconds.
+fn ffprobe_json(codec_types: &[&str], duration: &str) -> String {
+    let streams = codec_types
+        .iter()
+        .enumerate()
+        .map(|(index, codec_type)| {
+            serde_json::json!({
+                "index": index,
+                "codec_name": "test",
+                "codec_type": codec_type,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    serde_json::json!({
+        "streams": streams,
+        "format": {
+            "filename": "test.mkv",
+            "nb_streams": codec_types.len(),
+            "nb_programs": 0,
+            "format_name": "matroska,webm",
+            "format_long_name": "Matroska / WebM",
+            "start_time": "0.000000",
+            "duration": duration,
+            "size": "1024",
+            "bit_rate": "8192",
+        }
+    })
+    .to_string()
+}
+
+#[test]
+fn test_probe_problem() {
+    let fine = FFPStream::from_output(&ffprobe_json(&["video", "audio"], "60.0"), "");
+    assert_eq!(fine.problem(true), None);
+
+    let song = FFPStream::from_output(&ffprobe_json(&["audio"], "60.0"), "");
+    assert_eq!(song.problem(false), None);
+    assert_eq!(song.problem(true), Some(ProbeProblem::NoVideoStream));
+
+    let video = FFPStream::from_output(&ffprobe_json(&["video"], "60.0"), "");
+    assert_eq!(video.problem(false), Some(ProbeProblem::NoAudioStream));
+
+    let empty = FFPStream::from_output(&ffprobe_json(&["video", "audio"], "0.000000"), "");
+    assert_eq!(empty.problem(true), Some(ProbeProblem::ZeroDuration));
+
+    let truncated =
+        FFPStream::from_output("", "[mov,mp4,m4a,3gp,3g2,mj2 @ 0x0] moov atom not found\n");
+    assert!(matches!(
+        truncated.problem(true),
+        Some(ProbeProblem::Truncated(x)) if x.contains("moov atom not found")
+    ));
+
+    let unreadable =
+        FFPStream::from_output("", "test.mkv: Invalid data found when processing input");
+    assert_eq!(
+        unreadable.problem(true),
+        Some(ProbeProblem::FfprobeError(
+            "test.mkv: Invalid data found when processing input".into()
+        ))
+    );
+    assert_eq!(
+        FFPStream::from_output("", "").problem(true),
+        Some(ProbeProblem::Unreadable)
+    );
+}
+
+#[test]
+fn test_probe_warnings() {
+    // files that play fine are not flagged for what ffprobe warns about.
+    let warnings = [
+        "[h264 @ 0x0] non-existing PPS 0 referenced\n[h264 @ 0x0] decode_slice_header error\n",
+        "[matroska,webm @ 0x0] File ended prematurely at pos. 1024\n",
+        "[mp3 @ 0x0] Estimating duration from bitrate, this may be inaccurate\n",
+    ];
+
+    for stderr in warnings {
+        let file = FFPStream::from_output(&ffprobe_json(&["video", "audio"], "60.0"), stderr);
+        assert_eq!(file.problem(true), None, "{stderr}");
+    }
+
+    // unless the streams or duration are unusable.
+    let file = FFPStream::from_output(&ffprobe_json(&["video", "audio"], "0.0"), warnings[1]);
+    assert_eq!(
+        file.problem(true),
+        Some(ProbeProblem::FfprobeError(warnings[1].trim().into()))
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_reprobe() {
+    let tempdir = super::temp_dir(["Movie (2020).mkv"]);
+    let file = tempdir.path().join("Movie (2020).mkv");
+
+    let mut conn = dim_database::get_conn_memory()
+        .await
+        .expect("Failed to obtain a in-memory db pool.");
+    let library_id = create_library(&mut conn).await;
+
+    let id = {
+        let mut lock = conn.writer().lock_owned().await;
+        let mut tx = dim_database::write_tx(&mut lock).await.unwrap();
+
+        let id = InsertableMediaFile {
+            library_id,
+            target_file: file.to_string_lossy().to_string(),
+            raw_name: "Movie".into(),
+            raw_year: Some(2020),
+            corrupt: Some(false),
+            ..Default::default()
+        }
+        .insert(&mut tx)
+        .await
+        .unwrap();
+
+        tx.commit().await.unwrap();
+        id
+    };
+
+    // the file is empty, so it cant be played.
+    let mediafile = reprobe(&conn, id)
+        .await
+        .expect("Failed to probe mediafile.");
+    assert!(mediafile.probe_problem.is_some());
+    assert_eq!(mediafile.raw_name, "Movie");
+    assert_eq!(mediafile.raw_year, Some(2020));
+
+    let mut tx = conn.read().begin().await.unwrap();
+    let problems = MediaFile::get_problems(&mut tx, library_id).await.unwrap();
+    assert_eq!(problems.len(), 1);
+    assert_eq!(problems[0].id, id);
+}
@@ -0,0 +1,68 @@
+use super::super::ScanProgress;
+use super::super::ScanSummary;
+
+use dim_events::PushEventType;
+use dim_events::ScanPhase;
+
+use std::path::Path;
+
+#[test]
+fn test_progress_rate_limited() {
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let progress = ScanProgress::new(1, tx);
+
+    progress.discovered(Path::new("/movies/a.mkv"));
+    progress.discovered(Path::new("/movies/b.mkv"));
+    progress.discovered(Path::new("/movies/c.mkv"));
+
+    // only the first of a burst of updates gets dispatched.
+    assert!(rx.try_recv().is_ok());
+    assert!(rx.try_recv().is_err());
+
+    // phase changes are always dispatched.
+    progress.set_phase(ScanPhase::Probing);
+    let event: serde_json::Value = serde_json::from_str(&rx.try_recv().unwrap()).unwrap();
+    assert_eq!(event["id"], 1);
+    assert_eq!(event["type"], "EventScanProgress");
+    assert_eq!(event["phase"], "probing");
+    assert_eq!(event["discovered"], 3);
+    assert_eq!(event["current_path"], "/movies/c.mkv");
+}
+
+#[test]
+fn test_progress_summary() {
+    let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+    let progress = ScanProgress::new(1, tx);
+
+    progress.discovered(Path::new("/movies/a.mkv"));
+    progress.discovered(Path::new("/movies/b.mkv"));
+    progress.probed(Path::new("/movies/a.mkv"));
+    progress.failed("/movies/b.mkv", "ffprobe failed");
+    progress.matched(1);
+
+    let summary = ScanSummary {
+        added: 1,
+        ..Default::default()
+    };
+
+    match progress.summary(&summary) {
+        PushEventType::EventScanSummary {
+            added,
+            discovered,
+            probed,
+            matched,
+            failed,
+            errors,
+            ..
+        } => {
+            assert_eq!(
+                (added, discovered, probed, matched, failed),
+                (1, 2, 1, 1, 1)
+            );
+            assert_eq!(errors.len(), 1);
+            assert_eq!(errors[0].path, "/movies/b.mkv");
+            assert_eq!(errors[0].error, "ffprobe failed");
+        }
+        _ => panic!("Expected a summary event."),
+    }
+}
@@ -0,0 +1,101 @@
+use super::super::schedule::Cron;
+use super::super::schedule::Schedule;
+use super::super::schedule::ScheduleError;
+
+use chrono::NaiveDate;
+use chrono::NaiveDateTime;
+use chrono::TimeZone;
+use chrono::Utc;
+
+use dim_database::library::RescanSchedule;
+
+fn at(y: i32, m: u32, d: u32, h: u32, min: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(y, m, d)
+        .unwrap()
+        .and_hms_opt(h, min, 0)
+        .unwrap()
+}
+
+#[test]
+fn test_cron_next_after() {
+    // every day at 03:30
+    let cron = Cron::parse("30 3 * * *").unwrap();
+    assert_eq!(
+        cron.next_after(at(2023, 10, 29, 1, 0)),
+        Some(at(2023, 10, 29, 3, 30))
+    );
+    // strictly after, so a match at `after` isnt returned.
+    assert_eq!(
+        cron.next_after(at(2023, 10, 29, 3, 30)),
+        Some(at(2023, 10, 30, 3, 30))
+    );
+
+    // every 15 minutes
+    let cron = Cron::parse("*/15 * * * *").unwrap();
+    assert_eq!(
+        cron.next_after(at(2023, 12, 31, 23, 50)),
+        Some(at(2024, 1, 1, 0, 0))
+    );
+
+    // mondays and fridays at midnight, 2023-10-29 is a sunday.
+    let cron = Cron::parse("0 0 * * 1,5").unwrap();
+    assert_eq!(
+        cron.next_after(at(2023, 10, 29, 12, 0)),
+        Some(at(2023, 10, 30, 0, 0))
+    );
+    assert_eq!(
+        cron.next_after(at(2023, 10, 30, 0, 0)),
+        Some(at(2023, 11, 3, 0, 0))
+    );
+
+    // sundays written as 7
+    let cron = Cron::parse("0 12 * * 7").unwrap();
+    assert_eq!(
+        cron.next_after(at(2023, 10, 30, 0, 0)),
+        Some(at(2023, 11, 5, 12, 0))
+    );
+
+    // if both day fields are restricted either of them matches.
+    let cron = Cron::parse("0 0 1 * 1").unwrap();
+    assert_eq!(
+        cron.next_after(at(2023, 10, 29, 0, 0)),
+        Some(at(2023, 10, 30, 0, 0))
+    );
+    assert_eq!(
+        cron.next_after(at(2023, 10, 30, 0, 0)),
+        Some(at(2023, 11, 1, 0, 0))
+    );
+
+    // february 30th never happens.
+    let cron = Cron::parse("0 0 30 2 *").unwrap();
+    assert_eq!(cron.next_after(at(2023, 10, 29, 0, 0)), None);
+}
+
+#[test]
+fn test_cron_parse_errors() {
+    assert_eq!(
+        Cron::parse("0 3 * *").unwrap_err(),
+        ScheduleError::FieldCount(4)
+    );
+    assert!(Cron::parse("60 * * * *").is_err());
+    assert!(Cron::parse("* 5-2 * * *").is_err());
+    assert!(Cron::parse("*/0 * * * *").is_err());
+    assert!(Cron::parse("* * 0 * *").is_err());
+    assert!(Cron::parse("a * * * *").is_err());
+}
+
+#[test]
+fn test_interval_schedule() {
+    let last = Utc.with_ymd_and_hms(2023, 10, 29, 12, 0, 0).unwrap();
+    let schedule = Schedule::parse(&RescanSchedule::Interval { minutes: 90 }).unwrap();
+
+    assert_eq!(
+        schedule.next_run(last),
+        Some(Utc.with_ymd_and_hms(2023, 10, 29, 13, 30, 0).unwrap())
+    );
+
+    assert_eq!(
+        Schedule::parse(&RescanSchedule::Interval { minutes: 0 }),
+        Err(ScheduleError::InvalidInterval)
+    );
+}
@@ -0,0 +1,53 @@
+use crate::scanner::stacking::stack_info;
+use crate::streaming::stack::write_playlist;
+
+use std::path::Path;
+
+#[test]
+fn test_stack_info() {
+    let dir = Path::new("/movies/Movie (2020)");
+
+    let info = stack_info(&dir.join("Movie (2020) cd1.avi")).unwrap();
+    assert_eq!(info.part, 1);
+    assert_eq!(info.base, dir.join("Movie (2020).avi"));
+
+    for part in [
+        "Movie (2020).CD2.avi",
+        "Movie (2020)-part2.avi",
+        "Movie (2020) [pt 2].avi",
+    ] {
+        let other = stack_info(&dir.join(part)).unwrap();
+        assert_eq!(other.part, 2);
+        assert_eq!(other.key, info.key);
+    }
+
+    let disc = stack_info(&dir.join("Movie (2020).disc3.mkv")).unwrap();
+    assert_eq!(disc.part, 3);
+    assert_ne!(
+        disc.key, info.key,
+        "parts with different extensions dont stack"
+    );
+
+    assert!(stack_info(&dir.join("Movie (2020).avi")).is_none());
+    assert!(stack_info(&dir.join("cd1.avi")).is_none());
+    assert!(stack_info(&dir.join("Apartment 4.avi")).is_none());
+    assert!(stack_info(&dir.join("Counterpart 2.avi")).is_none());
+}
+
+#[test]
+fn test_write_playlist() {
+    let tempdir = super::temp_dir(vec!["Movie cd1.avi", "Movie cd2.avi"]);
+    let parts = [
+        (tempdir.path().join("Movie cd1.avi"), Some(3000)),
+        (tempdir.path().join("Movie cd2.avi"), None),
+    ];
+
+    let playlist = write_playlist(-1, parts.iter().map(|(x, d)| (x.as_path(), *d))).unwrap();
+    let content = std::fs::read_to_string(&playlist).unwrap();
+
+    assert_eq!(
+        content,
+        "ffconcat version 1.0\nfile part01.avi\nduration 3000\nfile part02.avi\n"
+    );
+    assert!(playlist.with_file_name("part02.avi").exists());
+}
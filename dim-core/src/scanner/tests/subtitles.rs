@@ -0,0 +1,99 @@
+use super::mediafile::create_library;
+use super::temp_dir;
+use crate::scanner::subtitles;
+
+use dim_database::mediafile::InsertableMediaFile;
+use dim_database::subtitle::SubtitleSidecar;
+
+use std::path::Path;
+
+#[test]
+fn test_parse_sidecar() {
+    let video = Path::new("/movies/Movie (2020)/Movie (2020).mkv");
+    let parse =
+        |x: &str| subtitles::parse_sidecar(video, &Path::new("/movies/Movie (2020)").join(x));
+
+    let sidecar = parse("Movie (2020).srt").unwrap();
+    assert_eq!(sidecar.codec, "srt");
+    assert_eq!(sidecar.language, None);
+    assert!(!sidecar.forced && !sidecar.sdh);
+
+    let sidecar = parse("Movie (2020).en.srt").unwrap();
+    assert_eq!(sidecar.language.as_deref(), Some("eng"));
+
+    let sidecar = parse("Movie (2020).forced.de.ass").unwrap();
+    assert_eq!(sidecar.codec, "ass");
+    assert_eq!(sidecar.language.as_deref(), Some("ger"));
+    assert!(sidecar.forced);
+
+    let sidecar = parse("Movie (2020).fre.SDH.VTT").unwrap();
+    assert_eq!(sidecar.codec, "vtt");
+    assert_eq!(sidecar.language.as_deref(), Some("fre"));
+    assert!(sidecar.sdh);
+
+    assert!(parse("Movie (2020).nfo").is_none());
+    assert!(parse("Movie (2020) Extended.en.srt").is_none());
+    assert!(parse("Other Movie.en.srt").is_none());
+    assert!(subtitles::parse_sidecar(video, Path::new("/elsewhere/Movie (2020).srt")).is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_index_sidecars() {
+    let tempdir = temp_dir(vec!["Movie.mkv", "Movie.en.srt", "Movie.forced.de.ass"]);
+
+    let mut conn = dim_database::get_conn_memory()
+        .await
+        .expect("Failed to obtain a in-memory db pool.");
+    let library_id = create_library(&mut conn).await;
+
+    let mediafile_id = {
+        let mut lock = conn.writer().lock_owned().await;
+        let mut tx = dim_database::write_tx(&mut lock).await.unwrap();
+        let id = InsertableMediaFile {
+            library_id,
+            target_file: tempdir
+                .path()
+                .join("Movie.mkv")
+                .to_string_lossy()
+                .to_string(),
+            raw_name: "Movie".into(),
+            ..Default::default()
+        }
+        .insert(&mut tx)
+        .await
+        .unwrap();
+
+        tx.commit().await.unwrap();
+        id
+    };
+
+    let sidecars = || async {
+        let mut tx = conn.read().begin().await.unwrap();
+        SubtitleSidecar::get_of_mediafile(&mut tx, mediafile_id)
+            .await
+            .unwrap()
+    };
+
+    assert_eq!(
+        subtitles::index_sidecars(&conn, library_id).await.unwrap(),
+        1
+    );
+    assert_eq!(sidecars().await.len(), 2);
+
+    // nothing changed on disk so nothing should be rewritten.
+    assert_eq!(
+        subtitles::index_sidecars(&conn, library_id).await.unwrap(),
+        0
+    );
+
+    std::fs::remove_file(tempdir.path().join("Movie.en.srt")).unwrap();
+    assert_eq!(
+        subtitles::index_sidecars(&conn, library_id).await.unwrap(),
+        1
+    );
+
+    let result = sidecars().await;
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0].language.as_deref(), Some("ger"));
+    assert!(result[0].forced);
+}
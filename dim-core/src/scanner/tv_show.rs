@@ -1,6 +1,10 @@
 #![allow(unstable_name_collisions)]
 #![allow(unused_imports)]
 
+use super::artwork;
+use super::confidence;
+use super::extras;
+use super::folders::folder_hints;
 use super::movie::asset_from_url;
 use super::MediaMatcher;
 use super::Metadata;
@@ -20,6 +24,7 @@ use dim_database::episode::InsertableEpisode;
 use dim_database::genre::Genre;
 use dim_database::genre::InsertableGenre;
 use dim_database::genre::InsertableGenreMedia;
+use dim_database::library::Library;
 use dim_database::library::MediaType;
 use dim_database::media::InsertableMedia;
 use dim_database::media::Media;
@@ -33,8 +38,14 @@ use dim_database::Transaction;
 
 use chrono::prelude::Utc;
 use chrono::Datelike;
+use chrono::NaiveDate;
 
 use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tracing::error;
 use tracing::info;
@@ -73,6 +84,12 @@ pub enum Error {
     GetSeasonId(#[serde(skip)] dim_database::DatabaseError),
     /// Failed to get tvshowid for season: {0:?}
     GetTvId(#[serde(skip)] dim_database::DatabaseError),
+    /// Failed to get the episode ordering of shows: {0:?}
+    GetOrdering(#[serde(skip)] dim_database::DatabaseError),
+    /// Failed to link mediafile to the episodes it covers: {0:?}
+    LinkEpisodes(#[serde(skip)] dim_database::DatabaseError),
+    /// Failed to attach extras to their parent: {0:?}
+    AttachExtras(#[serde(skip)] dim_database::DatabaseError),
     /// Season not found
     SeasonNotFound,
     /// Episode not found
@@ -82,6 +99,20 @@ pub enum Error {
 #[derive(Clone, Copy)]
 pub struct TvMatcher;
 
+/// Episode a file has been looked up as.
+struct Lookup {
+    file: MediaFile,
+    /// Filename metadata the episode was found with.
+    meta: Metadata,
+    /// Whether the episode number of `meta` was taken as a absolute episode number.
+    absolute: bool,
+    show: ExternalMedia,
+    season: ExternalSeason,
+    episode: ExternalEpisode,
+    /// Episodes after `episode` that the file covers as well.
+    following: Vec<ExternalEpisode>,
+}
+
 impl TvMatcher {
     async fn match_to_result<'life0>(
         &self,
@@ -92,14 +123,25 @@ impl TvMatcher {
         // TODO: insert poster and backdrops.
         let (emedia, eseason, eepisode) = result;
 
+        // NOTE: Local artwork comes first so that it takes priority over remote artwork.
+        let local = Library::prefers_local_artwork(tx, file.library_id)
+            .await
+            .unwrap_or(false)
+            .then(|| Path::new(&file.target_file));
+
+        let show_artwork = local.map(artwork::show_artwork).unwrap_or_default();
+
+        let mut poster_ids = artwork::insert_local_assets(tx, &show_artwork.posters)
+            .await
+            .inspect_err(|error| error!(?error, "Failed to insert local asset into db."))
+            .map_err(Error::PosterInsert)?;
+
         let posters = emedia
             .posters
             .iter()
             .filter_map(|x| asset_from_url(x))
             .collect::<Vec<_>>();
 
-        let mut poster_ids = vec![];
-
         for poster in posters {
             let asset = poster
                 .insert(&mut *tx)
@@ -116,7 +158,10 @@ impl TvMatcher {
             .filter_map(|x| asset_from_url(x))
             .collect::<Vec<_>>();
 
-        let mut backdrop_ids = vec![];
+        let mut backdrop_ids = artwork::insert_local_assets(tx, &show_artwork.backdrops)
+            .await
+            .inspect_err(|error| error!(?error, "Failed to insert local asset into db."))
+            .map_err(Error::BackdropInsert)?;
 
         for backdrop in backdrops {
             let asset = backdrop
@@ -169,73 +214,153 @@ impl TvMatcher {
                 .map_err(Error::CoupleGenre)?;
         }
 
-        let seasonid = self.match_to_season(tx, parent_id, eseason).await?;
+        let seasonid = self.match_to_season(tx, parent_id, eseason, local).await?;
         let episodeid = self
-            .match_to_episode(tx, file.clone(), seasonid, eepisode)
+            .match_to_episode(tx, file.clone(), seasonid, eepisode, local)
             .await?;
 
         // If the mediafile used to belong to a different episode/season/show we want to
         // recursively search if we need to delete the parents. If the parents have 0 children, we
         // want to erase their existance.
-        match file.media_id {
-            Some(x) if x != episodeid => {
-                let season_id = Episode::get_seasonid(tx, x)
-                    .await
-                    .inspect_err(
-                        |error| error!(?error, id = %x, "Failed to get seasonid for episode"),
-                    )
-                    .map_err(Error::GetSeasonId)?;
+        if let Some(x) = file.media_id.filter(|x| *x != episodeid) {
+            self.cleanup_episode(tx, x).await?;
+        }
 
-                let tvshow_id = Season::get_tvshowid(tx, season_id).await.inspect_err(
-                    |error| error!(?error, id = %x, "Failed to get tvshowid for season/episode."),
-                ).map_err(Error::GetTvId)?;
+        Ok((parent_id, seasonid, episodeid))
+    }
 
-                let count = Movie::count_children(tx, x).await.inspect_err(
-                    |error| error!(?error, id = %x, "Failed to obtain children count for episode."),
-                ).map_err(Error::ChildrenCount)?;
+    /// Method looks up the episode `episode` of the season `season` of the show `external_id`,
+    /// which a file gets assigned to by hand with [`Self::match_to_episode_number`]. Specials live
+    /// in season 0.
+    pub async fn find_episode_number(
+        provider: Arc<dyn ExternalQueryIntoShow>,
+        external_id: &str,
+        season: u64,
+        episode: u64,
+    ) -> Result<(ExternalMedia, ExternalSeason, ExternalEpisode), super::Error> {
+        let provider: Arc<dyn ExternalQueryShow> = provider
+            .into_query_show()
+            .expect("Scanner needs a show provider");
 
-                if count == 0 {
-                    Media::delete(tx, x)
-                        .await
-                        .inspect_err(
-                            |error| error!(?error, id = %x, "Failed to delete child-less episode"),
-                        )
-                        .map_err(Error::ChildCleanup)?;
-                }
+        let provided = match provider.search_by_id(external_id).await {
+            Ok(provided) => provided,
+            Err(e) => {
+                error!(%external_id, error = ?e, "Failed to find a show match.");
+                return Err(super::Error::InvalidExternalId);
+            }
+        };
 
-                let count = Season::count_children(tx, season_id)
-                    .await
-                    .inspect_err(
-                        |error| error!(?error, id = %x, "Failed to get children count for season"),
-                    )
-                    .map_err(Error::ChildrenCount)?;
+        let Some(season_result) = provider
+            .seasons_for_id(external_id)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .find(|x| x.season_number == season)
+        else {
+            return Err(Error::SeasonNotFound.into());
+        };
 
-                if count == 0 {
-                    Season::delete_by_id(tx, season_id)
-                        .await
-                        .inspect_err(
-                            |error| error!(?error, id = %x, "Failed to delete child-less season"),
-                        )
-                        .map_err(Error::ChildCleanup)?;
-                }
+        let Some(episode_result) = provider
+            .episodes_for_season(external_id, season)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .find(|x| x.episode_number == episode)
+        else {
+            return Err(Error::EpisodeNotFound.into());
+        };
 
-                let count = TVShow::count_children(tx, tvshow_id).await.inspect_err(
-                    |error| error!(?error, id = %x, "Failed to get children count for tv show."),
-                ).map_err(Error::ChildrenCount)?;
+        Ok((provided, season_result, episode_result))
+    }
 
-                if count == 0 {
-                    Media::delete(tx, tvshow_id)
-                        .await
-                        .inspect_err(
-                            |error| error!(?error, id = %x, "Failed to delete child-less tv show"),
-                        )
-                        .map_err(Error::ChildCleanup)?;
-                }
-            }
-            _ => {}
+    /// Method matches `file` to the episode looked up with [`Self::find_episode_number`], without
+    /// looking at the filename at all.
+    pub async fn match_to_episode_number(
+        &self,
+        tx: &mut Transaction<'_>,
+        file: MediaFile,
+        result: (ExternalMedia, ExternalSeason, ExternalEpisode),
+    ) -> Result<(), super::Error> {
+        let (_, seasonid, _) = self
+            .match_to_result(tx, file.clone(), result)
+            .await
+            .inspect_err(|error| error!(?error, "failed to match to result"))?;
+
+        // NOTE: A file assigned by hand only covers the one episode.
+        self.link_following_episodes(tx, &file, seasonid, Vec::new())
+            .await
+            .inspect_err(|error| error!(?error, "failed to unlink following episodes"))?;
+
+        Ok(())
+    }
+
+    /// Method removes a episode, and its season and show, if they have no children left.
+    async fn cleanup_episode(
+        &self,
+        tx: &mut Transaction<'_>,
+        episode_id: i64,
+    ) -> Result<(), Error> {
+        let season_id = Episode::get_seasonid(tx, episode_id)
+            .await
+            .inspect_err(|error| error!(?error, %episode_id, "Failed to get seasonid for episode"))
+            .map_err(Error::GetSeasonId)?;
+
+        let tvshow_id = Season::get_tvshowid(tx, season_id)
+            .await
+            .inspect_err(
+                |error| error!(?error, %episode_id, "Failed to get tvshowid for season/episode."),
+            )
+            .map_err(Error::GetTvId)?;
+
+        let count = Movie::count_children(tx, episode_id)
+            .await
+            .inspect_err(
+                |error| error!(?error, %episode_id, "Failed to obtain children count for episode."),
+            )
+            .map_err(Error::ChildrenCount)?;
+
+        if count == 0 {
+            Media::delete(tx, episode_id)
+                .await
+                .inspect_err(
+                    |error| error!(?error, %episode_id, "Failed to delete child-less episode"),
+                )
+                .map_err(Error::ChildCleanup)?;
         }
 
-        Ok((parent_id, seasonid, episodeid))
+        let count = Season::count_children(tx, season_id)
+            .await
+            .inspect_err(
+                |error| error!(?error, %episode_id, "Failed to get children count for season"),
+            )
+            .map_err(Error::ChildrenCount)?;
+
+        if count == 0 {
+            Season::delete_by_id(tx, season_id)
+                .await
+                .inspect_err(
+                    |error| error!(?error, %episode_id, "Failed to delete child-less season"),
+                )
+                .map_err(Error::ChildCleanup)?;
+        }
+
+        let count = TVShow::count_children(tx, tvshow_id)
+            .await
+            .inspect_err(
+                |error| error!(?error, %episode_id, "Failed to get children count for tv show."),
+            )
+            .map_err(Error::ChildrenCount)?;
+
+        if count == 0 {
+            Media::delete(tx, tvshow_id)
+                .await
+                .inspect_err(
+                    |error| error!(?error, %episode_id, "Failed to delete child-less tv show"),
+                )
+                .map_err(Error::ChildCleanup)?;
+        }
+
+        Ok(())
     }
 
     // FIXME: In cases where we can match against a show but not find a specific season or episode,
@@ -245,15 +370,23 @@ impl TvMatcher {
         tx: &mut Transaction<'_>,
         parent_id: i64,
         result: ExternalSeason,
+        local: Option<&Path>,
     ) -> Result<i64, Error> {
+        let local_posters = local
+            .map(|x| artwork::season_posters(x, result.season_number))
+            .unwrap_or_default();
+
+        let mut poster_ids = artwork::insert_local_assets(tx, &local_posters)
+            .await
+            .inspect_err(|error| error!(?error, "Failed to insert local asset into db."))
+            .map_err(Error::PosterInsert)?;
+
         let posters = result
             .posters
             .iter()
             .filter_map(|x| asset_from_url(x))
             .collect::<Vec<_>>();
 
-        let mut poster_ids = vec![];
-
         for poster in posters {
             let asset = poster
                 .insert(&mut *tx)
@@ -285,15 +418,49 @@ impl TvMatcher {
         file: MediaFile,
         seasonid: i64,
         result: ExternalEpisode,
+        local: Option<&Path>,
+    ) -> Result<i64, Error> {
+        let episode_id = self
+            .insert_episode(tx, &file, seasonid, result, local)
+            .await?;
+
+        let updated_mediafile = UpdateMediaFile {
+            media_id: Some(episode_id),
+            ..Default::default()
+        };
+
+        updated_mediafile
+            .update(&mut *tx, file.id)
+            .await
+            .inspect_err(|error| error!(?error, ?file, "Failed to update mediafile media id."))
+            .map_err(Error::UpdateMediafile)?;
+
+        Ok(episode_id)
+    }
+
+    /// Method inserts the episode `result` into the season `seasonid`, or returns the id of the
+    /// episode if it already exists.
+    async fn insert_episode(
+        &self,
+        tx: &mut Transaction<'_>,
+        file: &MediaFile,
+        seasonid: i64,
+        result: ExternalEpisode,
+        local: Option<&Path>,
     ) -> Result<i64, Error> {
+        let local_stills = local.map(artwork::episode_stills).unwrap_or_default();
+
+        let mut still_ids = artwork::insert_local_assets(tx, &local_stills)
+            .await
+            .inspect_err(|error| error!(?error, "Failed to insert local asset into db."))
+            .map_err(Error::PosterInsert)?;
+
         let stills = result
             .stills
             .iter()
             .filter_map(|x| asset_from_url(x))
             .collect::<Vec<_>>();
 
-        let mut still_ids = vec![];
-
         for still in stills {
             let asset = still
                 .insert(&mut *tx)
@@ -335,18 +502,158 @@ impl TvMatcher {
             .inspect_err(|error| error!(?error, ?file, "Failed to insert episode."))
             .map_err(Error::GetOrInsertEpisode)?;
 
-        let updated_mediafile = UpdateMediaFile {
-            media_id: Some(episode_id),
-            ..Default::default()
+        Ok(episode_id)
+    }
+
+    /// Method links a file spanning several episodes to the episodes after the first one, which
+    /// the file already got matched to. Episodes the file used to cover but doesnt anymore get
+    /// cleaned up.
+    async fn link_following_episodes(
+        &self,
+        tx: &mut Transaction<'_>,
+        file: &MediaFile,
+        seasonid: i64,
+        episodes: Vec<ExternalEpisode>,
+    ) -> Result<(), Error> {
+        let previous = MediaFile::get_linked_episodes(tx, file.id)
+            .await
+            .inspect_err(|error| error!(?error, ?file, "Failed to get linked episodes."))
+            .map_err(Error::LinkEpisodes)?;
+
+        if previous.is_empty() && episodes.is_empty() {
+            return Ok(());
+        }
+
+        let mut episode_ids = Vec::new();
+
+        // NOTE: Local stills belong to the first episode of the file.
+        for episode in episodes {
+            let episode_id = self
+                .insert_episode(tx, file, seasonid, episode, None)
+                .await?;
+
+            episode_ids.push(episode_id);
+        }
+
+        MediaFile::set_linked_episodes(tx, file.id, &episode_ids)
+            .await
+            .inspect_err(|error| error!(?error, ?file, "Failed to link episodes."))
+            .map_err(Error::LinkEpisodes)?;
+
+        for episode_id in previous.into_iter().filter(|x| !episode_ids.contains(x)) {
+            self.cleanup_episode(tx, episode_id).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Method looks up the episodes of the files stored below the same show folder. Usually all of
+    /// them belong to the same show, so once the show has been found for one of them the others
+    /// are looked up within it rather than searched for by their own names.
+    async fn lookup_show_folder(
+        provider: Arc<dyn ExternalQueryShow>,
+        work: Vec<(MediaFile, Vec<Metadata>)>,
+    ) -> Vec<Lookup> {
+        let mut work = work.into_iter();
+        let mut searched = Vec::new();
+
+        let found = loop {
+            let Some((file, metadata)) = work.next() else {
+                return Vec::new();
+            };
+
+            let result =
+                Self::lookup_metadata(Arc::clone(&provider), file.clone(), metadata.clone()).await;
+
+            match result {
+                Some(found) => break found,
+                None => searched.push((file, metadata)),
+            }
         };
 
-        updated_mediafile
-            .update(&mut *tx, file.id)
+        let show = found.show.clone();
+
+        // NOTE: Files we failed to find by their own names get another go within the show.
+        let lookups = searched
+            .into_iter()
+            .map(|(file, metadata)| (file, metadata, true))
+            .chain(work.map(|(file, metadata)| (file, metadata, false)))
+            .map(|(file, metadata, searched)| {
+                Self::lookup_in_folder(Arc::clone(&provider), file, metadata, &show, searched)
+            });
+
+        let mut matched = futures::future::join_all(lookups)
             .await
-            .inspect_err(|error| error!(?error, ?file, "Failed to update mediafile media id."))
-            .map_err(Error::UpdateMediafile)?;
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        matched.insert(0, found);
 
-        Ok(episode_id)
+        matched
+    }
+
+    /// Method looks up the episode `file`, stored below the same folder as episodes of `show`,
+    /// holds. Folders of loose files can hold several shows though, so files named after another
+    /// show, or which can't be found within `show`, are looked up by their own names unless that
+    /// has been `searched` already.
+    async fn lookup_in_folder(
+        provider: Arc<dyn ExternalQueryShow>,
+        file: MediaFile,
+        metadata: Vec<Metadata>,
+        show: &ExternalMedia,
+        searched: bool,
+    ) -> Option<Lookup> {
+        if !names_other_show(&metadata, show) {
+            let found =
+                Self::lookup_in_show(&*provider, file.clone(), metadata.clone(), show).await;
+
+            if found.is_some() {
+                return found;
+            }
+        }
+
+        if searched {
+            return None;
+        }
+
+        Self::lookup_metadata(provider, file, metadata).await
+    }
+
+    /// Method looks up the episode `file` holds within the show `show`.
+    async fn lookup_in_show(
+        provider: &dyn ExternalQueryShow,
+        file: MediaFile,
+        metadata: Vec<Metadata>,
+        show: &ExternalMedia,
+    ) -> Option<Lookup> {
+        for meta in metadata {
+            let Some((absolute, season, episode)) =
+                Self::find_any_episode(provider, &show.external_id, &meta).await
+            else {
+                continue;
+            };
+
+            let following = Self::find_following_episodes(
+                provider,
+                &show.external_id,
+                &meta,
+                &season,
+                &episode,
+            )
+            .await;
+
+            return Some(Lookup {
+                file,
+                meta,
+                absolute,
+                show: show.clone(),
+                season,
+                episode,
+                following,
+            });
+        }
+
+        None
     }
 
     #[instrument(skip(provider, metadata))]
@@ -354,10 +661,14 @@ impl TvMatcher {
         provider: Arc<dyn ExternalQueryShow>,
         file: MediaFile,
         metadata: Vec<Metadata>,
-    ) -> Option<(MediaFile, (ExternalMedia, ExternalSeason, ExternalEpisode))> {
+    ) -> Option<Lookup> {
         for meta in metadata {
             match provider
-                .search(meta.name.as_ref(), meta.year.map(|x| x as _))
+                .search_for_file(
+                    Path::new(&file.target_file),
+                    meta.name.as_ref(),
+                    meta.year.map(|x| x as _),
+                )
                 .await
             {
                 Ok(provided) => {
@@ -367,50 +678,281 @@ impl TvMatcher {
                         continue;
                     };
 
-                    let Ok(seasons) = provider.seasons_for_id(&first.external_id).await else {
-                        info!(
-                            ?meta,
-                            "Failed to find season match with the current metadata set."
-                        );
-                        continue;
-                    };
-
-                    // FIXME: If a file doesnt have season metadata, we want to default to
-                    // marking this file as an extra and put it in season 0
-                    let Some(season) = seasons
-                        .into_iter()
-                        .find(|x| x.season_number as i64 == meta.season.unwrap_or(0))
+                    let Some((absolute, season, episode)) =
+                        Self::find_any_episode(&*provider, &first.external_id, &meta).await
                     else {
-                        info!(
-                            ?meta,
-                            "Provider didnt return our desired season with current metadata."
-                        );
                         continue;
                     };
 
+                    let following = Self::find_following_episodes(
+                        &*provider,
+                        &first.external_id,
+                        &meta,
+                        &season,
+                        &episode,
+                    )
+                    .await;
+
+                    return Some(Lookup {
+                        file,
+                        meta,
+                        absolute,
+                        show: first,
+                        season,
+                        episode,
+                        following,
+                    });
+                }
+                Err(e) => error!(?meta, error = ?e, "Failed to find a movie match."),
+            }
+        }
+
+        None
+    }
+
+    /// Method looks up the episode `meta` points to with episodes numbered per season, and failing
+    /// that with episodes numbered absolutely. Which numbering a show uses is only known once we
+    /// know which of our shows it is, see [`Self::check_ordering`].
+    async fn find_any_episode(
+        provider: &dyn ExternalQueryShow,
+        external_id: &str,
+        meta: &Metadata,
+    ) -> Option<(bool, ExternalSeason, ExternalEpisode)> {
+        for absolute_ordering in [false, true] {
+            if let Ok((season, episode)) =
+                Self::find_episode(provider, external_id, meta, absolute_ordering).await
+            {
+                return Some((absolute_ordering, season, episode));
+            }
+        }
+
+        None
+    }
+
+    /// Method looks the episode of `lookup` up again if it wasnt found with the episode numbering
+    /// of the show it is going to be matched to. Returns `None` if the episode can't be found with
+    /// that numbering.
+    async fn check_ordering(
+        tx: &mut Transaction<'_>,
+        provider: &dyn ExternalQueryShow,
+        absolute_ordered: &HashSet<i64>,
+        mut lookup: Lookup,
+    ) -> Result<Option<Lookup>, Error> {
+        // NOTE: Files are matched to the show of the same name, see `InsertableMedia::lazy_insert`.
+        let show_id = Media::get_id_by_name(tx, &lookup.show.title)
+            .await
+            .inspect_err(|error| error!(?error, "Failed to get a show by name."))
+            .map_err(Error::GetOrdering)?;
+
+        let absolute_ordering = show_id.map_or(false, |x| absolute_ordered.contains(&x));
+
+        if lookup.absolute == absolute_ordering {
+            return Ok(Some(lookup));
+        }
+
+        let external_id = lookup.show.external_id.clone();
+        let Ok((season, episode)) =
+            Self::find_episode(provider, &external_id, &lookup.meta, absolute_ordering).await
+        else {
+            return Ok(None);
+        };
+
+        lookup.following =
+            Self::find_following_episodes(provider, &external_id, &lookup.meta, &season, &episode)
+                .await;
+        lookup.absolute = absolute_ordering;
+        lookup.season = season;
+        lookup.episode = episode;
+
+        Ok(Some(lookup))
+    }
+
+    /// Method looks up the season and episode of the show `external_id` that `meta` points to.
+    ///
+    /// Files named by air date are matched to the episode that aired on that date. Episode numbers
+    /// are taken as absolute episode numbers if the show is ordered absolutely.
+    /// They are also taken as such if the filename didnt carry a season number and the season we
+    /// assumed doesnt have the episode, as is common for anime (ie `One Piece - 1054`).
+    async fn find_episode(
+        provider: &dyn ExternalQueryShow,
+        external_id: &str,
+        meta: &Metadata,
+        absolute_ordering: bool,
+    ) -> Result<(ExternalSeason, ExternalEpisode), Error> {
+        let Ok(seasons) = provider.seasons_for_id(external_id).await else {
+            info!(
+                ?meta,
+                "Failed to find season match with the current metadata set."
+            );
+            return Err(Error::SeasonNotFound);
+        };
+
+        if let Some(air_date) = meta.air_date {
+            return Self::find_aired_episode(provider, external_id, seasons, air_date)
+                .await
+                .ok_or_else(|| {
+                    info!(
+                        ?meta,
+                        "Provider didnt return a episode aired on our desired date."
+                    );
+                    Error::EpisodeNotFound
+                });
+        }
+
+        let episode_number = meta.episode.unwrap_or(0);
+
+        // NOTE: Specials in season 0 keep their own numbering even in absolutely ordered shows.
+        if !absolute_ordering || meta.season == Some(0) {
+            // FIXME: If a file doesnt have season metadata, we want to default to
+            // marking this file as an extra and put it in season 0
+            let season = seasons
+                .iter()
+                .find(|x| x.season_number as i64 == meta.season.unwrap_or(0));
+
+            match season {
+                Some(season) => {
                     let Ok(episodes) = provider
-                        .episodes_for_season(&first.external_id, meta.season.unwrap_or(0) as _)
+                        .episodes_for_season(external_id, season.season_number)
                         .await
                     else {
                         // FIXME: We might want to propagate this error.
                         info!(?meta, "Failed to fetch episodes with current metadata set.");
-                        continue;
+                        return Err(Error::EpisodeNotFound);
                     };
 
-                    let Some(episode) = episodes
+                    if let Some(episode) = episodes
                         .into_iter()
-                        .find(|x| x.episode_number as i64 == meta.episode.unwrap_or(0))
-                    else {
+                        .find(|x| x.episode_number as i64 == episode_number)
+                    {
+                        return Ok((season.clone(), episode));
+                    }
+
+                    if !meta.season_assumed {
                         info!(
                             ?meta,
                             "Provider didnt return our desired episode with current metadata."
                         );
-                        continue;
-                    };
-
-                    return Some((file, (first, season, episode)));
+                        return Err(Error::EpisodeNotFound);
+                    }
                 }
-                Err(e) => error!(?meta, error = ?e, "Failed to find a movie match."),
+                None if !meta.season_assumed => {
+                    info!(
+                        ?meta,
+                        "Provider didnt return our desired season with current metadata."
+                    );
+                    return Err(Error::SeasonNotFound);
+                }
+                None => {}
+            }
+        }
+
+        // NOTE: Specials in season 0 dont count towards absolute episode numbers.
+        let mut seasons = seasons
+            .into_iter()
+            .filter(|x| x.season_number > 0)
+            .collect::<Vec<_>>();
+        seasons.sort_by_key(|x| x.season_number);
+
+        // NOTE: We only fetch seasons until we have enough episodes to cover the absolute episode
+        // number, long running shows can have dozens of seasons.
+        let mut fetched = Vec::new();
+        let mut total = 0;
+
+        for season in seasons {
+            if total >= episode_number {
+                break;
+            }
+
+            let Ok(mut episodes) = provider
+                .episodes_for_season(external_id, season.season_number)
+                .await
+            else {
+                info!(?meta, "Failed to fetch episodes with current metadata set.");
+                return Err(Error::EpisodeNotFound);
+            };
+
+            episodes.sort_by_key(|x| x.episode_number);
+            total += episodes.len() as i64;
+            fetched.push((season, episodes));
+        }
+
+        let Some((season_idx, episode_idx)) =
+            resolve_absolute(episode_number, fetched.iter().map(|(_, x)| x.len()))
+        else {
+            info!(
+                ?meta,
+                "Provider didnt return our desired absolute episode with current metadata."
+            );
+            return Err(Error::EpisodeNotFound);
+        };
+
+        let (season, episodes) = fetched.swap_remove(season_idx);
+
+        Ok((season, episodes[episode_idx].clone()))
+    }
+
+    /// Method looks up the episodes after `episode` that a file spanning several episodes covers,
+    /// ie `Show.S02E05E06.mkv`.
+    async fn find_following_episodes(
+        provider: &dyn ExternalQueryShow,
+        external_id: &str,
+        meta: &Metadata,
+        season: &ExternalSeason,
+        episode: &ExternalEpisode,
+    ) -> Vec<ExternalEpisode> {
+        let (Some(first), Some(last)) = (meta.episode, meta.last_episode) else {
+            return Vec::new();
+        };
+
+        let Ok(count) = u64::try_from(last - first) else {
+            return Vec::new();
+        };
+
+        let Ok(episodes) = provider
+            .episodes_for_season(external_id, season.season_number)
+            .await
+        else {
+            info!(?meta, "Failed to fetch episodes with current metadata set.");
+            return Vec::new();
+        };
+
+        episodes
+            .into_iter()
+            .filter(|x| {
+                x.episode_number > episode.episode_number
+                    && x.episode_number <= episode.episode_number + count
+            })
+            .collect()
+    }
+
+    /// Method looks for the episode of the show `external_id` that aired on `air_date`, which is
+    /// how daily shows such as talk shows name their episodes.
+    async fn find_aired_episode(
+        provider: &dyn ExternalQueryShow,
+        external_id: &str,
+        mut seasons: Vec<ExternalSeason>,
+        air_date: NaiveDate,
+    ) -> Option<(ExternalSeason, ExternalEpisode)> {
+        // NOTE: Daily shows often number their seasons by year, so we look at the season named
+        // after the year first, and otherwise start with the most recent seasons.
+        seasons.sort_by_key(|x| {
+            (
+                x.season_number != air_date.year() as u64,
+                Reverse(x.season_number),
+            )
+        });
+
+        for season in seasons {
+            let Ok(episodes) = provider
+                .episodes_for_season(external_id, season.season_number)
+                .await
+            else {
+                info!(?season, "Failed to fetch episodes of season.");
+                continue;
+            };
+
+            if let Some(episode) = episodes.into_iter().find(|x| x.air_date == Some(air_date)) {
+                return Some((season, episode));
             }
         }
 
@@ -418,6 +960,41 @@ impl TvMatcher {
     }
 }
 
+/// Function returns whether the filename of a file clearly names a show other than `show`, ie
+/// none of the candidates we are confident in share a word with the title of `show`.
+fn names_other_show(metadata: &[Metadata], show: &ExternalMedia) -> bool {
+    let title = confidence::words(&show.title, None);
+
+    let mut names = metadata
+        .iter()
+        .filter(|x| x.confidence >= confidence::LOW_CONFIDENCE)
+        .map(|x| confidence::words(&x.name, None))
+        .filter(|x| !x.is_empty())
+        .peekable();
+
+    names.peek().is_some() && names.all(|x| x.is_disjoint(&title))
+}
+
+/// Function maps the absolute episode number `absolute` onto the season it falls into, given the
+/// amount of episodes of each season in order. Returns the index of the season and the index of
+/// the episode within that season.
+fn resolve_absolute(
+    absolute: i64,
+    episode_counts: impl IntoIterator<Item = usize>,
+) -> Option<(usize, usize)> {
+    let mut remaining = usize::try_from(absolute).ok()?.checked_sub(1)?;
+
+    for (idx, count) in episode_counts.into_iter().enumerate() {
+        if remaining < count {
+            return Some((idx, remaining));
+        }
+
+        remaining -= count;
+    }
+
+    None
+}
+
 #[async_trait]
 impl MediaMatcher for TvMatcher {
     async fn batch_match(
@@ -430,24 +1007,90 @@ impl MediaMatcher for TvMatcher {
             .into_query_show()
             .expect("Scanner needs a show provider");
 
-        let metadata_futs = work
+        let absolute_ordered = TVShow::get_absolute_ordered(tx)
+            .await
+            .inspect_err(|error| error!(?error, "Failed to get absolutely ordered shows."))
+            .map_err(Error::GetOrdering)?
             .into_iter()
-            .map(|WorkUnit(file, metadata)| {
-                let provider_show = Arc::clone(&provider_show);
-                tokio::spawn(Self::lookup_metadata(provider_show, file, metadata))
-            })
+            .collect::<HashSet<_>>();
+
+        let library_id = work.first().map(|WorkUnit(file, _)| file.library_id);
+        let files = work
+            .iter()
+            .map(|WorkUnit(file, _)| file.target_file.clone())
             .collect::<Vec<_>>();
 
-        let metadata = futures::future::join_all(metadata_futs).await;
+        let roots: Vec<PathBuf> = match work.first() {
+            Some(WorkUnit(file, _)) => Library::get_one(tx, file.library_id)
+                .await
+                .map(|x| x.locations.into_iter().map(PathBuf::from).collect())
+                .unwrap_or_default(),
+            None => Vec::new(),
+        };
 
-        for meta in metadata.into_iter() {
-            if let Ok(Some((file, provided))) = meta {
-                self.match_to_result(tx, file, provided)
-                    .await
-                    .inspect_err(|error| error!(?error, "failed to match to result"))?;
+        // NOTE: All files below a show folder belong to the same show, so they get looked up
+        // together. Files outside of a show folder are looked up on their own. Extras would
+        // otherwise end up as bogus episodes.
+        let mut groups: Vec<Vec<(MediaFile, Vec<Metadata>)>> = Vec::new();
+        let mut show_folders = HashMap::new();
+
+        for WorkUnit(file, metadata) in work.into_iter().filter(|x| x.0.extra_type.is_none()) {
+            let show_folder = folder_hints(Path::new(&file.target_file), &roots).folder;
+
+            match show_folder.as_ref().and_then(|x| show_folders.get(x)) {
+                Some(&idx) => groups[idx].push((file, metadata)),
+                None => {
+                    if let Some(show_folder) = show_folder {
+                        show_folders.insert(show_folder, groups.len());
+                    }
+
+                    groups.push(vec![(file, metadata)]);
+                }
             }
         }
 
+        let metadata_futs = groups
+            .into_iter()
+            .map(|group| tokio::spawn(Self::lookup_show_folder(Arc::clone(&provider_show), group)))
+            .collect::<Vec<_>>();
+
+        let metadata = futures::future::join_all(metadata_futs).await;
+
+        for lookup in metadata.into_iter().filter_map(Result::ok).flatten() {
+            let Some(lookup) =
+                Self::check_ordering(tx, &*provider_show, &absolute_ordered, lookup).await?
+            else {
+                continue;
+            };
+
+            let Lookup {
+                file,
+                show,
+                season,
+                episode,
+                following,
+                ..
+            } = lookup;
+
+            let (_, seasonid, _) = self
+                .match_to_result(tx, file.clone(), (show, season, episode))
+                .await
+                .inspect_err(|error| error!(?error, "failed to match to result"))?;
+
+            self.link_following_episodes(tx, &file, seasonid, following)
+                .await
+                .inspect_err(|error| error!(?error, "failed to link following episodes"))?;
+        }
+
+        // Extras can end up in a earlier batch than their show, so we try to attach the extras
+        // stored next to any file of this batch that are still missing a parent.
+        if let Some(library_id) = library_id {
+            extras::attach_extras(tx, library_id, &files)
+                .await
+                .inspect_err(|error| error!(?error, "Failed to attach extras to their parent."))
+                .map_err(Error::AttachExtras)?;
+        }
+
         Ok(())
     }
 
@@ -472,80 +1115,73 @@ impl MediaMatcher for TvMatcher {
             }
         };
 
-        let mut season_result = None;
-        let mut episode_result = None;
-
-        for meta in metadata {
-            let Ok(seasons) = provider.seasons_for_id(external_id).await else {
-                info!(
-                    ?meta,
-                    "Failed to find season match with the current metadata set."
-                );
-                continue;
-            };
-
-            // FIXME: If a file doesnt have season metadata, we want to default to
-            // marking this file as an extra and put it in season 0
-            let Some(season) = seasons
-                .into_iter()
-                .find(|x| x.season_number as i64 == meta.season.unwrap_or(0))
-            else {
-                info!(
-                    ?meta,
-                    "Provider didnt return our desired season with current metadata."
-                );
-                continue;
-            };
-
-            let Ok(episodes) = provider
-                .episodes_for_season(external_id, meta.season.unwrap_or(0) as _)
+        // NOTE: Files are matched to the show of the same name, see `InsertableMedia::lazy_insert`.
+        let absolute_ordering = match Media::get_id_by_name(tx, &provided.title)
+            .await
+            .map_err(Error::GetOrdering)?
+        {
+            Some(show_id) => TVShow::absolute_ordering(tx, show_id)
                 .await
-            else {
-                // FIXME: We might want to propagate this error.
-                info!(?meta, "Failed to fetch episodes with current metadata set.");
-                continue;
-            };
+                .inspect_err(|error| error!(?error, "Failed to get the ordering of show."))
+                .map_err(Error::GetOrdering)?,
+            None => false,
+        };
 
-            let Some(episode) = episodes
-                .into_iter()
-                .find(|x| x.episode_number as i64 == meta.episode.unwrap_or(0))
-            else {
-                info!(
-                    ?meta,
-                    "Provider didnt return our desired episode with current metadata."
-                );
-                continue;
-            };
+        let mut result = Err(Error::SeasonNotFound);
+        let mut following = Vec::new();
 
-            season_result = Some(season);
-            episode_result = Some(episode);
+        for meta in metadata {
+            result = Self::find_episode(&*provider, external_id, &meta, absolute_ordering).await;
+
+            if let Ok((season, episode)) = &result {
+                following =
+                    Self::find_following_episodes(&*provider, external_id, &meta, season, episode)
+                        .await;
+                break;
+            }
         }
 
-        let Some(season_result) = season_result else {
-            return Err(Error::SeasonNotFound.into());
-        };
-        let Some(episode_result) = episode_result else {
-            return Err(Error::EpisodeNotFound.into());
-        };
+        let (season_result, episode_result) = result?;
 
-        self.match_to_result(tx, file, (provided, season_result, episode_result))
+        let (_, seasonid, _) = self
+            .match_to_result(tx, file.clone(), (provided, season_result, episode_result))
             .await
             .inspect_err(|error| error!(?error, "failed to match to result"))?;
 
+        self.link_following_episodes(tx, &file, seasonid, following)
+            .await
+            .inspect_err(|error| error!(?error, "failed to link following episodes"))?;
+
         Ok(())
     }
+
+    async fn cleanup(&self, tx: &mut Transaction<'_>, media_id: i64) -> Result<(), super::Error> {
+        Ok(self.cleanup_episode(tx, media_id).await?)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::super::parse_filenames;
     use super::super::tests::mediafile::create_library;
+    use super::super::FilenameParser;
+    use super::super::MediaMatcher;
+    use super::super::Metadata;
+    use super::resolve_absolute;
     use super::TvMatcher;
 
+    use async_trait::async_trait;
+    use chrono::NaiveDate;
+
+    use dim_extern_api::ExternalActor;
     use dim_extern_api::ExternalEpisode;
     use dim_extern_api::ExternalMedia;
+    use dim_extern_api::ExternalQuery;
+    use dim_extern_api::ExternalQueryShow;
     use dim_extern_api::ExternalSeason;
 
     use dim_database::episode::Episode;
+    use dim_database::library::MediaType;
     use dim_database::media::Media;
     use dim_database::mediafile::InsertableMediaFile;
     use dim_database::mediafile::MediaFile;
@@ -553,6 +1189,192 @@ mod tests {
     use dim_database::season::Season;
     use dim_database::tv::TVShow;
 
+    use std::sync::Arc;
+
+    /// Provider of shows with a special and two seasons of three episodes. Season `n` aired in
+    /// month `n` of 2020, one episode a day. Only titles starting with `Show` are found.
+    #[derive(Debug)]
+    struct Show;
+
+    #[async_trait]
+    impl ExternalQuery for Show {
+        async fn search(
+            &self,
+            title: &str,
+            _: Option<i32>,
+        ) -> dim_extern_api::Result<Vec<ExternalMedia>> {
+            if !title.starts_with("Show") {
+                return Ok(vec![]);
+            }
+
+            Ok(vec![ExternalMedia {
+                external_id: title.into(),
+                title: title.into(),
+                ..Default::default()
+            }])
+        }
+
+        async fn search_by_id(&self, _: &str) -> dim_extern_api::Result<ExternalMedia> {
+            unimplemented!()
+        }
+
+        async fn cast(&self, _: &str) -> dim_extern_api::Result<Vec<ExternalActor>> {
+            Ok(vec![])
+        }
+    }
+
+    #[async_trait]
+    impl ExternalQueryShow for Show {
+        async fn seasons_for_id(&self, _: &str) -> dim_extern_api::Result<Vec<ExternalSeason>> {
+            Ok((0..=2)
+                .map(|season_number| ExternalSeason {
+                    season_number,
+                    ..Default::default()
+                })
+                .collect())
+        }
+
+        async fn episodes_for_season(
+            &self,
+            _: &str,
+            season_number: u64,
+        ) -> dim_extern_api::Result<Vec<ExternalEpisode>> {
+            let count = if season_number == 0 { 1 } else { 3 };
+
+            Ok((1..=count)
+                .map(|episode_number| ExternalEpisode {
+                    external_id: format!("{season_number}x{episode_number}"),
+                    episode_number,
+                    air_date: NaiveDate::from_ymd_opt(
+                        2020,
+                        season_number.max(1) as u32,
+                        episode_number as u32,
+                    ),
+                    ..Default::default()
+                })
+                .collect())
+        }
+    }
+
+    fn meta(season: Option<i64>, episode: i64) -> Metadata {
+        Metadata {
+            name: "Show".into(),
+            year: None,
+            season: season.or(Some(1)),
+            episode: Some(episode),
+            last_episode: None,
+            season_assumed: season.is_none(),
+            air_date: None,
+            edition: None,
+            confidence: 0,
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn find_absolute_episode() {
+        let find = |meta: Metadata, absolute: bool| async move {
+            TvMatcher::find_episode(&Show, "1", &meta, absolute)
+                .await
+                .ok()
+                .map(|(season, episode)| (season.season_number, episode.episode_number))
+        };
+
+        // episode numbers of absolutely ordered shows run across seasons.
+        assert_eq!(find(meta(Some(1), 5), true).await, Some((2, 2)));
+        assert_eq!(find(meta(None, 3), true).await, Some((1, 3)));
+        assert_eq!(find(meta(None, 7), true).await, None);
+
+        // specials keep their own numbering.
+        assert_eq!(find(meta(Some(0), 1), true).await, Some((0, 1)));
+
+        // episodes numbered per season are only taken as absolute numbers if the season was
+        // assumed.
+        assert_eq!(find(meta(Some(1), 5), false).await, None);
+        assert_eq!(find(meta(None, 5), false).await, Some((2, 2)));
+        assert_eq!(find(meta(Some(2), 2), false).await, Some((2, 2)));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn find_aired_episode() {
+        let find = |air_date: Option<NaiveDate>| async move {
+            let meta = Metadata {
+                episode: None,
+                air_date,
+                ..meta(None, 0)
+            };
+
+            TvMatcher::find_episode(&Show, "1", &meta, false)
+                .await
+                .ok()
+                .map(|(season, episode)| (season.season_number, episode.episode_number))
+        };
+
+        assert_eq!(
+            find(NaiveDate::from_ymd_opt(2020, 2, 3)).await,
+            Some((2, 3))
+        );
+        assert_eq!(
+            find(NaiveDate::from_ymd_opt(2020, 1, 1)).await,
+            Some((1, 1))
+        );
+        assert_eq!(find(NaiveDate::from_ymd_opt(2020, 2, 4)).await, None);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn lookup_mixed_folder() {
+        let mut conn = dim_database::get_conn_memory()
+            .await
+            .expect("Failed to obtain a in-memory db pool.");
+        let library = create_library(&mut conn).await;
+
+        let mut lock = conn.writer.lock_owned().await;
+        let mut tx = write_tx(&mut lock).await.unwrap();
+
+        let parser = FilenameParser::new(MediaType::Tv, &Default::default(), &["/tv"]);
+        let files = [
+            "/tv/Downloads/ShowA.S01E01.mkv",
+            "/tv/Downloads/ShowA.S01E02.mkv",
+            "/tv/Downloads/ShowB.S01E01.mkv",
+        ];
+
+        let mut work = Vec::new();
+
+        for target_file in files {
+            let id = InsertableMediaFile {
+                library_id: library,
+                target_file: target_file.into(),
+                raw_name: target_file.into(),
+                ..Default::default()
+            }
+            .insert(&mut tx)
+            .await
+            .unwrap();
+
+            let file = MediaFile::get_one(&mut tx, id).await.unwrap();
+            let (_, metadata) = parse_filenames(std::iter::once(target_file), &parser)
+                .pop()
+                .unwrap();
+
+            work.push((file, metadata));
+        }
+
+        let found = TvMatcher::lookup_show_folder(Arc::new(Show), work).await;
+        let shows = found
+            .iter()
+            .map(|x| (x.file.target_file.as_str(), x.show.title.as_str()))
+            .collect::<Vec<_>>();
+
+        // episodes of another show stored next to the first one are looked up on their own.
+        assert_eq!(
+            shows,
+            [
+                (files[0], "ShowA"),
+                (files[1], "ShowA"),
+                (files[2], "ShowB"),
+            ]
+        );
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn match_show() {
         const MATCHER: TvMatcher = TvMatcher;
@@ -748,4 +1570,86 @@ mod tests {
         let episodes = Episode::get_all_of_season(&mut tx, s2).await.unwrap();
         assert_eq!(episodes.len(), 2);
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn cleanup_removed_episodes() {
+        const MATCHER: TvMatcher = TvMatcher;
+
+        let mut conn = dim_database::get_conn_memory()
+            .await
+            .expect("Failed to obtain a in-memory db pool.");
+        let library = create_library(&mut conn).await;
+
+        let mut lock = conn.writer.lock_owned().await;
+        let mut tx = write_tx(&mut lock).await.unwrap();
+
+        let mut result = (
+            ExternalMedia {
+                title: "Show 1".into(),
+                ..Default::default()
+            },
+            ExternalSeason {
+                season_number: 1,
+                ..Default::default()
+            },
+            ExternalEpisode {
+                episode_number: 1,
+                ..Default::default()
+            },
+        );
+
+        let mut matched = vec![];
+
+        for (file, season) in [("s1e1.mp4", 1), ("s2e1.mp4", 2)] {
+            let mfile_id = InsertableMediaFile {
+                library_id: library,
+                target_file: file.into(),
+                raw_name: "test".into(),
+                ..Default::default()
+            }
+            .insert(&mut tx)
+            .await
+            .unwrap();
+
+            result.1.season_number = season;
+            let mfile = MediaFile::get_one(&mut tx, mfile_id).await.unwrap();
+            let ids = MATCHER
+                .match_to_result(&mut tx, mfile, result.clone())
+                .await
+                .unwrap();
+
+            matched.push((mfile_id, ids));
+        }
+
+        let [(mfile1, (t1, s1, e1)), (mfile2, (_, s2, e2))] = matched[..] else {
+            unreachable!()
+        };
+
+        // the second season still has a episode, so only the first season is removed.
+        MediaFile::delete(&mut tx, mfile1).await.unwrap();
+        MATCHER.cleanup(&mut tx, e1).await.unwrap();
+
+        assert!(Media::get(&mut tx, e1).await.is_err());
+        assert!(Season::get_by_id(&mut tx, s1).await.is_err());
+        assert!(Season::get_by_id(&mut tx, s2).await.is_ok());
+        assert!(Media::get(&mut tx, t1).await.is_ok());
+
+        MediaFile::delete(&mut tx, mfile2).await.unwrap();
+        MATCHER.cleanup(&mut tx, e2).await.unwrap();
+
+        assert!(Season::get_by_id(&mut tx, s2).await.is_err());
+        assert!(Media::get(&mut tx, t1).await.is_err());
+    }
+
+    #[test]
+    fn resolve_absolute_episode() {
+        let counts = [61, 16, 14];
+
+        assert_eq!(resolve_absolute(1, counts), Some((0, 0)));
+        assert_eq!(resolve_absolute(61, counts), Some((0, 60)));
+        assert_eq!(resolve_absolute(62, counts), Some((1, 0)));
+        assert_eq!(resolve_absolute(91, counts), Some((2, 13)));
+        assert_eq!(resolve_absolute(92, counts), None);
+        assert_eq!(resolve_absolute(0, counts), None);
+    }
 }
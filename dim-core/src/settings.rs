@@ -30,6 +30,15 @@ pub struct GlobalSettings {
     pub secret_key: Option<[u8; 32]>,
     pub enable_hwaccel: bool,
     pub version: String,
+
+    /// Number of days files that went missing are kept before they get purged, `0` keeps them
+    /// forever.
+    #[serde(default = "default_missing_grace_period_days")]
+    pub missing_grace_period_days: u64,
+}
+
+fn default_missing_grace_period_days() -> u64 {
+    30
 }
 
 impl Default for GlobalSettings {
@@ -63,6 +72,7 @@ impl Default for GlobalSettings {
             secret_key: None,
             enable_hwaccel: false,
             version: String::new(),
+            missing_grace_period_days: default_missing_grace_period_days(),
         }
     }
 }
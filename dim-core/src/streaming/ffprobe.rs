@@ -1,4 +1,5 @@
 use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::collections::VecDeque;
 use std::process::Stdio;
 use tokio::process::Command;
@@ -11,12 +12,35 @@ pub enum Error {
     FfprobeError,
 }
 
+/// Reason a file can't be played, as found out by probing it.
+#[derive(Clone, Debug, PartialEq, Eq, displaydoc::Display, thiserror::Error)]
+pub enum ProbeProblem {
+    /// Container is truncated: {0}
+    Truncated(String),
+    /// ffprobe failed to read the file: {0}
+    FfprobeError(String),
+    /// ffprobe failed to read the file.
+    Unreadable,
+    /// File has no video stream.
+    NoVideoStream,
+    /// File has no audio stream.
+    NoAudioStream,
+    /// File has no duration.
+    ZeroDuration,
+}
+
+/// Messages ffprobe prints when a container ends before it should.
+static TRUNCATION_MARKERS: &[&str] = &["moov atom not found", "partial file", "premature end"];
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FFPStream {
     streams: Vec<Stream>,
     format: Format,
     #[serde(default)]
     corrupt: bool,
+    /// Errors printed by ffprobe while reading the file.
+    #[serde(skip)]
+    errors: Option<String>,
 }
 
 impl Default for FFPStream {
@@ -25,6 +49,7 @@ impl Default for FFPStream {
             corrupt: true,
             streams: Default::default(),
             format: Default::default(),
+            errors: None,
         }
     }
 }
@@ -54,6 +79,7 @@ pub struct Stream {
     pub duration: Option<String>,
     pub color_range: Option<String>,
     pub color_space: Option<String>,
+    pub color_transfer: Option<String>,
     pub disposition: Option<Disposition>,
 }
 
@@ -124,6 +150,8 @@ struct Format {
     pub duration: String,
     pub size: String,
     pub bit_rate: String,
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
 }
 
 pub struct FFProbeCtx {
@@ -165,15 +193,67 @@ impl FFProbeCtx {
             error!(status = ?output.status, stderr = %stderr, "ffprobe exited with an error status.");
         }
 
-        let json = String::from_utf8_lossy(output.stdout.as_slice());
-
-        let de = serde_json::from_str(&json).unwrap_or_default();
-
-        Ok(de)
+        Ok(FFPStream::from_output(
+            &String::from_utf8_lossy(output.stdout.as_slice()),
+            &String::from_utf8_lossy(output.stderr.as_slice()),
+        ))
     }
 }
 
 impl FFPStream {
+    /// Method parses the json ffprobe printed to stdout, keeping what it printed to stderr as the
+    /// errors it ran into. Output that can't be parsed yields a corrupt stream.
+    pub fn from_output(stdout: &str, stderr: &str) -> Self {
+        let stderr = stderr.trim();
+
+        Self {
+            errors: (!stderr.is_empty()).then(|| stderr.to_owned()),
+            ..serde_json::from_str(stdout).unwrap_or_default()
+        }
+    }
+
+    /// Method returns the reason this file can't be played, if there is one. `needs_video` tells
+    /// whether the file must have a video stream, audio files only need a audio stream.
+    ///
+    /// ffprobe warns about plenty of files that play just fine, ie h264 streams referencing a
+    /// missing PPS, so what it prints is only reported when the streams or duration are unusable.
+    pub fn problem(&self, needs_video: bool) -> Option<ProbeProblem> {
+        let stderr = || {
+            let errors = self.errors.clone()?;
+            let lowercase = errors.to_lowercase();
+
+            if TRUNCATION_MARKERS.iter().any(|x| lowercase.contains(x)) {
+                return Some(ProbeProblem::Truncated(errors));
+            }
+
+            Some(ProbeProblem::FfprobeError(errors))
+        };
+
+        if self.corrupt || self.streams.is_empty() {
+            return Some(stderr().unwrap_or(ProbeProblem::Unreadable));
+        }
+
+        // NOTE: Cover art embedded in a file shows up as a video stream too.
+        let has_video = self
+            .find_by_type("video")
+            .iter()
+            .any(|x| x.disposition.as_ref().map_or(true, |d| d.attached_pic == 0));
+
+        if needs_video && !has_video {
+            return Some(ProbeProblem::NoVideoStream);
+        }
+
+        if !needs_video && self.find_by_type("audio").is_empty() {
+            return Some(ProbeProblem::NoAudioStream);
+        }
+
+        if self.get_ms().unwrap_or_default() == 0 {
+            return Some(stderr().unwrap_or(ProbeProblem::ZeroDuration));
+        }
+
+        None
+    }
+
     pub fn get_container(&self) -> String {
         self.format.format_name.clone()
     }
@@ -210,6 +290,20 @@ impl FFPStream {
         self.find_by_type("video").first()?.width
     }
 
+    /// Method returns the HDR format of the primary video stream, or `None` if its SDR.
+    pub fn get_hdr(&self) -> Option<String> {
+        match self
+            .find_by_type("video")
+            .first()?
+            .color_transfer
+            .as_deref()?
+        {
+            "smpte2084" => Some("HDR10".into()),
+            "arib-std-b67" => Some("HLG".into()),
+            _ => None,
+        }
+    }
+
     pub fn get_primary(&self, codec_type: &str) -> Option<&Stream> {
         let mut streams: VecDeque<_> = self.find_by_type(codec_type).into();
 
@@ -248,6 +342,25 @@ impl FFPStream {
             .ok()
     }
 
+    /// Returns the value of a container level tag. Tag names are matched case-insensitively as
+    /// different containers use different casing (`ARTIST` in vorbis comments, `artist` in id3).
+    pub fn get_tag(&self, key: &str) -> Option<&str> {
+        self.format
+            .tags
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Returns the stream holding embedded cover art if there is one.
+    pub fn get_attached_pic(&self) -> Option<&Stream> {
+        self.find_by_type("video").into_iter().find(|x| {
+            x.disposition
+                .as_ref()
+                .map_or(false, |d| d.attached_pic == 1)
+        })
+    }
+
     pub fn is_corrupt(&self) -> bool {
         self.corrupt
     }
@@ -275,4 +388,6 @@ pub struct Disposition {
     pub forced: i64,
     pub hearing_impaired: i64,
     pub visual_impaired: i64,
+    #[serde(default)]
+    pub attached_pic: i64,
 }
@@ -1,4 +1,5 @@
 pub mod ffprobe;
+pub mod stack;
 
 use cfg_if::cfg_if;
 
@@ -0,0 +1,61 @@
+//! Stacked movies are streamed as one continuous file through ffmpeg's concat demuxer.
+//!
+//! The concat demuxer refuses absolute paths unless it is explicitly told that the playlist is
+//! safe, which we cant do as we dont control the arguments nightfall passes to ffmpeg. Instead we
+//! link every part into a directory next to the playlist and reference the links by their names.
+
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Function writes a ffconcat playlist which plays back `parts` in order and returns its path.
+///
+/// # Arguments
+/// * `id` - id of the mediafile that is being streamed, used to name the playlist.
+/// * `parts` - path and duration in seconds of every part of the stack.
+pub fn write_playlist<'a>(
+    id: i64,
+    parts: impl IntoIterator<Item = (&'a Path, Option<i64>)>,
+) -> io::Result<PathBuf> {
+    let dir = std::env::temp_dir().join("dim-stacks").join(id.to_string());
+
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir)?;
+    }
+
+    std::fs::create_dir_all(&dir)?;
+
+    let mut playlist = String::from("ffconcat version 1.0\n");
+
+    for (idx, (part, duration)) in parts.into_iter().enumerate() {
+        let name = match part.extension().and_then(|x| x.to_str()) {
+            Some(ext) => format!("part{:02}.{ext}", idx + 1),
+            None => format!("part{:02}", idx + 1),
+        };
+
+        link(part, &dir.join(&name))?;
+
+        playlist.push_str(&format!("file {name}\n"));
+
+        // NOTE: Knowing the duration of every part lets ffmpeg seek without opening every part.
+        if let Some(duration) = duration {
+            playlist.push_str(&format!("duration {duration}\n"));
+        }
+    }
+
+    let path = dir.join("stack.ffconcat");
+    std::fs::File::create(&path)?.write_all(playlist.as_bytes())?;
+
+    Ok(path)
+}
+
+#[cfg(unix)]
+fn link(original: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(original, link)
+}
+
+#[cfg(not(unix))]
+fn link(original: &Path, link: &Path) -> io::Result<()> {
+    std::fs::hard_link(original, link)
+}
@@ -0,0 +1,162 @@
+//! Several files of the same media are versions of it, ie a theatrical 4K remux next to a 1080p
+//! Final Cut. This module labels those versions and picks the one that gets played back by default.
+
+use crate::utils::codec_pretty;
+
+use dim_database::mediafile::MediaFile;
+use dim_database::user::VersionPreference;
+
+use std::cmp::Reverse;
+
+/// Function returns a human readable label for a version, ie `Final Cut - 2160p HDR10 HEVC`.
+pub fn label(file: &MediaFile) -> String {
+    let quality = [
+        file.quality.as_ref().map(|x| format!("{}p", x)),
+        file.hdr.clone(),
+        file.codec.as_deref().map(codec_pretty),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join(" ");
+
+    match file.edition.as_deref() {
+        Some(edition) if !quality.is_empty() => format!("{} - {}", edition, quality),
+        Some(edition) => edition.to_owned(),
+        None if !quality.is_empty() => quality,
+        None => "Unknown".into(),
+    }
+}
+
+/// Function returns the versions found in `files` ordered so that the version that should be
+/// played back by default comes first.
+///
+/// A version picked explicitly by the user always comes first. The remaining versions are ranked
+/// by how well they fit the user's `rule` and then by their resolution. Stacked movies only list
+/// their first part as the streaming layer plays back the whole stack anyway.
+pub fn rank(
+    files: Vec<MediaFile>,
+    rule: &VersionPreference,
+    picked: Option<i64>,
+) -> Vec<MediaFile> {
+    let mut versions = files
+        .into_iter()
+        .filter(|x| x.stack_part.map_or(true, |part| part <= 1))
+        .collect::<Vec<_>>();
+
+    versions.sort_by_key(|x| {
+        let height = x.quality.as_deref().and_then(|x| x.parse::<i64>().ok());
+
+        let edition = match (rule.edition.as_deref(), x.edition.as_deref()) {
+            (Some(wanted), Some(edition)) => wanted.eq_ignore_ascii_case(edition),
+            _ => false,
+        };
+
+        let fits_resolution = match (rule.max_resolution, height) {
+            (Some(max), Some(height)) => height <= max,
+            _ => true,
+        };
+
+        let hdr = rule.hdr.map_or(true, |wanted| wanted == x.hdr.is_some());
+
+        Reverse((
+            Some(x.id) == picked,
+            edition,
+            fits_resolution,
+            hdr,
+            height.unwrap_or_default(),
+        ))
+    });
+
+    versions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::label;
+    use super::rank;
+    use crate::scanner::tests::mediafile::create_library;
+
+    use dim_database::library::MediaType;
+    use dim_database::media::InsertableMedia;
+    use dim_database::mediafile::InsertableMediaFile;
+    use dim_database::mediafile::MediaFile;
+    use dim_database::user::VersionPreference;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_rank_versions() {
+        let mut conn = dim_database::get_conn_memory()
+            .await
+            .expect("Failed to obtain a in-memory db pool.");
+        let library_id = create_library(&mut conn).await;
+
+        let mut lock = conn.writer().lock_owned().await;
+        let mut tx = dim_database::write_tx(&mut lock).await.unwrap();
+
+        let media_id = InsertableMedia {
+            library_id,
+            name: "Blade Runner".into(),
+            media_type: MediaType::Movie,
+            ..Default::default()
+        }
+        .insert(&mut tx)
+        .await
+        .unwrap();
+
+        let versions = [
+            ("Blade Runner (1982).mkv", "2160", Some("HDR10"), None),
+            (
+                "Blade Runner (1982) {edition-Final Cut}.mkv",
+                "1080",
+                None,
+                Some("Final Cut"),
+            ),
+        ];
+
+        for (file, quality, hdr, edition) in versions {
+            InsertableMediaFile {
+                library_id,
+                media_id: Some(media_id),
+                target_file: format!("/movies/{}", file),
+                raw_name: "Blade Runner".into(),
+                quality: Some(quality.into()),
+                codec: Some("hevc".into()),
+                hdr: hdr.map(Into::into),
+                edition: edition.map(Into::into),
+                ..Default::default()
+            }
+            .insert(&mut tx)
+            .await
+            .unwrap();
+        }
+
+        let files = MediaFile::get_of_media(&mut tx, media_id).await.unwrap();
+        let (remux, final_cut) = (files[0].id, files[1].id);
+
+        assert_eq!(label(&files[0]), "2160p HDR10 HEVC");
+        assert_eq!(label(&files[1]), "Final Cut - 1080p HEVC");
+
+        // without a rule the version with the highest resolution wins.
+        let ranked = rank(files.clone(), &Default::default(), None);
+        assert_eq!(ranked[0].id, remux);
+
+        let rule = VersionPreference {
+            edition: Some("final cut".into()),
+            ..Default::default()
+        };
+        assert_eq!(rank(files.clone(), &rule, None)[0].id, final_cut);
+
+        let rule = VersionPreference {
+            max_resolution: Some(1080),
+            ..Default::default()
+        };
+        assert_eq!(rank(files.clone(), &rule, None)[0].id, final_cut);
+
+        // a version picked by the user beats any rule.
+        let rule = VersionPreference {
+            edition: Some("Final Cut".into()),
+            ..Default::default()
+        };
+        assert_eq!(rank(files, &rule, Some(remux))[0].id, remux);
+    }
+}
@@ -0,0 +1,43 @@
+-- Artists are not media objects by themselves, they only group albums together.
+CREATE TABLE artist (
+    id INTEGER NOT NULL,
+    library_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    PRIMARY KEY (id),
+
+    FOREIGN KEY (library_id) REFERENCES library(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX artist_idx ON artist(library_id, name);
+
+-- Albums inherit from _tblmedia the same way tv shows do, this way they get cards, posters and
+-- genres for free.
+CREATE TABLE album (
+    id INTEGER NOT NULL,
+    artist_id INTEGER,
+    PRIMARY KEY (id),
+
+    FOREIGN KEY (id) REFERENCES _tblmedia (id) ON DELETE CASCADE,
+    FOREIGN KEY (artist_id) REFERENCES artist (id) ON DELETE SET NULL
+);
+
+-- Tracks inherit from _tblmedia the same way episodes do, mediafiles and progress get linked
+-- against them.
+CREATE TABLE track (
+    id INTEGER NOT NULL,
+    album_id INTEGER NOT NULL,
+    track_number INTEGER,
+    disc_number INTEGER,
+    PRIMARY KEY (id),
+
+    FOREIGN KEY (id) REFERENCES _tblmedia (id) ON DELETE CASCADE,
+    FOREIGN KEY (album_id) REFERENCES _tblmedia (id) ON DELETE CASCADE
+);
+
+CREATE INDEX track_album_idx ON track(album_id);
+
+-- Album and track names are only unique per artist or album respectively, so we exclude them from
+-- the media name index.
+DROP INDEX media_idx;
+CREATE UNIQUE INDEX media_idx ON _tblmedia(library_id, name, media_type)
+WHERE _tblmedia.media_type NOT IN ("episode", "album", "track");
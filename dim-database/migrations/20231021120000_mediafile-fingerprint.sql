@@ -0,0 +1,7 @@
+-- Fingerprint of the file on disk at the time it was last probed. Used to skip unchanged files on
+-- rescans.
+ALTER TABLE mediafile ADD COLUMN file_size INTEGER;
+ALTER TABLE mediafile ADD COLUMN mtime INTEGER;
+ALTER TABLE mediafile ADD COLUMN inode INTEGER;
+-- Set when a rescan couldn't find the file on disk anymore.
+ALTER TABLE mediafile ADD COLUMN missing BOOLEAN NOT NULL DEFAULT 0;
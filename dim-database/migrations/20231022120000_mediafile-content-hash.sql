@@ -0,0 +1,4 @@
+-- Partial hash of the contents of a file, used to find the previous row of a file that has been
+-- moved.
+ALTER TABLE mediafile ADD COLUMN content_hash TEXT;
+CREATE INDEX mediafile_content_hash_idx ON mediafile(library_id, content_hash);
@@ -0,0 +1,2 @@
+-- Metadata provider used to match the media of a library.
+ALTER TABLE library ADD COLUMN metadata_provider TEXT NOT NULL DEFAULT 'tmdb';
@@ -0,0 +1,3 @@
+-- Whether artwork stored next to media files takes priority over artwork from the metadata
+-- provider.
+ALTER TABLE library ADD COLUMN prefer_local_artwork BOOLEAN NOT NULL DEFAULT 1;
@@ -0,0 +1,15 @@
+-- External subtitle files found next to a mediafile, ie `Movie.en.srt` or `Movie.forced.de.ass`.
+CREATE TABLE subtitle_sidecar (
+    id INTEGER NOT NULL,
+    mediafile_id INTEGER NOT NULL,
+    target_file TEXT NOT NULL,
+    codec TEXT NOT NULL,
+    language TEXT,
+    forced BOOLEAN NOT NULL DEFAULT 0,
+    sdh BOOLEAN NOT NULL DEFAULT 0,
+    PRIMARY KEY (id),
+
+    FOREIGN KEY (mediafile_id) REFERENCES mediafile(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX subtitle_sidecar_idx ON subtitle_sidecar(mediafile_id, target_file);
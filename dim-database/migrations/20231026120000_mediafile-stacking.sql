@@ -0,0 +1,5 @@
+-- Movies split into several files (`Movie.cd1.avi`, `Movie.cd2.avi`) form a stack. All parts of a
+-- stack share the same key, the part number records the playback order.
+ALTER TABLE mediafile ADD COLUMN stack_key TEXT;
+ALTER TABLE mediafile ADD COLUMN stack_part INTEGER;
+CREATE INDEX mediafile_stack_idx ON mediafile(library_id, stack_key);
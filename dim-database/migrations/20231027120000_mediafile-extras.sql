@@ -0,0 +1,4 @@
+-- Trailers, featurettes and other extras are stored as mediafiles of their parent media. The type
+-- tells them apart from the regular versions of the media.
+ALTER TABLE mediafile ADD COLUMN extra_type TEXT;
+CREATE INDEX mediafile_extra_idx ON mediafile(library_id, extra_type);
@@ -0,0 +1,17 @@
+-- Several files of the same media are versions of it. Versions are told apart by their edition
+-- (ie `Final Cut`) and their HDR format on top of their resolution and codec.
+ALTER TABLE mediafile ADD COLUMN edition TEXT;
+ALTER TABLE mediafile ADD COLUMN hdr TEXT;
+
+-- Version of a media a user has picked to be played back by default.
+CREATE TABLE preferred_version (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    media_id INTEGER NOT NULL,
+    mediafile_id INTEGER NOT NULL,
+    FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY(media_id) REFERENCES _tblmedia(id) ON DELETE CASCADE,
+    FOREIGN KEY(mediafile_id) REFERENCES mediafile(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX preferred_version_idx ON preferred_version(user_id, media_id);
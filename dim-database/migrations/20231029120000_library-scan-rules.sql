@@ -0,0 +1,2 @@
+-- Rules deciding which files the scanner picks up for a library, stored as json.
+ALTER TABLE library ADD COLUMN scan_rules TEXT NOT NULL DEFAULT '{}';
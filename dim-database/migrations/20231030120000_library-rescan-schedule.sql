@@ -0,0 +1,4 @@
+-- Schedule on which a library gets rescanned, stored as json. NULL disables scheduled rescans.
+ALTER TABLE library ADD COLUMN rescan_schedule TEXT;
+-- Unix timestamp of when the last full scan of the library has finished.
+ALTER TABLE library ADD COLUMN last_scanned_at INTEGER;
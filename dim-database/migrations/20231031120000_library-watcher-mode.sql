@@ -0,0 +1,2 @@
+-- How changes to the locations of a library are detected, `auto` picks polling for network mounts.
+ALTER TABLE library ADD COLUMN watcher_mode TEXT NOT NULL DEFAULT 'auto';
@@ -0,0 +1,48 @@
+-- Time in seconds since the unix epoch at which the file was first found to be missing. Missing
+-- files are kept around for a grace period, in case the drive or share they are on comes back.
+ALTER TABLE mediafile ADD COLUMN missing_since INTEGER;
+
+UPDATE mediafile SET missing_since = CAST(strftime('%s', 'now') AS INTEGER) WHERE missing;
+
+CREATE TRIGGER mediafile_missing_since
+AFTER UPDATE OF missing ON mediafile
+WHEN new.missing != old.missing
+BEGIN
+    UPDATE mediafile
+    SET missing_since = CASE WHEN new.missing THEN CAST(strftime('%s', 'now') AS INTEGER) END
+    WHERE id = new.id;
+END;
+
+-- Media objects all of whose files are missing, browse routes hide them until the files come back.
+-- Shows and albums are missing once the files of all their episodes or tracks are missing.
+-- Episodes after the first one of a file spanning several episodes are only linked through
+-- `mediafile_episode`, so they have to count towards the missing media as well.
+-- NOTE: `mediafile_episode` is created by a later migration, sqlite only resolves the tables of a
+-- view once it is queried.
+CREATE VIEW missing_media AS
+WITH media_files AS (
+    SELECT mediafile.media_id AS media_id, mediafile.missing AS missing
+    FROM mediafile
+    WHERE mediafile.media_id IS NOT NULL AND mediafile.extra_type IS NULL
+    UNION ALL
+    SELECT mediafile_episode.episode_id AS media_id, mediafile.missing AS missing
+    FROM mediafile_episode
+    INNER JOIN mediafile ON mediafile.id = mediafile_episode.mediafile_id
+)
+SELECT media_files.media_id AS id
+FROM media_files
+GROUP BY media_files.media_id
+HAVING MIN(media_files.missing) = 1
+UNION
+SELECT _tblseason.tvshowid AS id
+FROM media_files
+INNER JOIN episode ON episode.id = media_files.media_id
+INNER JOIN _tblseason ON _tblseason.id = episode.seasonid
+GROUP BY _tblseason.tvshowid
+HAVING MIN(media_files.missing) = 1
+UNION
+SELECT track.album_id AS id
+FROM mediafile
+INNER JOIN track ON track.id = mediafile.media_id
+GROUP BY track.album_id
+HAVING MIN(mediafile.missing) = 1;
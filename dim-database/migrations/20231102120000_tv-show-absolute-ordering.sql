@@ -0,0 +1,7 @@
+-- Shows whose episodes are numbered from the first episode of the show onwards instead of per
+-- season, as is common for anime (ie `One Piece - 1054`).
+CREATE TABLE tv_show_ordering (
+    id INTEGER PRIMARY KEY,
+    absolute_ordering BOOLEAN NOT NULL DEFAULT 0,
+    FOREIGN KEY(id) REFERENCES _tblmedia(id) ON DELETE CASCADE
+);
@@ -0,0 +1,11 @@
+-- Files spanning several episodes (ie `Show.S02E05E06.mkv`) point to their first episode through
+-- `mediafile.media_id`, the episodes after it are linked here.
+CREATE TABLE mediafile_episode (
+    mediafile_id INTEGER NOT NULL,
+    episode_id INTEGER NOT NULL,
+    PRIMARY KEY (mediafile_id, episode_id),
+    FOREIGN KEY(mediafile_id) REFERENCES mediafile(id) ON DELETE CASCADE,
+    FOREIGN KEY(episode_id) REFERENCES _tblmedia(id) ON DELETE CASCADE
+);
+
+CREATE INDEX mediafile_episode_episode_idx ON mediafile_episode(episode_id);
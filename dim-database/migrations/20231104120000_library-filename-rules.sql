@@ -0,0 +1,2 @@
+-- Regex rules extracting metadata from the filenames of a library, stored as a json array.
+ALTER TABLE library ADD COLUMN filename_rules TEXT NOT NULL DEFAULT '[]';
@@ -0,0 +1,3 @@
+-- Confidence from 0 to 100 in the metadata parsed from the filename of a mediafile. Files scanned
+-- before confidences were recorded have none.
+ALTER TABLE mediafile ADD COLUMN parse_confidence INTEGER;
@@ -0,0 +1,3 @@
+-- Reason a mediafile can't be played, as found out by ffprobe when it was last probed. `NULL` if
+-- the file is fine or was probed before problems were recorded.
+ALTER TABLE mediafile ADD COLUMN probe_problem TEXT;
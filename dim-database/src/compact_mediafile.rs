@@ -50,7 +50,7 @@ impl CompactMediafile {
         Ok(sqlx::query_as!(
             Record,
             r#"SELECT id, raw_name as name, duration, target_file FROM mediafile
-               WHERE library_id = ? AND media_id IS NULL"#,
+               WHERE library_id = ? AND media_id IS NULL AND extra_type IS NULL AND NOT missing"#,
             library_id
         )
         .fetch_all(tx)
@@ -68,7 +68,7 @@ impl CompactMediafile {
         Ok(sqlx::query_as!(
             Record,
             r#"SELECT id, raw_name as name, duration, target_file FROM mediafile
-               WHERE mediafile.media_id = ?"#,
+               WHERE mediafile.media_id = ? AND mediafile.extra_type IS NULL"#,
             media_id
         )
         .fetch_all(tx)
@@ -99,4 +99,25 @@ impl CompactMediafile {
         .map(Into::into)
         .collect())
     }
+
+    /// Method will return all mediafiles for a album.
+    pub async fn all_for_album(
+        tx: &mut Transaction<'_>,
+        album_id: i64,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            Record,
+            "SELECT mediafile.id, raw_name as name, duration, target_file FROM mediafile
+             INNER JOIN track ON mediafile.media_id = track.id
+             WHERE track.album_id = ?
+             GROUP BY track.id
+             ",
+            album_id
+        )
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect())
+    }
 }
@@ -63,7 +63,7 @@ impl Episode {
             FROM episode
             INNER JOIN season on season.id = episode.seasonid
             WHERE season.tvshowid = ?
-            ORDER BY episode_ ASC, season.season_number ASC
+            ORDER BY season.season_number = 0, episode_ ASC, season.season_number ASC
             LIMIT 1"#,
             tv_id
         )
@@ -256,7 +256,8 @@ impl Episode {
         Ok(record.into_episode(ep))
     }
 
-    /// Function will query for the episode after the episode passed in.
+    /// Function will query for the episode before the episode passed in. Specials in season 0
+    /// dont come before the first season.
     pub async fn get_prev_episode(
         &self,
         conn: &mut crate::Transaction<'_>,
@@ -273,7 +274,7 @@ impl Episode {
             ) AND ((
                 episode.episode_ < ? AND
                 season.season_number = ?
-            ) OR season.season_number < ?)
+            ) OR (season.season_number < ? AND season.season_number > 0))
             ORDER BY season.season_number DESC, episode.episode_ DESC
             LIMIT 1"#,
             self.seasonid,
@@ -333,6 +334,39 @@ impl Episode {
         .seasonid)
     }
 
+    /// Method returns the ids of all episodes covered by the file `mediafile_id`, if it covers the
+    /// media `id`. A file spanning several episodes (ie `Show.S02E05E06.mkv`) covers all of them.
+    /// Otherwise only `id` itself is returned.
+    ///
+    /// # Arguments
+    /// * `id` - id of the media, usually a episode.
+    /// * `mediafile_id` - id of the file of the media.
+    pub async fn get_sharing_files(
+        tx: &mut crate::Transaction<'_>,
+        id: i64,
+        mediafile_id: i64,
+    ) -> Result<Vec<i64>, DatabaseError> {
+        let ids = sqlx::query!(
+            r#"SELECT media_id AS "id!: i64" FROM mediafile
+            WHERE id = $1 AND media_id IS NOT NULL AND extra_type IS NULL
+            UNION
+            SELECT episode_id FROM mediafile_episode
+            WHERE mediafile_id = $1"#,
+            mediafile_id
+        )
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .map(|x| x.id)
+        .collect::<Vec<_>>();
+
+        if !ids.contains(&id) {
+            return Ok(vec![id]);
+        }
+
+        Ok(ids)
+    }
+
     /// Method deletes a episode based on the tv show id, season number, and episode number
     ///
     /// # Arguments
@@ -21,13 +21,16 @@ pub mod library;
 pub mod media;
 pub mod mediafile;
 pub mod movie;
+pub mod music;
 pub mod progress;
 pub mod query_ext;
 pub mod rw_pool;
 pub mod season;
+pub mod subtitle;
 pub mod tv;
 pub mod user;
 pub mod utils;
+pub mod version;
 
 #[cfg(test)]
 pub mod tests;
@@ -4,6 +4,9 @@ use serde::Serialize;
 use std::convert::TryFrom;
 use std::fmt;
 
+use sqlx::Decode;
+use sqlx::Encode;
+
 /// Enum represents a media type and can be used on a library or on a media.
 /// When returned in a http response, the fields are lowercase.
 #[derive(Copy, Serialize, Debug, Clone, Eq, PartialEq, Deserialize, Hash, sqlx::Type)]
@@ -13,6 +16,9 @@ pub enum MediaType {
     Movie,
     Tv,
     Episode,
+    Music,
+    Album,
+    Track,
 }
 
 impl fmt::Display for MediaType {
@@ -24,6 +30,9 @@ impl fmt::Display for MediaType {
                 Self::Movie => "movie",
                 Self::Tv => "tv",
                 Self::Episode => "episode",
+                Self::Music => "music",
+                Self::Album => "album",
+                Self::Track => "track",
             }
         )
     }
@@ -37,6 +46,9 @@ impl TryFrom<&str> for MediaType {
             "movie" | "movies" => Ok(Self::Movie),
             "tv" | "tv_show" | "tv show" | "tv shows" => Ok(Self::Tv),
             "episode" | "episodes" | "ep" => Ok(Self::Episode),
+            "music" => Ok(Self::Music),
+            "album" | "albums" => Ok(Self::Album),
+            "track" | "tracks" => Ok(Self::Track),
             _ => Err(()),
         }
     }
@@ -56,6 +68,232 @@ impl Default for MediaType {
     }
 }
 
+/// Enum represents the metadata provider used to match the media of a library.
+#[derive(Copy, Serialize, Debug, Clone, Eq, PartialEq, Deserialize, Hash, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum MetadataProvider {
+    /// Metadata is fetched from TMDB.
+    Tmdb,
+    /// Metadata is read from Kodi-style `.nfo` sidecar files only.
+    Nfo,
+    /// Metadata is read from `.nfo` sidecar files, falling back to TMDB for files without one.
+    NfoThenTmdb,
+}
+
+impl Default for MetadataProvider {
+    fn default() -> Self {
+        Self::Tmdb
+    }
+}
+
+/// Enum represents how the filesystem watcher of a library detects changes to its locations.
+#[derive(Copy, Serialize, Debug, Clone, Eq, PartialEq, Deserialize, Hash, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum WatcherMode {
+    /// Use native filesystem events, unless a location is on a filesystem which doesn't reliably
+    /// generate them, like NFS or SMB mounts.
+    Auto,
+    /// Always use native filesystem events.
+    Native,
+    /// Always poll the locations for changes.
+    Poll,
+}
+
+impl Default for WatcherMode {
+    fn default() -> Self {
+        Self::Auto
+    }
+}
+
+/// Rules deciding which files the scanner picks up for a library.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ScanRules {
+    /// Extensions picked up on top of the ones supported by default, ie `iso`.
+    #[serde(default)]
+    pub extra_exts: Vec<String>,
+    /// Extensions that shouldn't be picked up even though they are supported by default.
+    #[serde(default)]
+    pub excluded_exts: Vec<String>,
+    /// Gitignore-style globs, relative to the locations of the library, of files and directories
+    /// that should be skipped, ie `**/Sample/`.
+    #[serde(default)]
+    pub exclude_globs: Vec<String>,
+    /// Files smaller than this many bytes are skipped. This is mostly useful to skip samples.
+    #[serde(default)]
+    pub min_file_size: Option<u64>,
+    /// Whether symlinks should be followed while walking the locations of the library.
+    #[serde(default = "default_true")]
+    pub follow_symlinks: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for ScanRules {
+    fn default() -> Self {
+        Self {
+            extra_exts: vec![],
+            excluded_exts: vec![],
+            exclude_globs: vec![],
+            min_file_size: None,
+            follow_symlinks: true,
+        }
+    }
+}
+
+impl<DB: sqlx::Database> sqlx::Type<DB> for ScanRules
+where
+    String: sqlx::Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <String as sqlx::Type<DB>>::type_info()
+    }
+}
+
+impl<'r, DB: sqlx::Database> Decode<'r, DB> for ScanRules
+where
+    &'r str: Decode<'r, DB>,
+{
+    fn decode(
+        value: <DB as sqlx::database::HasValueRef<'r>>::ValueRef,
+    ) -> Result<Self, sqlx::error::BoxDynError> {
+        let value = <&str as Decode<DB>>::decode(value)?;
+        Ok(serde_json::from_str(value).unwrap_or_default())
+    }
+}
+
+impl<'q, DB: sqlx::Database> Encode<'q, DB> for ScanRules
+where
+    String: Encode<'q, DB>,
+{
+    fn encode_by_ref(
+        &self,
+        buf: &mut <DB as sqlx::database::HasArguments<'q>>::ArgumentBuffer,
+    ) -> sqlx::encode::IsNull {
+        let val = serde_json::to_string(self).unwrap_or_default();
+        <String as Encode<DB>>::encode(val, buf)
+    }
+}
+
+/// Regex rules extracting metadata from filenames, for releases following odd naming schemes.
+/// Rules are tried in order before the built-in parsers and use the named captures `title`,
+/// `year`, `season` and `episode`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct FilenameRules(pub Vec<String>);
+
+impl<DB: sqlx::Database> sqlx::Type<DB> for FilenameRules
+where
+    String: sqlx::Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <String as sqlx::Type<DB>>::type_info()
+    }
+}
+
+impl<'r, DB: sqlx::Database> Decode<'r, DB> for FilenameRules
+where
+    &'r str: Decode<'r, DB>,
+{
+    fn decode(
+        value: <DB as sqlx::database::HasValueRef<'r>>::ValueRef,
+    ) -> Result<Self, sqlx::error::BoxDynError> {
+        let value = <&str as Decode<DB>>::decode(value)?;
+        Ok(serde_json::from_str(value).unwrap_or_default())
+    }
+}
+
+impl<'q, DB: sqlx::Database> Encode<'q, DB> for FilenameRules
+where
+    String: Encode<'q, DB>,
+{
+    fn encode_by_ref(
+        &self,
+        buf: &mut <DB as sqlx::database::HasArguments<'q>>::ArgumentBuffer,
+    ) -> sqlx::encode::IsNull {
+        let val = serde_json::to_string(self).unwrap_or_default();
+        <String as Encode<DB>>::encode(val, buf)
+    }
+}
+
+/// Schedule on which a library gets rescanned. Useful for locations which dont generate
+/// filesystem events, like network shares.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RescanSchedule {
+    /// Rescan every `minutes` minutes.
+    Interval { minutes: u64 },
+    /// Rescan whenever the cron expression matches, in local time. Expressions have five fields:
+    /// `minute hour day-of-month month day-of-week`.
+    Cron { expr: String },
+}
+
+impl<DB: sqlx::Database> sqlx::Type<DB> for RescanSchedule
+where
+    String: sqlx::Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <String as sqlx::Type<DB>>::type_info()
+    }
+}
+
+impl<'r, DB: sqlx::Database> Decode<'r, DB> for RescanSchedule
+where
+    &'r str: Decode<'r, DB>,
+{
+    fn decode(
+        value: <DB as sqlx::database::HasValueRef<'r>>::ValueRef,
+    ) -> Result<Self, sqlx::error::BoxDynError> {
+        let value = <&str as Decode<DB>>::decode(value)?;
+        Ok(serde_json::from_str(value)?)
+    }
+}
+
+/// Schedule as read from the database. Like invalid scan rules fall back to the defaults, a invalid
+/// schedule is treated as no schedule at all, so that a single bad row doesn't keep us from loading
+/// the libraries.
+struct StoredRescanSchedule(Option<RescanSchedule>);
+
+impl<DB: sqlx::Database> sqlx::Type<DB> for StoredRescanSchedule
+where
+    String: sqlx::Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <String as sqlx::Type<DB>>::type_info()
+    }
+}
+
+impl<'r, DB: sqlx::Database> Decode<'r, DB> for StoredRescanSchedule
+where
+    &'r str: Decode<'r, DB>,
+{
+    fn decode(
+        value: <DB as sqlx::database::HasValueRef<'r>>::ValueRef,
+    ) -> Result<Self, sqlx::error::BoxDynError> {
+        let schedule = <RescanSchedule as Decode<DB>>::decode(value)
+            .inspect_err(|error| tracing::warn!(?error, "Ignoring invalid rescan schedule."))
+            .ok();
+
+        Ok(Self(schedule))
+    }
+}
+
+impl<'q, DB: sqlx::Database> Encode<'q, DB> for RescanSchedule
+where
+    String: Encode<'q, DB>,
+{
+    fn encode_by_ref(
+        &self,
+        buf: &mut <DB as sqlx::database::HasArguments<'q>>::ArgumentBuffer,
+    ) -> sqlx::encode::IsNull {
+        let val = serde_json::to_string(self).unwrap_or_default();
+        <String as Encode<DB>>::encode(val, buf)
+    }
+}
+
 /// Library struct which we can use to deserialize database queries into.
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Library {
@@ -68,11 +306,25 @@ pub struct Library {
     pub locations: Vec<String>,
 
     /// Enum used to identify the media type that this library contains. At the
-    /// moment only `movie` and `tv` are supported
-    // TODO: support mixed content, music
+    /// moment only `movie`, `tv` and `music` are supported
+    // TODO: support mixed content
     pub media_type: MediaType,
     /// Is library hidden?
     pub hidden: bool,
+    /// Metadata provider used to match the media of this library.
+    pub metadata_provider: MetadataProvider,
+    /// Whether artwork stored next to the media takes priority over remote artwork.
+    pub prefer_local_artwork: bool,
+    /// Rules deciding which files the scanner picks up.
+    pub scan_rules: ScanRules,
+    /// Regex rules extracting metadata from filenames.
+    pub filename_rules: FilenameRules,
+    /// Schedule on which the library gets rescanned, if any.
+    pub rescan_schedule: Option<RescanSchedule>,
+    /// Unix timestamp of when the last full scan of the library has finished.
+    pub last_scanned_at: Option<i64>,
+    /// How changes to the locations of the library are detected.
+    pub watcher_mode: WatcherMode,
 }
 
 impl Library {
@@ -82,7 +334,7 @@ impl Library {
     /// This method will not return the locations indexed for this library, if you need those you
     /// must query for them separately.
     pub async fn get_all(conn: &mut crate::Transaction<'_>) -> Vec<Self> {
-        sqlx::query!(r#"SELECT id, name, media_type as "media_type: MediaType", hidden as "hidden: bool" FROM library WHERE NOT hidden"#)
+        sqlx::query!(r#"SELECT id, name, media_type as "media_type: MediaType", hidden as "hidden: bool", metadata_provider as "metadata_provider: MetadataProvider", prefer_local_artwork as "prefer_local_artwork: bool", scan_rules as "scan_rules: ScanRules", filename_rules as "filename_rules: FilenameRules", rescan_schedule as "rescan_schedule: StoredRescanSchedule", last_scanned_at, watcher_mode as "watcher_mode: WatcherMode" FROM library WHERE NOT hidden"#)
             .fetch_all(&mut *conn)
             .await
             .unwrap_or_default()
@@ -92,6 +344,13 @@ impl Library {
                 name: x.name,
                 media_type: x.media_type,
                 hidden: x.hidden,
+                metadata_provider: x.metadata_provider,
+                prefer_local_artwork: x.prefer_local_artwork,
+                scan_rules: x.scan_rules,
+                filename_rules: x.filename_rules,
+                rescan_schedule: x.rescan_schedule.and_then(|x| x.0),
+                last_scanned_at: x.last_scanned_at,
+                watcher_mode: x.watcher_mode,
                 locations: vec![],
             })
             .collect()
@@ -121,7 +380,7 @@ impl Library {
         lib_id: i64,
     ) -> Result<Self, DatabaseError> {
         let library = sqlx::query!(
-            r#"SELECT id, name, media_type as "media_type: MediaType", hidden as "hidden: bool" FROM library
+            r#"SELECT id, name, media_type as "media_type: MediaType", hidden as "hidden: bool", metadata_provider as "metadata_provider: MetadataProvider", prefer_local_artwork as "prefer_local_artwork: bool", scan_rules as "scan_rules: ScanRules", filename_rules as "filename_rules: FilenameRules", rescan_schedule as "rescan_schedule: StoredRescanSchedule", last_scanned_at, watcher_mode as "watcher_mode: WatcherMode" FROM library
             WHERE id = ?"#,
             lib_id
         )
@@ -141,6 +400,13 @@ impl Library {
             name: library.name,
             media_type: library.media_type,
             hidden: library.hidden,
+            metadata_provider: library.metadata_provider,
+            prefer_local_artwork: library.prefer_local_artwork,
+            scan_rules: library.scan_rules,
+            filename_rules: library.filename_rules,
+            rescan_schedule: library.rescan_schedule.and_then(|x| x.0),
+            last_scanned_at: library.last_scanned_at,
+            watcher_mode: library.watcher_mode,
             locations,
         })
     }
@@ -172,6 +438,38 @@ impl Library {
         )
     }
 
+    /// Method records that a full scan of the library has finished at `timestamp`.
+    pub async fn set_last_scanned(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+        timestamp: i64,
+    ) -> Result<usize, DatabaseError> {
+        Ok(sqlx::query!(
+            "UPDATE library SET last_scanned_at = ? WHERE id = ?",
+            timestamp,
+            id
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected() as usize)
+    }
+
+    /// Method returns whether artwork stored next to the media of a library takes priority over
+    /// remote artwork.
+    pub async fn prefers_local_artwork(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<bool, DatabaseError> {
+        Ok(sqlx::query!(
+            r#"SELECT prefer_local_artwork as "prefer_local_artwork: bool" FROM library
+            WHERE id = ?"#,
+            id
+        )
+        .fetch_one(&mut *conn)
+        .await?
+        .prefer_local_artwork)
+    }
+
     pub async fn get_size(
         tx: &mut crate::Transaction<'_>,
         id: i64,
@@ -192,6 +490,22 @@ pub struct InsertableLibrary {
     pub name: String,
     pub locations: Vec<String>,
     pub media_type: MediaType,
+    #[serde(default)]
+    pub metadata_provider: MetadataProvider,
+    #[serde(default = "default_prefer_local_artwork")]
+    pub prefer_local_artwork: bool,
+    #[serde(default)]
+    pub scan_rules: ScanRules,
+    #[serde(default)]
+    pub filename_rules: FilenameRules,
+    #[serde(default)]
+    pub rescan_schedule: Option<RescanSchedule>,
+    #[serde(default)]
+    pub watcher_mode: WatcherMode,
+}
+
+fn default_prefer_local_artwork() -> bool {
+    true
 }
 
 impl InsertableLibrary {
@@ -201,9 +515,16 @@ impl InsertableLibrary {
     /// * `conn` - mutable reference to a sqlx transaction.
     pub async fn insert(&self, conn: &mut crate::Transaction<'_>) -> Result<i64, DatabaseError> {
         let lib_id = sqlx::query!(
-            r#"INSERT INTO library (name, media_type) VALUES ($1, $2)"#,
+            r#"INSERT INTO library (name, media_type, metadata_provider, prefer_local_artwork, scan_rules, filename_rules, rescan_schedule, watcher_mode)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"#,
             self.name,
-            self.media_type
+            self.media_type,
+            self.metadata_provider,
+            self.prefer_local_artwork,
+            self.scan_rules,
+            self.filename_rules,
+            self.rescan_schedule,
+            self.watcher_mode
         )
         .execute(&mut *conn)
         .await?
@@ -223,3 +544,52 @@ impl InsertableLibrary {
         Ok(lib_id)
     }
 }
+
+/// Same as [`Library`](Library) except everything is optional. Used to change the settings of a
+/// library.
+#[derive(Clone, Default, Deserialize, Debug)]
+pub struct UpdateLibrary {
+    pub name: Option<String>,
+    pub metadata_provider: Option<MetadataProvider>,
+    pub prefer_local_artwork: Option<bool>,
+    pub scan_rules: Option<ScanRules>,
+    pub filename_rules: Option<FilenameRules>,
+    /// `null` disables scheduled rescans, leaving the field out keeps the current schedule.
+    #[serde(default, deserialize_with = "double_option")]
+    pub rescan_schedule: Option<Option<RescanSchedule>>,
+    pub watcher_mode: Option<WatcherMode>,
+}
+
+/// Function deserializes a field that is present, even if `null`, into `Some`.
+fn double_option<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+impl UpdateLibrary {
+    /// Method updates the settings of the library with the id supplied.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the library we want to update.
+    pub async fn update(
+        &self,
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<usize, DatabaseError> {
+        crate::opt_update!(conn,
+            "UPDATE library SET name = ? WHERE id = ?" => (self.name, id),
+            "UPDATE library SET metadata_provider = ? WHERE id = ?" => (self.metadata_provider, id),
+            "UPDATE library SET prefer_local_artwork = ? WHERE id = ?" => (self.prefer_local_artwork, id),
+            "UPDATE library SET scan_rules = ? WHERE id = ?" => (self.scan_rules, id),
+            "UPDATE library SET filename_rules = ? WHERE id = ?" => (self.filename_rules, id),
+            "UPDATE library SET rescan_schedule = ? WHERE id = ?" => (self.rescan_schedule, id),
+            "UPDATE library SET watcher_mode = ? WHERE id = ?" => (self.watcher_mode, id)
+        );
+
+        Ok(1)
+    }
+}
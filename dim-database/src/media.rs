@@ -56,7 +56,8 @@ impl Media {
     ) -> Result<Vec<Self>, DatabaseError> {
         Ok(sqlx::query_as!(
                 Media,
-                r#"SELECT id, library_id, name, description, rating as "rating: _", year, added, poster_path, backdrop_path, media_type as "media_type: _" FROM media WHERE library_id = ? AND NOT media_type = "episode""#,
+                r#"SELECT id, library_id, name, description, rating as "rating: _", year, added, poster_path, backdrop_path, media_type as "media_type: _" FROM media WHERE library_id = ? AND media_type NOT IN ("episode", "track")
+                AND id NOT IN (SELECT id FROM missing_media)"#,
                 library_id
             )
             .fetch_all(&mut *conn)
@@ -91,7 +92,7 @@ impl Media {
     ) -> Result<Self, DatabaseError> {
         Ok(sqlx::query_as!(
                 Media,
-                r#"SELECT id, library_id, name, description, rating as "rating: _", year, added, poster_path, backdrop_path, media_type as "media_type: _" FROM media WHERE library_id = ? AND name = ? AND NOT media_type = "episode""#,
+                r#"SELECT id, library_id, name, description, rating as "rating: _", year, added, poster_path, backdrop_path, media_type as "media_type: _" FROM media WHERE library_id = ? AND name = ? AND media_type NOT IN ("episode", "track")"#,
                 library_id,
                 name,
             )
@@ -122,7 +123,8 @@ impl Media {
             r#"SELECT _tblmedia.id
                 FROM _tblmedia
                 JOIN library ON library.id = _tblmedia.library_id
-                WHERE NOT _tblmedia.media_type = "episode" AND NOT library.hidden
+                WHERE _tblmedia.media_type NOT IN ("episode", "track") AND NOT library.hidden
+                AND _tblmedia.id NOT IN (SELECT id FROM missing_media)
                 ORDER BY rating DESC
                 LIMIT ?"#,
             limit
@@ -140,7 +142,8 @@ impl Media {
             r#"SELECT _tblmedia.id
                 FROM _tblmedia
                 JOIN library ON library.id = _tblmedia.library_id
-                WHERE NOT _tblmedia.media_type = "episode" AND NOT library.hidden
+                WHERE _tblmedia.media_type NOT IN ("episode", "track") AND NOT library.hidden
+                AND _tblmedia.id NOT IN (SELECT id FROM missing_media)
                 ORDER BY added DESC
                 LIMIT ?"#,
             limit
@@ -158,7 +161,8 @@ impl Media {
                 r#"SELECT media.id, media.library_id, media.name, description, rating as "rating: _", year, added, poster_path as "poster_path?", backdrop_path as "backdrop_path?", media.media_type as "media_type: _"
                 FROM media
                 JOIN library ON media.library_id = library.id
-                WHERE NOT media.media_type = "episode" AND NOT library.hidden
+                WHERE media.media_type NOT IN ("episode", "track") AND NOT library.hidden
+                AND media.id NOT IN (SELECT id FROM missing_media)
                 GROUP BY media.id
                 ORDER BY RANDOM()
                 LIMIT ?
@@ -178,7 +182,8 @@ impl Media {
                 r#"SELECT media.id, media.library_id, media.name, description, rating as "rating: _", year, added, poster_path, backdrop_path, media.media_type as "media_type: _"
                 FROM media
                 JOIN library ON library.id = media.library_id
-                WHERE NOT media.media_type = "episode" AND NOT library.hidden
+                WHERE media.media_type NOT IN ("episode", "track") AND NOT library.hidden
+                AND media.id NOT IN (SELECT id FROM missing_media)
                 AND UPPER(media.name) LIKE ?
                 LIMIT ?
                 "#,
@@ -197,7 +202,8 @@ impl Media {
                 FROM media
                 INNER JOIN genre_media ON genre_media.media_id = media.id
                 JOIN library ON library.id = media.library_id
-                WHERE NOT media.media_type = "episode" AND NOT library.hidden
+                WHERE media.media_type NOT IN ("episode", "track") AND NOT library.hidden
+                AND media.id NOT IN (SELECT id FROM missing_media)
                 AND genre_media.genre_id = ?
                 "#,
                 genre_id,
@@ -213,7 +219,8 @@ impl Media {
                 r#"SELECT media.id, media.library_id, media.name, description, rating as "rating: _", year, added, poster_path, backdrop_path, media.media_type as "media_type: _"
                 FROM media
                 JOIN library ON library.id = media.library_id
-                WHERE NOT media.media_type = "episode" AND NOT library.hidden
+                WHERE media.media_type NOT IN ("episode", "track") AND NOT library.hidden
+                AND media.id NOT IN (SELECT id FROM missing_media)
                 AND year = ?
                 "#,
                 year,
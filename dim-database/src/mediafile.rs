@@ -57,6 +57,75 @@ pub struct MediaFile {
     pub profile: Option<String>,
     /// Primary audio language
     pub audio_language: Option<String>,
+
+    /// Size of the file in bytes at the time it was last probed.
+    pub file_size: Option<i64>,
+    /// Last modification time of the file in seconds since the unix epoch at the time it was last
+    /// probed.
+    pub mtime: Option<i64>,
+    /// Inode of the file at the time it was last probed. Only available on unix platforms.
+    pub inode: Option<i64>,
+    /// Flag which tells us if the file couldn't be found on disk during the last scan.
+    pub missing: bool,
+    /// Time in seconds since the unix epoch at which the file was first found to be missing.
+    pub missing_since: Option<i64>,
+    /// Partial hash of the contents of the file. This lets us recognize a file that has been moved
+    /// while we weren't watching.
+    pub content_hash: Option<String>,
+    /// Key shared by all parts of a movie that is split into several files.
+    pub stack_key: Option<String>,
+    /// Position of this file within its stack, starting at 1.
+    pub stack_part: Option<i64>,
+    /// Type of extra this file is, ie `trailer`. Extras are attached to their parent media but
+    /// aren't versions of it.
+    pub extra_type: Option<ExtraType>,
+    /// Edition of the movie stored in this file, ie `Final Cut`.
+    pub edition: Option<String>,
+    /// HDR format of the video, ie `HDR10`, or `None` if the video is SDR.
+    pub hdr: Option<String>,
+    /// Confidence from 0 to 100 in the metadata parsed from the filename. Files with a low
+    /// confidence are likely to be matched wrongly.
+    pub parse_confidence: Option<i64>,
+    /// Reason the file can't be played, ie `File has no video stream.`, found out when the file
+    /// was last probed.
+    pub probe_problem: Option<String>,
+}
+
+/// Enum represents the type of a extra, like a trailer or a featurette, stored alongside a media.
+#[derive(Copy, Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Hash, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum ExtraType {
+    Trailer,
+    Featurette,
+    BehindTheScenes,
+    DeletedScene,
+    Interview,
+    Scene,
+    Short,
+    Other,
+}
+
+impl ExtraType {
+    /// Method returns the name under which the extra type is stored in the database.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Trailer => "trailer",
+            Self::Featurette => "featurette",
+            Self::BehindTheScenes => "behind_the_scenes",
+            Self::DeletedScene => "deleted_scene",
+            Self::Interview => "interview",
+            Self::Scene => "scene",
+            Self::Short => "short",
+            Self::Other => "other",
+        }
+    }
+}
+
+impl std::fmt::Display for ExtraType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 impl MediaFile {
@@ -71,7 +140,12 @@ impl MediaFile {
     ) -> Result<Vec<Self>, DatabaseError> {
         Ok(sqlx::query_as!(
             MediaFile,
-            "SELECT * FROM mediafile WHERE library_id = ?",
+            r#"SELECT id, media_id, library_id, target_file, raw_name, raw_year, quality, codec,
+                container, audio, original_resolution, duration, episode, season, corrupt, channels,
+                profile, audio_language, file_size, mtime, inode, missing, missing_since,
+                content_hash, stack_key, stack_part, extra_type as "extra_type: ExtraType", edition,
+                hdr, parse_confidence, probe_problem
+            FROM mediafile WHERE library_id = ?"#,
             library_id
         )
         .fetch_all(&mut *conn)
@@ -79,7 +153,7 @@ impl MediaFile {
     }
 
     /// Method returns all mediafiles associated with a library and filters for those not
-    /// associated with a media
+    /// associated with a media. Extras are skipped as they never get matched on their own.
     ///
     /// # Arguments
     /// * `conn` - mutable reference to a sqlx transaction.
@@ -90,14 +164,20 @@ impl MediaFile {
     ) -> Result<Vec<Self>, DatabaseError> {
         Ok(sqlx::query_as!(
             MediaFile,
-            "SELECT * FROM mediafile WHERE library_id = ? AND media_id IS NULL",
+            r#"SELECT id, media_id, library_id, target_file, raw_name, raw_year, quality, codec,
+                container, audio, original_resolution, duration, episode, season, corrupt, channels,
+                profile, audio_language, file_size, mtime, inode, missing, missing_since,
+                content_hash, stack_key, stack_part, extra_type as "extra_type: ExtraType", edition,
+                hdr, parse_confidence, probe_problem
+            FROM mediafile WHERE library_id = ? AND media_id IS NULL AND extra_type IS NULL"#,
             library_id
         )
         .fetch_all(&mut *conn)
         .await?)
     }
 
-    /// Method returns all mediafiles associated with a Media object.
+    /// Method returns all mediafiles associated with a Media object. Extras of the media are not
+    /// included, files spanning several episodes are included for all of them.
     ///
     /// # Arguments
     /// * `conn` - mutable reference to a sqlx transaction.
@@ -108,15 +188,282 @@ impl MediaFile {
     ) -> Result<Vec<Self>, DatabaseError> {
         Ok(sqlx::query_as!(
             MediaFile,
-            "SELECT mediafile.* FROM mediafile
-                INNER JOIN media ON media.id = mediafile.media_id
-                WHERE media.id = ?",
+            r#"SELECT mediafile.id, mediafile.media_id, mediafile.library_id, mediafile.target_file,
+                mediafile.raw_name, mediafile.raw_year, mediafile.quality, mediafile.codec,
+                mediafile.container, mediafile.audio, mediafile.original_resolution,
+                mediafile.duration, mediafile.episode, mediafile.season, mediafile.corrupt,
+                mediafile.channels, mediafile.profile, mediafile.audio_language,
+                mediafile.file_size, mediafile.mtime, mediafile.inode, mediafile.missing,
+                mediafile.missing_since, mediafile.content_hash, mediafile.stack_key,
+                mediafile.stack_part, mediafile.extra_type as "extra_type: ExtraType",
+                mediafile.edition, mediafile.hdr, mediafile.parse_confidence,
+                mediafile.probe_problem
+            FROM mediafile
+            WHERE (mediafile.media_id = $1 OR mediafile.id IN (
+                SELECT mediafile_id FROM mediafile_episode WHERE episode_id = $1
+            ))
+            AND mediafile.extra_type IS NULL"#,
             media_id
         )
         .fetch_all(&mut *conn)
         .await?)
     }
 
+    /// Method returns all extras attached to a Media object.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `media_id` - id of the media whose extras we want.
+    pub async fn get_extras_of_media(
+        conn: &mut crate::Transaction<'_>,
+        media_id: i64,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            MediaFile,
+            r#"SELECT id, media_id, library_id, target_file, raw_name, raw_year, quality, codec,
+                container, audio, original_resolution, duration, episode, season, corrupt, channels,
+                profile, audio_language, file_size, mtime, inode, missing, missing_since,
+                content_hash, stack_key, stack_part, extra_type as "extra_type: ExtraType", edition,
+                hdr, parse_confidence, probe_problem
+            FROM mediafile WHERE media_id = ? AND extra_type IS NOT NULL
+            ORDER BY extra_type, target_file"#,
+            media_id
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns all extras stored anywhere below `dir` which haven't been attached to a
+    /// media yet.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `library_id` - id of the library whose extras we want.
+    /// * `dir` - path of the directory, must end with a path separator.
+    pub async fn get_unattached_extras_in_dir(
+        conn: &mut crate::Transaction<'_>,
+        library_id: i64,
+        dir: &str,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            MediaFile,
+            r#"SELECT id, media_id, library_id, target_file, raw_name, raw_year, quality, codec,
+                container, audio, original_resolution, duration, episode, season, corrupt, channels,
+                profile, audio_language, file_size, mtime, inode, missing, missing_since,
+                content_hash, stack_key, stack_part, extra_type as "extra_type: ExtraType", edition,
+                hdr, parse_confidence, probe_problem
+            FROM mediafile
+            WHERE library_id = ? AND media_id IS NULL AND extra_type IS NOT NULL
+            AND substr(target_file, 1, length(?)) = ?"#,
+            library_id,
+            dir,
+            dir
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns the ids of the tv shows whose episodes are stored anywhere below `dir`.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `library_id` - id of the library the files belong to.
+    /// * `dir` - path of the directory, must end with a path separator.
+    pub async fn get_shows_in_dir(
+        conn: &mut crate::Transaction<'_>,
+        library_id: i64,
+        dir: &str,
+    ) -> Result<Vec<i64>, DatabaseError> {
+        Ok(sqlx::query!(
+            r#"SELECT DISTINCT _tblseason.tvshowid as "id!: i64" FROM mediafile
+            INNER JOIN episode ON episode.id = mediafile.media_id
+            INNER JOIN _tblseason ON _tblseason.id = episode.seasonid
+            WHERE mediafile.library_id = ? AND mediafile.extra_type IS NULL
+            AND substr(mediafile.target_file, 1, length(?)) = ?"#,
+            library_id,
+            dir,
+            dir
+        )
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(|x| x.id)
+        .collect())
+    }
+
+    /// Method returns all matched mediafiles, which aren't extras, stored directly inside of
+    /// `dir`.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `library_id` - id of the library the files belong to.
+    /// * `dir` - path of the directory, must end with a path separator.
+    pub async fn get_matched_in_dir(
+        conn: &mut crate::Transaction<'_>,
+        library_id: i64,
+        dir: &str,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        // NOTE: We compare prefixes with `substr` as paths can contain the wildcards of `LIKE`.
+        let files = sqlx::query_as!(
+            MediaFile,
+            r#"SELECT id, media_id, library_id, target_file, raw_name, raw_year, quality, codec,
+                container, audio, original_resolution, duration, episode, season, corrupt, channels,
+                profile, audio_language, file_size, mtime, inode, missing, missing_since,
+                content_hash, stack_key, stack_part, extra_type as "extra_type: ExtraType", edition,
+                hdr, parse_confidence, probe_problem
+            FROM mediafile
+            WHERE library_id = ? AND media_id IS NOT NULL AND extra_type IS NULL
+            AND substr(target_file, 1, length(?)) = ?"#,
+            library_id,
+            dir,
+            dir
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(files
+            .into_iter()
+            .filter(|x| !x.target_file[dir.len()..].contains(std::path::MAIN_SEPARATOR))
+            .collect())
+    }
+
+    /// Method returns all mediafiles stored anywhere below `dir`.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `library_id` - id of the library the files belong to.
+    /// * `dir` - path of the directory, must end with a path separator.
+    pub async fn get_in_dir(
+        conn: &mut crate::Transaction<'_>,
+        library_id: i64,
+        dir: &str,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            MediaFile,
+            r#"SELECT id, media_id, library_id, target_file, raw_name, raw_year, quality, codec,
+                container, audio, original_resolution, duration, episode, season, corrupt, channels,
+                profile, audio_language, file_size, mtime, inode, missing, missing_since,
+                content_hash, stack_key, stack_part, extra_type as "extra_type: ExtraType", edition,
+                hdr, parse_confidence, probe_problem
+            FROM mediafile
+            WHERE library_id = ? AND substr(target_file, 1, length(?)) = ?"#,
+            library_id,
+            dir,
+            dir
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns all missing mediafiles which went missing before `before`, oldest first.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `before` - time in seconds since the unix epoch.
+    pub async fn get_missing(
+        conn: &mut crate::Transaction<'_>,
+        before: i64,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            MediaFile,
+            r#"SELECT id, media_id, library_id, target_file, raw_name, raw_year, quality, codec,
+                container, audio, original_resolution, duration, episode, season, corrupt, channels,
+                profile, audio_language, file_size, mtime, inode, missing, missing_since,
+                content_hash, stack_key, stack_part, extra_type as "extra_type: ExtraType", edition,
+                hdr, parse_confidence, probe_problem
+            FROM mediafile
+            WHERE missing AND COALESCE(missing_since, 0) < ?
+            ORDER BY missing_since ASC"#,
+            before
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns the mediafiles of a library whose filename was parsed with a confidence
+    /// below `below`, least confident first. Extras and files scanned before confidences were
+    /// recorded are skipped.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `library_id` - id of the library.
+    /// * `below` - confidence from 0 to 100.
+    pub async fn get_low_confidence(
+        conn: &mut crate::Transaction<'_>,
+        library_id: i64,
+        below: i64,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            MediaFile,
+            r#"SELECT id, media_id, library_id, target_file, raw_name, raw_year, quality, codec,
+                container, audio, original_resolution, duration, episode, season, corrupt, channels,
+                profile, audio_language, file_size, mtime, inode, missing, missing_since,
+                content_hash, stack_key, stack_part, extra_type as "extra_type: ExtraType", edition,
+                hdr, parse_confidence, probe_problem
+            FROM mediafile
+            WHERE library_id = ? AND parse_confidence < ? AND extra_type IS NULL
+            ORDER BY parse_confidence ASC, target_file ASC"#,
+            library_id,
+            below
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns the mediafiles of a library which can't be played, either because they are
+    /// corrupt or because probing them ran into a problem. Missing files are skipped.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `library_id` - id of the library.
+    pub async fn get_problems(
+        conn: &mut crate::Transaction<'_>,
+        library_id: i64,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            MediaFile,
+            r#"SELECT id, media_id, library_id, target_file, raw_name, raw_year, quality, codec,
+                container, audio, original_resolution, duration, episode, season, corrupt, channels,
+                profile, audio_language, file_size, mtime, inode, missing, missing_since,
+                content_hash, stack_key, stack_part, extra_type as "extra_type: ExtraType", edition,
+                hdr, parse_confidence, probe_problem
+            FROM mediafile
+            WHERE library_id = ? AND (corrupt OR probe_problem IS NOT NULL) AND NOT missing
+            ORDER BY target_file ASC"#,
+            library_id
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method moves all mediafiles stored below the directory `from` to the directory `to`, by
+    /// replacing the prefix of their paths. Returns the number of mediafiles that were moved.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `library_id` - id of the library the files belong to.
+    /// * `from` - old path of the directory, must end with a path separator.
+    /// * `to` - new path of the directory, must end with a path separator.
+    pub async fn rename_dir(
+        conn: &mut crate::Transaction<'_>,
+        library_id: i64,
+        from: &str,
+        to: &str,
+    ) -> Result<usize, DatabaseError> {
+        Ok(sqlx::query!(
+            "UPDATE mediafile SET target_file = ? || substr(target_file, length(?) + 1)
+            WHERE library_id = ? AND substr(target_file, 1, length(?)) = ?",
+            to,
+            from,
+            library_id,
+            from,
+            from
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected() as usize)
+    }
+
     pub async fn get_of_show(
         conn: &mut crate::Transaction<'_>,
         id: i64,
@@ -125,11 +472,53 @@ impl MediaFile {
         // mediafile.mediaid, but given how often this is called, this might not be worth it.
         Ok(sqlx::query_as!(
             MediaFile,
-            "SELECT mediafile.* FROM _tblseason
-                INNER JOIN episode ON _tblseason.id = episode.seasonid
-                INNER JOIN mediafile ON mediafile.media_id = episode.id
-                WHERE _tblseason.tvshowid = ?
-                GROUP BY mediafile.id",
+            r#"SELECT mediafile.id, mediafile.media_id, mediafile.library_id, mediafile.target_file,
+                mediafile.raw_name, mediafile.raw_year, mediafile.quality, mediafile.codec,
+                mediafile.container, mediafile.audio, mediafile.original_resolution,
+                mediafile.duration, mediafile.episode, mediafile.season, mediafile.corrupt,
+                mediafile.channels, mediafile.profile, mediafile.audio_language,
+                mediafile.file_size, mediafile.mtime, mediafile.inode, mediafile.missing,
+                mediafile.missing_since, mediafile.content_hash, mediafile.stack_key,
+                mediafile.stack_part, mediafile.extra_type as "extra_type: ExtraType",
+                mediafile.edition, mediafile.hdr, mediafile.parse_confidence,
+                mediafile.probe_problem
+            FROM _tblseason
+            INNER JOIN episode ON _tblseason.id = episode.seasonid
+            INNER JOIN mediafile ON mediafile.media_id = episode.id
+            WHERE _tblseason.tvshowid = ?
+            GROUP BY mediafile.id"#,
+            id
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns all mediafiles linked to the tracks of a album.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the album.
+    pub async fn get_of_album(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            MediaFile,
+            r#"SELECT mediafile.id, mediafile.media_id, mediafile.library_id, mediafile.target_file,
+                mediafile.raw_name, mediafile.raw_year, mediafile.quality, mediafile.codec,
+                mediafile.container, mediafile.audio, mediafile.original_resolution,
+                mediafile.duration, mediafile.episode, mediafile.season, mediafile.corrupt,
+                mediafile.channels, mediafile.profile, mediafile.audio_language,
+                mediafile.file_size, mediafile.mtime, mediafile.inode, mediafile.missing,
+                mediafile.missing_since, mediafile.content_hash, mediafile.stack_key,
+                mediafile.stack_part, mediafile.extra_type as "extra_type: ExtraType",
+                mediafile.edition, mediafile.hdr, mediafile.parse_confidence,
+                mediafile.probe_problem
+            FROM track
+            INNER JOIN mediafile ON mediafile.media_id = track.id
+            WHERE track.album_id = ?
+            GROUP BY mediafile.id
+            ORDER BY track.disc_number, track.track_number"#,
             id
         )
         .fetch_all(&mut *conn)
@@ -145,11 +534,18 @@ impl MediaFile {
         conn: &mut crate::Transaction<'_>,
         id: i64,
     ) -> Result<Self, DatabaseError> {
-        Ok(
-            sqlx::query_as!(MediaFile, "SELECT * FROM mediafile WHERE id = ?", id)
-                .fetch_one(&mut *conn)
-                .await?,
+        Ok(sqlx::query_as!(
+            MediaFile,
+            r#"SELECT id, media_id, library_id, target_file, raw_name, raw_year, quality, codec,
+                container, audio, original_resolution, duration, episode, season, corrupt, channels,
+                profile, audio_language, file_size, mtime, inode, missing, missing_since,
+                content_hash, stack_key, stack_part, extra_type as "extra_type: ExtraType", edition,
+                hdr, parse_confidence, probe_problem
+            FROM mediafile WHERE id = ?"#,
+            id
         )
+        .fetch_one(&mut *conn)
+        .await?)
     }
 
     /// Method returns all metadata for a set of mediafile ids.
@@ -189,23 +585,156 @@ impl MediaFile {
     ) -> Result<Self, DatabaseError> {
         Ok(sqlx::query_as!(
             MediaFile,
-            r#"SELECT * FROM mediafile WHERE target_file = ?"#,
+            r#"SELECT id, media_id, library_id, target_file, raw_name, raw_year, quality, codec,
+                container, audio, original_resolution, duration, episode, season, corrupt, channels,
+                profile, audio_language, file_size, mtime, inode, missing, missing_since,
+                content_hash, stack_key, stack_part, extra_type as "extra_type: ExtraType", edition,
+                hdr, parse_confidence, probe_problem
+            FROM mediafile WHERE target_file = ?"#,
             file
         )
         .fetch_one(&mut *conn)
         .await?)
     }
 
-    /// Function will return the largest duration for a media.
+    /// Method returns all parts of a stack ordered by their part number.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `library_id` - id of the library the stack belongs to.
+    /// * `stack_key` - key shared by all parts of the stack.
+    pub async fn get_stack(
+        conn: &mut crate::Transaction<'_>,
+        library_id: i64,
+        stack_key: &str,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            MediaFile,
+            r#"SELECT id, media_id, library_id, target_file, raw_name, raw_year, quality, codec,
+                container, audio, original_resolution, duration, episode, season, corrupt, channels,
+                profile, audio_language, file_size, mtime, inode, missing, missing_since,
+                content_hash, stack_key, stack_part, extra_type as "extra_type: ExtraType", edition,
+                hdr, parse_confidence, probe_problem
+            FROM mediafile WHERE library_id = ? AND stack_key = ?
+            ORDER BY stack_part ASC"#,
+            library_id,
+            stack_key
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns the fingerprints of all mediafiles associated with a library.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `library_id` - id of the library whose mediafiles we want.
+    pub async fn get_fingerprints_by_lib(
+        conn: &mut crate::Transaction<'_>,
+        library_id: i64,
+    ) -> Result<Vec<MediaFileFingerprint>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            MediaFileFingerprint,
+            r#"SELECT id, target_file, file_size, mtime, inode, missing as "missing: bool",
+                probe_problem
+            FROM mediafile WHERE library_id = ?"#,
+            library_id
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns all mediafiles of a library whose contents hash to `content_hash`.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `library_id` - id of the library to search in.
+    /// * `content_hash` - partial content hash of the file we are looking for.
+    pub async fn get_by_content_hash(
+        conn: &mut crate::Transaction<'_>,
+        library_id: i64,
+        content_hash: &str,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            MediaFile,
+            r#"SELECT id, media_id, library_id, target_file, raw_name, raw_year, quality, codec,
+                container, audio, original_resolution, duration, episode, season, corrupt, channels,
+                profile, audio_language, file_size, mtime, inode, missing, missing_since,
+                content_hash, stack_key, stack_part, extra_type as "extra_type: ExtraType", edition,
+                hdr, parse_confidence, probe_problem
+            FROM mediafile WHERE library_id = ? AND content_hash = ?"#,
+            library_id,
+            content_hash
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns the ids of the episodes a file spanning several episodes covers on top of
+    /// the episode its `media_id` points to.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the mediafile.
+    pub async fn get_linked_episodes(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<Vec<i64>, DatabaseError> {
+        Ok(sqlx::query!(
+            "SELECT episode_id FROM mediafile_episode WHERE mediafile_id = ? ORDER BY episode_id",
+            id
+        )
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(|x| x.episode_id)
+        .collect())
+    }
+
+    /// Method replaces the episodes a file spanning several episodes covers on top of the episode
+    /// its `media_id` points to.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the mediafile.
+    /// * `episodes` - ids of the episodes covered by the mediafile.
+    pub async fn set_linked_episodes(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+        episodes: &[i64],
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!("DELETE FROM mediafile_episode WHERE mediafile_id = ?", id)
+            .execute(&mut *conn)
+            .await?;
+
+        for episode_id in episodes {
+            sqlx::query!(
+                "INSERT OR IGNORE INTO mediafile_episode (mediafile_id, episode_id) VALUES (?, ?)",
+                id,
+                episode_id
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Function will return the largest duration for a media. The parts of a stack count as one
+    /// file whose duration is the sum of the duration of all parts.
     pub async fn get_largest_duration(
         conn: &mut crate::Transaction<'_>,
         media_id: i64,
     ) -> Result<i64, DatabaseError> {
         Ok(sqlx::query!(
-            r#"SELECT MAX(COALESCE(mediafile.duration, 0)) as "duration!: i64" FROM mediafile
-            WHERE mediafile.media_id = ?
-            GROUP BY mediafile.media_id
-            LIMIT 1"#,
+            r#"SELECT COALESCE(MAX(duration), 0) as "duration!: i64" FROM (
+                SELECT SUM(COALESCE(mediafile.duration, 0)) as duration FROM mediafile
+                WHERE (mediafile.media_id = $1 OR mediafile.id IN (
+                    SELECT mediafile_id FROM mediafile_episode WHERE episode_id = $1
+                ))
+                AND mediafile.extra_type IS NULL
+                GROUP BY COALESCE(mediafile.stack_key, mediafile.id)
+            )"#,
             media_id
         )
         .fetch_one(&mut *conn)
@@ -243,6 +772,19 @@ impl MediaFile {
     }
 }
 
+/// Subset of [`MediaFile`] used by the scanner to decide whether a file on disk has changed since
+/// it was last probed.
+#[derive(Serialize, PartialEq, Eq, Debug, Clone)]
+pub struct MediaFileFingerprint {
+    pub id: i64,
+    pub target_file: String,
+    pub file_size: Option<i64>,
+    pub mtime: Option<i64>,
+    pub inode: Option<i64>,
+    pub missing: bool,
+    pub probe_problem: Option<String>,
+}
+
 /// Same as [`MediaFile`] except its missing the id field.
 #[derive(Clone, Serialize, Debug, Default)]
 pub struct InsertableMediaFile {
@@ -271,6 +813,19 @@ pub struct InsertableMediaFile {
     pub season: Option<i64>,
     /*** ***/
     pub corrupt: Option<bool>,
+
+    pub file_size: Option<i64>,
+    pub mtime: Option<i64>,
+    pub inode: Option<i64>,
+    pub content_hash: Option<String>,
+
+    pub stack_key: Option<String>,
+    pub stack_part: Option<i64>,
+    pub extra_type: Option<ExtraType>,
+    pub edition: Option<String>,
+    pub hdr: Option<String>,
+    pub parse_confidence: Option<i64>,
+    pub probe_problem: Option<String>,
 }
 
 impl InsertableMediaFile {
@@ -293,8 +848,10 @@ impl InsertableMediaFile {
         let id = sqlx::query!(
             r#"
             INSERT INTO mediafile (media_id, library_id, target_file, raw_name, raw_year, quality,
-            codec, container, audio, original_resolution, duration, episode, season, corrupt, channels, profile, audio_language)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)
+            codec, container, audio, original_resolution, duration, episode, season, corrupt, channels, profile, audio_language,
+            file_size, mtime, inode, content_hash, stack_key, stack_part, extra_type,
+            edition, hdr, parse_confidence, probe_problem)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27, $28)
         "#,
             self.media_id,
             self.library_id,
@@ -312,7 +869,18 @@ impl InsertableMediaFile {
             self.corrupt,
             self.channels,
             self.profile,
-            self.audio_language
+            self.audio_language,
+            self.file_size,
+            self.mtime,
+            self.inode,
+            self.content_hash,
+            self.stack_key,
+            self.stack_part,
+            self.extra_type,
+            self.edition,
+            self.hdr,
+            self.parse_confidence,
+            self.probe_problem
         )
         .execute(&mut *conn)
         .await?
@@ -347,6 +915,21 @@ pub struct UpdateMediaFile {
     pub season: Option<i64>,
     /*** ***/
     pub corrupt: Option<bool>,
+
+    pub file_size: Option<i64>,
+    pub mtime: Option<i64>,
+    pub inode: Option<i64>,
+    pub missing: Option<bool>,
+    pub content_hash: Option<String>,
+    pub stack_key: Option<String>,
+    pub stack_part: Option<i64>,
+    pub extra_type: Option<ExtraType>,
+    pub edition: Option<String>,
+    pub hdr: Option<String>,
+    pub parse_confidence: Option<i64>,
+    /// `Some(None)` clears the problem of a file which plays fine after being probed again.
+    #[serde(skip)]
+    pub probe_problem: Option<Option<String>>,
 }
 
 impl UpdateMediaFile {
@@ -377,7 +960,19 @@ impl UpdateMediaFile {
             "UPDATE mediafile SET corrupt = ? WHERE id = ?" => (self.corrupt, id),
             "UPDATE mediafile SET channels = ? WHERE id = ?" => (self.channels, id),
             "UPDATE mediafile SET profile = ? WHERE id = ?" => (self.profile, id),
-            "UPDATE mediafile SET audio_language = ? WHERE id = ?" => (self.audio_language, id)
+            "UPDATE mediafile SET audio_language = ? WHERE id = ?" => (self.audio_language, id),
+            "UPDATE mediafile SET file_size = ? WHERE id = ?" => (self.file_size, id),
+            "UPDATE mediafile SET mtime = ? WHERE id = ?" => (self.mtime, id),
+            "UPDATE mediafile SET inode = ? WHERE id = ?" => (self.inode, id),
+            "UPDATE mediafile SET missing = ? WHERE id = ?" => (self.missing, id),
+            "UPDATE mediafile SET content_hash = ? WHERE id = ?" => (self.content_hash, id),
+            "UPDATE mediafile SET stack_key = ? WHERE id = ?" => (self.stack_key, id),
+            "UPDATE mediafile SET stack_part = ? WHERE id = ?" => (self.stack_part, id),
+            "UPDATE mediafile SET extra_type = ? WHERE id = ?" => (self.extra_type, id),
+            "UPDATE mediafile SET edition = ? WHERE id = ?" => (self.edition, id),
+            "UPDATE mediafile SET hdr = ? WHERE id = ?" => (self.hdr, id),
+            "UPDATE mediafile SET parse_confidence = ? WHERE id = ?" => (self.parse_confidence, id),
+            "UPDATE mediafile SET probe_problem = ? WHERE id = ?" => (self.probe_problem, id)
         );
 
         Ok(1)
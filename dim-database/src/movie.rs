@@ -4,13 +4,16 @@ use crate::DatabaseError;
 pub struct Movie;
 
 impl Movie {
-    /// Method will return the number of mediafiles linked against this media object.
+    /// Method will return the number of mediafiles linked against this media object. Extras dont
+    /// count as children, files spanning several episodes count towards all of them.
     pub async fn count_children(
         conn: &mut crate::Transaction<'_>,
         id: i64,
     ) -> Result<i64, DatabaseError> {
         Ok(sqlx::query!(
-            "SELECT COUNT(*) AS count FROM mediafile WHERE media_id = ?",
+            r#"SELECT
+                (SELECT COUNT(*) FROM mediafile WHERE media_id = $1 AND extra_type IS NULL)
+                + (SELECT COUNT(*) FROM mediafile_episode WHERE episode_id = $1) AS "count!: i64""#,
             id
         )
         .fetch_one(&mut *conn)
@@ -0,0 +1,507 @@
+use crate::media::InsertableMedia;
+use crate::media::Media;
+use crate::DatabaseError;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Struct represents a artist entry in the database. Artists are scoped to a library and are used
+/// to group albums together.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct Artist {
+    pub id: i64,
+    /// Library this artist belongs to.
+    pub library_id: i64,
+    /// Name of the artist, usually taken from the `album_artist` or `artist` tag.
+    pub name: String,
+}
+
+impl Artist {
+    /// Method returns all artists that belong to a library ordered by their name.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `library_id` - id of the library we'd like to discriminate against.
+    pub async fn get_all(
+        conn: &mut crate::Transaction<'_>,
+        library_id: i64,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            Self,
+            r#"SELECT id as "id!", library_id, name FROM artist
+            WHERE library_id = ?
+            ORDER BY name ASC"#,
+            library_id
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns a artist based on its id.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the artist.
+    pub async fn get_by_id(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<Self, DatabaseError> {
+        Ok(sqlx::query_as!(
+            Self,
+            r#"SELECT id as "id!", library_id, name FROM artist WHERE id = ?"#,
+            id
+        )
+        .fetch_one(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns the artist of a album if the album has one.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `album_id` - id of the album.
+    pub async fn get_of_album(
+        conn: &mut crate::Transaction<'_>,
+        album_id: i64,
+    ) -> Result<Option<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            Self,
+            r#"SELECT artist.id as "id!", artist.library_id, artist.name FROM artist
+            INNER JOIN album ON album.artist_id = artist.id
+            WHERE album.id = ?"#,
+            album_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?)
+    }
+
+    pub async fn count_children(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<i64, DatabaseError> {
+        Ok(sqlx::query!(
+            "SELECT COUNT(album.id) AS count FROM album WHERE album.artist_id = ?",
+            id
+        )
+        .fetch_one(&mut *conn)
+        .await?
+        .count as _)
+    }
+
+    /// Method deletes a artist. Albums of this artist will be unlinked but not deleted.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the artist to delete.
+    pub async fn delete(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<usize, DatabaseError> {
+        Ok(sqlx::query!("DELETE FROM artist WHERE id = ?", id)
+            .execute(&mut *conn)
+            .await?
+            .rows_affected() as usize)
+    }
+}
+
+/// Struct represents a insertable artist.
+#[derive(Clone, Serialize, Deserialize, Default, Debug)]
+pub struct InsertableArtist {
+    pub library_id: i64,
+    pub name: String,
+}
+
+impl InsertableArtist {
+    /// Method inserts a new artist if one with the same name doesnt already exist in the library.
+    /// Returns the id of the new or existing artist.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    pub async fn insert(&self, conn: &mut crate::Transaction<'_>) -> Result<i64, DatabaseError> {
+        if let Some(r) = sqlx::query!(
+            r#"SELECT id as "id!" FROM artist WHERE library_id = ? AND name = ?"#,
+            self.library_id,
+            self.name
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        {
+            return Ok(r.id);
+        }
+
+        Ok(sqlx::query!(
+            "INSERT INTO artist (library_id, name) VALUES ($1, $2)",
+            self.library_id,
+            self.name
+        )
+        .execute(&mut *conn)
+        .await?
+        .last_insert_rowid())
+    }
+}
+
+/// Album struct encapsulates a media entry representing a album.
+#[derive(Clone, Serialize, Debug)]
+pub struct Album {
+    pub id: i64,
+    /// Artist foreign key, albums without a artist tag dont have one.
+    pub artist_id: Option<i64>,
+
+    /// Reference to a media object which represents this album. Behind the scenes album inherits
+    /// all fields from media.
+    #[serde(flatten)]
+    pub media: Media,
+}
+
+/// This struct is purely used for querying albums which later gets converted into a Album struct.
+#[derive(PartialEq, Debug, Copy, Clone, sqlx::FromRow)]
+pub struct AlbumWrapper {
+    pub id: i64,
+    pub artist_id: Option<i64>,
+}
+
+impl Album {
+    /// Method returns a album based on its id.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the album.
+    pub async fn get_by_id(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<Self, DatabaseError> {
+        let wrapper = sqlx::query_as!(
+            AlbumWrapper,
+            r#"SELECT id as "id!", artist_id FROM album WHERE id = ?"#,
+            id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        let media = Media::get(conn, wrapper.id).await?;
+
+        Ok(wrapper.into_album(media))
+    }
+
+    /// Method returns all albums of a artist ordered by their release year.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `artist_id` - id of the artist.
+    pub async fn get_of_artist(
+        conn: &mut crate::Transaction<'_>,
+        artist_id: i64,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        let wrappers = sqlx::query_as!(
+            AlbumWrapper,
+            r#"SELECT album.id as "id!", album.artist_id FROM album
+            INNER JOIN _tblmedia ON _tblmedia.id = album.id
+            WHERE album.artist_id = ?
+            ORDER BY _tblmedia.year, _tblmedia.name"#,
+            artist_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut albums = vec![];
+
+        for wrapper in wrappers {
+            if let Ok(media) = Media::get(&mut *conn, wrapper.id).await {
+                albums.push(wrapper.into_album(media));
+            }
+        }
+
+        Ok(albums)
+    }
+
+    /// Method looks up a album by its name and artist within a library.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `library_id` - id of the library.
+    /// * `artist_id` - optional id of the artist of the album.
+    /// * `name` - name of the album.
+    pub async fn get_id_by_name(
+        conn: &mut crate::Transaction<'_>,
+        library_id: i64,
+        artist_id: Option<i64>,
+        name: &str,
+    ) -> Result<Option<i64>, DatabaseError> {
+        Ok(sqlx::query!(
+            r#"SELECT album.id as "id!" FROM album
+            INNER JOIN _tblmedia ON _tblmedia.id = album.id
+            WHERE _tblmedia.library_id = ?
+            AND _tblmedia.name = ?
+            AND album.artist_id IS ?"#,
+            library_id,
+            name,
+            artist_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .map(|x| x.id))
+    }
+
+    /// Method looks up a album by its name, regardless of its artist, among the albums with
+    /// tracks stored below `dir`. Compilations without a album artist have a different artist on
+    /// every track, so this is how their tracks find each other.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `library_id` - id of the library.
+    /// * `name` - name of the album.
+    /// * `dir` - path of the directory, must end with a path separator.
+    pub async fn get_id_in_dir(
+        conn: &mut crate::Transaction<'_>,
+        library_id: i64,
+        name: &str,
+        dir: &str,
+    ) -> Result<Option<i64>, DatabaseError> {
+        Ok(sqlx::query!(
+            r#"SELECT album.id as "id!" FROM album
+            INNER JOIN _tblmedia ON _tblmedia.id = album.id
+            INNER JOIN track ON track.album_id = album.id
+            INNER JOIN mediafile ON mediafile.media_id = track.id
+            WHERE _tblmedia.library_id = ?
+            AND _tblmedia.name = ?
+            AND substr(mediafile.target_file, 1, length(?)) = ?
+            LIMIT 1"#,
+            library_id,
+            name,
+            dir,
+            dir
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .map(|x| x.id))
+    }
+
+    pub async fn count_children(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<i64, DatabaseError> {
+        Ok(sqlx::query!(
+            "SELECT COUNT(track.id) AS count FROM track WHERE track.album_id = ?",
+            id
+        )
+        .fetch_one(&mut *conn)
+        .await?
+        .count as _)
+    }
+}
+
+impl AlbumWrapper {
+    pub fn into_album(self, media: Media) -> Album {
+        Album {
+            id: self.id,
+            artist_id: self.artist_id,
+            media,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct InsertableAlbum {
+    pub media: InsertableMedia,
+    pub artist_id: Option<i64>,
+}
+
+impl InsertableAlbum {
+    /// Method inserts a new album into the database. If a album with the same name by the same
+    /// artist already exists, its id is returned instead.
+    pub async fn insert(&self, conn: &mut crate::Transaction<'_>) -> Result<i64, DatabaseError> {
+        if let Some(id) = Album::get_id_by_name(
+            &mut *conn,
+            self.media.library_id,
+            self.artist_id,
+            &self.media.name,
+        )
+        .await?
+        {
+            return Ok(id);
+        }
+
+        self.insert_blind(conn).await
+    }
+
+    /// Method inserts a new album into the database without checking whether a album with the same
+    /// name already exists. Compilations without a album artist are told apart by the directory
+    /// their tracks are stored in, which is up to the caller to check.
+    pub async fn insert_blind(
+        &self,
+        conn: &mut crate::Transaction<'_>,
+    ) -> Result<i64, DatabaseError> {
+        // NOTE: use insert blind here as album names are only unique per artist.
+        let media_id = self.media.insert_blind(&mut *conn).await?;
+        sqlx::query!(
+            "INSERT INTO album (id, artist_id) VALUES ($1, $2)",
+            media_id,
+            self.artist_id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(media_id)
+    }
+}
+
+/// Track struct encapsulates a media entry representing a single track of a album.
+#[derive(Clone, Serialize, Debug)]
+pub struct Track {
+    pub id: i64,
+    /// Album foreign key.
+    pub album_id: i64,
+    /// Track number within its disc.
+    pub track_number: Option<i64>,
+    /// Disc number within its album.
+    pub disc_number: Option<i64>,
+
+    /// Reference to a media object which represents this track.
+    #[serde(flatten)]
+    pub media: Media,
+}
+
+/// This struct is purely used for querying tracks which later gets converted into a Track struct.
+#[derive(PartialEq, Debug, Copy, Clone, sqlx::FromRow)]
+pub struct TrackWrapper {
+    pub id: i64,
+    pub album_id: i64,
+    pub track_number: Option<i64>,
+    pub disc_number: Option<i64>,
+}
+
+impl Track {
+    /// Method returns a track based on its id.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the track.
+    pub async fn get_by_id(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<Self, DatabaseError> {
+        let wrapper = sqlx::query_as!(
+            TrackWrapper,
+            r#"SELECT id as "id!", album_id, track_number, disc_number FROM track WHERE id = ?"#,
+            id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        let media = Media::get(conn, wrapper.id).await?;
+
+        Ok(wrapper.into_track(media))
+    }
+
+    /// Method returns all of the tracks belonging to a album ordered by disc and track number.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `album_id` - id of the album.
+    pub async fn get_all_of_album(
+        conn: &mut crate::Transaction<'_>,
+        album_id: i64,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        let wrappers = sqlx::query_as!(
+            TrackWrapper,
+            r#"SELECT id as "id!", album_id, track_number, disc_number FROM track
+            WHERE album_id = ?
+            ORDER BY disc_number, track_number"#,
+            album_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut tracks = vec![];
+
+        for wrapper in wrappers {
+            if let Ok(media) = Media::get(&mut *conn, wrapper.id).await {
+                tracks.push(wrapper.into_track(media));
+            }
+        }
+
+        Ok(tracks)
+    }
+
+    /// Method returns the id of the album a track belongs to.
+    pub async fn get_albumid(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<i64, DatabaseError> {
+        Ok(sqlx::query!("SELECT album_id FROM track WHERE id = ?", id)
+            .fetch_one(&mut *conn)
+            .await?
+            .album_id)
+    }
+
+    pub async fn count_children(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<i64, DatabaseError> {
+        Ok(sqlx::query!(
+            "SELECT COUNT(mediafile.id) AS count FROM mediafile WHERE mediafile.media_id = ?",
+            id
+        )
+        .fetch_one(&mut *conn)
+        .await?
+        .count as _)
+    }
+}
+
+impl TrackWrapper {
+    pub fn into_track(self, media: Media) -> Track {
+        Track {
+            id: self.id,
+            album_id: self.album_id,
+            track_number: self.track_number,
+            disc_number: self.disc_number,
+            media,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct InsertableTrack {
+    pub media: InsertableMedia,
+    pub album_id: i64,
+    pub track_number: Option<i64>,
+    pub disc_number: Option<i64>,
+}
+
+impl InsertableTrack {
+    /// Method inserts a new track into the database. Tracks with the same name, disc and track
+    /// number on the same album are deduplicated.
+    pub async fn insert(&self, conn: &mut crate::Transaction<'_>) -> Result<i64, DatabaseError> {
+        if let Some(r) = sqlx::query!(
+            r#"SELECT track.id as "id!" FROM track
+            INNER JOIN _tblmedia ON _tblmedia.id = track.id
+            WHERE track.album_id = ?
+            AND track.track_number IS ?
+            AND track.disc_number IS ?
+            AND _tblmedia.name = ?"#,
+            self.album_id,
+            self.track_number,
+            self.disc_number,
+            self.media.name
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        {
+            return Ok(r.id);
+        }
+
+        let media_id = self.media.insert_blind(&mut *conn).await?;
+        sqlx::query!(
+            "INSERT INTO track (id, album_id, track_number, disc_number)
+            VALUES ($1, $2, $3, $4)",
+            media_id,
+            self.album_id,
+            self.track_number,
+            self.disc_number
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(media_id)
+    }
+}
@@ -106,7 +106,9 @@ impl Progress {
         // FIXME: Use query_as macro instead of query_as function when https://github.com/launchbadge/sqlx/issues/1249 is fixed.
         let record = sqlx::query_as::<_, Record>(
             "SELECT progress.delta, MAX(mediafile.duration) duration FROM _tblmedia
-            INNER JOIN mediafile ON mediafile.media_id = _tblmedia.id
+            INNER JOIN mediafile ON mediafile.media_id = _tblmedia.id OR mediafile.id IN (
+                SELECT mediafile_id FROM mediafile_episode WHERE episode_id = _tblmedia.id
+            )
             LEFT OUTER JOIN progress ON progress.media_id = _tblmedia.id AND progress.user_id = ?
             WHERE _tblmedia.id = ?
             GROUP BY _tblmedia.id
@@ -95,7 +95,8 @@ impl Season {
         )
     }
 
-    /// Method will return the oldest season for a tv show that is available.
+    /// Method will return the oldest season for a tv show that is available. Specials in season 0
+    /// only come first if the show has no other seasons.
     ///
     /// # Arguments
     /// * `conn` - mutable reference to a sqlx transaction.
@@ -109,7 +110,7 @@ impl Season {
             r#"SELECT id as "id!", season_number, tvshowid, added, poster as "poster?"
             FROM season
             WHERE tvshowid = ?
-            ORDER BY season_number ASC"#,
+            ORDER BY season_number = 0, season_number ASC"#,
             tv_id,
         )
         .fetch_one(&mut *conn)
@@ -0,0 +1,153 @@
+use crate::DatabaseError;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Struct represents a external subtitle file found next to a mediafile.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub struct SubtitleSidecar {
+    pub id: i64,
+    /// Mediafile this subtitle belongs to.
+    pub mediafile_id: i64,
+    /// Path of the subtitle file on the filesystem.
+    pub target_file: String,
+    /// Format of the subtitle file, ie `srt`, `ass`, `ssa` or `vtt`.
+    pub codec: String,
+    /// ISO 639 language code parsed from the filename.
+    pub language: Option<String>,
+    /// Whether the subtitles only cover foreign dialogue.
+    pub forced: bool,
+    /// Whether the subtitles are meant for the deaf and hard of hearing.
+    pub sdh: bool,
+}
+
+impl SubtitleSidecar {
+    /// Method returns all subtitle sidecars of a mediafile.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `mediafile_id` - id of the mediafile.
+    pub async fn get_of_mediafile(
+        conn: &mut crate::Transaction<'_>,
+        mediafile_id: i64,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            Self,
+            r#"SELECT id as "id!", mediafile_id, target_file, codec, language,
+                forced as "forced: bool", sdh as "sdh: bool"
+            FROM subtitle_sidecar
+            WHERE mediafile_id = ?
+            ORDER BY target_file ASC"#,
+            mediafile_id
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns all subtitle sidecars of the mediafiles of a library.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `library_id` - id of the library.
+    pub async fn get_of_library(
+        conn: &mut crate::Transaction<'_>,
+        library_id: i64,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            Self,
+            r#"SELECT subtitle_sidecar.id as "id!", subtitle_sidecar.mediafile_id,
+                subtitle_sidecar.target_file, subtitle_sidecar.codec, subtitle_sidecar.language,
+                subtitle_sidecar.forced as "forced: bool", subtitle_sidecar.sdh as "sdh: bool"
+            FROM subtitle_sidecar
+            INNER JOIN mediafile ON mediafile.id = subtitle_sidecar.mediafile_id
+            WHERE mediafile.library_id = ?"#,
+            library_id
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method removes all subtitle sidecars of a mediafile.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `mediafile_id` - id of the mediafile.
+    pub async fn delete_of_mediafile(
+        conn: &mut crate::Transaction<'_>,
+        mediafile_id: i64,
+    ) -> Result<usize, DatabaseError> {
+        Ok(sqlx::query!(
+            "DELETE FROM subtitle_sidecar WHERE mediafile_id = ?",
+            mediafile_id
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected() as usize)
+    }
+
+    /// Method moves all subtitle sidecars of a library stored below the directory `from` to the
+    /// directory `to`. Returns the number of sidecars that were moved.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `library_id` - id of the library.
+    /// * `from` - old path of the directory, must end with a path separator.
+    /// * `to` - new path of the directory, must end with a path separator.
+    pub async fn rename_dir(
+        conn: &mut crate::Transaction<'_>,
+        library_id: i64,
+        from: &str,
+        to: &str,
+    ) -> Result<usize, DatabaseError> {
+        Ok(sqlx::query!(
+            "UPDATE subtitle_sidecar SET target_file = ? || substr(target_file, length(?) + 1)
+            WHERE substr(target_file, 1, length(?)) = ?
+            AND mediafile_id IN (SELECT id FROM mediafile WHERE library_id = ?)",
+            to,
+            from,
+            from,
+            from,
+            library_id
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected() as usize)
+    }
+}
+
+/// Struct represents a subtitle sidecar that can be inserted into the database.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Debug, Default)]
+pub struct InsertableSubtitleSidecar {
+    pub mediafile_id: i64,
+    pub target_file: String,
+    pub codec: String,
+    pub language: Option<String>,
+    pub forced: bool,
+    pub sdh: bool,
+}
+
+impl InsertableSubtitleSidecar {
+    /// Method inserts a new subtitle sidecar into the database. If the sidecar already exists its
+    /// flags get updated.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    pub async fn insert(&self, conn: &mut crate::Transaction<'_>) -> Result<i64, DatabaseError> {
+        Ok(sqlx::query!(
+            r#"INSERT INTO subtitle_sidecar (mediafile_id, target_file, codec, language, forced, sdh)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (mediafile_id, target_file) DO UPDATE
+            SET codec = $3, language = $4, forced = $5, sdh = $6
+            RETURNING id as "id!: i64""#,
+            self.mediafile_id,
+            self.target_file,
+            self.codec,
+            self.language,
+            self.forced,
+            self.sdh
+        )
+        .fetch_one(&mut *conn)
+        .await?
+        .id)
+    }
+}
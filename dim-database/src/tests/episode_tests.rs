@@ -1,11 +1,14 @@
 use crate::episode;
 use crate::get_conn_memory;
 use crate::media;
+use crate::mediafile;
+use crate::movie;
 use crate::season;
 use crate::write_tx;
 
 use super::library_tests::create_test_library;
 use super::media_tests::insert_media;
+use super::mediafile_tests::insert_mediafile_with_mediaid;
 
 use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
@@ -268,3 +271,113 @@ async fn test_get_next_episode() {
     let second_ep = first_ep.get_next_episode(&mut tx).await.unwrap();
     assert_eq!(second_ep.episode, 2);
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_multi_episode_file() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _lib = create_test_library(&mut tx).await;
+    let tv = insert_media(&mut tx).await;
+
+    let season = season::InsertableSeason {
+        season_number: 2,
+        ..Default::default()
+    }
+    .insert(&mut tx, tv)
+    .await
+    .unwrap();
+
+    let mut episodes = Vec::new();
+
+    for number in 5..=6 {
+        let episode = episode::InsertableEpisode {
+            media: media::InsertableMedia {
+                library_id: _lib,
+                name: format!("Episode {number}"),
+                ..Default::default()
+            },
+            seasonid: season,
+            episode: number,
+        }
+        .insert(&mut tx)
+        .await
+        .unwrap();
+
+        episodes.push(episode);
+    }
+
+    let file = insert_mediafile_with_mediaid(&mut tx, episodes[0]).await;
+    mediafile::MediaFile::set_linked_episodes(&mut tx, file, &episodes[1..])
+        .await
+        .unwrap();
+
+    let linked = mediafile::MediaFile::get_linked_episodes(&mut tx, file)
+        .await
+        .unwrap();
+    assert_eq!(linked, vec![episodes[1]]);
+
+    let files = mediafile::MediaFile::get_of_media(&mut tx, episodes[1])
+        .await
+        .unwrap();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].id, file);
+
+    let count = movie::Movie::count_children(&mut tx, episodes[1])
+        .await
+        .unwrap();
+    assert_eq!(count, 1);
+
+    for id in episodes.iter() {
+        let mut sharing = episode::Episode::get_sharing_files(&mut tx, *id, file)
+            .await
+            .unwrap();
+        sharing.sort();
+        assert_eq!(sharing, episodes);
+    }
+
+    // progress in a file of its own doesnt carry over to the other episodes.
+    let own_file = mediafile::InsertableMediaFile {
+        library_id: _lib,
+        target_file: "/dev/null/own".into(),
+        raw_name: "Test".into(),
+        media_id: Some(episodes[1]),
+        ..Default::default()
+    }
+    .insert(&mut tx)
+    .await
+    .unwrap();
+
+    let sharing = episode::Episode::get_sharing_files(&mut tx, episodes[1], own_file)
+        .await
+        .unwrap();
+    assert_eq!(sharing, vec![episodes[1]]);
+
+    mediafile::MediaFile::delete(&mut tx, own_file)
+        .await
+        .unwrap();
+
+    // episodes only linked to a missing file are missing too.
+    mediafile::UpdateMediaFile {
+        missing: Some(true),
+        ..Default::default()
+    }
+    .update(&mut tx, file)
+    .await
+    .unwrap();
+
+    let missing: Vec<i64> = sqlx::query_scalar("SELECT id FROM missing_media")
+        .fetch_all(&mut tx)
+        .await
+        .unwrap();
+    assert!(episodes.iter().all(|x| missing.contains(x)));
+    assert!(missing.contains(&tv));
+
+    mediafile::MediaFile::set_linked_episodes(&mut tx, file, &[])
+        .await
+        .unwrap();
+
+    let count = movie::Movie::count_children(&mut tx, episodes[1])
+        .await
+        .unwrap();
+    assert_eq!(count, 0);
+}
@@ -11,6 +11,12 @@ pub async fn create_test_library(conn: &mut crate::Transaction<'_>) -> i64 {
         name: format!("test{}", _LIB.load(Ordering::Relaxed)),
         locations: vec![format!("/dev/null{}", _LIB.load(Ordering::Relaxed))],
         media_type: library::MediaType::Movie,
+        metadata_provider: Default::default(),
+        prefer_local_artwork: true,
+        scan_rules: Default::default(),
+        filename_rules: Default::default(),
+        rescan_schedule: None,
+        watcher_mode: Default::default(),
     };
 
     _LIB.fetch_add(1, Ordering::SeqCst);
@@ -34,6 +40,102 @@ async fn test_get_one() {
     let result = library::Library::get_one(&mut tx, id).await.unwrap();
 
     assert_eq!(result.media_type, library::MediaType::Movie);
+    assert_eq!(result.metadata_provider, library::MetadataProvider::Tmdb);
+    assert_eq!(result.scan_rules, library::ScanRules::default());
+    assert!(result.scan_rules.follow_symlinks);
+    assert_eq!(result.watcher_mode, library::WatcherMode::Auto);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_update() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let id = create_test_library(&mut tx).await;
+    let before = library::Library::get_one(&mut tx, id).await.unwrap();
+
+    let scan_rules = library::ScanRules {
+        extra_exts: vec!["iso".into()],
+        exclude_globs: vec!["**/Sample/".into()],
+        min_file_size: Some(100 * 1024 * 1024),
+        follow_symlinks: false,
+        ..Default::default()
+    };
+
+    let filename_rules = library::FilenameRules(vec![r"^(?P<title>.+)\.Ep(?P<episode>\d+)".into()]);
+
+    library::UpdateLibrary {
+        prefer_local_artwork: Some(false),
+        scan_rules: Some(scan_rules.clone()),
+        filename_rules: Some(filename_rules.clone()),
+        watcher_mode: Some(library::WatcherMode::Poll),
+        ..Default::default()
+    }
+    .update(&mut tx, id)
+    .await
+    .unwrap();
+
+    let result = library::Library::get_one(&mut tx, id).await.unwrap();
+    assert!(!result.prefer_local_artwork);
+    assert_eq!(result.scan_rules, scan_rules);
+    assert_eq!(result.filename_rules, filename_rules);
+    assert_eq!(result.watcher_mode, library::WatcherMode::Poll);
+    // fields that weren't supplied stay untouched.
+    assert_eq!(result.name, before.name);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rescan_schedule() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let id = create_test_library(&mut tx).await;
+
+    let result = library::Library::get_one(&mut tx, id).await.unwrap();
+    assert_eq!(result.rescan_schedule, None);
+    assert_eq!(result.last_scanned_at, None);
+
+    let update: library::UpdateLibrary =
+        serde_json::from_str(r#"{"rescan_schedule": {"cron": {"expr": "0 3 * * *"}}}"#).unwrap();
+    update.update(&mut tx, id).await.unwrap();
+    library::Library::set_last_scanned(&mut tx, id, 1000)
+        .await
+        .unwrap();
+
+    let result = library::Library::get_one(&mut tx, id).await.unwrap();
+    assert_eq!(
+        result.rescan_schedule,
+        Some(library::RescanSchedule::Cron {
+            expr: "0 3 * * *".into()
+        })
+    );
+    assert_eq!(result.last_scanned_at, Some(1000));
+
+    // leaving the field out keeps the schedule.
+    let update: library::UpdateLibrary = serde_json::from_str(r#"{"name": "renamed"}"#).unwrap();
+    update.update(&mut tx, id).await.unwrap();
+    let result = library::Library::get_one(&mut tx, id).await.unwrap();
+    assert!(result.rescan_schedule.is_some());
+
+    // `null` disables it.
+    let update: library::UpdateLibrary =
+        serde_json::from_str(r#"{"rescan_schedule": null}"#).unwrap();
+    update.update(&mut tx, id).await.unwrap();
+    let result = library::Library::get_one(&mut tx, id).await.unwrap();
+    assert_eq!(result.rescan_schedule, None);
+
+    // a invalid schedule is ignored instead of hiding the library.
+    sqlx::query("UPDATE library SET rescan_schedule = '{\"hourly\": true}' WHERE id = ?")
+        .bind(id)
+        .execute(&mut tx)
+        .await
+        .unwrap();
+
+    let result = library::Library::get_one(&mut tx, id).await.unwrap();
+    assert_eq!(result.rescan_schedule, None);
+
+    let all = library::Library::get_all(&mut tx).await;
+    assert!(all
+        .iter()
+        .any(|x| x.id == id && x.rescan_schedule.is_none()));
 }
 
 #[tokio::test(flavor = "multi_thread")]
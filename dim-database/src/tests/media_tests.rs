@@ -1,6 +1,7 @@
 use crate::get_conn_memory;
 use crate::library;
 use crate::media;
+use crate::mediafile;
 use crate::write_tx;
 
 use super::library_tests::create_test_library;
@@ -66,6 +67,31 @@ async fn test_get_all() {
     assert_eq!(result.len(), 10);
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_all_hides_missing() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let library_id = create_test_library(&mut tx).await;
+    let media_id = insert_media(&mut tx).await;
+    let mfile_id = insert_mediafile_with_mediaid(&mut tx, media_id).await;
+
+    let set_missing = |missing| mediafile::UpdateMediaFile {
+        missing: Some(missing),
+        ..Default::default()
+    };
+
+    set_missing(true).update(&mut tx, mfile_id).await.unwrap();
+    let result = media::Media::get_all(&mut tx, library_id).await.unwrap();
+    assert!(result.is_empty());
+
+    // missing media can still be looked up directly.
+    media::Media::get(&mut tx, media_id).await.unwrap();
+
+    set_missing(false).update(&mut tx, mfile_id).await.unwrap();
+    let result = media::Media::get_all(&mut tx, library_id).await.unwrap();
+    assert_eq!(result.len(), 1);
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_get_by_name_and_lib() {
     let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
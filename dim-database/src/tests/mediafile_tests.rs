@@ -171,3 +171,295 @@ async fn test_get_of_media() {
     assert_eq!(result[0].media_id, Some(media_id));
     assert_eq!(result[0].id, mfile);
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_fingerprints() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let lib_id = create_test_library(&mut tx).await;
+    let id = insert_mediafile(&mut tx).await;
+
+    let result = mediafile::MediaFile::get_fingerprints_by_lib(&mut tx, lib_id)
+        .await
+        .unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0].file_size, None);
+    assert!(!result[0].missing);
+
+    let update = mediafile::UpdateMediaFile {
+        file_size: Some(1024),
+        mtime: Some(1697896800),
+        inode: Some(42),
+        missing: Some(true),
+        ..Default::default()
+    };
+
+    update.update(&mut tx, id).await.unwrap();
+
+    let result = mediafile::MediaFile::get_fingerprints_by_lib(&mut tx, lib_id)
+        .await
+        .unwrap();
+    assert_eq!(result[0].file_size, Some(1024));
+    assert_eq!(result[0].mtime, Some(1697896800));
+    assert_eq!(result[0].inode, Some(42));
+    assert!(result[0].missing);
+
+    let mfile = mediafile::MediaFile::get_one(&mut tx, id).await.unwrap();
+    assert!(mfile.missing);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_missing_since() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _lib_id = create_test_library(&mut tx).await;
+    let id = insert_mediafile(&mut tx).await;
+
+    let set_missing = |missing| mediafile::UpdateMediaFile {
+        missing: Some(missing),
+        ..Default::default()
+    };
+
+    set_missing(true).update(&mut tx, id).await.unwrap();
+
+    let mfile = mediafile::MediaFile::get_one(&mut tx, id).await.unwrap();
+    let since = mfile.missing_since.expect("missing_since should be set");
+
+    // flagging the file as missing again keeps the original time.
+    set_missing(true).update(&mut tx, id).await.unwrap();
+    let mfile = mediafile::MediaFile::get_one(&mut tx, id).await.unwrap();
+    assert_eq!(mfile.missing_since, Some(since));
+
+    let result = mediafile::MediaFile::get_missing(&mut tx, since)
+        .await
+        .unwrap();
+    assert!(result.is_empty());
+
+    let result = mediafile::MediaFile::get_missing(&mut tx, since + 1)
+        .await
+        .unwrap();
+    assert_eq!(result.len(), 1);
+
+    set_missing(false).update(&mut tx, id).await.unwrap();
+    let mfile = mediafile::MediaFile::get_one(&mut tx, id).await.unwrap();
+    assert_eq!(mfile.missing_since, None);
+
+    let result = mediafile::MediaFile::get_missing(&mut tx, i64::MAX)
+        .await
+        .unwrap();
+    assert!(result.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_stack() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let lib_id = create_test_library(&mut tx).await;
+    let media_id = super::media_tests::insert_media(&mut tx).await;
+
+    for (part, duration) in [(2, 1200), (1, 3000)] {
+        mediafile::InsertableMediaFile {
+            library_id: lib_id,
+            media_id: Some(media_id),
+            target_file: format!("/movies/Movie cd{}.avi", part),
+            raw_name: "Movie".into(),
+            duration: Some(duration),
+            stack_key: Some("/movies/Movie.avi".into()),
+            stack_part: Some(part),
+            ..Default::default()
+        }
+        .insert(&mut tx)
+        .await
+        .unwrap();
+    }
+
+    // a standalone version of the same movie that is shorter than the stack.
+    mediafile::InsertableMediaFile {
+        library_id: lib_id,
+        media_id: Some(media_id),
+        target_file: "/movies/Movie.mkv".into(),
+        raw_name: "Movie".into(),
+        duration: Some(4000),
+        ..Default::default()
+    }
+    .insert(&mut tx)
+    .await
+    .unwrap();
+
+    let stack = mediafile::MediaFile::get_stack(&mut tx, lib_id, "/movies/Movie.avi")
+        .await
+        .unwrap();
+    assert_eq!(stack.len(), 2);
+    assert_eq!(stack[0].stack_part, Some(1));
+    assert_eq!(stack[1].stack_part, Some(2));
+
+    let duration = mediafile::MediaFile::get_largest_duration(&mut tx, media_id)
+        .await
+        .unwrap();
+    assert_eq!(duration, 4200);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rename_dir() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let lib_id = create_test_library(&mut tx).await;
+
+    for file in [
+        "/tv/Show (2019)/S01E01.mkv",
+        "/tv/Show (2019)/Season 2/S02E01.mkv",
+        // shares a prefix with the directory, but isn't inside of it.
+        "/tv/Show (2019) Extras/S01E01.mkv",
+        // `%` and `_` would be wildcards if we used `LIKE`.
+        "/tv/Show_100%/S01E01.mkv",
+    ] {
+        mediafile::InsertableMediaFile {
+            library_id: lib_id,
+            target_file: file.into(),
+            raw_name: "Show".into(),
+            ..Default::default()
+        }
+        .insert(&mut tx)
+        .await
+        .unwrap();
+    }
+
+    let result = mediafile::MediaFile::get_in_dir(&mut tx, lib_id, "/tv/Show (2019)/")
+        .await
+        .unwrap();
+    assert_eq!(result.len(), 2);
+
+    let result = mediafile::MediaFile::get_in_dir(&mut tx, lib_id, "/tv/Show_1000/")
+        .await
+        .unwrap();
+    assert!(result.is_empty());
+
+    let renamed = mediafile::MediaFile::rename_dir(
+        &mut tx,
+        lib_id,
+        "/tv/Show (2019)/",
+        "/tv/Show (2019) [1080p]/",
+    )
+    .await
+    .unwrap();
+    assert_eq!(renamed, 2);
+
+    let mut result = mediafile::MediaFile::get_by_lib(&mut tx, lib_id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|x| x.target_file)
+        .collect::<Vec<_>>();
+    result.sort();
+
+    assert_eq!(
+        result,
+        vec![
+            "/tv/Show (2019) Extras/S01E01.mkv",
+            "/tv/Show (2019) [1080p]/S01E01.mkv",
+            "/tv/Show (2019) [1080p]/Season 2/S02E01.mkv",
+            "/tv/Show_100%/S01E01.mkv",
+        ]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_low_confidence() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let lib_id = create_test_library(&mut tx).await;
+
+    for (file, confidence) in [
+        ("/movies/Movie (2020).mkv", Some(90)),
+        ("/movies/mv-x.mkv", Some(20)),
+        ("/movies/Other.mkv", Some(45)),
+        // scanned before confidences were recorded.
+        ("/movies/Old.mkv", None),
+    ] {
+        mediafile::InsertableMediaFile {
+            library_id: lib_id,
+            target_file: file.into(),
+            raw_name: "Movie".into(),
+            parse_confidence: confidence,
+            ..Default::default()
+        }
+        .insert(&mut tx)
+        .await
+        .unwrap();
+    }
+
+    let result = mediafile::MediaFile::get_low_confidence(&mut tx, lib_id, 50)
+        .await
+        .unwrap();
+    assert_eq!(result.len(), 2);
+    assert_eq!(result[0].target_file, "/movies/mv-x.mkv");
+    assert_eq!(result[1].target_file, "/movies/Other.mkv");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_problems() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let lib_id = create_test_library(&mut tx).await;
+
+    let mut ids = vec![];
+
+    for (file, corrupt, problem) in [
+        ("/movies/Fine.mkv", Some(false), None),
+        ("/movies/Corrupt.mkv", Some(true), None),
+        (
+            "/movies/Silent.mkv",
+            Some(false),
+            Some("File has no audio stream."),
+        ),
+        ("/movies/Gone.mkv", Some(true), None),
+    ] {
+        let id = mediafile::InsertableMediaFile {
+            library_id: lib_id,
+            target_file: file.into(),
+            raw_name: "Movie".into(),
+            corrupt,
+            probe_problem: problem.map(ToString::to_string),
+            ..Default::default()
+        }
+        .insert(&mut tx)
+        .await
+        .unwrap();
+
+        ids.push(id);
+    }
+
+    mediafile::UpdateMediaFile {
+        missing: Some(true),
+        ..Default::default()
+    }
+    .update(&mut tx, ids[3])
+    .await
+    .unwrap();
+
+    let result = mediafile::MediaFile::get_problems(&mut tx, lib_id)
+        .await
+        .unwrap();
+    assert_eq!(result.len(), 2);
+    assert_eq!(result[0].target_file, "/movies/Corrupt.mkv");
+    assert_eq!(result[1].target_file, "/movies/Silent.mkv");
+    assert_eq!(
+        result[1].probe_problem.as_deref(),
+        Some("File has no audio stream.")
+    );
+
+    // probing the file again fixed it.
+    mediafile::UpdateMediaFile {
+        probe_problem: Some(None),
+        ..Default::default()
+    }
+    .update(&mut tx, ids[2])
+    .await
+    .unwrap();
+
+    let result = mediafile::MediaFile::get_problems(&mut tx, lib_id)
+        .await
+        .unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0].target_file, "/movies/Corrupt.mkv");
+}
@@ -4,7 +4,10 @@ pub mod library_tests;
 pub mod media_tests;
 pub mod mediafile_tests;
 pub mod movie_tests;
+pub mod music_tests;
 pub mod progress_tests;
 pub mod season_tests;
+pub mod subtitle_tests;
 pub mod tv_tests;
 pub mod user_tests;
+pub mod version_tests;
@@ -0,0 +1,111 @@
+use crate::get_conn_memory;
+use crate::library::MediaType;
+use crate::media;
+use crate::music;
+use crate::write_tx;
+
+use super::library_tests::create_test_library;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_insert_artist_album_track() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let library_id = create_test_library(&mut tx).await;
+
+    let artist = music::InsertableArtist {
+        library_id,
+        name: "Test Artist".into(),
+    };
+
+    let artist_id = artist.insert(&mut tx).await.unwrap();
+    // inserting the same artist twice should return the same id.
+    assert_eq!(artist.insert(&mut tx).await.unwrap(), artist_id);
+
+    let album = music::InsertableAlbum {
+        media: media::InsertableMedia {
+            library_id,
+            name: "Greatest Hits".into(),
+            media_type: MediaType::Album,
+            ..Default::default()
+        },
+        artist_id: Some(artist_id),
+    };
+
+    let album_id = album.insert(&mut tx).await.unwrap();
+    assert_eq!(album.insert(&mut tx).await.unwrap(), album_id);
+
+    // a album with the same name by a different artist should be a separate album.
+    let other_artist = music::InsertableArtist {
+        library_id,
+        name: "Other Artist".into(),
+    }
+    .insert(&mut tx)
+    .await
+    .unwrap();
+
+    let other_album = music::InsertableAlbum {
+        media: media::InsertableMedia {
+            library_id,
+            name: "Greatest Hits".into(),
+            media_type: MediaType::Album,
+            ..Default::default()
+        },
+        artist_id: Some(other_artist),
+    }
+    .insert(&mut tx)
+    .await
+    .unwrap();
+
+    assert_ne!(album_id, other_album);
+
+    for (disc, track, name) in [(2, 1, "Third"), (1, 2, "Second"), (1, 1, "Intro")] {
+        music::InsertableTrack {
+            media: media::InsertableMedia {
+                library_id,
+                name: name.into(),
+                media_type: MediaType::Track,
+                ..Default::default()
+            },
+            album_id,
+            track_number: Some(track),
+            disc_number: Some(disc),
+        }
+        .insert(&mut tx)
+        .await
+        .unwrap();
+    }
+
+    let tracks = music::Track::get_all_of_album(&mut tx, album_id)
+        .await
+        .unwrap();
+    let names = tracks
+        .iter()
+        .map(|x| x.media.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["Intro", "Second", "Third"]);
+
+    let count = music::Album::count_children(&mut tx, album_id)
+        .await
+        .unwrap();
+    assert_eq!(count, 3);
+
+    let albums = music::Album::get_of_artist(&mut tx, artist_id)
+        .await
+        .unwrap();
+    assert_eq!(albums.len(), 1);
+    assert_eq!(albums[0].media.name, "Greatest Hits".to_string());
+
+    let artist = music::Artist::get_of_album(&mut tx, album_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(artist.name, "Test Artist".to_string());
+
+    let artists = music::Artist::get_all(&mut tx, library_id).await.unwrap();
+    assert_eq!(artists.len(), 2);
+
+    // tracks should not show up when browsing the library, but albums should.
+    let browse = media::Media::get_all(&mut tx, library_id).await.unwrap();
+    assert_eq!(browse.len(), 2);
+    assert!(browse.iter().all(|x| x.media_type == MediaType::Album));
+}
@@ -0,0 +1,56 @@
+use crate::get_conn_memory;
+use crate::subtitle;
+use crate::write_tx;
+
+use super::library_tests::create_test_library;
+use super::mediafile_tests::insert_mediafile;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_insert_and_get() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let library_id = create_test_library(&mut tx).await;
+    let mediafile_id = insert_mediafile(&mut tx).await;
+
+    let sidecar = subtitle::InsertableSubtitleSidecar {
+        mediafile_id,
+        target_file: "/dev/null.en.srt".into(),
+        codec: "srt".into(),
+        language: Some("en".into()),
+        ..Default::default()
+    };
+
+    let id = sidecar.insert(&mut tx).await.unwrap();
+
+    // inserting the same file again should update the existing row.
+    let forced = subtitle::InsertableSubtitleSidecar {
+        forced: true,
+        ..sidecar.clone()
+    };
+    assert_eq!(forced.insert(&mut tx).await.unwrap(), id);
+
+    let result = subtitle::SubtitleSidecar::get_of_mediafile(&mut tx, mediafile_id)
+        .await
+        .unwrap();
+
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0].id, id);
+    assert_eq!(result[0].language.as_deref(), Some("en"));
+    assert!(result[0].forced);
+    assert!(!result[0].sdh);
+
+    let result = subtitle::SubtitleSidecar::get_of_library(&mut tx, library_id)
+        .await
+        .unwrap();
+    assert_eq!(result.len(), 1);
+
+    let deleted = subtitle::SubtitleSidecar::delete_of_mediafile(&mut tx, mediafile_id)
+        .await
+        .unwrap();
+    assert_eq!(deleted, 1);
+
+    let result = subtitle::SubtitleSidecar::get_of_mediafile(&mut tx, mediafile_id)
+        .await
+        .unwrap();
+    assert!(result.is_empty());
+}
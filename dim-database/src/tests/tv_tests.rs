@@ -1,5 +1,10 @@
+use crate::get_conn_memory;
 use crate::library;
 use crate::media;
+use crate::tv::TVShow;
+use crate::write_tx;
+
+use super::library_tests::create_test_library;
 
 pub async fn insert_tv(conn: &mut crate::Transaction<'_>) -> i64 {
     let media = media::InsertableMedia {
@@ -17,3 +22,32 @@ pub async fn insert_tv(conn: &mut crate::Transaction<'_>) -> i64 {
     let id = media.insert(&mut *conn).await.unwrap();
     id
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_absolute_ordering() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+
+    let _lib = create_test_library(&mut tx).await;
+    let tv = insert_tv(&mut tx).await;
+
+    assert!(!TVShow::absolute_ordering(&mut tx, tv).await.unwrap());
+    assert!(TVShow::get_absolute_ordered(&mut tx)
+        .await
+        .unwrap()
+        .is_empty());
+
+    TVShow::set_absolute_ordering(&mut tx, tv, true)
+        .await
+        .unwrap();
+    assert!(TVShow::absolute_ordering(&mut tx, tv).await.unwrap());
+    assert_eq!(
+        TVShow::get_absolute_ordered(&mut tx).await.unwrap(),
+        vec![tv]
+    );
+
+    TVShow::set_absolute_ordering(&mut tx, tv, false)
+        .await
+        .unwrap();
+    assert!(!TVShow::absolute_ordering(&mut tx, tv).await.unwrap());
+}
@@ -0,0 +1,63 @@
+use crate::get_conn_memory;
+use crate::mediafile;
+use crate::version;
+use crate::write_tx;
+
+use super::library_tests::create_test_library;
+use super::media_tests::insert_media;
+use super::user_tests::insert_user;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_preferred_version() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _library = create_test_library(&mut tx).await;
+    let user = insert_user(&mut tx).await;
+    let media = insert_media(&mut tx).await;
+
+    let mut versions = vec![];
+    for edition in ["Theatrical Cut", "Final Cut"] {
+        let id = mediafile::InsertableMediaFile {
+            library_id: 1,
+            media_id: Some(media),
+            target_file: format!("/movies/Blade Runner {{edition-{}}}.mkv", edition),
+            raw_name: "Blade Runner".into(),
+            edition: Some(edition.into()),
+            ..Default::default()
+        }
+        .insert(&mut tx)
+        .await
+        .unwrap();
+
+        versions.push(id);
+    }
+
+    let (first, second) = (versions[0], versions[1]);
+
+    let result = version::PreferredVersion::get(&mut tx, user.id, media)
+        .await
+        .unwrap();
+    assert_eq!(result, None);
+
+    version::PreferredVersion::set(&mut tx, user.id, media, first)
+        .await
+        .unwrap();
+    version::PreferredVersion::set(&mut tx, user.id, media, second)
+        .await
+        .unwrap();
+
+    let result = version::PreferredVersion::get(&mut tx, user.id, media)
+        .await
+        .unwrap();
+    assert_eq!(result, Some(second));
+
+    let rows = version::PreferredVersion::clear(&mut tx, user.id, media)
+        .await
+        .unwrap();
+    assert_eq!(rows, 1);
+
+    let result = version::PreferredVersion::get(&mut tx, user.id, media)
+        .await
+        .unwrap();
+    assert_eq!(result, None);
+}
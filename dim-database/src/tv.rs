@@ -29,4 +29,66 @@ impl TVShow {
         .await?
         .count)
     }
+
+    /// Method returns whether the episodes of a show are numbered absolutely, ie from the first
+    /// episode of the show onwards instead of per season.
+    ///
+    /// # Arguments
+    /// * `tx` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the tv show.
+    pub async fn absolute_ordering(
+        tx: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<bool, DatabaseError> {
+        Ok(sqlx::query!(
+            r#"SELECT absolute_ordering as "absolute_ordering: bool" FROM tv_show_ordering
+            WHERE id = ?"#,
+            id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .map_or(false, |x| x.absolute_ordering))
+    }
+
+    /// Method sets whether the episodes of a show are numbered absolutely.
+    ///
+    /// # Arguments
+    /// * `tx` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the tv show.
+    /// * `absolute_ordering` - whether episodes are numbered absolutely.
+    pub async fn set_absolute_ordering(
+        tx: &mut crate::Transaction<'_>,
+        id: i64,
+        absolute_ordering: bool,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "INSERT INTO tv_show_ordering (id, absolute_ordering) VALUES ($1, $2)
+            ON CONFLICT (id) DO UPDATE SET absolute_ordering = $2",
+            id,
+            absolute_ordering
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Method returns the ids of all shows whose episodes are numbered absolutely.
+    ///
+    /// # Arguments
+    /// * `tx` - mutable reference to a sqlx transaction.
+    pub async fn get_absolute_ordered(
+        tx: &mut crate::Transaction<'_>,
+    ) -> Result<Vec<i64>, DatabaseError> {
+        Ok(sqlx::query!(
+            r#"SELECT _tblmedia.id FROM _tblmedia
+            INNER JOIN tv_show_ordering ON tv_show_ordering.id = _tblmedia.id
+            WHERE tv_show_ordering.absolute_ordering AND _tblmedia.media_type = "tv""#
+        )
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .map(|x| x.id)
+        .collect())
+    }
 }
@@ -48,6 +48,21 @@ pub enum DefaultVideoQuality {
     Resolution(u64, u64),
 }
 
+/// Rule used to pick the version of a media that gets played back by default.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VersionPreference {
+    /// Edition that should be preferred, ie `Director's Cut`.
+    #[serde(default)]
+    pub edition: Option<String>,
+    /// Highest resolution (in lines) that should be preferred, versions with a higher
+    /// resolution are only picked if nothing else is available.
+    #[serde(default)]
+    pub max_resolution: Option<i64>,
+    /// Whether HDR versions should be preferred or avoided. If not set we dont care.
+    #[serde(default)]
+    pub hdr: Option<bool>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserSettings {
     /// Theme of the app
@@ -76,6 +91,9 @@ pub struct UserSettings {
     show_hovercards: bool,
     /// Whether to auto play next video
     enable_autoplay: bool,
+    /// Rule used to pick the version of a media that gets played back by default.
+    #[serde(default)]
+    pub preferred_version: VersionPreference,
 }
 
 impl<DB: sqlx::Database> sqlx::Type<DB> for UserSettings
@@ -126,6 +144,7 @@ impl Default for UserSettings {
             show_hovercards: true,
             default_video_quality: DefaultVideoQuality::DirectPlay,
             enable_autoplay: true,
+            preferred_version: Default::default(),
         }
     }
 }
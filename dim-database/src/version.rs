@@ -0,0 +1,73 @@
+use crate::user::UserID;
+use crate::DatabaseError;
+
+/// Version of a media a user has picked to be played back by default.
+pub struct PreferredVersion;
+
+impl PreferredVersion {
+    /// Method returns the id of the mediafile a user has picked for a media, if any.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - id of the user.
+    /// * `media_id` - id of the media.
+    pub async fn get(
+        conn: &mut crate::Transaction<'_>,
+        uid: UserID,
+        media_id: i64,
+    ) -> Result<Option<i64>, DatabaseError> {
+        Ok(sqlx::query_scalar!(
+            "SELECT mediafile_id FROM preferred_version WHERE user_id = ? AND media_id = ?",
+            uid,
+            media_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?)
+    }
+
+    /// Method picks `mediafile_id` as the version of a media played back by default for a user.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - id of the user.
+    /// * `media_id` - id of the media.
+    /// * `mediafile_id` - id of the mediafile that should be played back.
+    pub async fn set(
+        conn: &mut crate::Transaction<'_>,
+        uid: UserID,
+        media_id: i64,
+        mediafile_id: i64,
+    ) -> Result<usize, DatabaseError> {
+        Ok(sqlx::query!(
+            "INSERT OR REPLACE INTO preferred_version (user_id, media_id, mediafile_id)
+            VALUES ($1, $2, $3)",
+            uid,
+            media_id,
+            mediafile_id
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected() as usize)
+    }
+
+    /// Method removes the version a user has picked for a media.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - id of the user.
+    /// * `media_id` - id of the media.
+    pub async fn clear(
+        conn: &mut crate::Transaction<'_>,
+        uid: UserID,
+        media_id: i64,
+    ) -> Result<usize, DatabaseError> {
+        Ok(sqlx::query!(
+            "DELETE FROM preferred_version WHERE user_id = ? AND media_id = ?",
+            uid,
+            media_id
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected() as usize)
+    }
+}
@@ -42,6 +42,33 @@ pub enum PushEventType {
     EventStartedScanning,
     /// A library has finished scanning.
     EventStoppedScanning,
+    /// Progress of a running scan. These events are rate-limited.
+    EventScanProgress {
+        phase: ScanPhase,
+        /// Number of files found by the directory walker so far.
+        discovered: usize,
+        /// Number of files probed so far.
+        probed: usize,
+        /// Number of files handed to the matcher so far.
+        matched: usize,
+        /// Number of files that failed to be probed or matched.
+        failed: usize,
+        /// Path of the file that was last worked on.
+        current_path: Option<String>,
+    },
+    /// Summary of a finished scan.
+    EventScanSummary {
+        added: usize,
+        changed: usize,
+        missing: usize,
+        moved: usize,
+        discovered: usize,
+        probed: usize,
+        matched: usize,
+        failed: usize,
+        /// Errors of the files that failed to be probed or matched.
+        errors: Vec<ScanFileError>,
+    },
     /// Tell client auth is ok
     EventAuthOk,
     /// Tell client their token is wrong or missing
@@ -49,4 +76,34 @@ pub enum PushEventType {
     /// Matched mediafile. This hints to a listener that they must remove this mediafile from a
     /// list, or update its state.
     MediafileMatched { mediafile: i64, library_id: i64 },
+    /// Files which can't be played have been found while scanning a library.
+    EventProblemFiles { files: Vec<ProblemFile> },
+}
+
+/// Phase a scan is in. As the stages of a scan run concurrently, this is the earliest stage that
+/// is still running.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanPhase {
+    /// Library directories are being walked.
+    Walking,
+    /// Files are being probed with ffprobe.
+    Probing,
+    /// Files are being matched against the metadata provider.
+    Matching,
+}
+
+/// A file that failed to be scanned.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ScanFileError {
+    pub path: String,
+    pub error: String,
+}
+
+/// A file that can't be played.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ProblemFile {
+    pub mediafile: i64,
+    pub path: String,
+    pub problem: String,
 }
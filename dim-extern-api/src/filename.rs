@@ -2,50 +2,352 @@ pub use anitomy::Anitomy;
 use anitomy::ElementCategory;
 pub use torrent_name_parser::Metadata as TorrentMetadata;
 
-#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+use chrono::NaiveDate;
+use regex::Regex;
+use serde::Serialize;
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize)]
 pub struct Metadata {
     pub name: String,
     pub year: Option<i64>,
     pub season: Option<i64>,
     pub episode: Option<i64>,
+    /// Last episode of a file spanning several episodes, ie `Show.S02E05E06` or `Show.S02E05-06`.
+    pub last_episode: Option<i64>,
+    /// Whether `season` was assumed because the filename only carries an episode number. Such
+    /// episode numbers might be absolute episode numbers, ie `One Piece - 1054`.
+    pub season_assumed: bool,
+    /// Air date of a episode of a daily show, ie `The.Daily.Show.2023.05.01`.
+    pub air_date: Option<NaiveDate>,
+    /// Edition of a movie, ie `Director's Cut`.
+    pub edition: Option<String>,
+    /// Confidence from 0 to 100 that this is the right reading of the filename. Extractors leave
+    /// this at 0, it gets filled in by the scanner when it ranks the candidates of a file.
+    pub confidence: u8,
 }
 
 pub trait FilenameMetadata {
     fn from_str(s: &str) -> Option<Metadata>;
+
+    /// Function extracts metadata from `s` with the help of the filename rules of a library.
+    /// Extractors that dont make use of rules ignore them.
+    fn from_str_with_rules(s: &str, _rules: &[Regex]) -> Option<Metadata> {
+        Self::from_str(s)
+    }
+}
+
+/// Editions we recognise when they are part of a filename without being tagged explicitly.
+static KNOWN_EDITIONS: &[&str] = &[
+    "Director's Cut",
+    "Directors Cut",
+    "Extended Cut",
+    "Extended Edition",
+    "Final Cut",
+    "Theatrical Cut",
+    "Theatrical",
+    "Ultimate Cut",
+    "Ultimate Edition",
+    "Unrated",
+    "Uncut",
+    "Special Edition",
+    "Collector's Edition",
+    "Anniversary Edition",
+    "Remastered",
+    "Criterion",
+    "IMAX",
+];
+
+/// Function returns the edition of a movie named in `s` and `s` with the edition tag removed.
+///
+/// Editions can be tagged explicitly as in `Blade Runner (1982) {edition-Final Cut}`, otherwise
+/// we look for a handful of well known edition names.
+pub fn split_edition(s: &str) -> (String, Option<String>) {
+    // NOTE: `to_ascii_lowercase` keeps byte offsets intact so we can slice `s` with them.
+    let lowercase = s.to_ascii_lowercase();
+
+    if let Some(start) = lowercase.find("{edition-") {
+        if let Some(len) = lowercase[start..].find('}') {
+            let edition = s[start + "{edition-".len()..start + len].trim();
+            let stripped = format!("{} {}", &s[..start], &s[start + len + 1..]);
+
+            return (
+                stripped.split_whitespace().collect::<Vec<_>>().join(" "),
+                Some(edition.to_owned()).filter(|x| !x.is_empty()),
+            );
+        }
+    }
+
+    // NOTE: dots and underscores are commonly used instead of spaces. Editions must be whole
+    // words, `Uncut` shouldnt match `Uncuttable`.
+    let normalized = lowercase.replace(['.', '_'], " ");
+    let is_boundary = |idx: usize| {
+        normalized[idx..]
+            .chars()
+            .next()
+            .map_or(true, |x| !x.is_alphanumeric())
+    };
+
+    let found = KNOWN_EDITIONS.iter().find_map(|edition| {
+        let needle = edition.to_ascii_lowercase();
+
+        normalized
+            .match_indices(&needle)
+            .map(|(start, _)| (start, start + needle.len()))
+            .find(|&(start, end)| {
+                let before = normalized[..start]
+                    .chars()
+                    .next_back()
+                    .map_or(true, |x| !x.is_alphanumeric());
+
+                before && is_boundary(end)
+            })
+            .map(|range| (edition, range))
+    });
+
+    match found {
+        Some((edition, (start, end))) => {
+            let prefix = s[..start].trim_end_matches(['.', '-', '_', ' ']);
+
+            (
+                format!("{prefix}{}", &s[end..]),
+                Some(edition.replace("Directors", "Director's")),
+            )
+        }
+        None => (s.to_owned(), None),
+    }
+}
+
+/// Function returns the air date of a episode named in `s` and `s` with the air date removed.
+///
+/// Daily shows such as talk shows and news programs name their episodes by the date they aired
+/// on, ie `The.Daily.Show.2023.05.01`, in which case there is no season or episode number to go
+/// by. The date would otherwise also be mistaken for the year the show was released in. The
+/// extractors leave this to the caller, as only episodes are named this way.
+pub fn split_air_date(s: &str) -> (String, Option<NaiveDate>) {
+    let bytes = s.as_bytes();
+    let is_digit = |idx: usize| bytes.get(idx).map_or(false, u8::is_ascii_digit);
+    let number = |range: std::ops::Range<usize>| -> Option<u32> {
+        std::str::from_utf8(&bytes[range]).ok()?.parse().ok()
+    };
+
+    for start in 0..bytes.len().saturating_sub(9) {
+        let separator = bytes[start + 4];
+
+        // NOTE: The date must not be part of a longer number and has to use the same separator
+        // between all of its components.
+        if (start > 0 && is_digit(start - 1))
+            || is_digit(start + 10)
+            || !matches!(separator, b'.' | b'-' | b'_' | b' ')
+            || bytes[start + 7] != separator
+            || ![0..4, 5..7, 8..10]
+                .into_iter()
+                .all(|x| x.map(|x| start + x).all(is_digit))
+        {
+            continue;
+        }
+
+        let (Some(year), Some(month), Some(day)) = (
+            number(start..start + 4),
+            number(start + 5..start + 7),
+            number(start + 8..start + 10),
+        ) else {
+            continue;
+        };
+
+        if let Some(date) = NaiveDate::from_ymd_opt(year as i32, month, day) {
+            let prefix = s[..start].trim_end_matches(['.', '-', '_', ' ']);
+            return (format!("{prefix}{}", &s[start + 10..]), Some(date));
+        }
+    }
+
+    (s.to_owned(), None)
+}
+
+/// Function returns the last episode of a file spanning several episodes, such as
+/// `Show.S02E05E06`, `Show.S02E05-06` or `Show.S02E05-E06`.
+pub fn last_episode(s: &str) -> Option<i64> {
+    let lowercase = s.to_ascii_lowercase();
+    let bytes = lowercase.as_bytes();
+    let digits = |start: usize| {
+        bytes[start..]
+            .iter()
+            .take_while(|x| x.is_ascii_digit())
+            .count()
+    };
+    let number = |start: usize, len: usize| lowercase[start..start + len].parse::<i64>().ok();
+
+    for (idx, _) in lowercase.match_indices('s') {
+        if idx > 0 && bytes[idx - 1].is_ascii_alphanumeric() {
+            continue;
+        }
+
+        let season_len = digits(idx + 1);
+        let mut pos = idx + 1 + season_len;
+
+        if season_len == 0 || bytes.get(pos) != Some(&b'e') || digits(pos + 1) == 0 {
+            continue;
+        }
+
+        let first = number(pos + 1, digits(pos + 1))?;
+        pos += 1 + digits(pos + 1);
+
+        let mut last = None;
+
+        loop {
+            let separator = match (bytes.get(pos), bytes.get(pos + 1)) {
+                (Some(b'-'), Some(b'e')) => 2,
+                (Some(b'-' | b'e'), _) => 1,
+                _ => break,
+            };
+
+            let len = digits(pos + separator);
+
+            // NOTE: Guards against resolutions and such, ie `Show.S02E05-1080p`.
+            if len == 0
+                || bytes
+                    .get(pos + separator + len)
+                    .map_or(false, |x| x.is_ascii_alphanumeric() && *x != b'e')
+            {
+                break;
+            }
+
+            last = number(pos + separator, len);
+            pos += separator + len;
+        }
+
+        return last.filter(|x| *x > first);
+    }
+
+    None
 }
 
 impl FilenameMetadata for TorrentMetadata {
     fn from_str(s: &str) -> Option<Metadata> {
-        let metadata = TorrentMetadata::from(s).ok()?;
+        let (s, edition) = split_edition(s);
+        let metadata = TorrentMetadata::from(&s).ok()?;
 
         Some(Metadata {
             name: metadata.title().to_owned(),
             year: metadata.year().map(|x| x as i64),
             season: metadata.season().map(|x| x as i64),
             episode: metadata.episode().map(|x| x as i64),
+            last_episode: last_episode(&s),
+            season_assumed: false,
+            air_date: None,
+            edition,
+            confidence: 0,
         })
     }
 }
 
 impl FilenameMetadata for Anitomy {
     fn from_str(s: &str) -> Option<Metadata> {
-        let metadata = match Anitomy::new().parse(s) {
+        let (s, edition) = split_edition(s);
+        let metadata = match Anitomy::new().parse(&s) {
             Ok(v) | Err(v) => v,
         };
 
+        let season = metadata
+            .get(ElementCategory::AnimeSeason)
+            .and_then(|x| x.parse().ok());
+
         Some(Metadata {
             name: metadata.get(ElementCategory::AnimeTitle)?.to_string(),
             year: metadata
                 .get(ElementCategory::AnimeYear)
                 .and_then(|x| x.parse().ok()),
             // If season isnt specified we assume season 1 here.
-            season: metadata
-                .get(ElementCategory::AnimeSeason)
-                .and_then(|x| x.parse().ok())
-                .or(Some(1)),
+            season: season.or(Some(1)),
             episode: metadata
                 .get(ElementCategory::EpisodeNumber)
                 .and_then(|x| x.parse().ok()),
+            last_episode: last_episode(&s),
+            season_assumed: season.is_none(),
+            air_date: None,
+            edition,
+            confidence: 0,
+        })
+    }
+}
+
+/// Filename metadata extractor driven by regex rules supplied by the user, for releases following
+/// naming schemes none of the other extractors make sense of. Rules are tried in order and pick
+/// out metadata with the named captures `title`, `year`, `season` and `episode`, of which only
+/// `title` is required.
+#[derive(Clone, Debug, Default)]
+pub struct RegexExtractor {
+    rules: Vec<Regex>,
+}
+
+impl RegexExtractor {
+    /// Method compiles `rules` into a extractor, failing on the first rule that isnt a valid regex
+    /// or doesnt capture a `title`.
+    pub fn new(rules: impl IntoIterator<Item = impl AsRef<str>>) -> Result<Self, String> {
+        let rules = rules
+            .into_iter()
+            .map(|rule| {
+                let rule = rule.as_ref();
+                let regex = Regex::new(rule).map_err(|e| e.to_string())?;
+
+                if !regex.capture_names().flatten().any(|x| x == "title") {
+                    return Err(format!("Rule `{rule}` doesnt capture a `title`."));
+                }
+
+                Ok(regex)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { rules })
+    }
+
+    /// Method returns whether the extractor has no rules at all.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Method extracts metadata from `s` with the first rule that matches.
+    pub fn extract(&self, s: &str) -> Option<Metadata> {
+        Self::from_str_with_rules(s, &self.rules)
+    }
+}
+
+impl FilenameMetadata for RegexExtractor {
+    /// Without rules there is nothing to match `s` against.
+    fn from_str(_: &str) -> Option<Metadata> {
+        None
+    }
+
+    fn from_str_with_rules(s: &str, rules: &[Regex]) -> Option<Metadata> {
+        let (_, edition) = split_edition(s);
+
+        rules.iter().find_map(|rule| {
+            let captures = rule.captures(s)?;
+            let number = |name: &str| captures.name(name)?.as_str().trim().parse().ok();
+
+            // NOTE: dots and underscores are commonly used instead of spaces.
+            let name = captures
+                .name("title")?
+                .as_str()
+                .replace(['.', '_'], " ")
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            let season = number("season");
+            let episode = number("episode");
+
+            // NOTE: Like anitomy we assume the first season if a rule only captures episodes.
+            Some(Metadata {
+                name,
+                year: number("year"),
+                season: season.or(episode.map(|_| 1)),
+                episode,
+                last_episode: None,
+                season_assumed: season.is_none() && episode.is_some(),
+                air_date: None,
+                edition: edition.clone(),
+                confidence: 0,
+            })
         })
     }
 }
@@ -58,23 +360,140 @@ pub struct CombinedExtractor;
 
 impl FilenameMetadata for CombinedExtractor {
     fn from_str(s: &str) -> Option<Metadata> {
-        let metadata_tnp = TorrentMetadata::from(s).ok()?;
-        let metadata_anitomy = match Anitomy::new().parse(s) {
+        let (s, edition) = split_edition(s);
+        let metadata_tnp = TorrentMetadata::from(&s).ok()?;
+        let metadata_anitomy = match Anitomy::new().parse(&s) {
             Ok(v) | Err(v) => v,
         };
 
+        let season = metadata_anitomy
+            .get(ElementCategory::AnimeSeason)
+            .and_then(|x| x.parse().ok());
+
         Some(Metadata {
             name: metadata_tnp.title().to_owned(),
             year: metadata_tnp.year().map(|x| x as i64),
             // If season isnt specified we assume season 1 here as some releases only have a
             // episode number and no season number.
-            season: metadata_anitomy
-                .get(ElementCategory::AnimeSeason)
-                .and_then(|x| x.parse().ok())
-                .or(Some(1)),
+            season: season.or(Some(1)),
             episode: metadata_anitomy
                 .get(ElementCategory::EpisodeNumber)
                 .and_then(|x| x.parse().ok()),
+            last_episode: last_episode(&s),
+            season_assumed: season.is_none(),
+            air_date: None,
+            edition,
+            confidence: 0,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::last_episode;
+    use super::split_air_date;
+    use super::split_edition;
+    use super::Anitomy;
+    use super::FilenameMetadata;
+    use super::NaiveDate;
+    use super::RegexExtractor;
+
+    #[test]
+    fn test_split_edition() {
+        let (name, edition) = split_edition("Blade Runner (1982) {edition-Final Cut}");
+        assert_eq!(name, "Blade Runner (1982)");
+        assert_eq!(edition.as_deref(), Some("Final Cut"));
+
+        let (name, edition) = split_edition("Blade Runner {Edition-The Final Cut} (1982) 2160p");
+        assert_eq!(name, "Blade Runner (1982) 2160p");
+        assert_eq!(edition.as_deref(), Some("The Final Cut"));
+
+        let (name, edition) = split_edition("Aliens.1986.Directors.Cut.1080p.BluRay");
+        assert_eq!(name, "Aliens.1986.1080p.BluRay");
+        assert_eq!(edition.as_deref(), Some("Director's Cut"));
+
+        let (name, edition) = split_edition("Movie (2004) Unrated.mkv");
+        assert_eq!(name, "Movie (2004).mkv");
+        assert_eq!(edition.as_deref(), Some("Unrated"));
+
+        // editions have to be whole words.
+        let (name, edition) = split_edition("The.Uncuttable.Man.2019.1080p.mkv");
+        assert_eq!(name, "The.Uncuttable.Man.2019.1080p.mkv");
+        assert_eq!(edition, None);
+
+        let (name, edition) = split_edition("Remastering Life (2015) IMAXIMUM.mkv");
+        assert_eq!(name, "Remastering Life (2015) IMAXIMUM.mkv");
+        assert_eq!(edition, None);
+
+        let (name, edition) = split_edition("Blade Runner (1982) 2160p Remux");
+        assert_eq!(name, "Blade Runner (1982) 2160p Remux");
+        assert_eq!(edition, None);
+    }
+
+    #[test]
+    fn test_season_assumed() {
+        let metadata = Anitomy::from_str("[SubsPlease] One Piece - 1054 (1080p).mkv").unwrap();
+        assert_eq!(metadata.season, Some(1));
+        assert_eq!(metadata.episode, Some(1054));
+        assert!(metadata.season_assumed);
+
+        let metadata = Anitomy::from_str("Spy x Family S02E05 1080p.mkv").unwrap();
+        assert_eq!(metadata.season, Some(2));
+        assert!(!metadata.season_assumed);
+    }
+
+    #[test]
+    fn test_split_air_date() {
+        let (name, date) = split_air_date("The.Daily.Show.2023.05.01.mkv");
+        assert_eq!(name, "The.Daily.Show.mkv");
+        assert_eq!(date, NaiveDate::from_ymd_opt(2023, 5, 1));
+
+        let (name, date) = split_air_date("Jeopardy! 2021-11-30 1080p.mkv");
+        assert_eq!(name, "Jeopardy! 1080p.mkv");
+        assert_eq!(date, NaiveDate::from_ymd_opt(2021, 11, 30));
+
+        let (name, date) = split_air_date("Show 2023.05-01.mkv");
+        assert_eq!(name, "Show 2023.05-01.mkv");
+        assert_eq!(date, None);
+
+        let (_, date) = split_air_date("Show 2023.13.01.mkv");
+        assert_eq!(date, None);
+
+        let (_, date) = split_air_date("Show S01E01 1080p.mkv");
+        assert_eq!(date, None);
+    }
+
+    #[test]
+    fn test_last_episode() {
+        assert_eq!(last_episode("Show.S02E05E06.mkv"), Some(6));
+        assert_eq!(last_episode("Show.S02E05-06.mkv"), Some(6));
+        assert_eq!(last_episode("Show S02E05-E07 1080p.mkv"), Some(7));
+        assert_eq!(last_episode("Show.S02E05.1080p.mkv"), None);
+        assert_eq!(last_episode("Show.S02E05-1080p.mkv"), None);
+        assert_eq!(last_episode("Show.S02E05-04.mkv"), None);
+    }
+
+    #[test]
+    fn test_regex_extractor() {
+        let extractor = RegexExtractor::new([
+            r"^(?P<title>.+?)\.Ep(?P<episode>\d+)\.Vol(?P<season>\d+)",
+            r"^\[(?P<year>\d{4})\] (?P<title>.+)$",
+        ])
+        .unwrap();
+
+        let metadata = extractor.extract("Some_Show.Ep12.Vol3.HOUSE").unwrap();
+        assert_eq!(metadata.name, "Some Show");
+        assert_eq!(metadata.season, Some(3));
+        assert_eq!(metadata.episode, Some(12));
+
+        let metadata = extractor.extract("[1999] Some Movie").unwrap();
+        assert_eq!(metadata.name, "Some Movie");
+        assert_eq!(metadata.year, Some(1999));
+        assert!(!metadata.season_assumed);
+
+        assert!(extractor.extract("Unrelated").is_none());
+        assert!(RegexExtractor::from_str("[1999] Some Movie").is_none());
+        assert!(RegexExtractor::new([r"(?P<episode>\d+)"]).is_err());
+        assert!(RegexExtractor::new([r"(?P<title>"]).is_err());
+    }
+}
@@ -2,12 +2,15 @@
 //! the implementations for various external APIs, such as TMDB.
 
 pub mod filename;
+pub mod local;
 pub mod mock;
+pub mod nfo;
 pub mod tmdb;
 
 use async_trait::async_trait;
 
 use std::fmt::Debug;
+use std::path::Path;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -94,6 +97,8 @@ pub struct ExternalEpisode {
     pub episode_number: u64,
     pub stills: Vec<String>,
     pub duration: Option<Duration>,
+    /// Date the episode first aired on.
+    pub air_date: Option<chrono::NaiveDate>,
 }
 
 impl ExternalEpisode {
@@ -136,6 +141,16 @@ pub trait ExternalQuery: Debug + Send + Sync {
     /// Search by title and year. This must return a Vec of `ExternalMedia` sorted by the search
     /// score.
     async fn search(&self, title: &str, year: Option<i32>) -> Result<Vec<ExternalMedia>>;
+    /// Search for the media contained in `file`. Providers which source their metadata from the
+    /// filesystem can override this, by default this is the same as calling `search`.
+    async fn search_for_file(
+        &self,
+        _file: &Path,
+        title: &str,
+        year: Option<i32>,
+    ) -> Result<Vec<ExternalMedia>> {
+        self.search(title, year).await
+    }
     /// Search by external id. This must return a singular `ExternalMedia` which has the id passed
     /// in.
     async fn search_by_id(&self, external_id: &str) -> Result<ExternalMedia>;
@@ -0,0 +1,41 @@
+//! A metadata provider for libraries whose matchers do not rely on any external metadata agents.
+//!
+//! Music libraries for example source all of their metadata from the tags embedded in the files
+//! themselves, however the scanner interfaces still require a provider to be passed in.
+
+use crate::Error;
+use crate::ExternalActor;
+use crate::ExternalMedia;
+use crate::ExternalQuery;
+use crate::ExternalQueryIntoShow;
+use crate::IntoQueryShow;
+use crate::Result;
+
+/// Provider that never returns any results.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LocalProvider;
+
+#[async_trait::async_trait]
+impl ExternalQuery for LocalProvider {
+    async fn search(&self, title: &str, year: Option<i32>) -> Result<Vec<ExternalMedia>> {
+        Err(Error::NoResults {
+            query: title.into(),
+            year,
+        })
+    }
+
+    async fn search_by_id(&self, external_id: &str) -> Result<ExternalMedia> {
+        Err(Error::NoResults {
+            query: external_id.into(),
+            year: None,
+        })
+    }
+
+    async fn cast(&self, _: &str) -> Result<Vec<ExternalActor>> {
+        Ok(vec![])
+    }
+}
+
+impl IntoQueryShow for LocalProvider {}
+
+impl ExternalQueryIntoShow for LocalProvider {}
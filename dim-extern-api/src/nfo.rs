@@ -0,0 +1,1231 @@
+//! A metadata provider which reads Kodi-style `.nfo` sidecar files.
+//!
+//! Movies are described by a `<filename>.nfo` or a `movie.nfo` next to the video file. Tv shows
+//! are described by a `tvshow.nfo` in the root folder of the show, seasons by an optional
+//! `season.nfo` and episodes by a `<filename>.nfo` next to the episode. The external ids handed
+//! out by this provider are the paths of the sidecars prefixed with `nfo://`. Only ids pointing
+//! into one of the locations of the library are accepted, so that a client can't make us read
+//! arbitrary files.
+//!
+//! Optionally a fallback provider (usually TMDB) can be configured. It is used for files that
+//! have no sidecar, and to fill in whatever a sidecar that references a tmdb id leaves out. Without
+//! a fallback the provider works fully offline.
+
+use crate::Error;
+use crate::ExternalActor;
+use crate::ExternalEpisode;
+use crate::ExternalMedia;
+use crate::ExternalQuery;
+use crate::ExternalQueryIntoShow;
+use crate::ExternalQueryShow;
+use crate::ExternalSeason;
+use crate::IntoQueryShow;
+use crate::MediaSearchType;
+use crate::Result;
+
+use async_trait::async_trait;
+use chrono::DateTime;
+use chrono::NaiveDate;
+use chrono::TimeZone;
+use chrono::Utc;
+use dashmap::DashMap;
+use serde::Deserialize;
+use tracing::warn;
+use url::Url;
+
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Prefix of all external ids handed out by this provider.
+const ID_PREFIX: &str = "nfo://";
+/// How many directories above a episode we look for a `tvshow.nfo`.
+const MAX_SHOW_DEPTH: usize = 3;
+/// How long the sidecars indexed for a show are cached. The tv matcher queries seasons and
+/// episodes for every single file, we dont want to walk the show folder every time.
+const SHOW_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Kind of document a `.nfo` file contains, taken from its root element.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NfoKind {
+    Movie,
+    TvShow,
+    Season,
+    Episode,
+}
+
+/// Contents of a `.nfo` file. The same struct is used for all kinds of documents as they share
+/// most of their elements.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Nfo {
+    pub title: Option<String>,
+    pub originaltitle: Option<String>,
+    pub year: Option<String>,
+    pub plot: Option<String>,
+    pub outline: Option<String>,
+    pub premiered: Option<String>,
+    pub aired: Option<String>,
+    /// Runtime in minutes.
+    pub runtime: Option<String>,
+    /// Legacy single rating out of 10.
+    pub rating: Option<String>,
+    pub ratings: Option<NfoRatings>,
+    pub uniqueid: Vec<NfoUniqueId>,
+    /// Legacy id element, usually a imdb id.
+    pub id: Option<String>,
+    pub tmdbid: Option<String>,
+    pub genre: Vec<String>,
+    pub thumb: Vec<NfoThumb>,
+    pub fanart: Option<NfoFanart>,
+    pub actor: Vec<NfoActor>,
+    pub season: Option<String>,
+    pub episode: Option<String>,
+    pub seasonnumber: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct NfoRatings {
+    pub rating: Vec<NfoRating>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct NfoRating {
+    #[serde(rename = "@name")]
+    pub name: Option<String>,
+    #[serde(rename = "@max")]
+    pub max: Option<String>,
+    #[serde(rename = "@default")]
+    pub default: Option<String>,
+    pub value: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct NfoUniqueId {
+    #[serde(rename = "@type")]
+    pub kind: Option<String>,
+    #[serde(rename = "$text")]
+    pub value: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct NfoThumb {
+    #[serde(rename = "@aspect")]
+    pub aspect: Option<String>,
+    #[serde(rename = "@season")]
+    pub season: Option<String>,
+    #[serde(rename = "$text")]
+    pub value: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct NfoFanart {
+    pub thumb: Vec<NfoThumb>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct NfoActor {
+    pub name: Option<String>,
+    pub role: Option<String>,
+    pub thumb: Option<String>,
+}
+
+/// Function returns the name of the root element of a xml document.
+fn root_element(contents: &str) -> Option<&str> {
+    let mut rest = contents;
+
+    loop {
+        let start = rest.find('<')?;
+        rest = &rest[start + 1..];
+
+        // skip the xml declaration, comments and doctypes.
+        if rest.starts_with('?') || rest.starts_with('!') {
+            continue;
+        }
+
+        let end = rest.find(|c: char| c.is_whitespace() || c == '>' || c == '/')?;
+        return Some(&rest[..end]);
+    }
+}
+
+/// Function parses the contents of a `.nfo` file.
+pub fn parse(contents: &str) -> Option<(NfoKind, Nfo)> {
+    let root = root_element(contents)?;
+    let kind = match root {
+        "movie" => NfoKind::Movie,
+        "tvshow" => NfoKind::TvShow,
+        "season" => NfoKind::Season,
+        "episodedetails" => NfoKind::Episode,
+        _ => return None,
+    };
+
+    // NOTE: Some tools append a url after the document, and multi-episode files contain several
+    // documents. Neither is valid xml so we only parse the first document.
+    let closing = format!("</{root}>");
+    let end = contents
+        .find(&closing)
+        .map(|x| x + closing.len())
+        .unwrap_or(contents.len());
+
+    match quick_xml::de::from_str(&contents[..end]) {
+        Ok(nfo) => Some((kind, nfo)),
+        Err(error) => {
+            warn!(?error, "Failed to parse nfo file.");
+            None
+        }
+    }
+}
+
+/// Function reads and parses the `.nfo` file at `path`, returning it only if it is of `kind`.
+fn read(path: &Path, kind: NfoKind) -> Option<Nfo> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    parse(&contents)
+        .filter(|(x, _)| *x == kind)
+        .map(|(_, nfo)| nfo)
+}
+
+/// Function returns a non-empty, trimmed version of `value`.
+fn non_empty(value: &Option<String>) -> Option<String> {
+    value
+        .as_deref()
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(ToOwned::to_owned)
+}
+
+/// Function resolves a reference to artwork found in a sidecar. Remote artwork is returned as is,
+/// local artwork is resolved relative to the directory of the sidecar and turned into a `file://`
+/// url.
+fn resolve_artwork(dir: &Path, value: &str) -> Option<String> {
+    let value = value.trim();
+
+    if value.starts_with("http://") || value.starts_with("https://") {
+        return Some(value.to_owned());
+    }
+
+    let path = dir.join(value);
+
+    if value.is_empty() || !path.is_file() {
+        return None;
+    }
+
+    Url::from_file_path(path).ok().map(String::from)
+}
+
+fn id_for(path: &Path) -> String {
+    format!("{ID_PREFIX}{}", path.display())
+}
+
+fn parse_number(value: &Option<String>) -> Option<u64> {
+    non_empty(value)?.parse().ok()
+}
+
+impl Nfo {
+    pub fn title(&self) -> Option<String> {
+        non_empty(&self.title).or_else(|| non_empty(&self.originaltitle))
+    }
+
+    pub fn description(&self) -> Option<String> {
+        non_empty(&self.plot).or_else(|| non_empty(&self.outline))
+    }
+
+    pub fn release_date(&self) -> Option<DateTime<Utc>> {
+        let date = non_empty(&self.premiered)
+            .or_else(|| non_empty(&self.aired))
+            .and_then(|x| NaiveDate::parse_from_str(&x, "%Y-%m-%d").ok())
+            .or_else(|| NaiveDate::from_ymd_opt(parse_number(&self.year)? as i32, 1, 1))?;
+
+        Utc.from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+            .single()
+    }
+
+    /// Method returns the default rating out of 10.
+    pub fn rating(&self) -> Option<f64> {
+        let ratings = self.ratings.as_ref().map(|x| x.rating.as_slice());
+        let rating = ratings.and_then(|x| {
+            x.iter()
+                .find(|x| x.default.as_deref() == Some("true"))
+                .or_else(|| x.first())
+        });
+
+        if let Some(rating) = rating {
+            let value: f64 = non_empty(&rating.value)?.parse().ok()?;
+            let max: f64 = non_empty(&rating.max)
+                .and_then(|x| x.parse().ok())
+                .unwrap_or(10.0);
+
+            return Some(value / max * 10.0);
+        }
+
+        non_empty(&self.rating)?.parse().ok()
+    }
+
+    pub fn duration(&self) -> Option<Duration> {
+        parse_number(&self.runtime).map(|x| Duration::from_secs(x * 60))
+    }
+
+    pub fn genres(&self) -> Vec<String> {
+        self.genre
+            .iter()
+            // some tools write all genres into one element.
+            .flat_map(|x| x.split('/'))
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .map(ToOwned::to_owned)
+            .collect()
+    }
+
+    /// Method returns the external ids the sidecar references, as a list of `(type, id)` pairs.
+    pub fn unique_ids(&self) -> Vec<(String, String)> {
+        let mut ids = self
+            .uniqueid
+            .iter()
+            .filter_map(|x| Some((non_empty(&x.kind)?, non_empty(&x.value)?)))
+            .collect::<Vec<_>>();
+
+        if let Some(id) = non_empty(&self.tmdbid) {
+            ids.push(("tmdb".into(), id));
+        }
+
+        if let Some(id) = non_empty(&self.id).filter(|x| x.starts_with("tt")) {
+            ids.push(("imdb".into(), id));
+        }
+
+        ids
+    }
+
+    pub fn tmdb_id(&self) -> Option<String> {
+        self.unique_ids()
+            .into_iter()
+            .find(|(kind, _)| kind == "tmdb")
+            .map(|(_, id)| id)
+    }
+
+    fn thumbs(&self, dir: &Path, filter: impl Fn(&NfoThumb) -> bool) -> Vec<String> {
+        self.thumb
+            .iter()
+            .filter(|x| filter(x))
+            .filter_map(|x| resolve_artwork(dir, x.value.as_deref()?))
+            .collect()
+    }
+
+    pub fn posters(&self, dir: &Path) -> Vec<String> {
+        self.thumbs(dir, |x| {
+            x.season.is_none() && matches!(x.aspect.as_deref(), None | Some("poster"))
+        })
+    }
+
+    pub fn season_posters(&self, dir: &Path, season: u64) -> Vec<String> {
+        self.thumbs(dir, |x| {
+            parse_number(&x.season) == Some(season)
+                && matches!(x.aspect.as_deref(), None | Some("poster"))
+        })
+    }
+
+    pub fn backdrops(&self, dir: &Path) -> Vec<String> {
+        self.fanart
+            .iter()
+            .flat_map(|x| x.thumb.iter())
+            .filter_map(|x| resolve_artwork(dir, x.value.as_deref()?))
+            .chain(self.thumbs(dir, |x| x.aspect.as_deref() == Some("fanart")))
+            .collect()
+    }
+
+    fn into_media(self, path: &Path) -> ExternalMedia {
+        let dir = path.parent().unwrap_or(path);
+
+        ExternalMedia {
+            external_id: id_for(path),
+            title: self.title().unwrap_or_else(|| {
+                dir.file_name()
+                    .map(|x| x.to_string_lossy().to_string())
+                    .unwrap_or_default()
+            }),
+            description: self.description(),
+            release_date: self.release_date(),
+            posters: self.posters(dir),
+            backdrops: self.backdrops(dir),
+            genres: self.genres(),
+            rating: self.rating(),
+            duration: self.duration(),
+        }
+    }
+
+    fn into_episode(self, path: &Path) -> Option<ExternalEpisode> {
+        let dir = path.parent().unwrap_or(path);
+
+        Some(ExternalEpisode {
+            external_id: id_for(path),
+            episode_number: parse_number(&self.episode)?,
+            title: self.title(),
+            description: self.description(),
+            stills: self.posters(dir),
+            duration: self.duration(),
+            air_date: non_empty(&self.aired)
+                .and_then(|x| NaiveDate::parse_from_str(&x, "%Y-%m-%d").ok()),
+        })
+    }
+}
+
+/// Function looks for the sidecar describing the movie `file`.
+fn movie_sidecar(file: &Path) -> Option<PathBuf> {
+    let dir = file.parent()?;
+
+    [file.with_extension("nfo"), dir.join("movie.nfo")]
+        .into_iter()
+        .find(|x| x.is_file())
+}
+
+/// Function looks for the `tvshow.nfo` of the show that the episode `file` belongs to. The search
+/// stops at the location of the library that `file` is stored in.
+fn show_sidecar(file: &Path, locations: &[PathBuf]) -> Option<PathBuf> {
+    file.ancestors()
+        .skip(1)
+        .take(MAX_SHOW_DEPTH)
+        .take_while(|dir| locations.iter().any(|x| dir.starts_with(x)))
+        .map(|x| x.join("tvshow.nfo"))
+        .find(|x| x.is_file())
+}
+
+/// All the sidecars found in the folder of a show.
+#[derive(Debug, Default)]
+struct ShowIndex {
+    show: Nfo,
+    seasons: Vec<(PathBuf, Nfo)>,
+    episodes: Vec<(PathBuf, Nfo)>,
+}
+
+impl ShowIndex {
+    fn build(show_nfo: &Path) -> Option<Self> {
+        fn walk(dir: &Path, depth: usize, index: &mut ShowIndex) {
+            let Ok(entries) = std::fs::read_dir(dir) else {
+                return;
+            };
+
+            for path in entries.filter_map(|x| x.ok()).map(|x| x.path()) {
+                if path.is_dir() {
+                    if depth > 0 {
+                        walk(&path, depth - 1, index);
+                    }
+                    continue;
+                }
+
+                if path.extension().and_then(|x| x.to_str()) != Some("nfo") {
+                    continue;
+                }
+
+                let Some((kind, nfo)) = std::fs::read_to_string(&path).ok().and_then(|x| parse(&x))
+                else {
+                    continue;
+                };
+
+                match kind {
+                    NfoKind::Season => index.seasons.push((path, nfo)),
+                    NfoKind::Episode => index.episodes.push((path, nfo)),
+                    _ => {}
+                }
+            }
+        }
+
+        let mut index = Self {
+            show: read(show_nfo, NfoKind::TvShow)?,
+            ..Default::default()
+        };
+
+        walk(show_nfo.parent()?, MAX_SHOW_DEPTH - 1, &mut index);
+
+        Some(index)
+    }
+
+    fn season_numbers(&self) -> Vec<u64> {
+        let mut numbers = self
+            .episodes
+            .iter()
+            .filter_map(|(_, x)| parse_number(&x.season))
+            .collect::<Vec<_>>();
+
+        numbers.sort_unstable();
+        numbers.dedup();
+        numbers
+    }
+
+    fn season(&self, show_id: &str, show_dir: &Path, number: u64) -> ExternalSeason {
+        let season_nfo = self.seasons.iter().find(|(_, x)| {
+            parse_number(&x.seasonnumber).or_else(|| parse_number(&x.season)) == Some(number)
+        });
+
+        let mut posters = self.show.season_posters(show_dir, number);
+
+        if let Some((path, nfo)) = season_nfo {
+            posters.extend(nfo.posters(path.parent().unwrap_or(show_dir)));
+        }
+
+        ExternalSeason {
+            external_id: format!("{show_id}?season={number}"),
+            title: season_nfo.and_then(|(_, x)| x.title()),
+            description: season_nfo.and_then(|(_, x)| x.description()),
+            posters,
+            season_number: number,
+        }
+    }
+}
+
+/// Metadata provider backed by `.nfo` sidecar files.
+#[derive(Debug, Clone)]
+pub struct NfoProvider {
+    media_type: MediaSearchType,
+    fallback: Option<Arc<dyn ExternalQueryIntoShow>>,
+    locations: Vec<PathBuf>,
+    shows: Arc<DashMap<PathBuf, (Instant, Arc<ShowIndex>)>>,
+}
+
+impl NfoProvider {
+    /// Create a provider which reads sidecars of movies.
+    pub fn movies() -> Self {
+        Self::new(MediaSearchType::Movie)
+    }
+
+    /// Create a provider which reads sidecars of tv shows.
+    pub fn tv_shows() -> Self {
+        Self::new(MediaSearchType::Tv)
+    }
+
+    fn new(media_type: MediaSearchType) -> Self {
+        Self {
+            media_type,
+            fallback: None,
+            locations: vec![],
+            shows: Default::default(),
+        }
+    }
+
+    /// Only read sidecars inside of `locations`, usually the locations of the library.
+    pub fn with_locations(
+        mut self,
+        locations: impl IntoIterator<Item = impl Into<PathBuf>>,
+    ) -> Self {
+        self.locations = locations.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Use `fallback` for files without a sidecar and to fill in missing fields.
+    pub fn with_fallback(mut self, fallback: Arc<dyn ExternalQueryIntoShow>) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    /// Method returns the path of the sidecar `external_id` refers to, or `None` if it isn't one of
+    /// our ids. Ids pointing outside of the locations of the library are rejected.
+    fn path_for(&self, external_id: &str) -> Result<Option<PathBuf>> {
+        let Some(path) = external_id.strip_prefix(ID_PREFIX).map(PathBuf::from) else {
+            return Ok(None);
+        };
+
+        let inside = path.is_absolute()
+            && !path.components().any(|x| x == Component::ParentDir)
+            && self.locations.iter().any(|x| path.starts_with(x));
+
+        if !inside {
+            warn!(
+                external_id,
+                "Refusing to read sidecar outside of the library."
+            );
+            return Err(Error::NoResults {
+                query: external_id.into(),
+                year: None,
+            });
+        }
+
+        Ok(Some(path))
+    }
+
+    async fn blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> Result<T> {
+        tokio::task::spawn_blocking(f).await.map_err(Error::other)
+    }
+
+    /// Method fills in the fields a sidecar left empty with the results of the fallback provider.
+    async fn fill_from_fallback(
+        &self,
+        mut media: ExternalMedia,
+        tmdb_id: Option<String>,
+    ) -> ExternalMedia {
+        let (Some(fallback), Some(tmdb_id)) = (self.fallback.as_ref(), tmdb_id) else {
+            return media;
+        };
+
+        let Ok(remote) = fallback.search_by_id(&tmdb_id).await else {
+            return media;
+        };
+
+        media.description = media.description.or(remote.description);
+        media.release_date = media.release_date.or(remote.release_date);
+        media.rating = media.rating.or(remote.rating);
+        media.duration = media.duration.or(remote.duration);
+
+        if media.posters.is_empty() {
+            media.posters = remote.posters;
+        }
+
+        if media.backdrops.is_empty() {
+            media.backdrops = remote.backdrops;
+        }
+
+        if media.genres.is_empty() {
+            media.genres = remote.genres;
+        }
+
+        media
+    }
+
+    async fn media_for_sidecar(&self, path: PathBuf) -> Result<ExternalMedia> {
+        let kind = match self.media_type {
+            MediaSearchType::Movie => NfoKind::Movie,
+            MediaSearchType::Tv => NfoKind::TvShow,
+        };
+
+        let nfo_path = path.clone();
+        let nfo = Self::blocking(move || read(&nfo_path, kind))
+            .await?
+            .ok_or_else(|| Error::NoResults {
+                query: id_for(&path),
+                year: None,
+            })?;
+
+        let tmdb_id = nfo.tmdb_id();
+        Ok(self
+            .fill_from_fallback(nfo.into_media(&path), tmdb_id)
+            .await)
+    }
+
+    async fn show_index(&self, show_nfo: &Path) -> Result<Arc<ShowIndex>> {
+        if let Some(entry) = self.shows.get(show_nfo) {
+            if entry.0.elapsed() < SHOW_CACHE_TTL {
+                return Ok(entry.1.clone());
+            }
+        }
+
+        let path = show_nfo.to_owned();
+        let index = Self::blocking(move || ShowIndex::build(&path))
+            .await?
+            .map(Arc::new)
+            .ok_or_else(|| Error::NoResults {
+                query: id_for(show_nfo),
+                year: None,
+            })?;
+
+        self.shows
+            .insert(show_nfo.to_owned(), (Instant::now(), index.clone()));
+
+        Ok(index)
+    }
+
+    fn fallback_show(&self) -> Option<&dyn ExternalQueryShow> {
+        self.fallback.as_ref()?.as_query_show()
+    }
+}
+
+#[async_trait]
+impl ExternalQuery for NfoProvider {
+    async fn search(&self, title: &str, year: Option<i32>) -> Result<Vec<ExternalMedia>> {
+        match self.fallback.as_ref() {
+            Some(fallback) => fallback.search(title, year).await,
+            None => Err(Error::NoResults {
+                query: title.into(),
+                year,
+            }),
+        }
+    }
+
+    async fn search_for_file(
+        &self,
+        file: &Path,
+        title: &str,
+        year: Option<i32>,
+    ) -> Result<Vec<ExternalMedia>> {
+        let media_type = self.media_type;
+        let file = file.to_owned();
+        let locations = self.locations.clone();
+        let sidecar = Self::blocking(move || match media_type {
+            MediaSearchType::Movie => movie_sidecar(&file),
+            MediaSearchType::Tv => show_sidecar(&file, &locations),
+        })
+        .await?;
+
+        if let Some(sidecar) = sidecar {
+            if let Ok(media) = self.media_for_sidecar(sidecar).await {
+                return Ok(vec![media]);
+            }
+        }
+
+        self.search(title, year).await
+    }
+
+    async fn search_by_id(&self, external_id: &str) -> Result<ExternalMedia> {
+        if let Some(path) = self.path_for(external_id)? {
+            return self.media_for_sidecar(path).await;
+        }
+
+        match self.fallback.as_ref() {
+            Some(fallback) => fallback.search_by_id(external_id).await,
+            None => Err(Error::NoResults {
+                query: external_id.into(),
+                year: None,
+            }),
+        }
+    }
+
+    async fn cast(&self, external_id: &str) -> Result<Vec<ExternalActor>> {
+        let Some(path) = self.path_for(external_id)? else {
+            return match self.fallback.as_ref() {
+                Some(fallback) => fallback.cast(external_id).await,
+                None => Ok(vec![]),
+            };
+        };
+
+        let kind = match self.media_type {
+            MediaSearchType::Movie => NfoKind::Movie,
+            MediaSearchType::Tv => NfoKind::TvShow,
+        };
+
+        let nfo = Self::blocking(move || read(&path, kind)).await?;
+
+        Ok(nfo
+            .map(|x| x.actor)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|actor| {
+                let name = non_empty(&actor.name)?;
+
+                Some(ExternalActor {
+                    external_id: name.clone(),
+                    name,
+                    profile_path: non_empty(&actor.thumb),
+                    character: non_empty(&actor.role).unwrap_or_default(),
+                })
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl ExternalQueryShow for NfoProvider {
+    async fn seasons_for_id(&self, external_id: &str) -> Result<Vec<ExternalSeason>> {
+        let Some(show_nfo) = self.path_for(external_id)? else {
+            return match self.fallback_show() {
+                Some(fallback) => fallback.seasons_for_id(external_id).await,
+                None => Err(Error::NoSeasonsFound { id: 0 }),
+            };
+        };
+
+        let index = self.show_index(&show_nfo).await?;
+        let show_dir = show_nfo.parent().unwrap_or(&show_nfo);
+
+        let mut seasons = index
+            .season_numbers()
+            .into_iter()
+            .map(|x| index.season(external_id, show_dir, x))
+            .collect::<Vec<_>>();
+
+        // NOTE: Seasons without any episode sidecars can still be matched through the fallback.
+        if let (Some(fallback), Some(tmdb_id)) = (self.fallback_show(), index.show.tmdb_id()) {
+            for season in fallback.seasons_for_id(&tmdb_id).await.unwrap_or_default() {
+                if !seasons
+                    .iter()
+                    .any(|x| x.season_number == season.season_number)
+                {
+                    seasons.push(ExternalSeason {
+                        external_id: format!("{external_id}?season={}", season.season_number),
+                        ..season
+                    });
+                }
+            }
+        }
+
+        seasons.sort_by_key(|x| x.season_number);
+
+        if seasons.is_empty() {
+            return Err(Error::NoSeasonsFound { id: 0 });
+        }
+
+        Ok(seasons)
+    }
+
+    async fn episodes_for_season(
+        &self,
+        external_id: &str,
+        season_number: u64,
+    ) -> Result<Vec<ExternalEpisode>> {
+        let Some(show_nfo) = self.path_for(external_id)? else {
+            return match self.fallback_show() {
+                Some(fallback) => {
+                    fallback
+                        .episodes_for_season(external_id, season_number)
+                        .await
+                }
+                None => Err(Error::NoEpisodesFound {
+                    id: 0,
+                    season: season_number,
+                }),
+            };
+        };
+
+        let index = self.show_index(&show_nfo).await?;
+
+        let mut episodes = index
+            .episodes
+            .iter()
+            .filter(|(_, x)| parse_number(&x.season) == Some(season_number))
+            .filter_map(|(path, x)| x.clone().into_episode(path))
+            .collect::<Vec<_>>();
+
+        // NOTE: Episodes without a sidecar can still be matched through the fallback.
+        if let (Some(fallback), Some(tmdb_id)) = (self.fallback_show(), index.show.tmdb_id()) {
+            let remote = fallback
+                .episodes_for_season(&tmdb_id, season_number)
+                .await
+                .unwrap_or_default();
+
+            for episode in remote {
+                if !episodes
+                    .iter()
+                    .any(|x| x.episode_number == episode.episode_number)
+                {
+                    episodes.push(episode);
+                }
+            }
+        }
+
+        episodes.sort_by_key(|x| x.episode_number);
+
+        if episodes.is_empty() {
+            return Err(Error::NoEpisodesFound {
+                id: 0,
+                season: season_number,
+            });
+        }
+
+        Ok(episodes)
+    }
+}
+
+impl IntoQueryShow for NfoProvider {
+    fn as_query_show<'a>(&'a self) -> Option<&'a dyn ExternalQueryShow> {
+        match self.media_type {
+            MediaSearchType::Tv => Some(self),
+            MediaSearchType::Movie => None,
+        }
+    }
+
+    fn into_query_show(self: Arc<Self>) -> Option<Arc<dyn ExternalQueryShow>> {
+        match self.media_type {
+            MediaSearchType::Tv => Some(self),
+            MediaSearchType::Movie => None,
+        }
+    }
+}
+
+impl ExternalQueryIntoShow for NfoProvider {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOVIE: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes" ?>
+<!-- created by some tool -->
+<movie>
+    <title>Inception</title>
+    <originaltitle>Inception</originaltitle>
+    <plot>A thief who steals corporate secrets.</plot>
+    <runtime>148</runtime>
+    <ratings>
+        <rating name="imdb" max="10">
+            <value>8.8</value>
+        </rating>
+        <rating name="themoviedb" max="100" default="true">
+            <value>84</value>
+        </rating>
+    </ratings>
+    <uniqueid type="imdb">tt1375666</uniqueid>
+    <uniqueid type="tmdb" default="true">27205</uniqueid>
+    <genre>Action</genre>
+    <genre>Science Fiction / Thriller</genre>
+    <thumb aspect="poster">https://example.com/poster.jpg</thumb>
+    <thumb aspect="banner">https://example.com/banner.jpg</thumb>
+    <fanart>
+        <thumb>https://example.com/fanart.jpg</thumb>
+    </fanart>
+    <premiered>2010-07-15</premiered>
+    <actor>
+        <name>Leonardo DiCaprio</name>
+        <role>Cobb</role>
+    </actor>
+</movie>
+https://www.themoviedb.org/movie/27205"#;
+
+    #[test]
+    fn parse_movie() {
+        let (kind, nfo) = parse(MOVIE).expect("Failed to parse movie nfo.");
+        assert_eq!(kind, NfoKind::Movie);
+
+        let media = nfo
+            .clone()
+            .into_media(Path::new("/movies/Inception/movie.nfo"));
+        assert_eq!(media.external_id, "nfo:///movies/Inception/movie.nfo");
+        assert_eq!(media.title, "Inception");
+        assert_eq!(
+            media.description.as_deref(),
+            Some("A thief who steals corporate secrets.")
+        );
+        assert_eq!(media.duration, Some(Duration::from_secs(148 * 60)));
+        assert_eq!(media.rating, Some(8.4));
+        assert_eq!(media.genres, vec!["Action", "Science Fiction", "Thriller"]);
+        assert_eq!(media.posters, vec!["https://example.com/poster.jpg"]);
+        assert_eq!(media.backdrops, vec!["https://example.com/fanart.jpg"]);
+        assert_eq!(
+            media.release_date,
+            Utc.with_ymd_and_hms(2010, 7, 15, 0, 0, 0).single()
+        );
+
+        assert_eq!(nfo.tmdb_id().as_deref(), Some("27205"));
+        assert_eq!(nfo.actor.len(), 1);
+    }
+
+    #[test]
+    fn parse_episode() {
+        let contents = r#"<episodedetails>
+            <title>Pilot</title>
+            <season>1</season>
+            <episode>1</episode>
+            <aired>2008-01-20</aired>
+        </episodedetails>
+        <episodedetails>
+            <title>Cat's in the Bag...</title>
+            <season>1</season>
+            <episode>2</episode>
+        </episodedetails>"#;
+
+        let (kind, nfo) = parse(contents).expect("Failed to parse episode nfo.");
+        assert_eq!(kind, NfoKind::Episode);
+
+        let episode = nfo
+            .into_episode(Path::new("/tv/Show/Season 1/S01E01.nfo"))
+            .unwrap();
+        assert_eq!(episode.title.as_deref(), Some("Pilot"));
+        assert_eq!(episode.episode_number, 1);
+        assert_eq!(episode.air_date, NaiveDate::from_ymd_opt(2008, 1, 20));
+    }
+
+    #[test]
+    fn parse_unknown_root() {
+        assert!(parse("<musicvideo><title>x</title></musicvideo>").is_none());
+        assert!(parse("https://www.themoviedb.org/movie/27205").is_none());
+    }
+
+    /// Fallback provider which returns a fixed set of metadata and counts the lookups by id.
+    #[derive(Debug, Default)]
+    struct Fallback {
+        lookups: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl ExternalQuery for Fallback {
+        async fn search(&self, title: &str, _: Option<i32>) -> Result<Vec<ExternalMedia>> {
+            Ok(vec![ExternalMedia {
+                external_id: "1".into(),
+                title: title.into(),
+                ..Default::default()
+            }])
+        }
+
+        async fn search_by_id(&self, external_id: &str) -> Result<ExternalMedia> {
+            self.lookups
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+
+            Ok(ExternalMedia {
+                external_id: external_id.into(),
+                title: "Remote".into(),
+                description: Some("Remote description.".into()),
+                posters: vec!["https://example.com/remote.jpg".into()],
+                genres: vec!["Drama".into()],
+                ..Default::default()
+            })
+        }
+
+        async fn cast(&self, _: &str) -> Result<Vec<ExternalActor>> {
+            Ok(vec![])
+        }
+    }
+
+    #[async_trait]
+    impl ExternalQueryShow for Fallback {
+        async fn seasons_for_id(&self, _: &str) -> Result<Vec<ExternalSeason>> {
+            Ok((1..=2)
+                .map(|season_number| ExternalSeason {
+                    season_number,
+                    ..Default::default()
+                })
+                .collect())
+        }
+
+        async fn episodes_for_season(&self, _: &str, _: u64) -> Result<Vec<ExternalEpisode>> {
+            Ok((1..=3)
+                .map(|episode_number| ExternalEpisode {
+                    external_id: format!("remote-{episode_number}"),
+                    episode_number,
+                    ..Default::default()
+                })
+                .collect())
+        }
+    }
+
+    impl IntoQueryShow for Fallback {
+        fn as_query_show<'a>(&'a self) -> Option<&'a dyn ExternalQueryShow> {
+            Some(self)
+        }
+
+        fn into_query_show(self: Arc<Self>) -> Option<Arc<dyn ExternalQueryShow>> {
+            Some(self)
+        }
+    }
+
+    impl ExternalQueryIntoShow for Fallback {}
+
+    /// Function creates a empty directory for a test and writes `files` into it.
+    fn library(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("dim-nfo-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+
+        for (path, contents) in files {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+
+        root
+    }
+
+    #[tokio::test]
+    async fn search_for_file() {
+        let root = library(
+            "search",
+            &[
+                ("Inception (2010)/Inception (2010).mkv", ""),
+                ("Inception (2010)/movie.nfo", MOVIE),
+                ("Other (2020)/Other (2020).mkv", ""),
+            ],
+        );
+
+        let provider = NfoProvider::movies().with_locations([&root]);
+
+        let result = provider
+            .search_for_file(
+                &root.join("Inception (2010)/Inception (2010).mkv"),
+                "Inception",
+                Some(2010),
+            )
+            .await
+            .expect("Failed to read sidecar.");
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].title, "Inception");
+        assert_eq!(
+            result[0].external_id,
+            id_for(&root.join("Inception (2010)/movie.nfo"))
+        );
+
+        // the id we handed out can be resolved again.
+        let media = provider
+            .search_by_id(&result[0].external_id)
+            .await
+            .expect("Failed to resolve nfo id.");
+        assert_eq!(media.title, "Inception");
+
+        // without a sidecar or fallback there is nothing to match against.
+        assert!(provider
+            .search_for_file(&root.join("Other (2020)/Other (2020).mkv"), "Other", None)
+            .await
+            .is_err());
+
+        let provider = provider.with_fallback(Arc::new(Fallback::default()));
+        let result = provider
+            .search_for_file(&root.join("Other (2020)/Other (2020).mkv"), "Other", None)
+            .await
+            .expect("Failed to search fallback.");
+        assert_eq!(result[0].external_id, "1");
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn ids_outside_of_library() {
+        let root = library("outside", &[("Inception (2010)/movie.nfo", MOVIE)]);
+        let outside = library("outside-other", &[("movie.nfo", MOVIE)]);
+
+        let provider = NfoProvider::movies().with_locations([root.join("Inception (2010)")]);
+
+        assert!(provider
+            .search_by_id(&id_for(&root.join("Inception (2010)/movie.nfo")))
+            .await
+            .is_ok());
+        assert!(provider
+            .search_by_id(&id_for(&outside.join("movie.nfo")))
+            .await
+            .is_err());
+        assert!(provider
+            .search_by_id(&id_for(
+                &root
+                    .join("Inception (2010)/../..")
+                    .join(outside.file_name().unwrap())
+                    .join("movie.nfo")
+            ))
+            .await
+            .is_err());
+        assert!(provider.search_by_id("nfo://movie.nfo").await.is_err());
+        assert!(provider.cast("nfo:///etc/passwd").await.is_err());
+
+        std::fs::remove_dir_all(root).unwrap();
+        std::fs::remove_dir_all(outside).unwrap();
+    }
+
+    #[tokio::test]
+    async fn show_sidecar_outside_of_library() {
+        let root = library(
+            "show-outside",
+            &[
+                ("tvshow.nfo", "<tvshow><title>Outside</title></tvshow>"),
+                ("Show/Season 1/S01E01.mkv", ""),
+            ],
+        );
+        let file = root.join("Show/Season 1/S01E01.mkv");
+
+        // the `tvshow.nfo` above the location of the library must not be picked up.
+        let provider = NfoProvider::tv_shows().with_locations([root.join("Show")]);
+        assert!(provider.search_for_file(&file, "Show", None).await.is_err());
+
+        let provider = NfoProvider::tv_shows().with_locations([&root]);
+        let result = provider
+            .search_for_file(&file, "Show", None)
+            .await
+            .expect("Failed to read sidecar.");
+        assert_eq!(result[0].title, "Outside");
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn fallback_merge() {
+        let root = library(
+            "merge",
+            &[(
+                "Movie/movie.nfo",
+                "<movie><title>Local</title><tmdbid>42</tmdbid><genre>Comedy</genre></movie>",
+            )],
+        );
+
+        let fallback = Arc::new(Fallback::default());
+        let provider = NfoProvider::movies()
+            .with_locations([&root])
+            .with_fallback(fallback.clone());
+
+        let media = provider
+            .search_by_id(&id_for(&root.join("Movie/movie.nfo")))
+            .await
+            .expect("Failed to read sidecar.");
+
+        // fields set in the sidecar win, the rest is filled in from the fallback.
+        assert_eq!(media.title, "Local");
+        assert_eq!(media.genres, vec!["Comedy"]);
+        assert_eq!(media.description.as_deref(), Some("Remote description."));
+        assert_eq!(media.posters, vec!["https://example.com/remote.jpg"]);
+        assert_eq!(
+            fallback.lookups.load(std::sync::atomic::Ordering::SeqCst),
+            1
+        );
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn show_index() {
+        let root = library(
+            "show",
+            &[
+                (
+                    "Show/tvshow.nfo",
+                    "<tvshow><title>Show</title><tmdbid>7</tmdbid></tvshow>",
+                ),
+                (
+                    "Show/Season 1/S01E01.nfo",
+                    "<episodedetails><title>Pilot</title><season>1</season><episode>1</episode></episodedetails>",
+                ),
+                (
+                    "Show/Season 1/S01E02.nfo",
+                    "<episodedetails><title>Second</title><season>1</season><episode>2</episode></episodedetails>",
+                ),
+            ],
+        );
+
+        let show_id = id_for(&root.join("Show/tvshow.nfo"));
+        let provider = NfoProvider::tv_shows().with_locations([&root]);
+
+        let seasons = provider
+            .seasons_for_id(&show_id)
+            .await
+            .expect("Failed to get seasons.");
+        assert_eq!(
+            seasons.iter().map(|x| x.season_number).collect::<Vec<_>>(),
+            vec![1]
+        );
+
+        let episodes = provider
+            .episodes_for_season(&show_id, 1)
+            .await
+            .expect("Failed to get episodes.");
+        assert_eq!(
+            episodes
+                .iter()
+                .map(|x| x.title_or_episode())
+                .collect::<Vec<_>>(),
+            vec!["Pilot", "Second"]
+        );
+
+        // sidecars added while the index is cached are only picked up once it expires.
+        std::fs::write(
+            root.join("Show/Season 1/S01E03.nfo"),
+            "<episodedetails><season>1</season><episode>3</episode></episodedetails>",
+        )
+        .unwrap();
+
+        let episodes = provider.episodes_for_season(&show_id, 1).await.unwrap();
+        assert_eq!(episodes.len(), 2);
+
+        provider.shows.clear();
+        let episodes = provider.episodes_for_season(&show_id, 1).await.unwrap();
+        assert_eq!(episodes.len(), 3);
+
+        // episodes and seasons without sidecars come from the fallback.
+        let provider = provider.with_fallback(Arc::new(Fallback::default()));
+        let seasons = provider.seasons_for_id(&show_id).await.unwrap();
+        assert_eq!(seasons.len(), 2);
+        assert_eq!(seasons[1].external_id, format!("{show_id}?season=2"));
+
+        let episodes = provider.episodes_for_season(&show_id, 2).await.unwrap();
+        assert_eq!(
+            episodes
+                .iter()
+                .map(|x| x.external_id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["remote-1", "remote-2", "remote-3"]
+        );
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -207,7 +207,8 @@ mod tests {
             description: Some("Holden and his allies must stop Ashford and his team from destroying the Ring, and perhaps all of humanity.".into()),
             episode_number: 13,
             stills: vec!["https://image.tmdb.org/t/p/original/nE5kS7hHGmv3bTGVL1hlsVQKXo4.jpg".into()],
-            duration: None
+            duration: None,
+            air_date: chrono::NaiveDate::from_ymd_opt(2018, 6, 27),
         };
 
         assert_eq!(last, expected);
@@ -194,6 +194,7 @@ pub struct TvEpisode {
     pub still_path: Option<String>,
     pub vote_average: Option<f64>,
     pub vote_count: Option<u64>,
+    pub air_date: Option<String>,
 }
 
 impl From<TvEpisode> for ExternalEpisode {
@@ -204,6 +205,7 @@ impl From<TvEpisode> for ExternalEpisode {
             episode_number,
             overview,
             still_path,
+            air_date,
             ..
         } = episode;
 
@@ -216,6 +218,7 @@ impl From<TvEpisode> for ExternalEpisode {
                 .unwrap_or_default(),
             episode_number,
             duration: None,
+            air_date: air_date.and_then(|x| chrono::NaiveDate::parse_from_str(&x, "%Y-%m-%d").ok()),
         }
     }
 }
@@ -436,3 +436,57 @@ pub fn lang_from_iso639(tag: &str) -> Option<&'static str> {
         .find(|x| x.v2b() == tag)
         .map(|x| x.name())
 }
+
+/// Function normalizes a ISO 639-1 or ISO 639-2 language tag, like the ones found in the filenames
+/// of subtitle files, into a ISO 639-2/B code.
+pub fn iso639_from_tag(tag: &str) -> Option<&'static str> {
+    let tag = tag.to_lowercase();
+    let code = match tag.as_str() {
+        "ar" => "ara",
+        "bg" => "bul",
+        "ca" => "cat",
+        "cs" | "ces" => "cze",
+        "da" => "dan",
+        "de" | "deu" => "ger",
+        "el" | "ell" => "gre",
+        "en" => "eng",
+        "es" => "spa",
+        "et" => "est",
+        "fa" | "fas" => "per",
+        "fi" => "fin",
+        "fr" | "fra" => "fre",
+        "he" => "heb",
+        "hr" => "hrv",
+        "hu" => "hun",
+        "id" => "ind",
+        "is" | "isl" => "ice",
+        "it" => "ita",
+        "ja" => "jpn",
+        "ko" => "kor",
+        "lt" => "lit",
+        "lv" => "lav",
+        "ms" | "msa" => "may",
+        "nb" => "nob",
+        "nl" | "nld" => "dut",
+        "no" => "nor",
+        "pl" => "pol",
+        "pt" => "por",
+        "ro" | "ron" => "rum",
+        "ru" => "rus",
+        "sk" | "slk" => "slo",
+        "sl" => "slv",
+        "sr" => "srp",
+        "sv" => "swe",
+        "th" => "tha",
+        "tr" => "tur",
+        "uk" => "ukr",
+        "vi" => "vie",
+        "zh" | "zho" => "chi",
+        x => x,
+    };
+
+    dia_i18n::iso_639::LANG_CODES
+        .iter()
+        .find(|x| x.v2b() == code)
+        .map(|x| x.v2b())
+}
@@ -53,6 +53,7 @@ impl IntoResponse for DimErrorWrapper {
             DimError::InvalidCredentials => StatusCode::UNAUTHORIZED,
             DimError::InvalidMediaType => StatusCode::BAD_REQUEST,
             DimError::MissingFieldInBody { .. } => StatusCode::BAD_REQUEST,
+            DimError::InvalidLibrarySettings { .. } => StatusCode::BAD_REQUEST,
             DimError::UnsupportedFile => StatusCode::UNSUPPORTED_MEDIA_TYPE,
             DimError::LibraryNotFound => StatusCode::NOT_FOUND,
             DimError::NoToken => StatusCode::BAD_REQUEST,
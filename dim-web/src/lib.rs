@@ -47,7 +47,30 @@ fn library_routes(_app: AppState) -> Router<AppState> {
         )
         .route(
             "/api/v1/library/:id",
-            get(routes::library::library_get_one).delete(routes::library::library_delete),
+            get(routes::library::library_get_one)
+                .delete(routes::library::library_delete)
+                .patch(routes::library::library_patch),
+        )
+        .route(
+            "/api/v1/library/:id/scan",
+            post(routes::library::library_scan).delete(routes::library::library_cancel_scan),
+        )
+        .route(
+            "/api/v1/library/:id/low_confidence",
+            get(routes::library::library_get_low_confidence),
+        )
+        .route(
+            "/api/v1/library/:id/problems",
+            get(routes::library::library_get_problems),
+        )
+        .route(
+            "/api/v1/library/:id/parse",
+            post(routes::library::library_parse),
+        )
+        .route("/api/v1/scans", get(routes::library::get_scans))
+        .route(
+            "/api/v1/missing",
+            get(routes::library::get_missing).delete(routes::library::purge_missing),
         )
         .route(
             "/api/v1/library/:id/unmatched",
@@ -55,6 +78,20 @@ fn library_routes(_app: AppState) -> Router<AppState> {
         )
 }
 
+fn music_routes(_app: AppState) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/api/v1/library/:id/artists",
+            get(routes::music::get_library_artists),
+        )
+        .route("/api/v1/artist/:id", get(routes::music::get_artist_by_id))
+        .route("/api/v1/album/:id", get(routes::music::get_album_by_id))
+        .route(
+            "/api/v1/album/:id/tracks",
+            get(routes::music::get_album_tracks),
+        )
+}
+
 fn auth_routes(AppState { .. }: AppState) -> Router<AppState> {
     Router::new()
         .route("/api/v1/auth/login", post(routes::auth::login))
@@ -74,6 +111,14 @@ fn media_routes(AppState { .. }: AppState) -> Router<AppState> {
             "/api/v1/media/:id/files",
             get(routes::media::get_media_files),
         )
+        .route(
+            "/api/v1/media/:id/version",
+            post(routes::media::set_preferred_version),
+        )
+        .route(
+            "/api/v1/media/:id/extras",
+            get(routes::media::get_media_extras),
+        )
         .route(
             "/api/v1/media/:id/tree",
             get(routes::media::get_mediafile_tree),
@@ -244,8 +289,21 @@ pub async fn start_webserver(
             "/api/v1/mediafile/match",
             patch(routes::mediafile::rematch_mediafile),
         )
+        .route(
+            "/api/v1/mediafile/:id/episode",
+            patch(routes::mediafile::assign_episode),
+        )
+        .route(
+            "/api/v1/mediafile/:id/probe",
+            post(routes::mediafile::probe_mediafile),
+        )
         .route("/api/v1/tv/:id/season", get(routes::tv::get_tv_seasons))
+        .route(
+            "/api/v1/tv/:id/ordering",
+            patch(routes::tv::patch_tv_ordering),
+        )
         .merge(season_routes(app.clone()))
+        .merge(music_routes(app.clone()))
         .route(
             "/api/v1/episode/:id",
             patch(routes::tv::patch_episode_by_id).delete(routes::tv::delete_episode_by_id),
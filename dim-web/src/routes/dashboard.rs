@@ -29,7 +29,8 @@ pub async fn banners(
         if let Ok(x) = match media.media_type {
             MediaType::Tv => banner_for_show(&mut tx, &user, &media).await,
             MediaType::Movie => banner_for_movie(&mut tx, &user, &media).await,
-            _ => unreachable!(),
+            // NOTE: Banners only make sense for video content.
+            _ => continue,
         } {
             banners.push(x);
         }
@@ -9,14 +9,21 @@ use axum::Extension;
 use axum::Json;
 
 use dim_core::errors::DimError;
+use dim_core::scanner::confidence::LOW_CONFIDENCE;
+use dim_core::scanner::daemon;
 use dim_core::scanner::daemon::FsWatcher;
+use dim_core::scanner::jobs::ScanJob;
+use dim_core::scanner::missing;
+use dim_core::scanner::parse_filenames;
+use dim_core::scanner::scan_jobs;
+use dim_core::scanner::schedule::Schedule;
+use dim_core::scanner::FilenameParser;
 use dim_database::compact_mediafile::CompactMediafile;
-use dim_database::library::{InsertableLibrary, Library, MediaType};
+use dim_database::library::{FilenameRules, InsertableLibrary, Library, UpdateLibrary};
 use dim_database::media::Media;
 use dim_database::mediafile::MediaFile;
 use dim_database::user::User;
-
-use dim_extern_api::tmdb::TMDBMetadataProvider;
+use dim_extern_api::filename::RegexExtractor;
 
 use fuzzy_matcher::skim::SkimMatcherV2;
 use fuzzy_matcher::FuzzyMatcher;
@@ -43,6 +50,29 @@ pub async fn library_post(
         )
             .into_response();
     }
+
+    if let Some(schedule) = new_library.rescan_schedule.as_ref() {
+        if let Err(err) = Schedule::parse(schedule) {
+            return DimErrorWrapper(DimError::InvalidLibrarySettings {
+                description: err.to_string(),
+            })
+            .into_response();
+        }
+    }
+
+    if let Err(description) = RegexExtractor::new(&new_library.filename_rules.0) {
+        return DimErrorWrapper(DimError::InvalidLibrarySettings { description }).into_response();
+    }
+
+    // NOTE: albums, tracks and episodes are media types, libraries can't hold them on their own.
+    let Some(provider) = dim_core::core::provider_for(
+        new_library.media_type,
+        new_library.metadata_provider,
+        &new_library.locations,
+    ) else {
+        return DimErrorWrapper(DimError::InvalidMediaType).into_response();
+    };
+
     let mut lock = state.conn.writer().lock_owned().await;
 
     let mut tx = match dim_database::write_tx(&mut lock).await {
@@ -72,26 +102,18 @@ pub async fn library_post(
 
     let tx_clone = state.event_tx.clone();
 
-    const TMDB_KEY: &str = "38c372f5bc572c8aadde7a802638534e";
-    let provider = TMDBMetadataProvider::new(TMDB_KEY);
-
-    let provider = match new_library.media_type {
-        MediaType::Movie => Arc::new(provider.movies()) as Arc<_>,
-        MediaType::Tv => Arc::new(provider.tv_shows()) as Arc<_>,
-        _ => unreachable!(),
-    };
-
-    let mut fs_watcher = FsWatcher::new(
+    match FsWatcher::new(
         state.conn.clone(),
         id,
         new_library.media_type,
         tx_clone.clone(),
         Arc::clone(&provider),
-    );
+    ) {
+        Ok(fs_watcher) => daemon::spawn_watcher(fs_watcher),
+        Err(err) => tracing::error!(?err, "Failed to start the fs-watcher"),
+    }
 
-    tokio::spawn(async move { fs_watcher.start_daemon().await });
-    let mut conn = state.conn.clone();
-    tokio::spawn(async move { dim_core::scanner::start(&mut conn, id, tx_clone, provider).await });
+    scan_jobs().enqueue(state.conn.clone(), id, tx_clone, provider);
 
     Json(serde_json::json!({ "id": id })).into_response()
 }
@@ -129,6 +151,11 @@ pub async fn library_delete(
         })?;
     }
 
+    // Scans and the watcher would otherwise keep inserting files into the library while we delete
+    // it.
+    scan_jobs().cancel(id);
+    daemon::stop_watcher(id);
+
     let delete_lib_fut = async move {
         let inner = async {
             let mut lock = conn.writer().lock_owned().await;
@@ -155,6 +182,315 @@ pub async fn library_delete(
     Ok(StatusCode::NO_CONTENT)
 }
 
+/// Method mapped to `PATCH /api/v1/library/<id>` updates the settings, like the scan rules or the
+/// rescan schedule, of the library with the supplied id. New scan rules take effect on the next
+/// scan, the watcher of the library is restarted so that it picks up the new settings right away.
+pub async fn library_patch(
+    Extension(user): Extension<User>,
+    State(AppState { conn, event_tx, .. }): State<AppState>,
+    Path(id): Path<i64>,
+    Json(data): Json<UpdateLibrary>,
+) -> Result<StatusCode, DimErrorWrapper> {
+    if !user.has_role("owner") {
+        return Err(DimErrorWrapper(DimError::Unauthorized));
+    }
+
+    if let Some(Some(schedule)) = data.rescan_schedule.as_ref() {
+        Schedule::parse(schedule).map_err(|err| {
+            DimErrorWrapper(DimError::InvalidLibrarySettings {
+                description: err.to_string(),
+            })
+        })?;
+    }
+
+    if let Some(rules) = data.filename_rules.as_ref() {
+        RegexExtractor::new(&rules.0).map_err(|description| {
+            DimErrorWrapper(DimError::InvalidLibrarySettings { description })
+        })?;
+    }
+
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = dim_database::write_tx(&mut lock).await.map_err(|err| {
+        DimErrorWrapper(DimError::DatabaseError {
+            description: err.to_string(),
+        })
+    })?;
+
+    if Library::get_one(&mut tx, id).await.is_err() {
+        return Err(DimError::LibraryNotFound.into());
+    }
+
+    data.update(&mut tx, id).await.map_err(|err| {
+        DimErrorWrapper(DimError::DatabaseError {
+            description: err.to_string(),
+        })
+    })?;
+
+    let library = Library::get_one(&mut tx, id).await.map_err(|err| {
+        DimErrorWrapper(DimError::DatabaseError {
+            description: err.to_string(),
+        })
+    })?;
+
+    tx.commit().await.map_err(|err| {
+        DimErrorWrapper(DimError::DatabaseError {
+            description: err.to_string(),
+        })
+    })?;
+    drop(lock);
+
+    let provider = dim_core::core::provider_for(
+        library.media_type,
+        library.metadata_provider,
+        &library.locations,
+    );
+
+    if let Some(provider) = provider {
+        match FsWatcher::new(conn, id, library.media_type, event_tx, provider) {
+            Ok(fs_watcher) => daemon::spawn_watcher(fs_watcher),
+            Err(err) => tracing::error!(?err, "Failed to restart the fs-watcher"),
+        }
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+pub struct ParseArgs {
+    path: String,
+    /// Rules to try instead of the rules stored for the library.
+    #[serde(default)]
+    filename_rules: Option<FilenameRules>,
+}
+
+/// Method mapped to `POST /api/v1/library/<id>/parse` shows the metadata the scanner would extract
+/// from the filename of a sample path, which makes it easy to try out filename rules before
+/// saving them. Returns the result of the filename rules alone, under `rule`, and the candidates of
+/// all parsers ranked by their confidence, under `metadata`.
+pub async fn library_parse(
+    Extension(user): Extension<User>,
+    State(AppState { conn, .. }): State<AppState>,
+    Path(id): Path<i64>,
+    Json(args): Json<ParseArgs>,
+) -> Result<Json<serde_json::Value>, DimErrorWrapper> {
+    if !user.has_role("owner") {
+        return Err(DimErrorWrapper(DimError::Unauthorized));
+    }
+
+    let mut tx = conn.read().begin().await.map_err(|err| {
+        DimErrorWrapper(DimError::DatabaseError {
+            description: err.to_string(),
+        })
+    })?;
+
+    let library = Library::get_one(&mut tx, id)
+        .await
+        .map_err(|_| DimErrorWrapper(DimError::LibraryNotFound))?;
+    drop(tx);
+
+    let rules = args.filename_rules.unwrap_or(library.filename_rules);
+    let extractor = RegexExtractor::new(&rules.0)
+        .map_err(|description| DimErrorWrapper(DimError::InvalidLibrarySettings { description }))?;
+
+    let path = std::path::Path::new(&args.path);
+    let rule = path
+        .file_stem()
+        .and_then(|x| x.to_str())
+        .and_then(|x| extractor.extract(x));
+    let parser = FilenameParser::new(library.media_type, &rules, &library.locations);
+    let metadata = parse_filenames(std::iter::once(path), &parser)
+        .pop()
+        .map(|(_, x)| x)
+        .unwrap_or_default();
+
+    Ok(Json(serde_json::json!({
+        "rule": rule,
+        "metadata": metadata,
+    })))
+}
+
+/// Method mapped to `POST /api/v1/library/<id>/scan` queues a scan of the library with the
+/// supplied id. If a scan of the library is already queued, that job is returned instead.
+pub async fn library_scan(
+    Extension(user): Extension<User>,
+    State(AppState { conn, event_tx, .. }): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<ScanJob>, DimErrorWrapper> {
+    if !user.has_role("owner") {
+        return Err(DimErrorWrapper(DimError::Unauthorized));
+    }
+
+    let mut tx = conn.read().begin().await.map_err(|err| {
+        DimErrorWrapper(DimError::DatabaseError {
+            description: err.to_string(),
+        })
+    })?;
+
+    let library = Library::get_one(&mut tx, id)
+        .await
+        .map_err(|_| DimErrorWrapper(DimError::LibraryNotFound))?;
+    drop(tx);
+
+    let provider = dim_core::core::provider_for(
+        library.media_type,
+        library.metadata_provider,
+        &library.locations,
+    )
+    .ok_or(DimErrorWrapper(DimError::InvalidMediaType))?;
+
+    Ok(Json(scan_jobs().enqueue(conn, id, event_tx, provider)))
+}
+
+/// Method mapped to `DELETE /api/v1/library/<id>/scan` cancels the queued and running scans of
+/// the library with the supplied id. Returns the jobs that got cancelled.
+pub async fn library_cancel_scan(
+    Extension(user): Extension<User>,
+    Path(id): Path<i64>,
+) -> Result<Json<Vec<ScanJob>>, DimErrorWrapper> {
+    if !user.has_role("owner") {
+        return Err(DimErrorWrapper(DimError::Unauthorized));
+    }
+
+    let cancelled = scan_jobs().cancel(id);
+
+    if cancelled.is_empty() {
+        return Err(DimErrorWrapper(DimError::NotFoundError));
+    }
+
+    Ok(Json(cancelled))
+}
+
+/// Method mapped to `GET /api/v1/scans` returns all queued, running and recently finished scans.
+pub async fn get_scans(
+    Extension(user): Extension<User>,
+) -> Result<Json<Vec<ScanJob>>, DimErrorWrapper> {
+    if !user.has_role("owner") {
+        return Err(DimErrorWrapper(DimError::Unauthorized));
+    }
+
+    Ok(Json(scan_jobs().list()))
+}
+
+#[derive(Deserialize)]
+pub struct MissingArgs {
+    library_id: Option<i64>,
+}
+
+/// Method mapped to `GET /api/v1/missing` returns all mediafiles that couldn't be found on disk,
+/// oldest first. The files can be limited to a library with the `library_id` query parameter.
+pub async fn get_missing(
+    Extension(user): Extension<User>,
+    State(AppState { conn, .. }): State<AppState>,
+    Query(params): Query<MissingArgs>,
+) -> Result<Json<Vec<MediaFile>>, DimErrorWrapper> {
+    if !user.has_role("owner") {
+        return Err(DimErrorWrapper(DimError::Unauthorized));
+    }
+
+    let mut tx = conn.read().begin().await.map_err(|err| {
+        DimErrorWrapper(DimError::DatabaseError {
+            description: err.to_string(),
+        })
+    })?;
+
+    let files = MediaFile::get_missing(&mut tx, i64::MAX)
+        .await
+        .map_err(|err| {
+            DimErrorWrapper(DimError::DatabaseError {
+                description: err.to_string(),
+            })
+        })?
+        .into_iter()
+        .filter(|x| params.library_id.map_or(true, |id| x.library_id == id))
+        .collect();
+
+    Ok(Json(files))
+}
+
+/// Method mapped to `DELETE /api/v1/missing` purges all missing mediafiles right away, without
+/// waiting for their grace period to run out. Media left without files are removed as well. The
+/// purge can be limited to a library with the `library_id` query parameter. Returns the mediafiles
+/// that have been purged.
+pub async fn purge_missing(
+    Extension(user): Extension<User>,
+    State(AppState { conn, .. }): State<AppState>,
+    Query(params): Query<MissingArgs>,
+) -> Result<Json<Vec<MediaFile>>, DimErrorWrapper> {
+    if !user.has_role("owner") {
+        return Err(DimErrorWrapper(DimError::Unauthorized));
+    }
+
+    let purged = missing::purge_missing(&conn, params.library_id, i64::MAX)
+        .await
+        .map_err(|err| DimErrorWrapper(DimError::ScannerError(err)))?;
+
+    Ok(Json(purged))
+}
+
+#[derive(Deserialize)]
+pub struct LowConfidenceArgs {
+    below: Option<u8>,
+}
+
+/// Method mapped to `GET /api/v1/library/<id>/low_confidence` returns the mediafiles of a library
+/// whose filenames were parsed with a low confidence, least confident first. Such files are likely
+/// to be matched wrongly and are worth a review. The threshold can be changed with the `below`
+/// query parameter.
+pub async fn library_get_low_confidence(
+    Extension(user): Extension<User>,
+    State(AppState { conn, .. }): State<AppState>,
+    Path(id): Path<i64>,
+    Query(params): Query<LowConfidenceArgs>,
+) -> Result<Json<Vec<MediaFile>>, DimErrorWrapper> {
+    if !user.has_role("owner") {
+        return Err(DimErrorWrapper(DimError::Unauthorized));
+    }
+
+    let mut tx = conn.read().begin().await.map_err(|err| {
+        DimErrorWrapper(DimError::DatabaseError {
+            description: err.to_string(),
+        })
+    })?;
+
+    let below = params.below.unwrap_or(LOW_CONFIDENCE);
+    let files = MediaFile::get_low_confidence(&mut tx, id, below.into())
+        .await
+        .map_err(|err| {
+            DimErrorWrapper(DimError::DatabaseError {
+                description: err.to_string(),
+            })
+        })?;
+
+    Ok(Json(files))
+}
+
+/// Method mapped to `GET /api/v1/library/<id>/problems` returns the mediafiles of a library which
+/// can't be played, along with the reason found out when they were probed. Files can be probed
+/// again with `POST /api/v1/mediafile/<id>/probe` once they have been fixed.
+pub async fn library_get_problems(
+    Extension(user): Extension<User>,
+    State(AppState { conn, .. }): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Vec<MediaFile>>, DimErrorWrapper> {
+    if !user.has_role("owner") {
+        return Err(DimErrorWrapper(DimError::Unauthorized));
+    }
+
+    let mut tx = conn.read().begin().await.map_err(|err| {
+        DimErrorWrapper(DimError::DatabaseError {
+            description: err.to_string(),
+        })
+    })?;
+
+    let files = MediaFile::get_problems(&mut tx, id).await.map_err(|err| {
+        DimErrorWrapper(DimError::DatabaseError {
+            description: err.to_string(),
+        })
+    })?;
+
+    Ok(Json(files))
+}
+
 /// Method mapped to `GET /api/v1/library` returns a list of all libraries in the database
 pub async fn library_get_all(State(state): State<AppState>) -> Response {
     let mut tx = match state.conn.read().begin().await {
@@ -228,7 +564,8 @@ pub async fn library_get_media(
         Record,
         r#"SELECT _tblmedia.id, name, assets.local_path as poster_path FROM _tblmedia
         LEFT JOIN assets ON _tblmedia.poster = assets.id
-        WHERE library_id = ? AND NOT media_type = "episode""#,
+        WHERE library_id = ? AND media_type NOT IN ("episode", "track")
+        AND _tblmedia.id NOT IN (SELECT id FROM missing_media)"#,
         id
     )
     .fetch_all(&mut tx)
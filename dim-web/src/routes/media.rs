@@ -12,19 +12,24 @@ use chrono::Datelike;
 use dim_core::scanner::movie;
 use dim_core::scanner::parse_filenames;
 use dim_core::scanner::tv_show;
+use dim_core::scanner::FilenameParser;
 use dim_core::scanner::MediaMatcher;
 use dim_core::scanner::WorkUnit;
 use dim_core::tree;
+use dim_core::versions;
 
 use dim_database::compact_mediafile::CompactMediafile;
 use dim_database::episode::Episode;
 use dim_database::genre::Genre;
+use dim_database::library::Library;
 use dim_database::library::MediaType;
 use dim_database::media::Media;
 use dim_database::media::UpdateMedia;
 use dim_database::mediafile::MediaFile;
+use dim_database::music::Track;
 use dim_database::progress::Progress;
 use dim_database::user::User;
+use dim_database::version::PreferredVersion;
 use dim_database::DatabaseError;
 
 use dim_extern_api::tmdb::TMDBMetadataProvider;
@@ -117,6 +122,9 @@ pub const TV_PROVIDER: Lazy<Arc<dyn ExternalQueryIntoShow>> =
 ///
 /// # Additional types
 /// [`MediaType`](`dim_database::library::MediaType`)
+///
+/// Movies, episodes and tracks additionally return their `versions` ordered by the preference of
+/// the user, and the id of the version that should be played back by `default_version`.
 pub async fn get_media_by_id(
     Path(id): Path<i64>,
     Extension(user): Extension<User>,
@@ -126,21 +134,22 @@ pub async fn get_media_by_id(
     let media = Media::get(&mut tx, id).await?;
 
     let media_id = match media.media_type {
-        MediaType::Movie | MediaType::Episode => id,
+        MediaType::Movie | MediaType::Episode | MediaType::Track => id,
         MediaType::Tv => Episode::get_first_for_show(&mut tx, id).await?.id,
+        MediaType::Album => Track::get_all_of_album(&mut tx, id)
+            .await?
+            .first()
+            .map(|x| x.id)
+            .unwrap_or(id),
+        MediaType::Music => return Err(Error::InvalidMediaType),
     };
 
     // TODO: at some point we want to issue a warning to the UI that none of the mediafiles with
     // this media have a duration (maybe because of corruption).
-    let duration = match MediaFile::get_of_media(&mut tx, media_id).await {
-        Ok(x) => x
-            .iter()
-            .filter_map(|x| x.duration)
-            .collect::<Vec<_>>()
-            .pop()
-            .unwrap_or(0),
-        Err(_) => 0,
-    };
+    // NOTE: the parts of a stacked movie are played back as one file, so their durations add up.
+    let duration = MediaFile::get_largest_duration(&mut tx, media_id)
+        .await
+        .unwrap_or(0);
 
     let genres = Genre::get_by_media(&mut tx, id)
         .await?
@@ -149,10 +158,13 @@ pub async fn get_media_by_id(
         .collect::<Vec<String>>();
 
     let progress = match media.media_type {
-        MediaType::Episode | MediaType::Movie => Progress::get_for_media_user(&mut tx, user.id, id)
-            .await
-            .map(|x| json!({"progress": x.delta}))
-            .ok(),
+        MediaType::Episode | MediaType::Movie | MediaType::Track => {
+            Progress::get_for_media_user(&mut tx, user.id, id)
+                .await
+                .map(|x| json!({"progress": x.delta}))
+                .ok()
+        }
+        MediaType::Album | MediaType::Music => None,
         MediaType::Tv => {
             if let Ok(Some(ep)) = Episode::get_last_watched_episode(&mut tx, id, user.id).await {
                 let (delta, duration) = Progress::get_progress_for_media(&mut tx, ep.id, user.id)
@@ -217,12 +229,21 @@ pub async fn get_media_by_id(
         })
     }
 
+    let versions = match media.media_type {
+        MediaType::Episode | MediaType::Movie | MediaType::Track => {
+            let picked = PreferredVersion::get(&mut tx, user.id, media.id).await?;
+            versions::rank(
+                MediaFile::get_of_media(&mut tx, media.id).await?,
+                &user.prefs.preferred_version,
+                picked,
+            )
+        }
+        _ => vec![],
+    };
+
     let quality_tags = match media.media_type {
-        MediaType::Episode | MediaType::Movie => json!({
-                media.id.to_string(): MediaFile::get_of_media(&mut tx, media.id)
-                    .await?
-                    .first()
-                    .map(mediafile_tags)
+        MediaType::Episode | MediaType::Movie | MediaType::Track => json!({
+                media.id.to_string(): versions.first().map(mediafile_tags)
         }),
         MediaType::Tv => {
             let mut result = MediaFile::get_of_show(&mut tx, media.id).await?;
@@ -234,6 +255,15 @@ pub async fn get_media_by_id(
                 .map(|x| (x.media_id.unwrap(), mediafile_tags(x)))
                 .collect::<HashMap<_, _>>())
         }
+        MediaType::Album => {
+            let result = MediaFile::get_of_album(&mut tx, media.id).await?;
+
+            json!(result
+                .iter()
+                .map(|x| (x.media_id.unwrap(), mediafile_tags(x)))
+                .collect::<HashMap<_, _>>())
+        }
+        MediaType::Music => json!({}),
     };
 
     let season_episode_tag = match media.media_type {
@@ -295,6 +325,8 @@ pub async fn get_media_by_id(
         "genres": genres,
         "duration": duration,
         "tags": quality_tags,
+        "versions": versions.iter().map(version_json).collect::<Vec<_>>(),
+        "default_version": versions.first().map(|x| x.id),
         ..?next_episode_id,
         ..?season_episode_tag,
         ..?progress
@@ -302,8 +334,28 @@ pub async fn get_media_by_id(
     .into_response())
 }
 
+/// Function returns the labelled summary of a version of a media.
+fn version_json(x: &MediaFile) -> serde_json::Value {
+    json!({
+        "id": x.id,
+        "label": versions::label(x),
+        "edition": x.edition,
+        "resolution": x.quality.as_ref().map(|x| format!("{}p", x)),
+        "hdr": x.hdr,
+        "codec": x.codec.as_deref().map(dim_core::utils::codec_pretty),
+    })
+}
+
+/// # GET `/api/v1/media/<id>/files`
+/// Method returns all mediafiles of a media. Every mediafile carries a human readable `label`.
+/// The versions of movies, episodes and tracks are ordered by the preference of the user, so the
+/// first mediafile is the one that should be played back by default.
+///
+/// # Authentication
+/// Method requires standard authentication.
 pub async fn get_media_files(
     Path(id): Path<i64>,
+    Extension(user): Extension<User>,
     State(AppState { conn, .. }): State<AppState>,
 ) -> Result<Response, Error> {
     let mut tx = conn.read().begin().await.map_err(DatabaseError::from)?;
@@ -311,12 +363,132 @@ pub async fn get_media_files(
 
     let mediafiles = match media_type {
         MediaType::Tv => MediaFile::get_of_show(&mut tx, id).await?,
-        MediaType::Episode | MediaType::Movie => MediaFile::get_of_media(&mut tx, id).await?,
+        MediaType::Album => MediaFile::get_of_album(&mut tx, id).await?,
+        MediaType::Episode | MediaType::Movie | MediaType::Track => {
+            let picked = PreferredVersion::get(&mut tx, user.id, id).await?;
+            versions::rank(
+                MediaFile::get_of_media(&mut tx, id).await?,
+                &user.prefs.preferred_version,
+                picked,
+            )
+        }
+        MediaType::Music => return Err(Error::InvalidMediaType),
     };
 
+    let mediafiles = mediafiles
+        .iter()
+        .map(|x| {
+            let mut value = serde_json::to_value(x).unwrap_or_default();
+            value["label"] = versions::label(x).into();
+            value
+        })
+        .collect::<Vec<_>>();
+
     Ok(axum::response::Json(json!(&mediafiles)).into_response())
 }
 
+#[derive(Deserialize)]
+pub struct VersionParams {
+    mediafile_id: Option<i64>,
+}
+
+/// # POST `/api/v1/media/<id>/version`
+/// Method picks the version of a media that gets played back by default for the user. Passing
+/// `null` as the `mediafile_id` resets the choice, after which the preferred-version rule in the
+/// user settings decides.
+///
+/// # Authentication
+/// Method requires standard authentication.
+///
+/// # Request Body
+/// ```text
+/// {
+///     "mediafile_id": int | null,
+/// }
+/// ```
+pub async fn set_preferred_version(
+    Path(id): Path<i64>,
+    Extension(user): Extension<User>,
+    State(AppState { conn, .. }): State<AppState>,
+    Json(params): Json<VersionParams>,
+) -> Result<impl IntoResponse, Error> {
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = dim_database::write_tx(&mut lock)
+        .await
+        .map_err(DatabaseError::from)?;
+
+    match params.mediafile_id {
+        Some(mediafile_id) => {
+            let mediafile = MediaFile::get_one(&mut tx, mediafile_id)
+                .await
+                .map_err(|_| Error::NotFoundError)?;
+
+            if mediafile.media_id != Some(id) || mediafile.extra_type.is_some() {
+                return Err(Error::NotFoundError);
+            }
+
+            PreferredVersion::set(&mut tx, user.id, id, mediafile_id).await?;
+        }
+        None => {
+            PreferredVersion::clear(&mut tx, user.id, id).await?;
+        }
+    }
+
+    tx.commit().await.map_err(DatabaseError::from)?;
+
+    Ok(StatusCode::OK)
+}
+
+/// # GET `/api/v1/media/<id>/extras`
+/// Method returns the trailers, featurettes and other extras attached to a media.
+///
+/// # Authentication
+/// Method requires standard authentication.
+///
+/// # Return Schema
+/// ```text
+/// [{
+///     "id": int,
+///     "name": string,
+///     "extra_type": string | enum,
+///     "duration": int,
+///     "duration_pretty": string,
+/// }]
+/// ```
+///
+/// # Additional types
+/// [`ExtraType`](`dim_database::mediafile::ExtraType`)
+pub async fn get_media_extras(
+    Path(id): Path<i64>,
+    State(AppState { conn, .. }): State<AppState>,
+) -> Result<Response, Error> {
+    let mut tx = conn.read().begin().await.map_err(DatabaseError::from)?;
+    Media::media_mediatype(&mut tx, id)
+        .await
+        .map_err(|_| Error::NotFoundError)?;
+
+    let extras = MediaFile::get_extras_of_media(&mut tx, id)
+        .await?
+        .into_iter()
+        .map(|x| {
+            let name = std::path::Path::new(&x.target_file)
+                .file_stem()
+                .map(|x| x.to_string_lossy().to_string())
+                .unwrap_or_else(|| x.raw_name.clone());
+
+            json!({
+                "id": x.id,
+                "name": name,
+                "extra_type": x.extra_type,
+                "duration": x.duration,
+                "duration_pretty": x.duration.map(|d| secs_to_pretty(d as u64)),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Ok(axum::response::Json(json!(&extras)).into_response())
+}
+
 /// # GET `/api/v1/media/<id>/tree`
 /// Method mappedReturns a tree of mediafiles for a given media object.
 ///
@@ -330,10 +502,12 @@ pub async fn get_mediafile_tree(
     let media_type = Media::media_mediatype(&mut tx, id).await?;
 
     let mut mediafiles = match media_type {
-        MediaType::Movie | MediaType::Episode => {
+        MediaType::Movie | MediaType::Episode | MediaType::Track => {
             CompactMediafile::all_for_media(&mut tx, id).await?
         }
         MediaType::Tv => CompactMediafile::all_for_tv(&mut tx, id).await?,
+        MediaType::Album => CompactMediafile::all_for_album(&mut tx, id).await?,
+        MediaType::Music => return Err(Error::InvalidMediaType),
     };
 
     // we want to pre-sort to ensure our tree is somewhat ordered.
@@ -487,16 +661,19 @@ pub async fn tmdb_search(Query(params): Query<TmdbSearchParams>) -> Result<Respo
 #[derive(Deserialize)]
 pub struct ProgressParams {
     offset: i64,
+    mediafile: Option<i64>,
 }
 
 /// Method mapped to `POST /api/v1/media/<id>/progress` is used to map progress for a certain media
-/// to the user. This is useful for remembering progress for a movie etc.
+/// to the user. This is useful for remembering progress for a movie etc. Files spanning several
+/// episodes map the progress to all the episodes they cover.
 ///
 /// # Arguments
 /// * `id` - id of the media to modify
 ///
 /// # Query params
 /// * `offset` - offset in seconds
+/// * `mediafile` - id of the file being played, only needed if the media has several files.
 pub async fn map_progress(
     State(AppState { conn, .. }): State<AppState>,
     Path(id): Path<i64>,
@@ -507,7 +684,24 @@ pub async fn map_progress(
     let mut tx = dim_database::write_tx(&mut lock)
         .await
         .map_err(DatabaseError::from)?;
-    Progress::set(&mut tx, params.offset, user.id, id).await?;
+
+    // NOTE: Without the file being played we can only tell which one it is if there is just one.
+    let mediafile = match params.mediafile {
+        Some(mediafile) => Some(mediafile),
+        None => match MediaFile::get_of_media(&mut tx, id).await?.as_slice() {
+            [file] => Some(file.id),
+            _ => None,
+        },
+    };
+
+    let ids = match mediafile {
+        Some(mediafile) => Episode::get_sharing_files(&mut tx, id, mediafile).await?,
+        None => vec![id],
+    };
+
+    for id in ids {
+        Progress::set(&mut tx, params.offset, user.id, id).await?;
+    }
     tx.commit().await.map_err(DatabaseError::from)?;
     Ok(StatusCode::OK)
 }
@@ -574,8 +768,11 @@ pub async fn rematch_media_by_id(
         .map_err(DatabaseError::from)?;
 
     for mediafile in mediafiles {
+        let library = Library::get_one(&mut tx, mediafile.library_id).await?;
+        let parser = FilenameParser::new(media_type, &library.filename_rules, &library.locations);
+
         let Some((_, metadata)) =
-            parse_filenames(IntoIterator::into_iter([&mediafile.target_file])).pop()
+            parse_filenames(IntoIterator::into_iter([&mediafile.target_file]), &parser).pop()
         else {
             continue;
         };
@@ -4,18 +4,24 @@ use axum::extract::State;
 use axum::response::IntoResponse;
 use axum::response::Json;
 use axum::response::Response;
+use axum::Extension;
 
+use dim_core::core::provider_for;
 use dim_core::scanner::movie;
 use dim_core::scanner::parse_filenames;
+use dim_core::scanner::problems;
 use dim_core::scanner::tv_show;
+use dim_core::scanner::FilenameParser;
 use dim_core::scanner::MediaMatcher;
 use dim_core::scanner::WorkUnit;
 
 use super::media::MOVIES_PROVIDER;
 use super::media::TV_PROVIDER;
 
+use dim_database::library::Library;
 use dim_database::library::MediaType;
 use dim_database::mediafile::MediaFile;
+use dim_database::user::User;
 use dim_database::DatabaseError;
 
 use dim_extern_api::ExternalQueryIntoShow;
@@ -43,6 +49,8 @@ pub enum Error {
     Database(#[from] DatabaseError),
     /// Failed to search for tmdb_id when rematching: {0}
     ExternalSearchError(String),
+    /// Failed to probe mediafile: {0}
+    ProbeFailed(dim_core::scanner::error::Error),
 }
 
 impl From<dim_core::scanner::error::Error> for Error {
@@ -67,6 +75,12 @@ impl IntoResponse for Error {
             Self::Database(_) => {
                 (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()).into_response()
             }
+            Self::ProbeFailed(dim_core::scanner::error::Error::MediafileNotFound(_)) => {
+                (StatusCode::NOT_FOUND, self.to_string()).into_response()
+            }
+            Self::ProbeFailed(_) => {
+                (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()).into_response()
+            }
         }
     }
 }
@@ -159,8 +173,11 @@ pub async fn rematch_mediafile(
         .map_err(DatabaseError::from)?;
 
     for mediafile in mediafiles {
+        let library = Library::get_one(&mut tx, mediafile.library_id).await?;
+        let parser = FilenameParser::new(media_type, &library.filename_rules, &library.locations);
+
         let Some((_, metadata)) =
-            parse_filenames(IntoIterator::into_iter([&mediafile.target_file])).pop()
+            parse_filenames(IntoIterator::into_iter([&mediafile.target_file]), &parser).pop()
         else {
             continue;
         };
@@ -183,3 +200,110 @@ pub async fn rematch_mediafile(
 
     Ok(StatusCode::OK)
 }
+
+#[derive(Deserialize)]
+pub struct AssignEpisodeArgs {
+    tmdb_id: String,
+    season: u64,
+    episode: u64,
+}
+
+/// Method mapped to `PATCH /api/v1/mediafile/<id>/episode` used to match a mediafile to a episode
+/// of a tv show by hand, regardless of what its filename says.
+///
+/// # Arguments
+/// * `id` - id of the mediafile we want to match
+///
+/// * `tmdb_id` - the external id of the tv show, as known to the metadata provider of the library
+/// * `season` - the season number of the episode, specials live in season 0
+/// * `episode` - the episode number of the episode
+///
+/// # Authorization
+/// This route requires the user to have `owner` permissions.
+pub async fn assign_episode(
+    Extension(user): Extension<User>,
+    State(AppState { conn, .. }): State<AppState>,
+    Path(id): Path<i64>,
+    Json(route_args): Json<AssignEpisodeArgs>,
+) -> Result<impl IntoResponse, Error> {
+    if !user.has_role("owner") {
+        return Err(Error::InvalidCredentials);
+    }
+
+    let mut tx = conn.read().begin().await.map_err(DatabaseError::from)?;
+
+    let mediafile = MediaFile::get_one(&mut tx, id)
+        .await
+        .map_err(DatabaseError::from)?;
+
+    let library = Library::get_one(&mut tx, mediafile.library_id).await?;
+    drop(tx);
+
+    if library.media_type != MediaType::Tv {
+        return Err(Error::InvalidMediaType);
+    }
+
+    info!(
+        %id,
+        season = route_args.season,
+        episode = route_args.episode,
+        "Assigning mediafile to episode"
+    );
+
+    let provider = provider_for(
+        library.media_type,
+        library.metadata_provider,
+        &library.locations,
+    )
+    .ok_or(Error::InvalidMediaType)?;
+
+    let result = tv_show::TvMatcher::find_episode_number(
+        provider,
+        &route_args.tmdb_id,
+        route_args.season,
+        route_args.episode,
+    )
+    .await
+    .map_err(|e| {
+        error!(?e, "Failed to find the episode to assign the mediafile to.");
+        Error::ExternalSearchError(e.to_string())
+    })?;
+
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = dim_database::write_tx(&mut lock)
+        .await
+        .map_err(DatabaseError::from)?;
+
+    tv_show::TvMatcher
+        .match_to_episode_number(&mut tx, mediafile, result)
+        .await
+        .map_err(|e| {
+            error!(?e, "Failed to assign mediafile to episode.");
+            Error::ExternalSearchError(e.to_string())
+        })?;
+
+    tx.commit().await.map_err(DatabaseError::from)?;
+
+    Ok(StatusCode::OK)
+}
+
+/// Method mapped to `POST /api/v1/mediafile/<id>/probe` probes a mediafile again, ie after it has
+/// been fixed or replaced on disk. The mediafile is returned with its problem, if it still has one.
+///
+/// # Arguments
+/// * `id` - id of the mediafile we want to probe
+pub async fn probe_mediafile(
+    Extension(user): Extension<User>,
+    State(AppState { conn, .. }): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<MediaFile>, Error> {
+    if !user.has_role("owner") {
+        return Err(Error::InvalidCredentials);
+    }
+
+    let mediafile = problems::reprobe(&conn, id)
+        .await
+        .map_err(Error::ProbeFailed)?;
+
+    Ok(Json(mediafile))
+}
@@ -4,6 +4,7 @@ pub mod filebrowser;
 pub mod library;
 pub mod media;
 pub mod mediafile;
+pub mod music;
 pub mod search;
 pub mod settings;
 pub mod statik;
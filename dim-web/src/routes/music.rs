@@ -0,0 +1,82 @@
+use crate::AppState;
+use axum::extract::Path;
+use axum::extract::State;
+use axum::response::IntoResponse;
+
+use dim_database::music::Album;
+use dim_database::music::Artist;
+use dim_database::music::Track;
+use dim_database::DatabaseError;
+
+use serde_json::json;
+
+use super::auth::AuthError;
+
+/// Method mapped to `GET /api/v1/library/<id>/artists` returns all artists of a music library.
+///
+/// # Arguments
+/// * `id` - id of the library.
+pub async fn get_library_artists(
+    State(AppState { conn, .. }): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AuthError> {
+    let mut tx = conn.read().begin().await.map_err(DatabaseError::from)?;
+    Ok(axum::response::Json(json!(&Artist::get_all(&mut tx, id).await?)).into_response())
+}
+
+/// Method mapped to `GET /api/v1/artist/<id>` returns info about a artist as well as all of
+/// their albums.
+///
+/// # Arguments
+/// * `id` - id of the artist.
+pub async fn get_artist_by_id(
+    State(AppState { conn, .. }): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AuthError> {
+    let mut tx = conn.read().begin().await.map_err(DatabaseError::from)?;
+    let artist = Artist::get_by_id(&mut tx, id).await?;
+    let albums = Album::get_of_artist(&mut tx, id).await?;
+
+    Ok(axum::response::Json(json!({
+        "id": artist.id,
+        "library_id": artist.library_id,
+        "name": artist.name,
+        "albums": albums,
+    }))
+    .into_response())
+}
+
+/// Method mapped to `GET /api/v1/album/<id>` returns info about a album, its artist and its
+/// tracks ordered by disc and track number.
+///
+/// # Arguments
+/// * `id` - id of the album.
+pub async fn get_album_by_id(
+    State(AppState { conn, .. }): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AuthError> {
+    let mut tx = conn.read().begin().await.map_err(DatabaseError::from)?;
+    let album = Album::get_by_id(&mut tx, id).await?;
+    let artist = Artist::get_of_album(&mut tx, id).await?;
+    let tracks = Track::get_all_of_album(&mut tx, id).await?;
+
+    Ok(axum::response::Json(json!({
+        "album": album,
+        "artist": artist,
+        "tracks": tracks,
+    }))
+    .into_response())
+}
+
+/// Method mapped to `GET /api/v1/album/<id>/tracks` returns all tracks of a album ordered by
+/// disc and track number.
+///
+/// # Arguments
+/// * `id` - id of the album.
+pub async fn get_album_tracks(
+    State(AppState { conn, .. }): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AuthError> {
+    let mut tx = conn.read().begin().await.map_err(DatabaseError::from)?;
+    Ok(axum::response::Json(json!(&Track::get_all_of_album(&mut tx, id).await?)).into_response())
+}
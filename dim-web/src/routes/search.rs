@@ -103,7 +103,8 @@ async fn search_by_name(
         Record,
         r#"SELECT _tblmedia.id, library_id, name, assets.local_path as poster_path FROM _tblmedia
            LEFT JOIN assets on _tblmedia.poster = assets.id
-           WHERE NOT media_type = "episode"
+           WHERE media_type NOT IN ("episode", "track")
+           AND _tblmedia.id NOT IN (SELECT id FROM missing_media)
            AND UPPER(name) LIKE ?
            LIMIT ?"#,
         query,
@@ -134,7 +135,8 @@ async fn search_by_genre(
                 FROM _tblmedia
                 LEFT JOIN assets on _tblmedia.poster = assets.id
                 INNER JOIN genre_media ON genre_media.media_id = _tblmedia.id
-                WHERE NOT media_type = "episode"
+                WHERE media_type NOT IN ("episode", "track")
+                AND _tblmedia.id NOT IN (SELECT id FROM missing_media)
                 AND genre_media.genre_id = ?
                 "#,
         genre_id,
@@ -163,7 +165,8 @@ async fn search_by_release_year(
         r#"SELECT _tblmedia.id, library_id, name, assets.local_path as poster_path
                 FROM _tblmedia
             LEFT JOIN assets on _tblmedia.poster = assets.id
-                WHERE NOT media_type = "episode"
+                WHERE media_type NOT IN ("episode", "track")
+                AND _tblmedia.id NOT IN (SELECT id FROM missing_media)
                 AND year = ?
                 "#,
         year,
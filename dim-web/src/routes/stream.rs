@@ -20,6 +20,7 @@ use dim_core::streaming::level_to_tag;
 use dim_core::utils::quality_to_label;
 
 use dim_database::mediafile::MediaFile;
+use dim_database::subtitle::SubtitleSidecar;
 use dim_database::user::DefaultVideoQuality;
 use dim_database::user::User;
 use dim_database::user::UserSettings;
@@ -78,18 +79,43 @@ pub async fn return_virtual_manifest(
 
     let gid = uuid::Uuid::new_v4();
 
-    let media = MediaFile::get_one(&mut tx, id)
+    let mut media = MediaFile::get_one(&mut tx, id)
         .await
         .map_err(|e| dim_core::errors::StreamingErrors::NoMediaFileFound(e.to_string()))?;
 
-    let target_file = media.target_file.clone();
+    let parts = match media.stack_key.as_deref() {
+        Some(key) => MediaFile::get_stack(&mut tx, media.library_id, key)
+            .await
+            .unwrap_or_default(),
+        None => vec![],
+    };
 
     // FIXME: When `fs::try_exists` gets stabilized we should use that as it will allow us to
     // detect if the user lacks permissions to access the file, etc.
-    if !path::Path::new(&target_file).exists() {
+    if !path::Path::new(&media.target_file).exists()
+        || parts
+            .iter()
+            .any(|x| !path::Path::new(&x.target_file).exists())
+    {
         return Err(dim_core::errors::StreamingErrors::FileDoesNotExist.into());
     }
 
+    // NOTE: The parts of a stacked movie get played back as one continuous file, this way the
+    // client sees a single timeline to seek in and reports a single progress value.
+    if parts.len() > 1 {
+        let playlist = dim_core::streaming::stack::write_playlist(
+            media.id,
+            parts
+                .iter()
+                .map(|x| (path::Path::new(&x.target_file), x.duration)),
+        )
+        .map_err(dim_core::errors::StreamingErrors::from)?;
+
+        media.target_file = playlist.to_string_lossy().to_string();
+    }
+
+    let target_file = media.target_file.clone();
+
     let info = FFProbeCtx::new(dim_core::streaming::FFPROBE_BIN.as_ref())
         .get_meta(target_file)
         .await
@@ -126,6 +152,12 @@ pub async fn return_virtual_manifest(
     )
     .await?;
 
+    let sidecars = SubtitleSidecar::get_of_mediafile(&mut tx, media.id)
+        .await
+        .unwrap_or_default();
+
+    create_external_subtitles(&sidecars, &stream_tracking, &gid, &state, params.force_ass).await?;
+
     stream_tracking.generate_sids(&gid).await;
 
     Ok(Json(&json!({
@@ -403,20 +435,9 @@ pub async fn create_subtitles(
             continue;
         }
 
-        let (mime, codec, output_codec) = if is_ssa {
-            ("text/ass", "ass", "ass")
-        } else {
-            ("text/vtt", "vtt", "webvtt")
-        };
-
         let ctx = ProfileContext {
             file: media.target_file.clone(),
             input_ctx: stream.clone().into(),
-            output_ctx: OutputCtx {
-                codec: output_codec.into(),
-                outdir: "-".into(),
-                ..Default::default()
-            },
             ..Default::default()
         };
 
@@ -427,35 +448,134 @@ pub async fn create_subtitles(
             .unwrap_or("Unknown")
             .to_string();
 
-        let title = stream.get_title().unwrap_or(lang.clone());
+        let title = stream.get_title().unwrap_or(lang);
+
+        create_subtitle(
+            ctx,
+            stream_tracking,
+            gid,
+            state,
+            is_ssa,
+            is_default,
+            title,
+            stream.get_language(),
+        )
+        .await?;
+    }
 
-        let profile_chain = get_profile_for(StreamType::Subtitle, &ctx);
-        let subtitle = state.create(profile_chain, ctx).await?;
+    Ok(())
+}
 
-        let chunk_path = if is_ssa {
-            format!("{}/data/stream.ass", subtitle.clone())
-        } else {
-            format!("{}/data/stream.vtt", subtitle.clone())
+/// Function adds the external subtitle files found next to a mediafile to the virtual manifest.
+pub async fn create_external_subtitles(
+    sidecars: &[SubtitleSidecar],
+    stream_tracking: &StreamTracking,
+    gid: &Uuid,
+    state: &StateManager,
+    force_ass: bool,
+) -> Result<(), DimErrorWrapper> {
+    for sidecar in sidecars {
+        let is_ssa = ["ssa", "ass"].contains(&sidecar.codec.as_str()) && force_ass;
+
+        // ffmpeg calls srt files subrip and vtt files webvtt.
+        let codec = match sidecar.codec.as_str() {
+            "srt" => "subrip",
+            "vtt" => "webvtt",
+            x => x,
         };
 
-        let virtual_manifest =
-            VirtualManifest::new(subtitle.clone(), chunk_path, None, ContentType::Subtitle)
-                .set_mime(mime)
-                .set_codecs(codec)
-                .set_bandwidth(1024)
-                .set_is_default(is_default)
-                .set_label(stream.get_title().unwrap_or(lang.clone()))
-                .set_lang(stream.get_language());
+        let ctx = ProfileContext {
+            file: sidecar.target_file.clone(),
+            input_ctx: InputCtx {
+                stream: 0,
+                codec: codec.into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
 
-        let title = title.replace("&", "and"); // dash.js seems to note like when there are `&` within titles.
-        let virtual_manifest = virtual_manifest.set_args([("title".to_string(), title)]);
+        let mut title = sidecar
+            .language
+            .as_deref()
+            .and_then(dim_core::utils::lang_from_iso639)
+            .unwrap_or("Unknown")
+            .to_string();
 
-        stream_tracking.insert(&gid, virtual_manifest).await;
+        if sidecar.forced {
+            title.push_str(" (Forced)");
+        }
+
+        if sidecar.sdh {
+            title.push_str(" (SDH)");
+        }
+
+        create_subtitle(
+            ctx,
+            stream_tracking,
+            gid,
+            state,
+            is_ssa,
+            false,
+            title,
+            sidecar.language.clone(),
+        )
+        .await?;
     }
 
     Ok(())
 }
 
+/// Function creates a subtitle stream which converts the input described by `ctx` into WebVTT,
+/// or ASS if `is_ssa` is set, and adds it to the virtual manifest.
+#[allow(clippy::too_many_arguments)]
+async fn create_subtitle(
+    mut ctx: ProfileContext,
+    stream_tracking: &StreamTracking,
+    gid: &Uuid,
+    state: &StateManager,
+    is_ssa: bool,
+    is_default: bool,
+    title: String,
+    lang: Option<String>,
+) -> Result<(), DimErrorWrapper> {
+    let (mime, codec, output_codec) = if is_ssa {
+        ("text/ass", "ass", "ass")
+    } else {
+        ("text/vtt", "vtt", "webvtt")
+    };
+
+    ctx.output_ctx = OutputCtx {
+        codec: output_codec.into(),
+        outdir: "-".into(),
+        ..Default::default()
+    };
+
+    let profile_chain = get_profile_for(StreamType::Subtitle, &ctx);
+    let subtitle = state.create(profile_chain, ctx).await?;
+
+    let chunk_path = if is_ssa {
+        format!("{}/data/stream.ass", subtitle.clone())
+    } else {
+        format!("{}/data/stream.vtt", subtitle.clone())
+    };
+
+    let virtual_manifest =
+        VirtualManifest::new(subtitle.clone(), chunk_path, None, ContentType::Subtitle)
+            .set_mime(mime)
+            .set_codecs(codec)
+            .set_bandwidth(1024)
+            .set_is_default(is_default)
+            .set_label(title.clone())
+            .set_lang(lang);
+
+    let title = title.replace("&", "and"); // dash.js seems to note like when there are `&` within titles.
+    let virtual_manifest = virtual_manifest.set_args([("title".to_string(), title)]);
+
+    stream_tracking.insert(&gid, virtual_manifest).await;
+
+    Ok(())
+}
+
 #[derive(Deserialize)]
 pub struct ManifestParams {
     start_num: Option<u64>,
@@ -3,13 +3,19 @@ use axum::extract::Json;
 use axum::extract::Path;
 use axum::extract::State;
 use axum::response::IntoResponse;
+use axum::Extension;
 
 use dim_database::episode::{Episode, UpdateEpisode};
+use dim_database::library::MediaType;
+use dim_database::media::Media;
 use dim_database::season::{Season, UpdateSeason};
+use dim_database::tv::TVShow;
+use dim_database::user::User;
 use dim_database::DatabaseError;
 
 use http::StatusCode;
 
+use serde::Deserialize;
 use serde_json::json;
 
 use super::auth::AuthError;
@@ -27,6 +33,52 @@ pub async fn get_tv_seasons(
     Ok(axum::response::Json(json!(&Season::get_all(&mut tx, id).await?)).into_response())
 }
 
+#[derive(Deserialize)]
+pub struct UpdateOrdering {
+    /// Whether the episodes of the show are numbered from the first episode of the show onwards
+    /// instead of per season.
+    pub absolute_ordering: bool,
+}
+
+/// Method mapped to `PATCH /api/v1/tv/<id>/ordering` sets how the episodes of a tv show are
+/// numbered. Files matched from then on have their episode numbers resolved accordingly.
+///
+/// # Arguments
+/// * `id` - id of the tv show.
+///
+/// # Authorization
+/// This route requires the user to have `owner` permissions.
+///
+/// # Data
+/// This route additionally requires you to pass in a json object by the format of
+/// `UpdateOrdering`.
+pub async fn patch_tv_ordering(
+    Extension(user): Extension<User>,
+    State(AppState { conn, .. }): State<AppState>,
+    Path(id): Path<i64>,
+    Json(ordering): Json<UpdateOrdering>,
+) -> Result<impl IntoResponse, AuthError> {
+    if !user.has_role("owner") {
+        return Err(AuthError::InvalidCredentials);
+    }
+
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = dim_database::write_tx(&mut lock)
+        .await
+        .map_err(DatabaseError::from)?;
+
+    if !matches!(Media::media_mediatype(&mut tx, id).await, Ok(MediaType::Tv)) {
+        return Err(AuthError::BadRequest(format!(
+            "media {id} is not a tv show"
+        )));
+    }
+
+    TVShow::set_absolute_ordering(&mut tx, id, ordering.absolute_ordering).await?;
+    tx.commit().await.map_err(DatabaseError::from)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
 /// Method mapped to `GET /api/v1/season/<id>` returns info about the season by `id`
 ///
 /// # Arguments
@@ -86,7 +138,7 @@ pub async fn get_season_episodes(
         FROM episode
         INNER JOIN _tblmedia on _tblmedia.id = episode.id
         LEFT JOIN assets ON assets.id = _tblmedia.backdrop
-        WHERE episode.seasonid = ?"#,
+        WHERE episode.seasonid = ? AND episode.id NOT IN (SELECT id FROM missing_media)"#,
         id
     ).fetch_all(&mut tx).await.unwrap_or_default();
 
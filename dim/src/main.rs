@@ -116,11 +116,19 @@ fn main() {
             }
         });
 
+        // NOTE: Scheduled rescans are skipped along with the scan on boot.
         if !global_settings.quiet_boot {
             tracing::info!("Scanning for media files...");
             dim::core::run_scanners(event_tx.clone()).await;
+
+            tokio::spawn(dim::scanner::schedule::run_scheduler(
+                pool.clone(),
+                event_tx.clone(),
+            ));
         }
 
+        tokio::spawn(dim::scanner::missing::run_purger(pool.clone()));
+
         tracing::info!("Launching Dim");
 
         let address = std::net::SocketAddr::new(